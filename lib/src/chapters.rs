@@ -0,0 +1,217 @@
+//! Chapters of long tracks, like podcast episodes and audiobooks
+//!
+//! Chapters can come from a Podcasting 2.0 `<podcast:chapters>` JSON document
+//! (<https://github.com/Podcastindex-org/podcast-namespace/blob/main/chapters/jsonChapters.md>)
+//! or from ID3v2 `CHAP` / `CTOC` frames embedded in the file.
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// How far into a chapter "previous chapter" restarts the current chapter instead of going to the one before it
+pub const PREVIOUS_CHAPTER_THRESHOLD: Duration = Duration::from_secs(3);
+
+/// A single chapter, which lasts until the next chapter starts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chapter {
+    /// The position in the track the chapter starts at
+    pub start: Duration,
+    pub title: String,
+}
+
+/// The root object of a JSON chapters document, only the parts we use
+#[derive(Debug, Deserialize)]
+struct JsonChapters {
+    #[serde(default)]
+    chapters: Vec<JsonChapter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonChapter {
+    start_time: f64,
+    #[serde(default)]
+    title: Option<String>,
+    /// Chapters with `toc: false` are only meant for things like changing the artwork, not for navigation
+    #[serde(default)]
+    toc: Option<bool>,
+}
+
+/// Parse a Podcasting 2.0 JSON chapters document, sorted by their start
+pub fn parse_json_chapters(body: &[u8]) -> Result<Vec<Chapter>> {
+    let doc: JsonChapters = serde_json::from_slice(body).context("parsing JSON chapters")?;
+
+    // negative, infinite or too large start times (which would panic in "from_secs_f64") are invalid
+    let mut chapters: Vec<(Duration, Option<String>)> = doc
+        .chapters
+        .into_iter()
+        .filter(|v| v.toc != Some(false))
+        .filter_map(|v| Some((Duration::try_from_secs_f64(v.start_time).ok()?, v.title)))
+        .collect();
+    chapters.sort_by_key(|v| v.0);
+
+    let chapters = chapters
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (start, title))| Chapter {
+            start,
+            title: title
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| format!("Chapter {}", idx + 1)),
+        })
+        .collect();
+
+    Ok(chapters)
+}
+
+/// Read the ID3v2 `CHAP` frames of the file at `path`, sorted by their start
+///
+/// If there is a top-level `CTOC` frame, only the chapters it lists are used, as the others are nested sub-chapters.
+/// Returns a empty list if the file has no ID3v2 tag or no chapters.
+pub fn read_id3_chapters(path: &Path) -> Vec<Chapter> {
+    let Ok(tag) = id3::Tag::read_from_path(path) else {
+        return Vec::new();
+    };
+
+    let top_level = tag.tables_of_contents().find(|toc| toc.top_level);
+
+    let mut chapters: Vec<Chapter> = tag
+        .chapters()
+        .filter(|chap| top_level.map_or(true, |toc| toc.elements.contains(&chap.element_id)))
+        .map(|chap| Chapter {
+            start: Duration::from_millis(u64::from(chap.start_time)),
+            title: chap
+                .frames
+                .iter()
+                .find(|frame| frame.id() == "TIT2")
+                .and_then(|frame| frame.content().text())
+                .filter(|v| !v.is_empty())
+                .map_or_else(|| chap.element_id.clone(), ToString::to_string),
+        })
+        .collect();
+    chapters.sort_by_key(|v| v.start);
+
+    chapters
+}
+
+/// Get the index of the chapter `position` is in, `None` if it is before the first chapter
+pub fn chapter_index_at(chapters: &[Chapter], position: Duration) -> Option<usize> {
+    chapters.iter().rposition(|v| v.start <= position)
+}
+
+/// Get the chapter `position` is in, `None` if it is before the first chapter
+pub fn chapter_at(chapters: &[Chapter], position: Duration) -> Option<&Chapter> {
+    chapter_index_at(chapters, position).and_then(|idx| chapters.get(idx))
+}
+
+/// Get the start of the chapter after the one at `position`, `None` if it is the last chapter
+pub fn next_chapter_start(chapters: &[Chapter], position: Duration) -> Option<Duration> {
+    chapters
+        .iter()
+        .find(|v| v.start > position)
+        .map(|v| v.start)
+}
+
+/// Get the start of the chapter before the one at `position`
+///
+/// Like "previous track", this restarts the current chapter if `position` is more than [`PREVIOUS_CHAPTER_THRESHOLD`] into it.
+/// Returns `None` if there are no chapters.
+pub fn previous_chapter_start(chapters: &[Chapter], position: Duration) -> Option<Duration> {
+    let Some(idx) = chapter_index_at(chapters, position) else {
+        return chapters.first().map(|_| Duration::ZERO);
+    };
+
+    if position - chapters[idx].start > PREVIOUS_CHAPTER_THRESHOLD || idx == 0 {
+        return Some(chapters[idx].start);
+    }
+
+    Some(chapters[idx - 1].start)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        chapter_at, next_chapter_start, parse_json_chapters, previous_chapter_start, Chapter,
+    };
+
+    fn chapter(start: u64, title: &str) -> Chapter {
+        Chapter {
+            start: Duration::from_secs(start),
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn should_parse_json_chapters() {
+        let body = br#"{
+            "version": "1.2.0",
+            "chapters": [
+                {"startTime": 120.5, "title": "Main topic"},
+                {"startTime": 0, "title": "Intro"},
+                {"startTime": 60, "title": "Sponsor image", "toc": false},
+                {"startTime": 300, "img": "https://example.com/outro.jpg"}
+            ]
+        }"#;
+
+        assert_eq!(
+            parse_json_chapters(body).unwrap(),
+            vec![
+                chapter(0, "Intro"),
+                Chapter {
+                    start: Duration::from_millis(120_500),
+                    title: "Main topic".to_string(),
+                },
+                chapter(300, "Chapter 3"),
+            ]
+        );
+        assert!(parse_json_chapters(b"<chapters/>").is_err());
+    }
+
+    #[test]
+    fn should_skip_invalid_chapter_starts() {
+        let body = br#"{
+            "version": "1.2.0",
+            "chapters": [
+                {"startTime": 1e300, "title": "Too late"},
+                {"startTime": -5, "title": "Too early"},
+                {"startTime": 10, "title": "Valid"}
+            ]
+        }"#;
+
+        assert_eq!(
+            parse_json_chapters(body).unwrap(),
+            vec![chapter(10, "Valid")]
+        );
+    }
+
+    #[test]
+    fn should_navigate_chapters() {
+        let chapters = [
+            chapter(10, "One"),
+            chapter(60, "Two"),
+            chapter(120, "Three"),
+        ];
+        let secs = Duration::from_secs;
+
+        assert_eq!(chapter_at(&chapters, secs(5)), None);
+        assert_eq!(chapter_at(&chapters, secs(60)), Some(&chapters[1]));
+        assert_eq!(chapter_at(&chapters, secs(500)), Some(&chapters[2]));
+
+        assert_eq!(next_chapter_start(&chapters, secs(5)), Some(secs(10)));
+        assert_eq!(next_chapter_start(&chapters, secs(60)), Some(secs(120)));
+        assert_eq!(next_chapter_start(&chapters, secs(130)), None);
+
+        // restart the current chapter
+        assert_eq!(previous_chapter_start(&chapters, secs(90)), Some(secs(60)));
+        // just started, go to the one before
+        assert_eq!(previous_chapter_start(&chapters, secs(62)), Some(secs(10)));
+        assert_eq!(previous_chapter_start(&chapters, secs(11)), Some(secs(10)));
+        assert_eq!(previous_chapter_start(&chapters, secs(5)), Some(secs(0)));
+        assert_eq!(previous_chapter_start(&[], secs(5)), None);
+    }
+}
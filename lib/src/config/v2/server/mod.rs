@@ -4,6 +4,7 @@ use std::{
     path::PathBuf,
 };
 
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 use crate::track::MediaType;
@@ -20,6 +21,7 @@ pub struct ServerSettings {
     pub com: ComSettings,
     pub player: PlayerSettings,
     pub podcast: PodcastSettings,
+    pub mpd: MpdSettings,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -33,6 +35,16 @@ pub struct PodcastSettings {
     pub max_download_retries: u8,
     /// Directory for downloaded Podcasts
     pub download_dir: PathBuf,
+    /// Refresh all podcast feeds on the server every this many minutes, `0` disables it
+    pub refresh_interval_minutes: u32,
+    /// Download new episodes after a feed got refreshed, can be overwritten per feed
+    pub auto_download: bool,
+    /// Only keep the newest this many downloaded episodes per feed, `0` keeps all, can be overwritten per feed
+    pub keep_downloaded: u32,
+    /// Delete downloaded episodes this many days after they have been played, `0` disables it, can be overwritten per feed
+    pub delete_played_after_days: u32,
+    /// Sync subscriptions and episode actions with a gpodder.net compatible server, disabled if unset
+    pub gpodder: Option<GpodderSettings>,
 }
 
 /// Get the default podcast dir, which uses OS-specific paths, or home/Music/podcast
@@ -52,10 +64,69 @@ impl Default for PodcastSettings {
             concurrent_downloads_max: NonZeroU8::new(3).unwrap(),
             max_download_retries: 3,
             download_dir: default_podcast_dir(),
+            refresh_interval_minutes: 0,
+            auto_download: false,
+            keep_downloaded: 0,
+            delete_played_after_days: 0,
+            gpodder: None,
         }
     }
 }
 
+/// Settings for the gpodder sync in [`PodcastSettings`]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GpodderSettings {
+    /// Base url of the server, like `https://gpodder.net`
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    /// Name of this device on the server, subscriptions are synced per device
+    #[serde(default = "GpodderSettings::default_device_id")]
+    pub device_id: String,
+    /// Sync every this many minutes, in addition to once on server start
+    #[serde(default = "GpodderSettings::default_sync_interval_minutes")]
+    pub sync_interval_minutes: NonZeroU32,
+}
+
+impl GpodderSettings {
+    fn default_device_id() -> String {
+        "termusic".to_string()
+    }
+
+    #[allow(clippy::missing_panics_doc)] // const unwrap
+    fn default_sync_interval_minutes() -> NonZeroU32 {
+        NonZeroU32::new(30).unwrap()
+    }
+}
+
+/// Settings for the MPD protocol frontend
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)] // allow missing fields and fill them with the `..Self::default()` in this struct
+pub struct MpdSettings {
+    /// Enable the MPD protocol listener, so that MPD clients can control the server
+    pub enabled: bool,
+    /// MPD listener interface / address
+    pub address: IpAddr,
+    /// MPD listener Port
+    pub port: u16,
+}
+
+impl Default for MpdSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "::1".parse().unwrap(),
+            port: 6600,
+        }
+    }
+}
+
+impl From<&MpdSettings> for SocketAddr {
+    fn from(value: &MpdSettings) -> Self {
+        Self::new(value.address, value.port)
+    }
+}
+
 // note that regardless of options, loops should never happen and also should never go outside of the root music_dir
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(untagged)]
@@ -261,6 +332,19 @@ pub struct PlayerSettings {
     pub random_track_quantity: NonZeroU32,
     /// Minimal amount of tracks a album needs to have before being chosen for "random album add"
     pub random_album_min_quantity: NonZeroU32,
+
+    /// Which ReplayGain tags to apply, only supported by the rusty backend
+    pub replaygain: ReplayGainMode,
+    /// Gain in dB added to the ReplayGain, as ReplayGain targets a lower loudness than most masters
+    pub replaygain_preamp: f32,
+    /// Seconds to crossfade between tracks, `0` to disable, only supported by the rusty backend
+    ///
+    /// Tracks of the same album (which are likely gapless) and podcasts are never crossfaded.
+    pub crossfade_secs: u64,
+    /// Equalizer presets and the active one, only supported by the rusty backend
+    pub equalizer: EqualizerSettings,
+    /// Name of the audio output device, the default device if unset or if it is not available, only supported by the rusty backend
+    pub output_device: Option<String>,
 }
 
 /// Get the default Music dir, which uses OS-specific paths, or home/Music
@@ -289,10 +373,162 @@ impl Default for PlayerSettings {
 
             random_track_quantity: NonZeroU32::new(20).unwrap(),
             random_album_min_quantity: NonZeroU32::new(5).unwrap(),
+
+            replaygain: ReplayGainMode::default(),
+            replaygain_preamp: 0.0,
+            crossfade_secs: 0,
+            equalizer: EqualizerSettings::default(),
+            output_device: None,
         }
     }
 }
 
+/// Which ReplayGain tags to apply to the volume of tracks
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    /// Do not apply any ReplayGain
+    #[default]
+    Off,
+    /// Apply the track gain, so that all tracks have about the same loudness
+    Track,
+    /// Apply the album gain, keeping the loudness differences within a album, or the track gain if there is none
+    Album,
+    /// Like [`Self::Album`], except for [`LoopMode::Random`] where [`Self::Track`] is used
+    Auto,
+}
+
+impl ReplayGainMode {
+    /// Resolve [`Self::Auto`] for the current `loop_mode`
+    #[must_use]
+    pub fn resolve(self, loop_mode: LoopMode) -> Self {
+        match (self, loop_mode) {
+            (Self::Auto, LoopMode::Random) => Self::Track,
+            (Self::Auto, _) => Self::Album,
+            (mode, _) => mode,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default)] // allow missing fields and fill them with the `..Self::default()` in this struct
+pub struct EqualizerSettings {
+    /// Apply the active preset
+    pub enabled: bool,
+    /// Name of the active preset
+    pub preset: String,
+    /// Named sets of bands, for example one for each pair of headphones or speakers
+    pub presets: Vec<EqualizerPreset>,
+}
+
+impl Default for EqualizerSettings {
+    fn default() -> Self {
+        let preset = EqualizerPreset::flat("flat".to_string());
+
+        Self {
+            enabled: false,
+            preset: preset.name.clone(),
+            presets: vec![preset],
+        }
+    }
+}
+
+impl EqualizerSettings {
+    /// Get the bands to apply, empty if disabled or if the active preset does not exist
+    #[must_use]
+    pub fn active_bands(&self) -> &[EqualizerBand] {
+        if !self.enabled {
+            return &[];
+        }
+
+        self.presets
+            .iter()
+            .find(|v| v.name == self.preset)
+            .map_or(&[], |v| v.bands.as_slice())
+    }
+
+    /// Enable or disable the equalizer and activate `preset`, replacing its bands if `bands` is not empty
+    ///
+    /// The preset is created with flat graphic bands if it does not exist yet.
+    pub fn set(&mut self, enabled: bool, preset: String, bands: Vec<EqualizerBand>) {
+        let idx = if let Some(idx) = self.presets.iter().position(|v| v.name == preset) {
+            idx
+        } else {
+            self.presets.push(EqualizerPreset::flat(preset.clone()));
+            self.presets.len() - 1
+        };
+
+        if !bands.is_empty() {
+            self.presets[idx].bands = bands;
+        }
+        self.enabled = enabled;
+        self.preset = preset;
+    }
+
+    /// Remove the preset with `name`, returns whether it existed
+    pub fn remove_preset(&mut self, name: &str) -> bool {
+        let len = self.presets.len();
+        self.presets.retain(|v| v.name != name);
+
+        len != self.presets.len()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct EqualizerPreset {
+    pub name: String,
+    pub bands: Vec<EqualizerBand>,
+}
+
+impl EqualizerPreset {
+    /// The center frequencies of a 10-band graphic equalizer, one octave apart
+    pub const GRAPHIC_FREQUENCIES: [f32; 10] = [
+        31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+    ];
+
+    /// Create a preset with the graphic bands and no gain
+    #[must_use]
+    pub fn flat(name: String) -> Self {
+        Self {
+            name,
+            bands: Self::GRAPHIC_FREQUENCIES
+                .iter()
+                .map(|frequency| EqualizerBand {
+                    frequency: *frequency,
+                    gain: 0.0,
+                    q: EqualizerBand::OCTAVE_Q,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A peaking filter of the equalizer
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct EqualizerBand {
+    /// Center frequency in Hz
+    pub frequency: f32,
+    /// Gain in dB, positive values boost and negative values cut
+    pub gain: f32,
+    /// Quality, higher values affect a narrower range around the frequency
+    pub q: f32,
+}
+
+impl EqualizerBand {
+    /// The quality for a bandwidth of one octave, as used by the graphic bands
+    pub const OCTAVE_Q: f32 = std::f32::consts::SQRT_2;
+
+    /// Get whether the values can be used for a filter
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.frequency.is_finite()
+            && self.frequency > 0.0
+            && self.gain.is_finite()
+            && self.q.is_finite()
+            && self.q > 0.0
+    }
+}
+
 /// Playlist loop modes
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -322,10 +558,28 @@ impl LoopMode {
             }
         }
     }
+
+    /// Convert to the representation used over gRPC
+    pub fn as_u32(self) -> u32 {
+        match self {
+            Self::Single => 0,
+            Self::Playlist => 1,
+            Self::Random => 2,
+        }
+    }
+
+    /// Convert from the representation used over gRPC, unknown values map to the default
+    pub fn from_u32(mode: u32) -> Self {
+        match mode {
+            0 => Self::Single,
+            2 => Self::Random,
+            _ => Self::Playlist,
+        }
+    }
 }
 
 /// Settings for the gRPC server (and potentially future ways to communicate)
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 // for now, require that both port and ip are specified at once
 // #[serde(default)] // allow missing fields and fill them with the `..Self::default()` in this struct
 pub struct ComSettings {
@@ -333,6 +587,47 @@ pub struct ComSettings {
     pub port: u16,
     /// gRPC server interface / address
     pub address: IpAddr,
+    /// Use a Unix domain socket at this path instead of `address` and `port`, only supported on unix
+    ///
+    /// Relative paths are resolved against `$XDG_RUNTIME_DIR`.
+    #[serde(default)]
+    pub socket_path: Option<PathBuf>,
+    /// Encrypt the TCP connection with TLS, disabled if unset
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+    /// Shared secret every request needs to present, disabled if unset
+    ///
+    /// The server rejects requests without a matching token, the tui sends it with every request.
+    #[serde(default, deserialize_with = "deserialize_token")]
+    pub token: Option<String>,
+}
+
+/// Deserialize [`ComSettings::token`], rejecting an empty token as that would likely be a misconfiguration
+fn deserialize_token<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let token = Option::<String>::deserialize(deserializer)?;
+    if token.as_deref().is_some_and(str::is_empty) {
+        return Err(<D::Error as serde::de::Error>::custom(
+            "token must not be empty, remove it to disable authentication",
+        ));
+    }
+
+    Ok(token)
+}
+
+impl ComSettings {
+    /// Whether a token would be sent as plaintext over the network
+    ///
+    /// This is the case when listening on a non-loopback TCP address with a token but without TLS.
+    #[must_use]
+    pub fn token_in_plaintext(&self) -> bool {
+        self.token.is_some()
+            && self.tls.is_none()
+            && self.socket_path.is_none()
+            && !self.address.is_loopback()
+    }
 }
 
 impl Default for ComSettings {
@@ -340,7 +635,54 @@ impl Default for ComSettings {
         Self {
             port: 50101,
             address: "::1".parse().unwrap(),
+            socket_path: None,
+            tls: None,
+            token: None,
+        }
+    }
+}
+
+/// TLS Settings for [`ComSettings`]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TlsSettings {
+    /// Path to a PEM encoded certificate
+    ///
+    /// For the server this is its own certificate (chain),
+    /// for the tui this is the certificate (or CA) to trust when connecting to the server.
+    pub cert: PathBuf,
+    /// Path to the PEM encoded private key of `cert`, only used and required by the server
+    #[serde(default)]
+    pub key: Option<PathBuf>,
+    /// Name to verify the server certificate against, only used by the tui
+    ///
+    /// Defaults to `address`.
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+impl ComSettings {
+    /// Get the full path of the Unix domain socket, if one is configured
+    ///
+    /// # Errors
+    ///
+    /// - if the path is relative and `$XDG_RUNTIME_DIR` could not be found
+    pub fn resolved_socket_path(&self) -> Result<Option<PathBuf>> {
+        let Some(path) = &self.socket_path else {
+            return Ok(None);
+        };
+
+        if path.is_absolute() {
+            return Ok(Some(path.clone()));
         }
+
+        let runtime_dir = dirs::runtime_dir().with_context(|| {
+            format!(
+                "\"$XDG_RUNTIME_DIR\" is not set, cannot resolve relative socket path {}",
+                path.display()
+            )
+        })?;
+
+        Ok(Some(runtime_dir.join(path)))
     }
 }
 
@@ -354,9 +696,9 @@ mod v1_interop {
     use std::{error::Error, fmt::Display, num::TryFromIntError};
 
     use super::{
-        ComSettings, LoopMode, NonZeroU32, NonZeroU8, PlayerSettings, PodcastSettings,
-        PositionYesNo, PositionYesNoLower, RememberLastPosition, ScanDepth, SeekStep,
-        ServerSettings,
+        ComSettings, LoopMode, MpdSettings, NonZeroU32, NonZeroU8, PlayerSettings, PodcastSettings,
+        PositionYesNo, PositionYesNoLower, RememberLastPosition, ReplayGainMode, ScanDepth,
+        SeekStep, ServerSettings,
     };
     use crate::config::v1;
 
@@ -430,6 +772,9 @@ mod v1_interop {
             let com_settings = ComSettings {
                 port: value.player_port,
                 address: value.player_interface,
+                socket_path: None,
+                tls: None,
+                token: None,
             };
 
             let podcast_settings = PodcastSettings {
@@ -447,6 +792,11 @@ mod v1_interop {
                 })?,
                 max_download_retries: value.podcast_max_retries.clamp(0, u8::MAX as usize) as u8,
                 download_dir: value.podcast_dir,
+                refresh_interval_minutes: 0,
+                auto_download: false,
+                keep_downloaded: 0,
+                delete_played_after_days: 0,
+                gpodder: None,
             };
 
             let player_settings = PlayerSettings {
@@ -484,12 +834,19 @@ mod v1_interop {
                         err,
                     )
                 })?,
+
+                replaygain: ReplayGainMode::default(),
+                replaygain_preamp: 0.0,
+                crossfade_secs: 0,
+                equalizer: EqualizerSettings::default(),
+                output_device: None,
             };
 
             Ok(Self {
                 com: com_settings,
                 player: player_settings,
                 podcast: podcast_settings,
+                mpd: MpdSettings::default(),
             })
         }
     }
@@ -517,7 +874,12 @@ mod v1_interop {
                 PodcastSettings {
                     concurrent_downloads_max: NonZeroU8::new(3).unwrap(),
                     max_download_retries: 3,
-                    download_dir: PathBuf::new()
+                    download_dir: PathBuf::new(),
+                    refresh_interval_minutes: 0,
+                    auto_download: false,
+                    keep_downloaded: 0,
+                    delete_played_after_days: 0,
+                    gpodder: None,
                 }
             );
 
@@ -525,7 +887,10 @@ mod v1_interop {
                 converted.com,
                 ComSettings {
                     port: 50101,
-                    address: "::1".parse().unwrap()
+                    address: "::1".parse().unwrap(),
+                    socket_path: None,
+                    tls: None,
+                    token: None,
                 }
             );
 
@@ -559,8 +924,110 @@ mod v1_interop {
                     set_discord_status: true,
                     random_track_quantity: NonZeroU32::new(20).unwrap(),
                     random_album_min_quantity: NonZeroU32::new(5).unwrap(),
+                    replaygain: ReplayGainMode::Off,
+                    replaygain_preamp: 0.0,
+                    crossfade_secs: 0,
+                    equalizer: EqualizerSettings::default(),
+                    output_device: None,
                 }
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{ComSettings, EqualizerBand, EqualizerPreset, EqualizerSettings, TlsSettings};
+
+    #[test]
+    fn should_resolve_socket_path() {
+        let mut com = ComSettings::default();
+        assert_eq!(com.resolved_socket_path().unwrap(), None);
+
+        com.socket_path = Some(PathBuf::from("/tmp/termusic.sock"));
+        assert_eq!(
+            com.resolved_socket_path().unwrap(),
+            Some(PathBuf::from("/tmp/termusic.sock"))
+        );
+    }
+
+    #[test]
+    fn should_deserialize_without_socket_path() {
+        let com: ComSettings = toml::from_str("port = 50101\naddress = \"::1\"").unwrap();
+        assert_eq!(com, ComSettings::default());
+    }
+
+    #[test]
+    fn should_deserialize_tls_and_token() {
+        let com: ComSettings = toml::from_str(
+            "port = 50101\naddress = \"::1\"\ntoken = \"secret\"\n[tls]\ncert = \"/etc/termusic/cert.pem\"\nkey = \"/etc/termusic/key.pem\"",
+        )
+        .unwrap();
+        assert_eq!(com.token.as_deref(), Some("secret"));
+        assert_eq!(
+            com.tls,
+            Some(TlsSettings {
+                cert: PathBuf::from("/etc/termusic/cert.pem"),
+                key: Some(PathBuf::from("/etc/termusic/key.pem")),
+                domain: None,
+            })
+        );
+        assert!(!com.token_in_plaintext());
+    }
+
+    #[test]
+    fn should_reject_empty_token() {
+        let res = toml::from_str::<ComSettings>("port = 50101\naddress = \"::1\"\ntoken = \"\"");
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn should_detect_plaintext_token() {
+        let mut com = ComSettings {
+            token: Some("secret".to_string()),
+            ..ComSettings::default()
+        };
+        // loopback
+        assert!(!com.token_in_plaintext());
+
+        com.address = "0.0.0.0".parse().unwrap();
+        assert!(com.token_in_plaintext());
+
+        com.socket_path = Some(PathBuf::from("termusic.sock"));
+        assert!(!com.token_in_plaintext());
+    }
+
+    #[test]
+    fn should_set_equalizer_presets() {
+        let mut equalizer = EqualizerSettings::default();
+        assert!(equalizer.active_bands().is_empty());
+
+        equalizer.set(true, "flat".to_string(), Vec::new());
+        assert_eq!(equalizer.active_bands().len(), 10);
+
+        let bands = vec![EqualizerBand {
+            frequency: 100.0,
+            gain: -3.0,
+            q: 0.7,
+        }];
+        equalizer.set(true, "speakers".to_string(), bands.clone());
+        assert_eq!(equalizer.active_bands(), bands.as_slice());
+        assert_eq!(equalizer.presets.len(), 2);
+
+        // a new preset starts flat
+        equalizer.set(true, "headphones".to_string(), Vec::new());
+        assert_eq!(
+            equalizer.active_bands(),
+            EqualizerPreset::flat(String::new()).bands.as_slice()
+        );
+
+        assert!(equalizer.remove_preset("headphones"));
+        assert!(!equalizer.remove_preset("headphones"));
+        assert!(equalizer.active_bands().is_empty());
+
+        equalizer.set(false, "speakers".to_string(), Vec::new());
+        assert!(equalizer.active_bands().is_empty());
+    }
+}
@@ -348,6 +348,14 @@ pub struct KeysPlayer {
     ///
     /// Will only apply in specific widgets (like the Playlist, but not in Config)
     pub seek_backward: KeyBinding,
+    /// Key to seek to the next chapter of the current track
+    ///
+    /// Will only apply in specific widgets (like the Playlist, but not in Config)
+    pub next_chapter: KeyBinding,
+    /// Key to seek to the start of the current chapter, or the previous one if it just started
+    ///
+    /// Will only apply in specific widgets (like the Playlist, but not in Config)
+    pub previous_chapter: KeyBinding,
     /// Key to increase speed (by a set amount)
     ///
     /// Will only apply in specific widgets (like the Playlist, but not in Config)
@@ -364,6 +372,8 @@ pub struct KeysPlayer {
 
     /// Key to save the current playlist as a "m3u" playlist
     pub save_playlist: KeyBinding,
+    /// Key to open the equalizer popup
+    pub open_equalizer: KeyBinding,
 }
 
 impl Default for KeysPlayer {
@@ -380,6 +390,8 @@ impl Default for KeysPlayer {
             volume_down: tuievents::Key::Char('-').into(),
             seek_forward: tuievents::Key::Char('f').into(),
             seek_backward: tuievents::Key::Char('b').into(),
+            next_chapter: tuievents::Key::Char(']').into(),
+            previous_chapter: tuievents::Key::Char('[').into(),
             speed_up: tuievents::KeyEvent::new(
                 tuievents::Key::Char('f'),
                 tuievents::KeyModifiers::CONTROL,
@@ -400,6 +412,11 @@ impl Default for KeysPlayer {
                 tuievents::KeyModifiers::CONTROL,
             )
             .into(),
+            open_equalizer: tuievents::KeyEvent::new(
+                tuievents::Key::Char('e'),
+                tuievents::KeyModifiers::CONTROL,
+            )
+            .into(),
         }
     }
 }
@@ -414,11 +431,14 @@ impl CheckConflict for KeysPlayer {
             (&self.volume_down, "volume_down"),
             (&self.seek_forward, "seek_forward"),
             (&self.seek_backward, "seek_backward"),
+            (&self.next_chapter, "next_chapter"),
+            (&self.previous_chapter, "previous_chapter"),
             (&self.speed_up, "speed_up"),
             (&self.speed_down, "speed_down"),
             (&self.toggle_prefetch, "toggle_prefetch"),
 
             (&self.save_playlist, "save_playlist"),
+            (&self.open_equalizer, "open_equalizer"),
         }
     }
 
@@ -1926,10 +1946,15 @@ mod v1_interop {
                     volume_down: player_volume_down_key,
                     seek_forward: value.global_player_seek_forward.into(),
                     seek_backward: value.global_player_seek_backward.into(),
+                    // not available in v1
+                    next_chapter: KeysPlayer::default().next_chapter,
+                    previous_chapter: KeysPlayer::default().previous_chapter,
                     speed_up: value.global_player_speed_up.into(),
                     speed_down: value.global_player_speed_down.into(),
                     toggle_prefetch: value.global_player_toggle_gapless.into(),
                     save_playlist: value.global_save_playlist.into(),
+                    // not available in v1
+                    open_equalizer: KeysPlayer::default().open_equalizer,
                 },
                 lyric_keys: KeysLyric {
                     adjust_offset_forwards: value.global_lyric_adjust_forward.into(),
@@ -2058,6 +2083,8 @@ mod v1_interop {
                 .into(),
                 seek_forward: tuievents::Key::Char('f').into(),
                 seek_backward: tuievents::Key::Char('b').into(),
+                next_chapter: tuievents::Key::Char(']').into(),
+                previous_chapter: tuievents::Key::Char('[').into(),
                 speed_up: tuievents::KeyEvent::new(
                     tuievents::Key::Char('f'),
                     tuievents::KeyModifiers::CONTROL,
@@ -2078,6 +2105,11 @@ mod v1_interop {
                     tuievents::KeyModifiers::CONTROL,
                 )
                 .into(),
+                open_equalizer: tuievents::KeyEvent::new(
+                    tuievents::Key::Char('e'),
+                    tuievents::KeyModifiers::CONTROL,
+                )
+                .into(),
             };
             assert_eq!(converted.player_keys, expected_player_keys);
 
@@ -2295,6 +2327,8 @@ mod v1_interop {
                 .into(),
                 seek_forward: tuievents::Key::Char('f').into(),
                 seek_backward: tuievents::Key::Char('b').into(),
+                next_chapter: tuievents::Key::Char(']').into(),
+                previous_chapter: tuievents::Key::Char('[').into(),
                 speed_up: tuievents::KeyEvent::new(
                     tuievents::Key::Char('f'),
                     tuievents::KeyModifiers::CONTROL,
@@ -2315,6 +2349,11 @@ mod v1_interop {
                     tuievents::KeyModifiers::CONTROL,
                 )
                 .into(),
+                open_equalizer: tuievents::KeyEvent::new(
+                    tuievents::Key::Char('e'),
+                    tuievents::KeyModifiers::CONTROL,
+                )
+                .into(),
             };
             assert_eq!(converted.player_keys, expected_player_keys);
         }
@@ -26,9 +26,11 @@ use rand::seq::SliceRandom;
 use serde_json::Value;
 // left for debug
 // use std::io::Write;
-use reqwest::{Client, ClientBuilder, StatusCode};
+use reqwest::{Client, ClientBuilder, StatusCode, Url};
 use std::time::Duration;
 
+use crate::podcast::PodcastFeed;
+
 const INVIDIOUS_INSTANCE_LIST: [&str; 7] = [
     "https://vid.puffyan.us",
     "https://inv.riverside.rocks",
@@ -49,6 +51,14 @@ const INVIDIOUS_INSTANCE_LIST: [&str; 7] = [
 
 const INVIDIOUS_DOMAINS: &str = "https://api.invidious.io/instances.json?sort_by=type,users";
 
+/// Hosts of youtube itself, for which a invidious instance needs to be picked
+const YOUTUBE_HOSTS: [&str; 4] = [
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+];
+
 #[derive(Clone, Debug)]
 pub struct Instance {
     pub domain: Option<String>,
@@ -71,6 +81,59 @@ pub struct YoutubeVideo {
     pub video_id: String,
 }
 
+/// A youtube channel or playlist, which can be subscribed to as a podcast feed through a invidious instance
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum YoutubeFeed {
+    /// A channel by its id (`UC...`)
+    Channel(String),
+    /// A playlist by its id (`PL...`)
+    Playlist(String),
+}
+
+impl YoutubeFeed {
+    /// Get the channel or playlist from a youtube or invidious url,
+    /// like `https://www.youtube.com/channel/UC...` or `https://yewtu.be/playlist?list=PL...`
+    ///
+    /// Also returns the invidious domain, or [`None`] if it is a youtube url.
+    pub fn from_url(url: &str) -> Option<(Self, Option<String>)> {
+        let url = Url::parse(url.trim()).ok()?;
+        let host = url.host_str()?;
+
+        let mut segments = url.path_segments()?;
+        let feed = match (segments.next(), segments.next()) {
+            (Some("channel"), Some(id)) if id.starts_with("UC") => Self::Channel(id.to_string()),
+            (Some("playlist" | "watch"), _) => {
+                let (_, id) = url.query_pairs().find(|(key, _)| key == "list")?;
+                if id.is_empty() {
+                    return None;
+                }
+                Self::Playlist(id.into_owned())
+            }
+            _ => return None,
+        };
+
+        let domain = if YOUTUBE_HOSTS.contains(&host) {
+            None
+        } else {
+            Some(url.origin().ascii_serialization())
+        };
+
+        Some((feed, domain))
+    }
+}
+
+/// Turn a youtube or invidious channel / playlist url into the url of its podcast feed, other urls are returned as-is
+///
+/// For youtube urls a working invidious instance is picked.
+pub async fn podcast_feed_url(url: &str) -> Result<String> {
+    let Some((feed, domain)) = YoutubeFeed::from_url(url) else {
+        return Ok(url.to_string());
+    };
+
+    let instance = Instance::connect(domain).await?;
+    Ok(instance.podcast_feed(&feed)?.url)
+}
+
 impl Default for Instance {
     fn default() -> Self {
         let client = Client::new();
@@ -93,18 +156,7 @@ impl Instance {
             .build()?;
 
         let mut domain = String::new();
-        let mut domains = vec![];
-
-        // prefor fetch invidious instance from website, but will provide 7 backups
-        if let Ok(domain_list) = Self::get_invidious_instance_list(&client).await {
-            domains = domain_list;
-        } else {
-            for item in &INVIDIOUS_INSTANCE_LIST {
-                domains.push((*item).to_string());
-            }
-        }
-
-        domains.shuffle(&mut rand::thread_rng());
+        let domains = Self::get_domains(&client).await;
 
         let mut video_result: Vec<YoutubeVideo> = Vec::new();
         for v in domains {
@@ -143,6 +195,58 @@ impl Instance {
         ))
     }
 
+    /// Connect to the invidious instance at `domain`, or to a working one from the instance list if [`None`]
+    pub async fn connect(domain: Option<String>) -> Result<Self> {
+        let client = ClientBuilder::new()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+
+        // a explicitly given instance is used as-is, as it may not have the statistics enabled
+        if let Some(domain) = domain {
+            return Ok(Self {
+                domain: Some(domain),
+                client,
+                query: None,
+            });
+        }
+
+        for v in Self::get_domains(&client).await {
+            let url = format!("{v}/api/v1/stats");
+            if let Ok(result) = client.get(&url).send().await {
+                if result.status() == StatusCode::OK {
+                    return Ok(Self {
+                        domain: Some(v),
+                        client,
+                        query: None,
+                    });
+                }
+            }
+        }
+
+        bail!("Something is wrong with your connection or all invidious servers are down.");
+    }
+
+    /// Get the podcast feed of a youtube channel or playlist on this instance,
+    /// its episodes resolve to the audio streams of the videos
+    pub fn podcast_feed(&self, feed: &YoutubeFeed) -> Result<PodcastFeed> {
+        let domain = self
+            .domain
+            .as_ref()
+            .filter(|v| !v.is_empty())
+            .ok_or(anyhow!("No server available"))?;
+
+        let url = match feed {
+            YoutubeFeed::Channel(id) => {
+                format!("{domain}/api/v1/channels/{}", urlencoding::encode(id))
+            }
+            YoutubeFeed::Playlist(id) => {
+                format!("{domain}/api/v1/playlists/{}", urlencoding::encode(id))
+            }
+        };
+
+        Ok(PodcastFeed::new(None, &url, None))
+    }
+
     // GetSearchQuery fetches query result from an Invidious instance.
     pub async fn get_search_query(&self, page: u32) -> Result<Vec<YoutubeVideo>> {
         if self.domain.is_none() {
@@ -244,6 +348,23 @@ impl Instance {
         Some((title, video_id, length_seconds))
     }
 
+    /// Get the instances to try in random order
+    ///
+    /// Prefers fetching the invidious instances from the website, but will provide 7 backups
+    async fn get_domains(client: &Client) -> Vec<String> {
+        let mut domains = match Self::get_invidious_instance_list(client).await {
+            Ok(domain_list) => domain_list,
+            Err(_) => INVIDIOUS_INSTANCE_LIST
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        };
+
+        domains.shuffle(&mut rand::thread_rng());
+
+        domains
+    }
+
     async fn get_invidious_instance_list(client: &Client) -> Result<Vec<String>> {
         let result = client.get(INVIDIOUS_DOMAINS).send().await?.text().await?;
         // Left here for debug
@@ -293,3 +414,62 @@ impl Instance {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_parse_youtube_feed_urls() {
+        assert_eq!(
+            YoutubeFeed::from_url("https://www.youtube.com/channel/UCabc123/videos"),
+            Some((YoutubeFeed::Channel("UCabc123".to_string()), None))
+        );
+        assert_eq!(
+            YoutubeFeed::from_url("https://www.youtube.com/watch?v=xyz&list=PLabc123"),
+            Some((YoutubeFeed::Playlist("PLabc123".to_string()), None))
+        );
+        assert_eq!(
+            YoutubeFeed::from_url("https://yewtu.be/playlist?list=PLabc123"),
+            Some((
+                YoutubeFeed::Playlist("PLabc123".to_string()),
+                Some("https://yewtu.be".to_string())
+            ))
+        );
+        assert_eq!(
+            YoutubeFeed::from_url("https://example.com/feed.xml?list=1"),
+            None
+        );
+        assert_eq!(
+            YoutubeFeed::from_url("https://yewtu.be/api/v1/channels/UCabc123"),
+            None
+        );
+    }
+
+    #[test]
+    fn should_get_podcast_feed() {
+        let instance = Instance {
+            domain: Some("https://yewtu.be".to_string()),
+            ..Instance::default()
+        };
+
+        assert_eq!(
+            instance
+                .podcast_feed(&YoutubeFeed::Channel("UCabc123".to_string()))
+                .unwrap()
+                .url,
+            "https://yewtu.be/api/v1/channels/UCabc123"
+        );
+        assert_eq!(
+            instance
+                .podcast_feed(&YoutubeFeed::Playlist("PLabc123".to_string()))
+                .unwrap()
+                .url,
+            "https://yewtu.be/api/v1/playlists/PLabc123"
+        );
+        assert!(Instance::default()
+            .podcast_feed(&YoutubeFeed::Channel("UCabc123".to_string()))
+            .is_err());
+    }
+}
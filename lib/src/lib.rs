@@ -5,6 +5,7 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
 
+pub mod chapters;
 pub mod config;
 pub mod invidious;
 pub mod library_db;
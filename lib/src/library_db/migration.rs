@@ -2,7 +2,7 @@ use anyhow::{bail, Context, Result};
 use rusqlite::Connection;
 
 /// The Current Database schema version this application is meant to run against
-pub(super) const DB_VERSION: u32 = 2;
+pub(super) const DB_VERSION: u32 = 3;
 /// The Lowest Database schema version this application supports migration up against
 ///
 /// Expection being "0" as that indicates a fresh database
@@ -68,6 +68,13 @@ fn apply_migrations(conn: &Connection, mut user_version: u32) -> Result<()> {
         user_version = set_user_version(conn, 2)?;
     }
 
+    if user_version == 2 {
+        // loudness analysis of the tracks
+        conn.execute_batch(include_str!("./migrations/003.sql"))
+            .context("Database version 3 could not be migrated")?;
+        user_version = set_user_version(conn, 3)?;
+    }
+
     Ok(())
 }
 
@@ -83,7 +90,7 @@ mod tests {
 
         assert_eq!(0, get_user_version(&conn).unwrap());
         migrate(&conn).unwrap();
-        assert_eq!(2, get_user_version(&conn).unwrap());
+        assert_eq!(DB_VERSION, get_user_version(&conn).unwrap());
 
         let all_tracks: Vec<String> = {
             let mut prep = conn.prepare("SELECT name FROM sqlite_schema WHERE type ='table' AND name NOT LIKE 'sqlite_%';").unwrap();
@@ -95,4 +102,27 @@ mod tests {
 
         assert_eq!(&all_tracks, &["tracks"]);
     }
+
+    #[test]
+    fn should_migrate_from_v2() {
+        let conn = gen_database();
+
+        conn.execute_batch(include_str!("./migrations/002.sql"))
+            .unwrap();
+        set_user_version(&conn, 2).unwrap();
+        conn.execute_batch(
+            "INSERT INTO tracks (file, duration, last_position) VALUES ('/music/a.mp3', 10, 0);",
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(DB_VERSION, get_user_version(&conn).unwrap());
+
+        let analyzed: (String, Option<f64>) = conn
+            .query_row("SELECT file, true_peak FROM tracks;", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(analyzed, ("/music/a.mp3".to_string(), None));
+    }
 }
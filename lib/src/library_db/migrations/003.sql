@@ -0,0 +1,5 @@
+-- the EBU R128 analysis of the tracks, "true_peak" is NULL if the track was not analyzed yet
+-- "loudness" is the integrated loudness in LUFS, NULL if the track is too short or silent
+ALTER TABLE tracks ADD COLUMN loudness REAL;
+-- linear sample amplitude
+ALTER TABLE tracks ADD COLUMN true_peak REAL;
@@ -27,7 +27,7 @@ use crate::track::Track;
 use crate::utils::{filetype_supported, get_app_config_path, get_pin_yin};
 use anyhow::Context;
 use parking_lot::Mutex;
-use rusqlite::{params, Connection, Error, Result};
+use rusqlite::{params, Connection, Error, OptionalExtension, Result};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, UNIX_EPOCH};
@@ -36,13 +36,34 @@ use track_db::TrackDBInsertable;
 mod migration;
 mod track_db;
 
-pub use track_db::{const_unknown, TrackDB};
+pub use track_db::{const_unknown, TrackDB, TrackLoudness};
 
+#[derive(Debug, Clone)]
 pub struct DataBase {
     conn: Arc<Mutex<Connection>>,
     max_depth: ScanDepth,
 }
 
+/// Limit the results of a query, for example to not send the whole library to a client at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Page {
+    /// The amount of results to skip
+    pub offset: u32,
+    /// The maximal amount of results to return, `0` means no limit
+    pub limit: u32,
+}
+
+impl Page {
+    /// Get the value for `LIMIT`, where a negative value means no limit
+    fn sql_limit(self) -> i64 {
+        if self.limit == 0 {
+            -1
+        } else {
+            i64::from(self.limit)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SearchCriteria {
     Artist,
@@ -66,6 +87,21 @@ impl From<usize> for SearchCriteria {
     }
 }
 
+/// Convert the `criteria` of the library gRPC requests, which only allows criterias that are columns in the database
+impl TryFrom<u32> for SearchCriteria {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> std::result::Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Artist,
+            1 => Self::Album,
+            2 => Self::Genre,
+            3 => Self::Directory,
+            _ => anyhow::bail!("Unknown library criteria: {value}"),
+        })
+    }
+}
+
 impl std::fmt::Display for SearchCriteria {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -88,6 +124,21 @@ impl DataBase {
         db_path.push("library.db");
         let conn = Connection::open(db_path).context("open/create database")?;
 
+        Self::from_connection(conn, config)
+    }
+
+    /// Create a database that only exists in memory, like for tests
+    ///
+    /// # Errors
+    ///
+    /// - if the database creation fails
+    pub fn new_in_memory(config: &ServerOverlay) -> anyhow::Result<Self> {
+        let conn = Connection::open_in_memory().context("open in-memory database")?;
+
+        Self::from_connection(conn, config)
+    }
+
+    fn from_connection(conn: Connection, config: &ServerOverlay) -> anyhow::Result<Self> {
         migration::migrate(&conn).context("Database creation / migration")?;
 
         let max_depth = config.get_library_scan_depth();
@@ -170,7 +221,9 @@ impl DataBase {
     }
 
     /// Synchronize the database with the on-disk paths (insert, update, remove), limited to `path` root
-    pub fn sync_database(&mut self, path: &Path) {
+    ///
+    /// The synchronization runs in the background, the returned handle can be used to wait for it.
+    pub fn sync_database(&mut self, path: &Path) -> std::thread::JoinHandle<Result<()>> {
         // add updated records
         let conn = self.conn.clone();
         let all_items = {
@@ -222,7 +275,7 @@ impl DataBase {
             }
 
             Ok(())
-        });
+        })
     }
 
     /// Get all Tracks in the database at once
@@ -260,6 +313,56 @@ impl DataBase {
         Ok(vec_records)
     }
 
+    /// Get the [`Page`] of Tracks by [`SearchCriteria`] ordered by name, and the amount of Tracks without paging
+    pub fn get_record_by_criteria_page(
+        &mut self,
+        criteria_val: &str,
+        criteria: &SearchCriteria,
+        page: Page,
+    ) -> Result<(Vec<TrackDB>, u32)> {
+        self.query_tracks_page(&format!("{criteria} = ?1"), criteria_val, page)
+    }
+
+    /// Get the [`Page`] of Tracks where the artist, title, album or file name contains `query`, case-insensitive (for ASCII),
+    /// ordered by name, and the amount of Tracks without paging
+    pub fn search_records(&mut self, query: &str, page: Page) -> Result<(Vec<TrackDB>, u32)> {
+        let filter = "artist LIKE ?1 ESCAPE '\\' OR title LIKE ?1 ESCAPE '\\' \
+            OR album LIKE ?1 ESCAPE '\\' OR name LIKE ?1 ESCAPE '\\'";
+        let pattern = format!("%{}%", escape_like(query));
+
+        self.query_tracks_page(filter, &pattern, page)
+    }
+
+    /// Get the [`Page`] of Tracks matching the condition `filter` with the parameter `?1`, and the amount of all matching Tracks
+    fn query_tracks_page(
+        &self,
+        filter: &str,
+        param: &str,
+        page: Page,
+    ) -> Result<(Vec<TrackDB>, u32)> {
+        let conn = self.conn.lock();
+
+        let total: u32 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM tracks WHERE {filter}"),
+            [param],
+            |row| row.get(0),
+        )?;
+
+        let search_str = format!(
+            "SELECT * FROM tracks WHERE {filter} ORDER BY name COLLATE NOCASE LIMIT ?2 OFFSET ?3"
+        );
+        let mut stmt = conn.prepare(&search_str)?;
+        let vec_records: Vec<TrackDB> = stmt
+            .query_map(
+                params![param, page.sql_limit(), page.offset],
+                TrackDB::try_from_row_named,
+            )?
+            .flatten()
+            .collect();
+
+        Ok((vec_records, total))
+    }
+
     /// Get a list of available distinct [`SearchCriteria`] (ie get Artist names deduplicated)
     pub fn get_criterias(&mut self, criteria: &SearchCriteria) -> Result<Vec<String>> {
         let search_str = format!("SELECT DISTINCT {criteria} FROM tracks");
@@ -278,6 +381,33 @@ impl DataBase {
         Ok(vec)
     }
 
+    /// Get the [`Page`] of distinct [`SearchCriteria`] values in order, and the amount of values without paging
+    pub fn get_criterias_page(
+        &mut self,
+        criteria: &SearchCriteria,
+        page: Page,
+    ) -> Result<(Vec<String>, u32)> {
+        let conn = self.conn.lock();
+
+        let total: u32 = conn.query_row(
+            &format!("SELECT COUNT(DISTINCT {criteria}) FROM tracks"),
+            [],
+            |row| row.get(0),
+        )?;
+
+        let search_str = format!(
+            "SELECT DISTINCT {criteria} FROM tracks \
+            ORDER BY {criteria} COLLATE NOCASE LIMIT ?1 OFFSET ?2"
+        );
+        let mut stmt = conn.prepare(&search_str)?;
+        let vec: Vec<String> = stmt
+            .query_map(params![page.sql_limit(), page.offset], |row| row.get(0))?
+            .flatten()
+            .collect();
+
+        Ok((vec, total))
+    }
+
     /// Get the stored `last_position` of a given track
     pub fn get_last_position(&mut self, track: &Track) -> Result<Duration> {
         let filename = track
@@ -326,6 +456,191 @@ impl DataBase {
 
         Err(Error::QueryReturnedNoRows)
     }
+
+    /// Get the analyzed loudness of the track at the full file path, [`None`] if not analyzed yet
+    pub fn get_loudness(&mut self, file_path: &str) -> Result<Option<TrackLoudness>> {
+        let search_str =
+            "SELECT loudness, true_peak FROM tracks WHERE file = ? AND true_peak IS NOT NULL";
+        let conn = self.conn.lock();
+        conn.query_row(search_str, [file_path], |row| {
+            Ok(TrackLoudness {
+                integrated: row.get(0)?,
+                true_peak: row.get(1)?,
+            })
+        })
+        .optional()
+    }
+
+    /// Store the analyzed loudness of the track at the full file path
+    pub fn set_loudness(&mut self, file_path: &str, loudness: TrackLoudness) -> Result<()> {
+        let query = "UPDATE tracks SET loudness = ?1, true_peak = ?2 WHERE file = ?3";
+        let conn = self.conn.lock();
+        conn.execute(
+            query,
+            params![loudness.integrated, loudness.true_peak, file_path],
+        )?;
+        Ok(())
+    }
+
+    /// Get the full file paths of all tracks that still need a loudness analysis, or of all tracks if `all` is set
+    pub fn get_files_to_analyze(&mut self, all: bool) -> Result<Vec<String>> {
+        let search_str = if all {
+            "SELECT DISTINCT file FROM tracks ORDER BY file"
+        } else {
+            "SELECT DISTINCT file FROM tracks WHERE true_peak IS NULL ORDER BY file"
+        };
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(search_str)?;
+
+        let vec: Vec<String> = stmt.query_map([], |row| row.get(0))?.flatten().collect();
+        Ok(vec)
+    }
+}
+
+/// Escape the special characters of a `LIKE` pattern, expecting `\\` as the escape character
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for char in value.chars() {
+        if matches!(char, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::track_db::TrackDBInsertable;
+    use super::{escape_like, DataBase, Page, SearchCriteria, TrackLoudness};
+    use crate::config::ServerOverlay;
+
+    /// Create a in-memory database with a track for each `(artist, title, name)`
+    fn db_with_tracks(tracks: &[(&str, &str, &str)]) -> DataBase {
+        let db = DataBase::new_in_memory(&ServerOverlay::default()).unwrap();
+        {
+            let conn = db.conn.lock();
+            for &(artist, title, name) in tracks {
+                TrackDBInsertable {
+                    artist,
+                    title,
+                    album: "empty",
+                    genre: "no type",
+                    file: name,
+                    duration: Duration::from_secs(10),
+                    name,
+                    ext: "mp3",
+                    directory: "/music",
+                    last_modified: "0".to_string(),
+                    last_position: Duration::ZERO,
+                }
+                .insert_track(&conn)
+                .unwrap();
+            }
+        }
+
+        db
+    }
+
+    #[test]
+    fn should_search_records() {
+        let mut db = db_with_tracks(&[
+            ("Some Artist", "First", "first.mp3"),
+            ("Other", "Second 100%", "second.mp3"),
+            ("Other", "Third", "some_file.mp3"),
+        ]);
+
+        let names = |query: &str, db: &mut DataBase| -> Vec<String> {
+            db.search_records(query, Page::default())
+                .unwrap()
+                .0
+                .into_iter()
+                .map(|v| v.name)
+                .collect()
+        };
+
+        assert_eq!(names("some", &mut db), ["first.mp3", "some_file.mp3"]);
+        assert_eq!(names("OTHER", &mut db), ["second.mp3", "some_file.mp3"]);
+        assert_eq!(names("0%", &mut db), ["second.mp3"]);
+        assert_eq!(names("e_f", &mut db), ["some_file.mp3"]);
+        assert!(names("nothing", &mut db).is_empty());
+    }
+
+    #[test]
+    fn should_page_records() {
+        let mut db = db_with_tracks(&[
+            ("B Artist", "First", "b.mp3"),
+            ("a Artist", "Second", "C.mp3"),
+            ("B Artist", "Third", "a.mp3"),
+        ]);
+        let page = |offset, limit| Page { offset, limit };
+
+        let (tracks, total) = db
+            .get_record_by_criteria_page("B Artist", &SearchCriteria::Artist, page(1, 1))
+            .unwrap();
+        let names: Vec<_> = tracks.into_iter().map(|v| v.name).collect();
+        assert_eq!((names, total), (vec!["b.mp3".to_string()], 2));
+
+        let (tracks, total) = db.search_records("artist", page(1, 0)).unwrap();
+        let names: Vec<_> = tracks.into_iter().map(|v| v.name).collect();
+        assert_eq!(
+            (names, total),
+            (vec!["b.mp3".to_string(), "C.mp3".to_string()], 3)
+        );
+
+        assert_eq!(
+            db.get_criterias_page(&SearchCriteria::Artist, Page::default())
+                .unwrap(),
+            (vec!["a Artist".to_string(), "B Artist".to_string()], 2)
+        );
+        assert_eq!(
+            db.get_criterias_page(&SearchCriteria::Artist, page(5, 2))
+                .unwrap(),
+            (Vec::new(), 2)
+        );
+    }
+
+    #[test]
+    fn should_store_loudness() {
+        let mut db = db_with_tracks(&[
+            ("Some Artist", "First", "first.mp3"),
+            ("Other", "Second", "second.mp3"),
+        ]);
+
+        assert_eq!(db.get_loudness("first.mp3").unwrap(), None);
+        assert_eq!(
+            db.get_files_to_analyze(false).unwrap(),
+            ["first.mp3", "second.mp3"]
+        );
+
+        let loudness = TrackLoudness {
+            integrated: Some(-9.5),
+            true_peak: 1.02,
+        };
+        db.set_loudness("first.mp3", loudness).unwrap();
+        let silent = TrackLoudness {
+            integrated: None,
+            true_peak: 0.0,
+        };
+        db.set_loudness("second.mp3", silent).unwrap();
+
+        assert_eq!(db.get_loudness("first.mp3").unwrap(), Some(loudness));
+        assert_eq!(db.get_loudness("second.mp3").unwrap(), Some(silent));
+        assert!(db.get_files_to_analyze(false).unwrap().is_empty());
+        assert_eq!(
+            db.get_files_to_analyze(true).unwrap(),
+            ["first.mp3", "second.mp3"]
+        );
+    }
+
+    #[test]
+    fn should_escape_like() {
+        assert_eq!(escape_like("a_b%c\\d"), "a\\_b\\%c\\\\d");
+        assert_eq!(escape_like("plain"), "plain");
+    }
 }
 
 #[cfg(test)]
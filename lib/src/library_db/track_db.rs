@@ -66,6 +66,15 @@ impl TrackDB {
     }
 }
 
+/// The EBU R128 loudness of a track, as stored in the database by the loudness analysis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackLoudness {
+    /// Integrated loudness in LUFS, [`None`] if the track is too short or silent to be measured
+    pub integrated: Option<f64>,
+    /// True peak as linear sample amplitude, can be above `1.0`
+    pub true_peak: f64,
+}
+
 /// A struct representing a [`Track`](Track) in the database to be inserted
 ///
 /// This is required as some fields are auto-generated by the database compared to [`TrackDB`]
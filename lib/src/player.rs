@@ -1,5 +1,9 @@
 #![allow(clippy::module_name_repetitions)]
 use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::config::v2::server::{EqualizerBand, EqualizerSettings, LoopMode};
+use crate::track::MediaType;
 
 // using lower mod to restrict clippy
 #[allow(clippy::pedantic)]
@@ -9,6 +13,8 @@ mod protobuf {
 
 pub use protobuf::*;
 
+pub mod auth;
+
 // implement transform function for easy use
 impl From<protobuf::Duration> for std::time::Duration {
     fn from(value: protobuf::Duration) -> Self {
@@ -64,15 +70,106 @@ pub struct TrackChangedInfo {
     pub title: Option<String>,
     /// Current progress of the track
     pub progress: Option<PlayerProgress>,
+    /// Metadata of the current track
+    pub track: Option<protobuf::Track>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum UpdateEvents {
-    MissedEvents { amount: u64 },
-    VolumeChanged { volume: u16 },
-    SpeedChanged { speed: i32 },
-    PlayStateChanged { playing: u32 },
+    MissedEvents {
+        amount: u64,
+    },
+    VolumeChanged {
+        volume: u16,
+    },
+    SpeedChanged {
+        speed: i32,
+    },
+    PlayStateChanged {
+        playing: u32,
+    },
     TrackChanged(TrackChangedInfo),
+    PlaylistChanged(UpdatePlaylistEvents),
+    LoopModeChanged {
+        mode: LoopMode,
+    },
+    GaplessChanged {
+        gapless: bool,
+    },
+    PodcastFeedChanged {
+        url: String,
+        state: PodcastFeedState,
+    },
+    PodcastDownload(PodcastDownloadInfo),
+}
+
+/// State of a podcast feed, as send by [`UpdateEvents::PodcastFeedChanged`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PodcastFeedState {
+    SyncStarted,
+    Synced,
+    SyncFailed,
+    Removed,
+}
+
+impl PodcastFeedState {
+    #[must_use]
+    pub fn as_u32(self) -> u32 {
+        match self {
+            Self::SyncStarted => 0,
+            Self::Synced => 1,
+            Self::SyncFailed => 2,
+            Self::Removed => 3,
+        }
+    }
+}
+
+impl TryFrom<u32> for PodcastFeedState {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::SyncStarted,
+            1 => Self::Synced,
+            2 => Self::SyncFailed,
+            3 => Self::Removed,
+            _ => anyhow::bail!("Unknown podcast feed state: {value}"),
+        })
+    }
+}
+
+/// Progress of a podcast episode download, as send by [`UpdateEvents::PodcastDownload`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PodcastDownloadInfo {
+    pub episode_id: i64,
+    /// Url of the episode
+    pub url: String,
+    pub title: String,
+    pub state: PodcastDownloadState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PodcastDownloadState {
+    Started,
+    /// Downloaded bytes and the total bytes, if known
+    Progress {
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    Completed,
+    Failed,
+}
+
+/// Changes to the playlist, as send by [`UpdateEvents::PlaylistChanged`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdatePlaylistEvents {
+    AddTrack(PlaylistAddTrack),
+    RemoveTrack(PlaylistRemoveTrack),
+    SwapTracks(PlaylistSwapTrack),
+    MoveTrack(PlaylistMoveTrack),
+    Cleared,
+    /// The whole playlist got replaced, like after a shuffle
+    Reloaded(PlaylistTracks),
 }
 
 type StreamTypes = protobuf::stream_updates::Type;
@@ -106,7 +203,32 @@ impl From<UpdateEvents> for protobuf::StreamUpdates {
                     .title
                     .map(protobuf::update_track_changed::OptionalTitle::Title),
                 progress: info.progress.map(Into::into),
+                track: info.track,
             }),
+            UpdateEvents::PlaylistChanged(ev) => {
+                StreamTypes::PlaylistChanged(UpdatePlaylistChanged {
+                    r#type: Some(ev.into()),
+                })
+            }
+            UpdateEvents::LoopModeChanged { mode } => {
+                StreamTypes::LoopModeChanged(UpdateLoopModeChanged {
+                    msg: Some(LoopModeReply {
+                        mode: mode.as_u32(),
+                    }),
+                })
+            }
+            UpdateEvents::GaplessChanged { gapless } => {
+                StreamTypes::GaplessChanged(UpdateGaplessChanged {
+                    msg: Some(ToggleGaplessReply { gapless }),
+                })
+            }
+            UpdateEvents::PodcastFeedChanged { url, state } => {
+                StreamTypes::PodcastFeedChanged(UpdatePodcastFeedChanged {
+                    url,
+                    state: state.as_u32(),
+                })
+            }
+            UpdateEvents::PodcastDownload(info) => StreamTypes::PodcastDownload(info.into()),
         };
 
         Self { r#type: Some(val) }
@@ -141,13 +263,621 @@ impl TryFrom<protobuf::StreamUpdates> for UpdateEvents {
                     v
                 }),
                 progress: ev.progress.map(Into::into),
+                track: ev.track,
             }),
+            stream_updates::Type::PlaylistChanged(ev) => Self::PlaylistChanged(
+                unwrap_msg(ev.r#type, "StreamUpdates.types.playlist_changed.type")?.try_into()?,
+            ),
+            stream_updates::Type::LoopModeChanged(ev) => Self::LoopModeChanged {
+                mode: LoopMode::from_u32(
+                    unwrap_msg(ev.msg, "StreamUpdates.types.loop_mode_changed.msg")?.mode,
+                ),
+            },
+            stream_updates::Type::GaplessChanged(ev) => Self::GaplessChanged {
+                gapless: unwrap_msg(ev.msg, "StreamUpdates.types.gapless_changed.msg")?.gapless,
+            },
+            stream_updates::Type::PodcastFeedChanged(ev) => Self::PodcastFeedChanged {
+                url: ev.url,
+                state: ev.state.try_into()?,
+            },
+            stream_updates::Type::PodcastDownload(ev) => Self::PodcastDownload(ev.try_into()?),
+        };
+
+        Ok(res)
+    }
+}
+
+impl From<PodcastDownloadInfo> for UpdatePodcastDownload {
+    fn from(value: PodcastDownloadInfo) -> Self {
+        let (state, downloaded, total) = match value.state {
+            PodcastDownloadState::Started => (0, 0, None),
+            PodcastDownloadState::Progress { downloaded, total } => (1, downloaded, total),
+            PodcastDownloadState::Completed => (2, 0, None),
+            PodcastDownloadState::Failed => (3, 0, None),
+        };
+
+        Self {
+            episode_id: value.episode_id,
+            url: value.url,
+            title: value.title,
+            state,
+            downloaded,
+            optional_total: total.map(protobuf::update_podcast_download::OptionalTotal::Total),
+        }
+    }
+}
+
+impl TryFrom<UpdatePodcastDownload> for PodcastDownloadInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UpdatePodcastDownload) -> Result<Self, Self::Error> {
+        let state = match value.state {
+            0 => PodcastDownloadState::Started,
+            1 => PodcastDownloadState::Progress {
+                downloaded: value.downloaded,
+                total: value.optional_total.map(|v| {
+                    let protobuf::update_podcast_download::OptionalTotal::Total(v) = v;
+                    v
+                }),
+            },
+            2 => PodcastDownloadState::Completed,
+            3 => PodcastDownloadState::Failed,
+            state => anyhow::bail!("Unknown podcast download state: {state}"),
+        };
+
+        Ok(Self {
+            episode_id: value.episode_id,
+            url: value.url,
+            title: value.title,
+            state,
+        })
+    }
+}
+
+type PlaylistChangedTypes = protobuf::update_playlist_changed::Type;
+
+impl From<UpdatePlaylistEvents> for PlaylistChangedTypes {
+    fn from(value: UpdatePlaylistEvents) -> Self {
+        match value {
+            UpdatePlaylistEvents::AddTrack(info) => Self::AddTrack(info.into()),
+            UpdatePlaylistEvents::RemoveTrack(info) => Self::RemoveTrack(info.into()),
+            UpdatePlaylistEvents::SwapTracks(info) => Self::SwapTracks(info.into()),
+            UpdatePlaylistEvents::MoveTrack(info) => Self::MoveTrack(info.into()),
+            UpdatePlaylistEvents::Cleared => Self::Cleared(PlaylistCleared {}),
+            UpdatePlaylistEvents::Reloaded(tracks) => Self::Reloaded(tracks),
+        }
+    }
+}
+
+impl TryFrom<PlaylistChangedTypes> for UpdatePlaylistEvents {
+    type Error = anyhow::Error;
+
+    fn try_from(value: PlaylistChangedTypes) -> Result<Self, Self::Error> {
+        let res = match value {
+            PlaylistChangedTypes::AddTrack(info) => Self::AddTrack(info.try_into()?),
+            PlaylistChangedTypes::RemoveTrack(info) => Self::RemoveTrack(info.into()),
+            PlaylistChangedTypes::SwapTracks(info) => Self::SwapTracks(info.into()),
+            PlaylistChangedTypes::MoveTrack(info) => Self::MoveTrack(info.into()),
+            PlaylistChangedTypes::Cleared(_) => Self::Cleared,
+            PlaylistChangedTypes::Reloaded(tracks) => Self::Reloaded(tracks),
         };
 
         Ok(res)
     }
 }
 
+/// Identify a track for playlist operations, without all the metadata a [`Track`](crate::track::Track) has
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlaylistTrackSource {
+    /// A local file path
+    Path(String),
+    /// A remote url, like a radio stream
+    Url(String),
+    /// The url of a podcast episode, looked up in the podcast database
+    PodcastUrl(String),
+}
+
+impl PlaylistTrackSource {
+    /// Get the source a given [`Track`](crate::track::Track) can be identified with
+    #[must_use]
+    pub fn from_track(track: &crate::track::Track) -> Option<Self> {
+        let file = track.file()?.to_string();
+        let source = match track.media_type {
+            MediaType::Music => Self::Path(file),
+            MediaType::LiveRadio => Self::Url(file),
+            MediaType::Podcast => Self::PodcastUrl(file),
+        };
+
+        Some(source)
+    }
+}
+
+impl From<PlaylistTrackSource> for protobuf::TrackId {
+    fn from(value: PlaylistTrackSource) -> Self {
+        let source = match value {
+            PlaylistTrackSource::Path(v) => protobuf::track_id::Source::Path(v),
+            PlaylistTrackSource::Url(v) => protobuf::track_id::Source::Url(v),
+            PlaylistTrackSource::PodcastUrl(v) => protobuf::track_id::Source::PodcastUrl(v),
+        };
+
+        Self {
+            source: Some(source),
+        }
+    }
+}
+
+impl TryFrom<protobuf::TrackId> for PlaylistTrackSource {
+    type Error = anyhow::Error;
+
+    fn try_from(value: protobuf::TrackId) -> Result<Self, Self::Error> {
+        let res = match unwrap_msg(value.source, "TrackId.source")? {
+            protobuf::track_id::Source::Path(v) => Self::Path(v),
+            protobuf::track_id::Source::Url(v) => Self::Url(v),
+            protobuf::track_id::Source::PodcastUrl(v) => Self::PodcastUrl(v),
+        };
+
+        Ok(res)
+    }
+}
+
+// mainly for server to grpc
+impl From<&crate::track::Track> for protobuf::Track {
+    fn from(value: &crate::track::Track) -> Self {
+        Self {
+            id: PlaylistTrackSource::from_track(value).map(Into::into),
+            optional_artist: value
+                .artist()
+                .map(|v| protobuf::track::OptionalArtist::Artist(v.to_string())),
+            optional_album: value
+                .album()
+                .map(|v| protobuf::track::OptionalAlbum::Album(v.to_string())),
+            optional_title: value
+                .title()
+                .map(|v| protobuf::track::OptionalTitle::Title(v.to_string())),
+            optional_genre: value
+                .genre()
+                .map(|v| protobuf::track::OptionalGenre::Genre(v.to_string())),
+            duration: Some(value.duration().into()),
+            has_cover: value.picture().is_some() || value.album_photo().is_some(),
+            chapters: value.chapters().iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&crate::chapters::Chapter> for protobuf::Chapter {
+    fn from(value: &crate::chapters::Chapter) -> Self {
+        Self {
+            start: Some(value.start.into()),
+            title: value.title.clone(),
+        }
+    }
+}
+
+impl protobuf::Track {
+    pub fn artist(&self) -> Option<&str> {
+        self.optional_artist.as_ref().map(|v| match v {
+            protobuf::track::OptionalArtist::Artist(v) => v.as_str(),
+        })
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.optional_album.as_ref().map(|v| match v {
+            protobuf::track::OptionalAlbum::Album(v) => v.as_str(),
+        })
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.optional_title.as_ref().map(|v| match v {
+            protobuf::track::OptionalTitle::Title(v) => v.as_str(),
+        })
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.optional_genre.as_ref().map(|v| match v {
+            protobuf::track::OptionalGenre::Genre(v) => v.as_str(),
+        })
+    }
+}
+
+// mainly for server to grpc
+impl From<crate::library_db::TrackDB> for protobuf::LibraryTrack {
+    fn from(value: crate::library_db::TrackDB) -> Self {
+        Self {
+            id: value.id,
+            artist: value.artist,
+            title: value.title,
+            album: value.album,
+            genre: value.genre,
+            file: value.file,
+            duration: Some(value.duration.into()),
+            name: value.name,
+            ext: value.ext,
+            directory: value.directory,
+            last_modified: value.last_modified,
+            last_position: Some(value.last_position.into()),
+        }
+    }
+}
+
+// mainly for grpc to client
+impl From<protobuf::LibraryTrack> for crate::library_db::TrackDB {
+    fn from(value: protobuf::LibraryTrack) -> Self {
+        Self {
+            id: value.id,
+            artist: value.artist,
+            title: value.title,
+            album: value.album,
+            genre: value.genre,
+            file: value.file,
+            duration: value.duration.map(Into::into).unwrap_or_default(),
+            name: value.name,
+            ext: value.ext,
+            directory: value.directory,
+            last_modified: value.last_modified,
+            last_position: value.last_position.map(Into::into).unwrap_or_default(),
+        }
+    }
+}
+
+// mainly for grpc to server
+impl From<protobuf::LibraryPage> for crate::library_db::Page {
+    fn from(value: protobuf::LibraryPage) -> Self {
+        Self {
+            offset: value.offset,
+            limit: value.limit,
+        }
+    }
+}
+
+// mainly for server to grpc
+impl From<&crate::podcast::Podcast> for protobuf::PodcastInfo {
+    fn from(value: &crate::podcast::Podcast) -> Self {
+        // protobuf does not support usize directly
+        #[allow(clippy::cast_possible_truncation)]
+        Self {
+            id: value.id,
+            title: value.title.clone(),
+            url: value.url.clone(),
+            optional_description: value
+                .description
+                .clone()
+                .map(protobuf::podcast_info::OptionalDescription::Description),
+            optional_author: value
+                .author
+                .clone()
+                .map(protobuf::podcast_info::OptionalAuthor::Author),
+            optional_image_url: value
+                .image_url
+                .clone()
+                .map(protobuf::podcast_info::OptionalImageUrl::ImageUrl),
+            last_checked: value.last_checked.timestamp(),
+            num_episodes: value.episodes.len() as u32,
+            num_unplayed: value.num_unplayed() as u32,
+            settings: Some(value.settings.into()),
+        }
+    }
+}
+
+impl From<crate::podcast::FeedSettings> for protobuf::PodcastFeedSettings {
+    fn from(value: crate::podcast::FeedSettings) -> Self {
+        use protobuf::podcast_feed_settings::{
+            OptionalAutoDownload, OptionalDeletePlayedAfterDays, OptionalKeepDownloaded,
+            OptionalPlaybackSpeed,
+        };
+
+        Self {
+            optional_auto_download: value.auto_download.map(OptionalAutoDownload::AutoDownload),
+            optional_keep_downloaded: value
+                .keep_downloaded
+                .map(OptionalKeepDownloaded::KeepDownloaded),
+            optional_delete_played_after_days: value
+                .delete_played_after_days
+                .map(OptionalDeletePlayedAfterDays::DeletePlayedAfterDays),
+            optional_playback_speed: value
+                .playback_speed
+                .map(OptionalPlaybackSpeed::PlaybackSpeed),
+        }
+    }
+}
+
+impl From<protobuf::PodcastFeedSettings> for crate::podcast::FeedSettings {
+    fn from(value: protobuf::PodcastFeedSettings) -> Self {
+        use protobuf::podcast_feed_settings::{
+            OptionalAutoDownload, OptionalDeletePlayedAfterDays, OptionalKeepDownloaded,
+            OptionalPlaybackSpeed,
+        };
+
+        Self {
+            auto_download: value
+                .optional_auto_download
+                .map(|OptionalAutoDownload::AutoDownload(v)| v),
+            keep_downloaded: value
+                .optional_keep_downloaded
+                .map(|OptionalKeepDownloaded::KeepDownloaded(v)| v),
+            delete_played_after_days: value
+                .optional_delete_played_after_days
+                .map(|OptionalDeletePlayedAfterDays::DeletePlayedAfterDays(v)| v),
+            playback_speed: value
+                .optional_playback_speed
+                .map(|OptionalPlaybackSpeed::PlaybackSpeed(v)| v),
+        }
+    }
+}
+
+// mainly for server to grpc
+impl From<&crate::podcast::episode::Episode> for protobuf::EpisodeInfo {
+    fn from(value: &crate::podcast::episode::Episode) -> Self {
+        Self {
+            id: value.id,
+            podcast_id: value.pod_id,
+            title: value.title.clone(),
+            url: value.url.clone(),
+            description: value.description.clone(),
+            optional_pubdate: value
+                .pubdate
+                .map(|v| protobuf::episode_info::OptionalPubdate::Pubdate(v.timestamp())),
+            optional_duration: value
+                .duration
+                .map(protobuf::episode_info::OptionalDuration::Duration),
+            optional_path: value.path.as_ref().map(|v| {
+                protobuf::episode_info::OptionalPath::Path(v.to_string_lossy().to_string())
+            }),
+            played: value.played,
+        }
+    }
+}
+
+// mainly for grpc to client, the episodes have to be requested separately
+impl From<protobuf::PodcastInfo> for crate::podcast::Podcast {
+    fn from(value: protobuf::PodcastInfo) -> Self {
+        use protobuf::podcast_info::{OptionalAuthor, OptionalDescription, OptionalImageUrl};
+
+        let sort_title = crate::podcast::RE_ARTICLES
+            .replace(&value.title.to_lowercase(), "")
+            .to_string();
+
+        Self {
+            id: value.id,
+            title: value.title,
+            sort_title,
+            url: value.url,
+            description: value
+                .optional_description
+                .map(|OptionalDescription::Description(v)| v),
+            author: value.optional_author.map(|OptionalAuthor::Author(v)| v),
+            explicit: None,
+            last_checked: chrono::DateTime::from_timestamp(value.last_checked, 0)
+                .unwrap_or_default(),
+            episodes: Vec::new(),
+            image_url: value
+                .optional_image_url
+                .map(|OptionalImageUrl::ImageUrl(v)| v),
+            settings: value.settings.unwrap_or_default().into(),
+            validators: crate::podcast::FeedValidators::default(),
+        }
+    }
+}
+
+// mainly for grpc to client
+impl From<protobuf::EpisodeInfo> for crate::podcast::episode::Episode {
+    fn from(value: protobuf::EpisodeInfo) -> Self {
+        use protobuf::episode_info::{OptionalDuration, OptionalPath, OptionalPubdate};
+
+        Self {
+            id: value.id,
+            pod_id: value.podcast_id,
+            title: value.title,
+            url: value.url,
+            guid: String::new(),
+            description: value.description,
+            pubdate: value
+                .optional_pubdate
+                .and_then(|OptionalPubdate::Pubdate(v)| chrono::DateTime::from_timestamp(v, 0)),
+            duration: value
+                .optional_duration
+                .map(|OptionalDuration::Duration(v)| v),
+            path: value
+                .optional_path
+                .map(|OptionalPath::Path(v)| std::path::PathBuf::from(v)),
+            played: value.played,
+            played_at: None,
+            last_position: None,
+            image_url: None,
+            enclosure_length: None,
+            chapters_url: None,
+            chapters: Vec::new(),
+            transcript_url: None,
+        }
+    }
+}
+
+/// Add tracks to the playlist at a given position
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistAddTrack {
+    /// Index to insert the tracks at, values above the playlist length append
+    pub at_index: u32,
+    pub tracks: Vec<PlaylistTrackSource>,
+}
+
+impl PlaylistAddTrack {
+    /// Create a new instance to add a single track
+    pub fn new_single(at_index: u32, track: PlaylistTrackSource) -> Self {
+        Self {
+            at_index,
+            tracks: vec![track],
+        }
+    }
+}
+
+impl From<PlaylistAddTrack> for protobuf::PlaylistTracksToAdd {
+    fn from(value: PlaylistAddTrack) -> Self {
+        Self {
+            at_index: value.at_index,
+            tracks: value.tracks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<protobuf::PlaylistTracksToAdd> for PlaylistAddTrack {
+    type Error = anyhow::Error;
+
+    fn try_from(value: protobuf::PlaylistTracksToAdd) -> Result<Self, Self::Error> {
+        Ok(Self {
+            at_index: value.at_index,
+            tracks: value
+                .tracks
+                .into_iter()
+                .map(PlaylistTrackSource::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Remove a range of tracks from the playlist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistRemoveTrack {
+    /// The first index to remove
+    pub at_index: u32,
+    /// The amount of tracks to remove, starting at `at_index`
+    pub amount: u32,
+}
+
+impl From<PlaylistRemoveTrack> for protobuf::PlaylistTracksToRemove {
+    fn from(value: PlaylistRemoveTrack) -> Self {
+        Self {
+            at_index: value.at_index,
+            amount: value.amount,
+        }
+    }
+}
+
+impl From<protobuf::PlaylistTracksToRemove> for PlaylistRemoveTrack {
+    fn from(value: protobuf::PlaylistTracksToRemove) -> Self {
+        Self {
+            at_index: value.at_index,
+            amount: value.amount,
+        }
+    }
+}
+
+/// Swap the position of two tracks in the playlist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistSwapTrack {
+    pub index_a: u32,
+    pub index_b: u32,
+}
+
+impl From<PlaylistSwapTrack> for protobuf::PlaylistSwapTracks {
+    fn from(value: PlaylistSwapTrack) -> Self {
+        Self {
+            index_a: value.index_a,
+            index_b: value.index_b,
+        }
+    }
+}
+
+impl From<protobuf::PlaylistSwapTracks> for PlaylistSwapTrack {
+    fn from(value: protobuf::PlaylistSwapTracks) -> Self {
+        Self {
+            index_a: value.index_a,
+            index_b: value.index_b,
+        }
+    }
+}
+
+/// Move a track in the playlist, shifting the tracks between the two indexes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistMoveTrack {
+    pub from_index: u32,
+    /// The index of the track after the move
+    pub to_index: u32,
+}
+
+impl From<PlaylistMoveTrack> for protobuf::PlaylistMoveTracks {
+    fn from(value: PlaylistMoveTrack) -> Self {
+        Self {
+            from_index: value.from_index,
+            to_index: value.to_index,
+        }
+    }
+}
+
+impl From<protobuf::PlaylistMoveTracks> for PlaylistMoveTrack {
+    fn from(value: protobuf::PlaylistMoveTracks) -> Self {
+        Self {
+            from_index: value.from_index,
+            to_index: value.to_index,
+        }
+    }
+}
+
+/// Enable or disable the equalizer and activate a preset, see [`EqualizerSettings::set`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EqualizerSet {
+    pub enabled: bool,
+    pub preset: String,
+    /// Replaces the bands of the preset, empty keeps its bands
+    pub bands: Vec<EqualizerBand>,
+}
+
+impl From<EqualizerSet> for protobuf::SetEqualizerRequest {
+    fn from(value: EqualizerSet) -> Self {
+        Self {
+            enabled: value.enabled,
+            preset: value.preset,
+            bands: value.bands.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<protobuf::SetEqualizerRequest> for EqualizerSet {
+    fn from(value: protobuf::SetEqualizerRequest) -> Self {
+        Self {
+            enabled: value.enabled,
+            preset: value.preset,
+            bands: value.bands.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<EqualizerBand> for protobuf::EqualizerBand {
+    fn from(value: EqualizerBand) -> Self {
+        Self {
+            frequency: value.frequency,
+            gain: value.gain,
+            q: value.q,
+        }
+    }
+}
+
+impl From<protobuf::EqualizerBand> for EqualizerBand {
+    fn from(value: protobuf::EqualizerBand) -> Self {
+        Self {
+            frequency: value.frequency,
+            gain: value.gain,
+            q: value.q,
+        }
+    }
+}
+
+impl From<&EqualizerSettings> for protobuf::EqualizerReply {
+    fn from(value: &EqualizerSettings) -> Self {
+        let bands = value
+            .presets
+            .iter()
+            .find(|v| v.name == value.preset)
+            .map(|v| v.bands.iter().copied().map(Into::into).collect())
+            .unwrap_or_default();
+
+        Self {
+            enabled: value.enabled,
+            preset: value.preset.clone(),
+            bands,
+            presets: value.presets.iter().map(|v| v.name.clone()).collect(),
+        }
+    }
+}
+
 /// Easily unwrap a given grpc option and covert it to a result, with a location on None
 fn unwrap_msg<T>(opt: Option<T>, place: &str) -> Result<T, anyhow::Error> {
     match opt {
@@ -160,3 +890,139 @@ fn unwrap_msg<T>(opt: Option<T>, place: &str) -> Result<T, anyhow::Error> {
 fn clamp_u16(val: u32) -> u16 {
     val.min(u32::from(u16::MAX)) as u16
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_convert_radio_track() {
+        let track = crate::track::Track::new_radio("http://radio.example");
+        let grpc = protobuf::Track::from(&track);
+
+        assert_eq!(grpc.title(), Some("Radio Station"));
+        assert_eq!(grpc.genre(), None);
+        assert_eq!(
+            grpc.id
+                .map(PlaylistTrackSource::try_from)
+                .transpose()
+                .unwrap(),
+            Some(PlaylistTrackSource::Url("http://radio.example".to_string()))
+        );
+        assert_eq!(
+            grpc.optional_title,
+            Some(protobuf::track::OptionalTitle::Title(
+                "Radio Station".to_string()
+            ))
+        );
+        assert_eq!(grpc.optional_genre, None);
+        assert!(!grpc.has_cover);
+    }
+
+    #[test]
+    fn should_roundtrip_podcast_events() {
+        let events = [
+            UpdateEvents::PodcastFeedChanged {
+                url: "http://feed.example".to_string(),
+                state: PodcastFeedState::SyncFailed,
+            },
+            UpdateEvents::PodcastDownload(PodcastDownloadInfo {
+                episode_id: 3,
+                url: "http://feed.example/ep.mp3".to_string(),
+                title: "Episode".to_string(),
+                state: PodcastDownloadState::Progress {
+                    downloaded: 10,
+                    total: Some(100),
+                },
+            }),
+            UpdateEvents::PodcastDownload(PodcastDownloadInfo {
+                episode_id: 3,
+                url: "http://feed.example/ep.mp3".to_string(),
+                title: "Episode".to_string(),
+                state: PodcastDownloadState::Completed,
+            }),
+        ];
+
+        for ev in events {
+            let grpc = protobuf::StreamUpdates::from(ev.clone());
+            assert_eq!(UpdateEvents::try_from(grpc).unwrap(), ev);
+        }
+    }
+
+    #[test]
+    fn should_roundtrip_podcast_info() {
+        let podcast = protobuf::PodcastInfo {
+            id: 2,
+            title: "The Show".to_string(),
+            url: "http://feed.example".to_string(),
+            optional_description: None,
+            optional_author: Some(protobuf::podcast_info::OptionalAuthor::Author(
+                "Someone".to_string(),
+            )),
+            optional_image_url: None,
+            last_checked: 100,
+            num_episodes: 0,
+            num_unplayed: 0,
+            settings: Some(protobuf::PodcastFeedSettings::default()),
+        };
+        let converted = crate::podcast::Podcast::from(podcast.clone());
+        assert_eq!(converted.sort_title, "show");
+        assert_eq!(protobuf::PodcastInfo::from(&converted), podcast);
+
+        let episode = protobuf::EpisodeInfo {
+            id: 3,
+            podcast_id: 2,
+            title: "Episode".to_string(),
+            url: "http://feed.example/ep.mp3".to_string(),
+            description: "Some description".to_string(),
+            optional_pubdate: Some(protobuf::episode_info::OptionalPubdate::Pubdate(200)),
+            optional_duration: Some(protobuf::episode_info::OptionalDuration::Duration(60)),
+            optional_path: Some(protobuf::episode_info::OptionalPath::Path(
+                "/podcasts/ep.mp3".to_string(),
+            )),
+            played: true,
+        };
+        let converted = crate::podcast::episode::Episode::from(episode.clone());
+        assert_eq!(protobuf::EpisodeInfo::from(&converted), episode);
+    }
+
+    #[test]
+    fn should_roundtrip_feed_settings() {
+        let settings = crate::podcast::FeedSettings {
+            auto_download: Some(true),
+            keep_downloaded: Some(0),
+            delete_played_after_days: None,
+            playback_speed: Some(15),
+        };
+
+        let grpc = protobuf::PodcastFeedSettings::from(settings);
+        assert_eq!(crate::podcast::FeedSettings::from(grpc), settings);
+    }
+
+    #[test]
+    fn should_roundtrip_track_changed() {
+        let track = crate::track::Track::new_radio("http://radio.example");
+        let ev = UpdateEvents::TrackChanged(TrackChangedInfo {
+            current_track_index: 1,
+            current_track_updated: true,
+            title: Some("Some Title".to_string()),
+            progress: None,
+            track: Some((&track).into()),
+        });
+
+        let grpc = protobuf::StreamUpdates::from(ev.clone());
+        assert_eq!(UpdateEvents::try_from(grpc).unwrap(), ev);
+    }
+
+    #[test]
+    fn should_roundtrip_move_track() {
+        let ev =
+            UpdateEvents::PlaylistChanged(UpdatePlaylistEvents::MoveTrack(PlaylistMoveTrack {
+                from_index: 4,
+                to_index: 1,
+            }));
+
+        let grpc = protobuf::StreamUpdates::from(ev.clone());
+        assert_eq!(UpdateEvents::try_from(grpc).unwrap(), ev);
+    }
+}
@@ -0,0 +1,158 @@
+//! Shared-secret token authentication for the gRPC service
+use anyhow::Context;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// Metadata key the token is sent in
+const AUTH_KEY: &str = "authorization";
+/// Scheme prefix of the value in [`AUTH_KEY`]
+const AUTH_SCHEME: &str = "Bearer ";
+
+/// Client-side interceptor that adds the configured token to every request
+#[derive(Clone, Default)]
+pub struct ClientTokenInterceptor {
+    header: Option<MetadataValue<Ascii>>,
+}
+
+// Manual implementation so that the token does not end up in logs
+impl std::fmt::Debug for ClientTokenInterceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientTokenInterceptor")
+            .field("header", &self.header.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl ClientTokenInterceptor {
+    /// Create a new interceptor, not adding anything if `token` is `None`
+    ///
+    /// # Errors
+    ///
+    /// - if the token contains characters that are not allowed in a header value
+    pub fn new(token: Option<&str>) -> anyhow::Result<Self> {
+        let header = token
+            .map(|token| format!("{AUTH_SCHEME}{token}").parse())
+            .transpose()
+            .context("Token contains characters not allowed in a header")?;
+
+        Ok(Self { header })
+    }
+}
+
+impl Interceptor for ClientTokenInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(header) = &self.header {
+            request.metadata_mut().insert(AUTH_KEY, header.clone());
+        }
+
+        Ok(request)
+    }
+}
+
+/// Server-side interceptor that rejects every request not carrying the configured token
+#[derive(Clone, Default)]
+pub struct ServerTokenInterceptor {
+    token: Option<String>,
+}
+
+// Manual implementation so that the token does not end up in logs
+impl std::fmt::Debug for ServerTokenInterceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerTokenInterceptor")
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl ServerTokenInterceptor {
+    /// Create a new interceptor, allowing every request if `token` is `None`
+    #[must_use]
+    pub fn new(token: Option<String>) -> Self {
+        Self { token }
+    }
+}
+
+impl Interceptor for ServerTokenInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(expected) = &self.token else {
+            return Ok(request);
+        };
+
+        let given = request
+            .metadata()
+            .get(AUTH_KEY)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix(AUTH_SCHEME));
+
+        match given {
+            Some(given) if constant_time_eq(given.as_bytes(), expected.as_bytes()) => Ok(request),
+            _ => Err(Status::unauthenticated("Invalid or missing token")),
+        }
+    }
+}
+
+/// Compare two byte slices without returning early on the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::service::Interceptor;
+    use tonic::{Code, Request};
+
+    use super::{ClientTokenInterceptor, ServerTokenInterceptor};
+
+    #[test]
+    fn should_allow_everything_without_token() {
+        let mut server = ServerTokenInterceptor::new(None);
+        assert!(server.call(Request::new(())).is_ok());
+    }
+
+    #[test]
+    fn should_accept_matching_token() {
+        let mut client = ClientTokenInterceptor::new(Some("secret")).unwrap();
+        let mut server = ServerTokenInterceptor::new(Some("secret".to_string()));
+
+        let request = client.call(Request::new(())).unwrap();
+        assert!(server.call(request).is_ok());
+    }
+
+    #[test]
+    fn should_reject_wrong_or_missing_token() {
+        let mut server = ServerTokenInterceptor::new(Some("secret".to_string()));
+
+        let mut client = ClientTokenInterceptor::new(Some("wrong")).unwrap();
+        let request = client.call(Request::new(())).unwrap();
+        assert_eq!(
+            server.call(request).unwrap_err().code(),
+            Code::Unauthenticated
+        );
+
+        let mut client = ClientTokenInterceptor::new(None).unwrap();
+        let request = client.call(Request::new(())).unwrap();
+        assert_eq!(
+            server.call(request).unwrap_err().code(),
+            Code::Unauthenticated
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_token_characters() {
+        assert!(ClientTokenInterceptor::new(Some("new\nline")).is_err());
+    }
+
+    #[test]
+    fn should_not_debug_print_token() {
+        let server = ServerTokenInterceptor::new(Some("secret".to_string()));
+        assert!(!format!("{server:?}").contains("secret"));
+
+        let client = ClientTokenInterceptor::new(Some("secret")).unwrap();
+        assert!(!format!("{client:?}").contains("secret"));
+    }
+}
@@ -0,0 +1,203 @@
+//! Parsing of Atom 1.0 feeds
+//!
+//! <https://datatracker.ietf.org/doc/html/rfc4287>
+use anyhow::{Context, Result};
+use atom_syndication::extension::ExtensionMap;
+use atom_syndication::{Entry, Feed};
+use chrono::Utc;
+
+use super::{
+    duration_to_int, json_chapters_url, podcast_prefix, transcript_url, EpisodeNoId,
+    FeedValidators, PodcastNoId,
+};
+
+/// Parse a Atom feed into a podcast, episodes are the entries with a `enclosure` link
+pub fn parse_feed_data(body: &[u8], url: &str) -> Result<PodcastNoId> {
+    let feed = Feed::read_from(body).context("parsing Atom feed")?;
+
+    let author = feed
+        .authors()
+        .first()
+        .map(|person| person.name().to_string())
+        .or_else(|| itunes_value(feed.extensions(), "author"));
+    let image_url = feed
+        .logo()
+        .or_else(|| feed.icon())
+        .map(ToString::to_string)
+        .or_else(|| itunes_attr(feed.extensions(), "image", "href"));
+    let podcast_prefix = podcast_prefix(feed.namespaces());
+
+    Ok(PodcastNoId {
+        title: feed.title().as_str().to_string(),
+        url: url.to_string(),
+        description: feed.subtitle().map(|v| v.as_str().to_string()),
+        author,
+        explicit: itunes_value(feed.extensions(), "explicit").and_then(|v| {
+            match v.to_lowercase().as_str() {
+                "yes" | "explicit" | "true" => Some(true),
+                "no" | "clean" | "false" => Some(false),
+                _ => None,
+            }
+        }),
+        last_checked: Utc::now(),
+        episodes: feed
+            .entries()
+            .iter()
+            .map(|entry| parse_episode_data(entry, podcast_prefix))
+            .collect(),
+        image_url,
+        validators: FeedValidators::default(),
+    })
+}
+
+/// Convert a Atom entry into a episode, the `url` is empty if there is no `enclosure` link
+///
+/// `podcast_prefix` is the prefix the feed uses for the Podcasting 2.0 namespace.
+fn parse_episode_data(entry: &Entry, podcast_prefix: &str) -> EpisodeNoId {
+    let enclosure = entry.links().iter().find(|link| link.rel() == "enclosure");
+    let url = enclosure
+        .map(|link| link.href().to_string())
+        .unwrap_or_default();
+    let enclosure_length = enclosure
+        .and_then(|link| link.length())
+        .and_then(|v| v.parse().ok())
+        .filter(|v| *v > 0);
+    let description = entry
+        .summary()
+        .map(|v| v.as_str().to_string())
+        .or_else(|| {
+            entry
+                .content()
+                .and_then(|v| v.value())
+                .map(ToString::to_string)
+        })
+        .unwrap_or_default();
+    let pubdate = entry.published().unwrap_or(entry.updated());
+
+    EpisodeNoId {
+        title: entry.title().as_str().to_string(),
+        url,
+        guid: entry.id().to_string(),
+        description,
+        pubdate: Some(pubdate.with_timezone(&Utc)),
+        duration: duration_to_int(itunes_value(entry.extensions(), "duration").as_deref())
+            .map(i64::from),
+        image_url: itunes_attr(entry.extensions(), "image", "href"),
+        enclosure_length,
+        chapters_url: entry
+            .extensions()
+            .get(podcast_prefix)
+            .and_then(|v| v.get("chapters"))
+            .and_then(|v| v.iter().find_map(|ext| json_chapters_url(ext.attrs()))),
+        transcript_url: entry
+            .extensions()
+            .get(podcast_prefix)
+            .and_then(|v| v.get("transcript"))
+            .and_then(|v| transcript_url(v.iter().map(|ext| ext.attrs()))),
+    }
+}
+
+/// Get the first `itunes:{name}` element
+fn itunes_extension<'a>(
+    extensions: &'a ExtensionMap,
+    name: &str,
+) -> Option<&'a atom_syndication::extension::Extension> {
+    extensions.get("itunes")?.get(name)?.first()
+}
+
+/// Get the text of the first `itunes:{name}` element
+fn itunes_value(extensions: &ExtensionMap, name: &str) -> Option<String> {
+    itunes_extension(extensions, name)?
+        .value()
+        .map(ToString::to_string)
+}
+
+/// Get the attribute `attr` of the first `itunes:{name}` element
+fn itunes_attr(extensions: &ExtensionMap, name: &str, attr: &str) -> Option<String> {
+    itunes_extension(extensions, name)?
+        .attrs()
+        .get(attr)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use pretty_assertions::assert_eq;
+
+    use super::parse_feed_data;
+
+    const FEED: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd" xmlns:pc="https://podcastindex.org/namespace/1.0">
+  <title>Example Podcast</title>
+  <subtitle>About examples</subtitle>
+  <id>urn:uuid:60a76c80-d399-11d9-b91C-0003939e0af6</id>
+  <updated>2024-01-02T10:00:00Z</updated>
+  <author><name>Jane Doe</name></author>
+  <logo>https://example.com/logo.png</logo>
+  <entry>
+    <title>Episode 2</title>
+    <id>urn:example:2</id>
+    <updated>2024-01-02T10:00:00Z</updated>
+    <published>2024-01-02T09:00:00+01:00</published>
+    <summary>The second one</summary>
+    <link rel="alternate" href="https://example.com/2"/>
+    <link rel="enclosure" type="audio/mpeg" length="1234" href="https://example.com/2.mp3"/>
+    <itunes:duration>01:02:03</itunes:duration>
+    <pc:chapters url="https://example.com/2.json" type="application/json+chapters"/>
+  </entry>
+  <entry>
+    <title>Not a episode</title>
+    <id>urn:example:1</id>
+    <updated>2024-01-01T10:00:00Z</updated>
+    <content type="html">&lt;p&gt;text&lt;/p&gt;</content>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn should_parse_atom_feed() {
+        let podcast = parse_feed_data(FEED.as_bytes(), "https://example.com/feed.atom").unwrap();
+
+        assert_eq!(podcast.title, "Example Podcast");
+        assert_eq!(podcast.url, "https://example.com/feed.atom");
+        assert_eq!(podcast.description.as_deref(), Some("About examples"));
+        assert_eq!(podcast.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            podcast.image_url.as_deref(),
+            Some("https://example.com/logo.png")
+        );
+        assert_eq!(podcast.episodes.len(), 2);
+
+        let episode = &podcast.episodes[0];
+        assert_eq!(episode.title, "Episode 2");
+        assert_eq!(episode.url, "https://example.com/2.mp3");
+        assert_eq!(episode.guid, "urn:example:2");
+        assert_eq!(episode.description, "The second one");
+        assert_eq!(
+            episode.pubdate,
+            Some(
+                DateTime::parse_from_rfc3339("2024-01-02T08:00:00Z")
+                    .unwrap()
+                    .into()
+            )
+        );
+        assert_eq!(episode.duration, Some(3723));
+        assert_eq!(episode.enclosure_length, Some(1234));
+        assert_eq!(
+            episode.chapters_url.as_deref(),
+            Some("https://example.com/2.json")
+        );
+
+        let entry = &podcast.episodes[1];
+        assert_eq!(entry.url, "");
+        assert_eq!(entry.description, "<p>text</p>");
+        assert_eq!(
+            entry.pubdate,
+            Some(
+                DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z")
+                    .unwrap()
+                    .into()
+            )
+        );
+    }
+}
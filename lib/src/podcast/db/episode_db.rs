@@ -3,6 +3,7 @@ use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
 use rusqlite::{named_params, params, Connection, Row};
 
+use crate::chapters::Chapter;
 use crate::podcast::episode::EpisodeNoId;
 
 use super::{convert_date, PodcastDBId};
@@ -19,15 +20,20 @@ pub struct EpisodeDB {
     pub pubdate: Option<DateTime<Utc>>,
     pub duration: Option<i64>,
     pub played: bool,
+    pub played_at: Option<DateTime<Utc>>,
     pub hidden: bool,
     pub last_position: Option<i64>,
     pub image_url: Option<String>,
+    pub enclosure_length: Option<u64>,
+    pub chapters_url: Option<String>,
+    pub chapters: Vec<Chapter>,
+    pub transcript_url: Option<String>,
 }
 
 impl EpisodeDB {
     /// Try to convert a given row to a [`EpisodeDB`] instance, using column names to resolve the values
     pub fn try_from_row_named(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
-        // NOTE: all the names in "get" below are the *column names* as defined in migrations/001.sql#table_episodes (pseudo link)
+        // NOTE: all the names in "get" below are the *column names* as defined in migrations/001.sql#table_episodes, 002.sql, 004.sql, 005.sql and 006.sql (pseudo link)
         Ok(Self {
             id: row.get("id")?,
             pod_id: row.get("podcast_id")?,
@@ -38,15 +44,20 @@ impl EpisodeDB {
             pubdate: convert_date(&row.get("pubdate")),
             duration: row.get("duration")?,
             played: row.get("played")?,
+            played_at: convert_date(&row.get("played_at")),
             hidden: row.get("hidden")?,
             last_position: row.get("last_position")?,
             image_url: row.get("image_url")?,
+            enclosure_length: row.get("enclosure_length")?,
+            chapters_url: row.get("chapters_url")?,
+            chapters: convert_chapters(row.get("chapters")?),
+            transcript_url: row.get("transcript_url")?,
         })
     }
 
     /// Try to convert a given row to a [`EpisodeDB`] instance, using column names to resolve the values (with renamed id because of conflicts)
     pub fn try_from_row_named_alias_id(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
-        // NOTE: all the names in "get" below are the *column names* as defined in migrations/001.sql#table_episodes (pseudo link)
+        // NOTE: all the names in "get" below are the *column names* as defined in migrations/001.sql#table_episodes, 002.sql, 004.sql, 005.sql and 006.sql (pseudo link)
         Ok(Self {
             id: row.get("epid")?,
             pod_id: row.get("podcast_id")?,
@@ -57,9 +68,14 @@ impl EpisodeDB {
             pubdate: convert_date(&row.get("pubdate")),
             duration: row.get("duration")?,
             played: row.get("played")?,
+            played_at: convert_date(&row.get("played_at")),
             hidden: row.get("hidden")?,
             last_position: row.get("last_position")?,
             image_url: row.get("image_url")?,
+            enclosure_length: row.get("enclosure_length")?,
+            chapters_url: row.get("chapters_url")?,
+            chapters: convert_chapters(row.get("chapters")?),
+            transcript_url: row.get("transcript_url")?,
         })
     }
 }
@@ -82,6 +98,9 @@ pub struct EpisodeDBInsertable<'a> {
     pub hidden: bool,
     pub last_position: Option<i64>,
     pub image_url: Option<&'a str>,
+    pub enclosure_length: Option<u64>,
+    pub chapters_url: Option<&'a str>,
+    pub transcript_url: Option<&'a str>,
 }
 
 impl<'a> EpisodeDBInsertable<'a> {
@@ -99,6 +118,9 @@ impl<'a> EpisodeDBInsertable<'a> {
             hidden: false,
             last_position: Some(0),
             image_url: value.image_url.as_deref(),
+            enclosure_length: value.enclosure_length,
+            chapters_url: value.chapters_url.as_deref(),
+            transcript_url: value.transcript_url.as_deref(),
         }
     }
 
@@ -107,8 +129,8 @@ impl<'a> EpisodeDBInsertable<'a> {
     pub fn insert_episode(&self, con: &Connection) -> Result<usize, rusqlite::Error> {
         let mut stmt = con.prepare_cached(
             "INSERT INTO episodes (podcast_id, title, url, guid,
-                description, pubdate, duration, played, hidden, last_position, image_url)
-                VALUES (:podid, :title, :url, :guid, :description, :pubdate, :duration, :played, :hidden, :last_position, :image_url);",
+                description, pubdate, duration, played, hidden, last_position, image_url, enclosure_length, chapters_url, transcript_url)
+                VALUES (:podid, :title, :url, :guid, :description, :pubdate, :duration, :played, :hidden, :last_position, :image_url, :enclosure_length, :chapters_url, :transcript_url);",
         )?;
         stmt.execute(named_params![
             ":podid": self.pod_id,
@@ -122,6 +144,9 @@ impl<'a> EpisodeDBInsertable<'a> {
             ":hidden": self.hidden,
             ":last_position": self.last_position,
             ":image_url": self.image_url,
+            ":enclosure_length": self.enclosure_length,
+            ":chapters_url": self.chapters_url,
+            ":transcript_url": self.transcript_url,
         ])
     }
 
@@ -132,6 +157,7 @@ impl<'a> EpisodeDBInsertable<'a> {
     /// - `hidden`
     /// - `played`
     /// - `last_position`
+    /// - `chapters`
     #[inline]
     pub fn update_episode(
         &self,
@@ -141,7 +167,9 @@ impl<'a> EpisodeDBInsertable<'a> {
         let mut stmt = con.prepare_cached(
             "UPDATE episodes SET title = :title, url = :url,
                     guid = :guid, description = :description, pubdate = :pubdate,
-                    duration = :duration, image_url = :image_url WHERE id = :epid;",
+                    duration = :duration, image_url = :image_url,
+                    enclosure_length = :enclosure_length, chapters_url = :chapters_url,
+                    transcript_url = :transcript_url WHERE id = :epid;",
         )?;
         stmt.execute(named_params![
             ":title": self.title,
@@ -151,11 +179,33 @@ impl<'a> EpisodeDBInsertable<'a> {
             ":pubdate": self.pubdate.map(|v| v.timestamp()),
             ":duration": self.duration,
             ":image_url": self.duration,
+            ":enclosure_length": self.enclosure_length,
+            ":chapters_url": self.chapters_url,
+            ":transcript_url": self.transcript_url,
             ":epid": id,
         ])
     }
 }
 
+/// Store the chapters of a episode
+pub fn update_chapters(
+    id: PodcastDBId,
+    chapters: &[Chapter],
+    con: &Connection,
+) -> Result<usize, rusqlite::Error> {
+    let chapters = serde_json::to_string(chapters)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(err.into()))?;
+    let mut stmt = con.prepare_cached("UPDATE episodes SET chapters = ? WHERE id = ?;")?;
+    stmt.execute(params![chapters, id])
+}
+
+/// Convert the stored chapters, ignoring invalid values as they can be fetched again
+fn convert_chapters(value: Option<String>) -> Vec<Chapter> {
+    value
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
 /// Delete a episode by id
 ///
 /// This also deletes all associated files (not removing the actual files)!
@@ -0,0 +1,70 @@
+use rusqlite::{named_params, params, Connection, OptionalExtension, Row};
+
+use super::PodcastDBId;
+use crate::podcast::FeedSettings;
+
+/// A struct representing the settings of a podcast feed in the database
+#[derive(Debug, Clone)]
+pub struct FeedSettingsDB {
+    pub podcast_id: PodcastDBId,
+    pub settings: FeedSettings,
+}
+
+impl FeedSettingsDB {
+    /// Try to convert a given row to a [`FeedSettingsDB`] instance, using column names to resolve the values
+    pub fn try_from_row_named(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
+        // NOTE: all the names in "get" below are the *column names* as defined in migrations/002.sql#table_podcast_settings (pseudo link)
+        Ok(Self {
+            podcast_id: row.get("podcast_id")?,
+            settings: FeedSettings {
+                auto_download: row.get("auto_download")?,
+                keep_downloaded: row.get("keep_downloaded")?,
+                delete_played_after_days: row.get("delete_played_after_days")?,
+                playback_speed: row.get("playback_speed")?,
+            },
+        })
+    }
+
+    /// Insert or replace the current [`FeedSettingsDB`] in the `podcast_settings` table
+    #[inline]
+    pub fn upsert(&self, con: &Connection) -> Result<usize, rusqlite::Error> {
+        let mut stmt = con.prepare_cached(
+            "INSERT OR REPLACE INTO podcast_settings (podcast_id, auto_download, keep_downloaded, delete_played_after_days, playback_speed)
+            VALUES (:podid, :auto_download, :keep_downloaded, :delete_played_after_days, :playback_speed);",
+        )?;
+        stmt.execute(named_params![
+            ":podid": self.podcast_id,
+            ":auto_download": self.settings.auto_download,
+            ":keep_downloaded": self.settings.keep_downloaded,
+            ":delete_played_after_days": self.settings.delete_played_after_days,
+            ":playback_speed": self.settings.playback_speed,
+        ])
+    }
+}
+
+/// Get the settings of a podcast, the defaults if none are stored
+pub fn get_feed_settings(
+    podcast_id: PodcastDBId,
+    con: &Connection,
+) -> Result<FeedSettings, rusqlite::Error> {
+    let mut stmt = con.prepare_cached("SELECT * FROM podcast_settings WHERE podcast_id = ?;")?;
+    let settings = stmt
+        .query_row(params![podcast_id], FeedSettingsDB::try_from_row_named)
+        .optional()?;
+
+    Ok(settings.map(|v| v.settings).unwrap_or_default())
+}
+
+/// Get the playback speed of the feed the episode with `url` belongs to, if set
+pub fn get_playback_speed(url: &str, con: &Connection) -> Result<Option<i32>, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "SELECT podcast_settings.playback_speed FROM episodes
+        JOIN podcast_settings ON episodes.podcast_id = podcast_settings.podcast_id
+        WHERE episodes.url = ?;",
+    )?;
+    let speed = stmt
+        .query_row(params![url], |row| row.get::<_, Option<i32>>(0))
+        .optional()?;
+
+    Ok(speed.flatten())
+}
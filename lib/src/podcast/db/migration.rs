@@ -3,7 +3,7 @@ use rusqlite::{params, Connection};
 use semver::Version;
 
 /// The Current Database schema version this application is meant to run against
-pub(super) const DB_VERSION: u32 = 1;
+pub(super) const DB_VERSION: u32 = 7;
 /// The Lowest Database schema version this application supports migration up against
 ///
 /// Expection being "0" as that indicates a fresh database
@@ -68,6 +68,48 @@ fn apply_migrations(conn: &Connection, mut user_version: u32) -> Result<()> {
         user_version = set_user_version(conn, 1)?;
     }
 
+    if user_version == 1 {
+        // per-feed settings and the time episodes got played
+        conn.execute_batch(include_str!("./migrations/002.sql"))
+            .context("PodcastDatabase version 2 could not be migrated")?;
+        user_version = set_user_version(conn, 2)?;
+    }
+
+    if user_version == 2 {
+        // etag / last-modified of the feeds
+        conn.execute_batch(include_str!("./migrations/003.sql"))
+            .context("PodcastDatabase version 3 could not be migrated")?;
+        user_version = set_user_version(conn, 3)?;
+    }
+
+    if user_version == 3 {
+        // enclosure length of the episodes
+        conn.execute_batch(include_str!("./migrations/004.sql"))
+            .context("PodcastDatabase version 4 could not be migrated")?;
+        user_version = set_user_version(conn, 4)?;
+    }
+
+    if user_version == 4 {
+        // chapters of the episodes
+        conn.execute_batch(include_str!("./migrations/005.sql"))
+            .context("PodcastDatabase version 5 could not be migrated")?;
+        user_version = set_user_version(conn, 5)?;
+    }
+
+    if user_version == 5 {
+        // transcripts of the episodes
+        conn.execute_batch(include_str!("./migrations/006.sql"))
+            .context("PodcastDatabase version 6 could not be migrated")?;
+        user_version = set_user_version(conn, 6)?;
+    }
+
+    if user_version == 6 {
+        // local changes and state for the gpodder sync
+        conn.execute_batch(include_str!("./migrations/007.sql"))
+            .context("PodcastDatabase version 7 could not be migrated")?;
+        user_version = set_user_version(conn, 7)?;
+    }
+
     Ok(())
 }
 
@@ -135,7 +177,7 @@ mod tests {
 
         assert_eq!(0, get_user_version(&conn).unwrap());
         migrate(&conn).unwrap();
-        assert_eq!(1, get_user_version(&conn).unwrap());
+        assert_eq!(DB_VERSION, get_user_version(&conn).unwrap());
 
         let all_tracks: Vec<String> = {
             let mut prep = conn.prepare("SELECT name FROM sqlite_schema WHERE type ='table' AND name NOT LIKE 'sqlite_%';").unwrap();
@@ -145,6 +187,49 @@ mod tests {
                 .collect()
         };
 
-        assert_eq!(&all_tracks, &["podcasts", "episodes", "files", "version"]);
+        assert_eq!(
+            &all_tracks,
+            &[
+                "podcasts",
+                "episodes",
+                "files",
+                "version",
+                "podcast_settings",
+                "episode_actions",
+                "subscription_changes",
+                "gpodder_sync",
+                "gpodder_pending_adds"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_migrate_from_v1() {
+        let conn = gen_database();
+
+        conn.execute_batch(include_str!("./migrations/001.sql"))
+            .unwrap();
+        set_user_version(&conn, 1).unwrap();
+        conn.execute_batch(
+            "INSERT INTO podcasts (id, title, url) VALUES (1, 'Podcast', 'https://example.com/feed');
+            INSERT INTO episodes (podcast_id, title, url, played) VALUES (1, 'Played', 'https://example.com/1', 1);
+            INSERT INTO episodes (podcast_id, title, url, played) VALUES (1, 'Unplayed', 'https://example.com/2', 0);",
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(DB_VERSION, get_user_version(&conn).unwrap());
+
+        let played_at: Vec<(String, Option<i64>)> = {
+            let mut prep = conn
+                .prepare("SELECT title, played_at FROM episodes ORDER BY id;")
+                .unwrap();
+            prep.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+                .unwrap()
+                .flatten()
+                .collect()
+        };
+        assert!(played_at[0].1.is_some());
+        assert_eq!(played_at[1], ("Unplayed".to_string(), None));
     }
 }
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS podcast_settings (
+    podcast_id INTEGER PRIMARY KEY NOT NULL,
+    auto_download INTEGER,
+    keep_downloaded INTEGER,
+    delete_played_after_days INTEGER,
+    playback_speed INTEGER,
+    FOREIGN KEY(podcast_id) REFERENCES podcasts(id) ON DELETE CASCADE
+);
+
+ALTER TABLE episodes ADD COLUMN played_at INTEGER;
+
+-- the actual time is unknown for already played episodes, so start counting from the migration
+UPDATE episodes SET played_at = strftime('%s', 'now') WHERE played = 1;
@@ -0,0 +1,3 @@
+-- HTTP validators of the last fetch, to only download a feed again if it changed
+ALTER TABLE podcasts ADD COLUMN etag TEXT;
+ALTER TABLE podcasts ADD COLUMN last_modified TEXT;
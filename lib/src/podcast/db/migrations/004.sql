@@ -0,0 +1,2 @@
+-- the size in bytes the feed reports for the episode file
+ALTER TABLE episodes ADD COLUMN enclosure_length INTEGER;
@@ -0,0 +1,3 @@
+-- the url of the "<podcast:chapters>" JSON document and the chapters fetched from it (as JSON)
+ALTER TABLE episodes ADD COLUMN chapters_url TEXT;
+ALTER TABLE episodes ADD COLUMN chapters TEXT;
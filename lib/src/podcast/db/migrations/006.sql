@@ -0,0 +1,2 @@
+-- the url of the "<podcast:transcript>" that gets cached next to the downloaded file
+ALTER TABLE episodes ADD COLUMN transcript_url TEXT;
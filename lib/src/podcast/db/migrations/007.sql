@@ -0,0 +1,30 @@
+-- local changes that still need to be uploaded by the gpodder sync
+-- only the latest of each action per episode is kept, so that this does not grow while the sync is disabled
+CREATE TABLE IF NOT EXISTS episode_actions (
+    episode_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    started INTEGER,
+    position INTEGER,
+    total INTEGER,
+    PRIMARY KEY (episode_id, action),
+    FOREIGN KEY (episode_id) REFERENCES episodes(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS subscription_changes (
+    url TEXT PRIMARY KEY NOT NULL,
+    -- "add" or "remove"
+    action TEXT NOT NULL
+);
+
+-- the "timestamp" values the gpodder server returned on the last sync, to only get newer changes
+CREATE TABLE IF NOT EXISTS gpodder_sync (
+    id INTEGER PRIMARY KEY NOT NULL CHECK (id = 0),
+    subscriptions_since INTEGER NOT NULL,
+    actions_since INTEGER NOT NULL
+);
+
+-- feeds subscribed to on other devices, until they got added locally
+CREATE TABLE IF NOT EXISTS gpodder_pending_adds (
+    url TEXT PRIMARY KEY NOT NULL
+);
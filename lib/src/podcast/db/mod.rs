@@ -5,18 +5,24 @@ use ahash::AHashMap;
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, NaiveDateTime, Utc};
 use episode_db::{EpisodeDB, EpisodeDBInsertable};
+use feed_settings_db::FeedSettingsDB;
 use file_db::{FileDB, FileDBInsertable};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use semver::Version;
 
-use super::{Episode, EpisodeNoId, Podcast, PodcastNoId, RE_ARTICLES};
+use super::{Episode, EpisodeNoId, FeedSettings, Podcast, PodcastNoId, RE_ARTICLES};
+use crate::chapters::Chapter;
+use crate::podcast::gpodder::EpisodeActionKind;
 use crate::track::Track;
 use podcast_db::{PodcastDB, PodcastDBInsertable};
+pub use sync_db::EpisodeActionDB;
 
 mod episode_db;
+mod feed_settings_db;
 mod file_db;
 mod migration;
 mod podcast_db;
+mod sync_db;
 
 /// The id type used in the podcast database
 pub type PodcastDBId = i64;
@@ -25,6 +31,10 @@ pub type PodcastDBId = i64;
 pub struct SyncResult {
     pub added: u64,
     pub updated: u64,
+    /// Ids of the added episodes
+    pub added_ids: Vec<PodcastDBId>,
+    /// The feed was not modified since the last sync, so nothing was updated
+    pub unchanged: bool,
 }
 
 /// Struct holding a sqlite database connection, with methods to interact
@@ -68,6 +78,7 @@ impl Database {
         let tx = conn.transaction()?;
 
         PodcastDBInsertable::from(podcast).insert_podcast(&tx)?;
+        sync_db::insert_subscription_add(&podcast.url, &tx)?;
 
         let pod_id: PodcastDBId = {
             let mut stmt = tx.prepare_cached("SELECT id FROM podcasts WHERE url = ?")?;
@@ -97,6 +108,14 @@ impl Database {
     /// Inserts a filepath to a downloaded episode.
     pub fn insert_file(&self, episode_id: PodcastDBId, path: &Path) -> Result<()> {
         FileDBInsertable::new(episode_id, path).insert_file(&self.conn)?;
+        sync_db::insert_file_action(episode_id, EpisodeActionKind::Download, &self.conn)?;
+
+        Ok(())
+    }
+
+    /// Stores the chapters fetched for an episode.
+    pub fn set_chapters(&self, episode_id: PodcastDBId, chapters: &[Chapter]) -> Result<()> {
+        episode_db::update_chapters(episode_id, chapters, &self.conn)?;
 
         Ok(())
     }
@@ -105,6 +124,7 @@ impl Database {
     /// user has chosen to delete the file.
     pub fn remove_file(&self, episode_id: PodcastDBId) -> Result<()> {
         file_db::delete_file(episode_id, &self.conn)?;
+        sync_db::insert_file_action(episode_id, EpisodeActionKind::Delete, &self.conn)?;
 
         Ok(())
     }
@@ -112,12 +132,16 @@ impl Database {
     /// Removes all file listings for the selected episode ids.
     pub fn remove_files(&self, episode_ids: &[PodcastDBId]) -> Result<()> {
         file_db::delete_files(episode_ids, &self.conn)?;
+        for episode_id in episode_ids {
+            sync_db::insert_file_action(*episode_id, EpisodeActionKind::Delete, &self.conn)?;
+        }
 
         Ok(())
     }
 
     /// Removes a podcast, all episodes, and files from the database.
     pub fn remove_podcast(&self, podcast_id: PodcastDBId) -> Result<()> {
+        sync_db::insert_subscription_remove(podcast_id, &self.conn)?;
         podcast_db::delete_podcast(podcast_id, &self.conn)?;
 
         Ok(())
@@ -133,6 +157,18 @@ impl Database {
         Ok(result)
     }
 
+    /// Updates a podcast whose feed was not modified since the last sync, only marking it as checked.
+    pub fn update_podcast_unchanged(&self, pod_id: PodcastDBId) -> Result<SyncResult> {
+        podcast_db::update_last_checked(pod_id, Utc::now(), &self.conn)?;
+
+        Ok(SyncResult {
+            added: 0,
+            updated: 0,
+            added_ids: Vec::new(),
+            unchanged: true,
+        })
+    }
+
     /// Updates metadata about episodes that already exist in database,
     /// or inserts new episodes.
     ///
@@ -160,6 +196,7 @@ impl Database {
 
         let mut inserted = 0;
         let mut updated = 0;
+        let mut added_ids = Vec::new();
         for new_ep in episodes.iter().rev() {
             let new_pd = new_ep.pubdate.map(|dt| dt.timestamp());
 
@@ -206,7 +243,7 @@ impl Database {
                     updated += 1;
                 }
             } else {
-                Self::insert_episode(&tx, podcast_id, new_ep)?;
+                added_ids.push(Self::insert_episode(&tx, podcast_id, new_ep)?);
 
                 inserted += 1;
             }
@@ -215,6 +252,8 @@ impl Database {
         Ok(SyncResult {
             added: inserted,
             updated,
+            added_ids,
+            unchanged: false,
         })
     }
 
@@ -234,6 +273,9 @@ impl Database {
             && new_ep.guid == old_ep.guid
             && new_ep.description == old_ep.description
             && new_ep.duration == old_ep.duration
+            && new_ep.enclosure_length == old_ep.enclosure_length
+            && new_ep.chapters_url == old_ep.chapters_url
+            && new_ep.transcript_url == old_ep.transcript_url
             && pd_match)
         {
             return true;
@@ -245,8 +287,9 @@ impl Database {
     pub fn set_played_status(&self, episode_id: PodcastDBId, played: bool) -> Result<()> {
         let mut stmt = self
             .conn
-            .prepare_cached("UPDATE episodes SET played = ? WHERE id = ?;")?;
-        stmt.execute(params![played, episode_id])?;
+            .prepare_cached("UPDATE episodes SET played = ?, played_at = ? WHERE id = ?;")?;
+        stmt.execute(params![played, played_at(played), episode_id])?;
+        sync_db::insert_played(episode_id, played, &self.conn)?;
         Ok(())
     }
 
@@ -260,8 +303,10 @@ impl Database {
         let tx = conn.transaction()?;
 
         for episode_id in episode_id_vec {
-            let mut stmt = tx.prepare_cached("UPDATE episodes SET played = ? WHERE id = ?;")?;
-            stmt.execute(params![played, episode_id])?;
+            let mut stmt =
+                tx.prepare_cached("UPDATE episodes SET played = ?, played_at = ? WHERE id = ?;")?;
+            stmt.execute(params![played, played_at(played), episode_id])?;
+            sync_db::insert_played(*episode_id, played, &tx)?;
         }
         tx.commit()?;
         Ok(())
@@ -285,33 +330,56 @@ impl Database {
         let podcasts = stmt
             .query_map([], PodcastDB::try_from_row_named)?
             .flatten()
-            .map(|podcast| {
-                let episodes = match self.get_episodes(podcast.id, false) {
-                    Ok(ep_list) => Ok(ep_list),
-                    Err(_) => Err(rusqlite::Error::QueryReturnedNoRows),
-                }?;
-
-                let title_lower = podcast.title.to_lowercase();
-                let sort_title = RE_ARTICLES.replace(&title_lower, "").to_string();
-
-                Ok(Podcast {
-                    id: podcast.id,
-                    title: podcast.title,
-                    sort_title,
-                    url: podcast.url,
-                    description: podcast.description,
-                    author: podcast.author,
-                    explicit: podcast.explicit,
-                    last_checked: podcast.last_checked,
-                    episodes,
-                    image_url: podcast.image_url,
-                })
-            })
+            .map(|podcast| self.podcast_with_episodes(podcast))
             .collect::<Result<_, rusqlite::Error>>()?;
 
         Ok(podcasts)
     }
 
+    /// Get a single podcast by its id, including its (not hidden) episodes
+    ///
+    /// Returns `None` if there is no podcast with that id.
+    pub fn get_podcast(&self, podcast_id: PodcastDBId) -> Result<Option<Podcast>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT * FROM podcasts WHERE id = ?;")?;
+        let podcast = stmt
+            .query_row(params![podcast_id], PodcastDB::try_from_row_named)
+            .optional()?;
+
+        Ok(podcast
+            .map(|podcast| self.podcast_with_episodes(podcast))
+            .transpose()?)
+    }
+
+    /// Load the episodes and settings of `podcast`
+    fn podcast_with_episodes(&self, podcast: PodcastDB) -> Result<Podcast, rusqlite::Error> {
+        let episodes = match self.get_episodes(podcast.id, false) {
+            Ok(ep_list) => Ok(ep_list),
+            Err(_) => Err(rusqlite::Error::QueryReturnedNoRows),
+        }?;
+
+        let settings = feed_settings_db::get_feed_settings(podcast.id, &self.conn)?;
+
+        let title_lower = podcast.title.to_lowercase();
+        let sort_title = RE_ARTICLES.replace(&title_lower, "").to_string();
+
+        Ok(Podcast {
+            id: podcast.id,
+            title: podcast.title,
+            sort_title,
+            url: podcast.url,
+            description: podcast.description,
+            author: podcast.author,
+            explicit: podcast.explicit,
+            last_checked: podcast.last_checked,
+            episodes,
+            image_url: podcast.image_url,
+            settings,
+            validators: podcast.validators,
+        })
+    }
+
     /// Generates list of episodes for a given podcast.
     pub fn get_episodes(&self, pod_id: PodcastDBId, include_hidden: bool) -> Result<Vec<Episode>> {
         let mut stmt = if include_hidden {
@@ -332,33 +400,63 @@ impl Database {
         };
 
         let episodes = stmt
-            .query_map(params![pod_id], |row| {
-                let episode = EpisodeDB::try_from_row_named_alias_id(row)?;
-                let file = FileDB::try_from_row_named_alias_id(row).ok();
-
-                Ok(Episode {
-                    id: episode.id,
-                    pod_id,
-                    title: episode.title,
-                    url: episode.url,
-                    guid: episode.guid,
-                    description: episode.description,
-                    pubdate: episode.pubdate,
-                    duration: episode.duration,
-                    path: file.map(|v| v.path),
-                    played: episode.played,
-                    last_position: episode.last_position,
-                    image_url: episode.image_url,
-                })
-            })?
+            .query_map(params![pod_id], episode_from_row)?
             .flatten()
             .collect();
 
         Ok(episodes)
     }
 
+    /// Get the (not hidden) episodes with the given ids, ids that do not exist are skipped
+    pub fn get_episodes_by_id(&self, episode_ids: &[PodcastDBId]) -> Result<Vec<Episode>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT episodes.id as epid, files.id as fileid, * FROM episodes
+                    LEFT JOIN files ON episodes.id = files.episode_id
+                    WHERE episodes.id = ?
+                    AND episodes.hidden = 0;",
+        )?;
+
+        let mut episodes = Vec::with_capacity(episode_ids.len());
+        for id in episode_ids {
+            if let Some(episode) = stmt.query_row(params![id], episode_from_row).optional()? {
+                episodes.push(episode);
+            }
+        }
+
+        Ok(episodes)
+    }
+
+    /// Get the settings of a podcast, the defaults if none have been set
+    pub fn get_feed_settings(&self, podcast_id: PodcastDBId) -> Result<FeedSettings> {
+        Ok(feed_settings_db::get_feed_settings(podcast_id, &self.conn)?)
+    }
+
+    /// Store the settings of a podcast
+    pub fn set_feed_settings(&self, podcast_id: PodcastDBId, settings: FeedSettings) -> Result<()> {
+        FeedSettingsDB {
+            podcast_id,
+            settings,
+        }
+        .upsert(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Get the playback speed set for the feed of the given podcast episode, if any
+    pub fn get_feed_playback_speed(&self, track: &Track) -> Result<Option<i32>> {
+        let Some(url) = track.file() else {
+            return Ok(None);
+        };
+
+        Ok(feed_settings_db::get_playback_speed(url, &self.conn)?)
+    }
+
     /// Deletes all rows in all tables
     pub fn clear_db(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM episode_actions;", [])?;
+        self.conn.execute("DELETE FROM subscription_changes;", [])?;
+        self.conn.execute("DELETE FROM gpodder_sync;", [])?;
+        self.conn.execute("DELETE FROM podcast_settings;", [])?;
         self.conn.execute("DELETE FROM files;", [])?;
         self.conn.execute("DELETE FROM episodes;", [])?;
         self.conn.execute("DELETE FROM podcasts;", [])?;
@@ -388,6 +486,13 @@ impl Database {
     /// - if the connection is unavailable
     /// - if the query fails
     pub fn set_last_position(&self, track: &Track, last_position: Duration) -> Result<()> {
+        sync_db::insert_play_by_url(
+            track.file().unwrap_or("Unknown File Name"),
+            i64::try_from(last_position.as_secs()).unwrap_or(i64::MAX),
+            &self.conn,
+        )
+        .context("recording the play action failed.")?;
+
         let query = "UPDATE episodes SET last_position = ?1 WHERE url = ?2";
         self.conn
             .execute(
@@ -402,6 +507,117 @@ impl Database {
 
         Ok(())
     }
+
+    /// Get the local episode actions the gpodder sync still needs to upload, oldest first
+    pub fn get_episode_actions(&self) -> Result<Vec<EpisodeActionDB>> {
+        Ok(sync_db::get_episode_actions(&self.conn)?)
+    }
+
+    /// Remove uploaded episode actions, unless they got replaced by a newer action since
+    pub fn remove_episode_actions(&self, actions: &[EpisodeActionDB]) -> Result<()> {
+        sync_db::delete_episode_actions(actions, &self.conn)?;
+
+        Ok(())
+    }
+
+    /// Get the local subscription changes the gpodder sync still needs to upload, as `(url, added)`
+    pub fn get_subscription_changes(&self) -> Result<Vec<(String, bool)>> {
+        Ok(sync_db::get_subscription_changes(&self.conn)?)
+    }
+
+    /// Remove uploaded subscription changes, unless they got changed since
+    pub fn remove_subscription_changes(&self, changes: &[(String, bool)]) -> Result<()> {
+        sync_db::delete_subscription_changes(changes, &self.conn)?;
+
+        Ok(())
+    }
+
+    /// Get the `(subscriptions_since, actions_since)` timestamps of the last gpodder sync, `0` if never synced
+    pub fn get_gpodder_state(&self) -> Result<(i64, i64)> {
+        Ok(sync_db::get_sync_state(&self.conn)?)
+    }
+
+    /// Store the timestamps the gpodder server returned, to only get newer changes on the next sync
+    pub fn set_gpodder_state(&self, subscriptions_since: i64, actions_since: i64) -> Result<()> {
+        sync_db::set_sync_state(subscriptions_since, actions_since, &self.conn)?;
+
+        Ok(())
+    }
+
+    /// Get the feeds subscribed to on other devices, which still need to be added locally
+    pub fn get_gpodder_pending_adds(&self) -> Result<Vec<String>> {
+        Ok(sync_db::get_pending_adds(&self.conn)?)
+    }
+
+    /// Replace the feeds subscribed to on other devices, which still need to be added locally
+    pub fn set_gpodder_pending_adds(&self, urls: &[String]) -> Result<()> {
+        sync_db::set_pending_adds(urls, &self.conn)?;
+
+        Ok(())
+    }
+
+    /// Find a episode by the urls (or the guid) used in the gpodder API, returning its id and duration
+    pub fn find_synced_episode(
+        &self,
+        podcast_url: &str,
+        episode_url: &str,
+        guid: Option<&str>,
+    ) -> Result<Option<(PodcastDBId, Option<i64>)>> {
+        Ok(sync_db::find_episode(
+            podcast_url,
+            episode_url,
+            guid,
+            &self.conn,
+        )?)
+    }
+
+    /// Apply a episode action from another device, without recording it as a local action
+    pub fn set_synced_position(
+        &self,
+        episode_id: PodcastDBId,
+        position: Duration,
+        played: bool,
+    ) -> Result<()> {
+        sync_db::update_remote_position(
+            episode_id,
+            i64::try_from(position.as_secs()).unwrap_or(i64::MAX),
+            played,
+            &self.conn,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Helper function to get the `played_at` value to store for a new `played` status
+fn played_at(played: bool) -> Option<i64> {
+    played.then(|| Utc::now().timestamp())
+}
+
+/// Convert a row of a `episodes LEFT JOIN files` query, with the ids aliased as `epid` and `fileid`
+fn episode_from_row(row: &rusqlite::Row<'_>) -> Result<Episode, rusqlite::Error> {
+    let episode = EpisodeDB::try_from_row_named_alias_id(row)?;
+    let file = FileDB::try_from_row_named_alias_id(row).ok();
+
+    Ok(Episode {
+        id: episode.id,
+        pod_id: episode.pod_id,
+        title: episode.title,
+        url: episode.url,
+        guid: episode.guid,
+        description: episode.description,
+        pubdate: episode.pubdate,
+        duration: episode.duration,
+        path: file.map(|v| v.path),
+        played: episode.played,
+        played_at: episode.played_at,
+        last_position: episode.last_position,
+        image_url: episode.image_url,
+        enclosure_length: episode.enclosure_length,
+        chapters_url: episode.chapters_url,
+        chapters: episode.chapters,
+        transcript_url: episode.transcript_url,
+    })
 }
 
 /// Helper function converting an (optional) Unix timestamp to a
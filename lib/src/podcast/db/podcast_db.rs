@@ -2,7 +2,7 @@ use chrono::{DateTime, Utc};
 use rusqlite::{named_params, params, Connection, Row};
 
 use super::{convert_date, PodcastDBId};
-use crate::podcast::PodcastNoId;
+use crate::podcast::{FeedValidators, PodcastNoId};
 
 /// A struct representing a podcast feed in the database
 #[derive(Debug, Clone)]
@@ -15,12 +15,13 @@ pub struct PodcastDB {
     pub explicit: Option<bool>,
     pub last_checked: DateTime<Utc>,
     pub image_url: Option<String>,
+    pub validators: FeedValidators,
 }
 
 impl PodcastDB {
     /// Try to convert a given row to a [`PodcastDB`] instance, using column names to resolve the values
     pub fn try_from_row_named(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
-        // NOTE: all the names in "get" below are the *column names* as defined in migrations/001.sql#table_podcasts (pseudo link)
+        // NOTE: all the names in "get" below are the *column names* as defined in migrations/001.sql#table_podcasts and 003.sql (pseudo link)
         let last_checked =
             convert_date(&row.get("last_checked")).ok_or(rusqlite::Error::InvalidQuery)?;
         Ok(PodcastDB {
@@ -32,6 +33,10 @@ impl PodcastDB {
             explicit: row.get("explicit")?,
             last_checked,
             image_url: row.get("image_url")?,
+            validators: FeedValidators {
+                etag: row.get("etag")?,
+                last_modified: row.get("last_modified")?,
+            },
         })
     }
 }
@@ -50,6 +55,8 @@ pub struct PodcastDBInsertable<'a> {
     pub explicit: Option<bool>,
     pub last_checked: DateTime<Utc>,
     pub image_url: Option<&'a str>,
+    pub etag: Option<&'a str>,
+    pub last_modified: Option<&'a str>,
 }
 
 impl<'a> From<&'a PodcastNoId> for PodcastDBInsertable<'a> {
@@ -62,6 +69,8 @@ impl<'a> From<&'a PodcastNoId> for PodcastDBInsertable<'a> {
             explicit: value.explicit,
             last_checked: value.last_checked,
             image_url: value.image_url.as_deref(),
+            etag: value.validators.etag.as_deref(),
+            last_modified: value.validators.last_modified.as_deref(),
         }
     }
 }
@@ -71,8 +80,8 @@ impl PodcastDBInsertable<'_> {
     #[inline]
     pub fn insert_podcast(&self, con: &Connection) -> Result<usize, rusqlite::Error> {
         let mut stmt = con.prepare_cached(
-            "INSERT INTO podcasts (title, url, description, author, explicit, last_checked, image_url)
-            VALUES (:title, :url, :description, :author, :explicit, :last_checked, :image_url);",
+            "INSERT INTO podcasts (title, url, description, author, explicit, last_checked, image_url, etag, last_modified)
+            VALUES (:title, :url, :description, :author, :explicit, :last_checked, :image_url, :etag, :last_modified);",
         )?;
         stmt.execute(named_params![
             ":title": self.title,
@@ -81,7 +90,9 @@ impl PodcastDBInsertable<'_> {
             ":author": self.author,
             ":explicit": self.explicit,
             ":last_checked": self.last_checked.timestamp(),
-            ":image_url": self.image_url
+            ":image_url": self.image_url,
+            ":etag": self.etag,
+            ":last_modified": self.last_modified,
         ])
     }
 
@@ -94,7 +105,8 @@ impl PodcastDBInsertable<'_> {
     ) -> Result<usize, rusqlite::Error> {
         let mut stmt = con.prepare_cached(
             "UPDATE podcasts SET title = :title, url = :url, description = :description,
-            author = :author, explicit = :explicit, last_checked = :last_checked
+            author = :author, explicit = :explicit, last_checked = :last_checked,
+            etag = :etag, last_modified = :last_modified
             WHERE id = :id;",
         )?;
         stmt.execute(named_params![
@@ -104,11 +116,23 @@ impl PodcastDBInsertable<'_> {
             ":author": self.author,
             ":explicit": self.explicit,
             ":last_checked": self.last_checked.timestamp(),
+            ":etag": self.etag,
+            ":last_modified": self.last_modified,
             ":id": id,
         ])
     }
 }
 
+/// Update only the `last_checked` of a podcast, for when the feed did not change
+pub fn update_last_checked(
+    id: PodcastDBId,
+    last_checked: DateTime<Utc>,
+    con: &Connection,
+) -> Result<usize, rusqlite::Error> {
+    let mut stmt = con.prepare_cached("UPDATE podcasts SET last_checked = ? WHERE id = ?;")?;
+    stmt.execute(params![last_checked.timestamp(), id])
+}
+
 /// Delete a podcast by id
 ///
 /// This also deletes all associated episodes and files (not removing the actual files)!
@@ -120,3 +144,61 @@ pub fn delete_podcast(id: PodcastDBId, con: &Connection) -> Result<usize, rusqli
     let mut stmt = con.prepare_cached("DELETE FROM podcasts WHERE id = ?;")?;
     stmt.execute(params![id])
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use pretty_assertions::assert_eq;
+    use rusqlite::params;
+
+    use super::super::migration::migrate;
+    use super::super::test_utils::gen_database;
+    use super::{update_last_checked, PodcastDB, PodcastDBInsertable};
+    use crate::podcast::{FeedValidators, PodcastNoId};
+
+    fn get_podcast(con: &rusqlite::Connection) -> PodcastDB {
+        con.query_row(
+            "SELECT * FROM podcasts WHERE id = ?;",
+            params![1],
+            PodcastDB::try_from_row_named,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_store_validators() {
+        let con = gen_database();
+        migrate(&con).unwrap();
+
+        let mut podcast = PodcastNoId {
+            title: "Podcast".to_string(),
+            url: "https://example.com/feed".to_string(),
+            description: None,
+            author: None,
+            explicit: None,
+            last_checked: DateTime::from_timestamp(100, 0).unwrap(),
+            episodes: Vec::new(),
+            image_url: None,
+            validators: FeedValidators {
+                etag: Some("\"abc\"".to_string()),
+                last_modified: None,
+            },
+        };
+        PodcastDBInsertable::from(&podcast)
+            .insert_podcast(&con)
+            .unwrap();
+        assert_eq!(get_podcast(&con).validators, podcast.validators);
+
+        podcast.validators.last_modified = Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string());
+        PodcastDBInsertable::from(&podcast)
+            .update_podcast(1, &con)
+            .unwrap();
+        assert_eq!(get_podcast(&con).validators, podcast.validators);
+
+        let now = DateTime::<Utc>::from_timestamp(200, 0).unwrap();
+        update_last_checked(1, now, &con).unwrap();
+        let stored = get_podcast(&con);
+        assert_eq!(stored.last_checked, now);
+        assert_eq!(stored.validators, podcast.validators);
+    }
+}
@@ -0,0 +1,288 @@
+use chrono::Utc;
+use rusqlite::{named_params, params, Connection, OptionalExtension, Row};
+
+use super::PodcastDBId;
+use crate::podcast::gpodder::EpisodeActionKind;
+
+/// A struct representing a local episode action in the database, that still needs to be uploaded by the gpodder sync
+///
+/// Contains the urls of the episode and podcast, as the gpodder API identifies episodes by them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpisodeActionDB {
+    pub episode_id: PodcastDBId,
+    pub podcast_url: String,
+    pub episode_url: String,
+    pub guid: String,
+    pub action: EpisodeActionKind,
+    /// Unix timestamp of when the action happened
+    pub timestamp: i64,
+    /// Position in seconds playback started at, only for [`EpisodeActionKind::Play`]
+    pub started: Option<i64>,
+    /// Position in seconds playback stopped at, only for [`EpisodeActionKind::Play`]
+    pub position: Option<i64>,
+    /// Duration of the episode in seconds, only for [`EpisodeActionKind::Play`]
+    pub total: Option<i64>,
+}
+
+impl EpisodeActionDB {
+    /// Try to convert a given row to a [`EpisodeActionDB`] instance, using column names to resolve the values
+    pub fn try_from_row_named(row: &Row<'_>) -> Result<Self, rusqlite::Error> {
+        // NOTE: all the names in "get" below are the *column names* as defined in migrations/007.sql#table_episode_actions (pseudo link)
+        // or aliases in "get_episode_actions"
+        let action: String = row.get("action")?;
+        Ok(Self {
+            episode_id: row.get("episode_id")?,
+            podcast_url: row.get("podcast_url")?,
+            episode_url: row.get("episode_url")?,
+            guid: row.get::<_, Option<String>>("guid")?.unwrap_or_default(),
+            action: EpisodeActionKind::from_name(&action).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Text,
+                    format!("unknown episode action \"{action}\"").into(),
+                )
+            })?,
+            timestamp: row.get("timestamp")?,
+            started: row.get("started")?,
+            position: row.get("position")?,
+            total: row.get("total")?,
+        })
+    }
+}
+
+/// Record that the episode with `url` was played until `position` seconds
+///
+/// Needs to be called before `last_position` is updated, as that is used as the start of the playback.
+pub fn insert_play_by_url(
+    url: &str,
+    position: i64,
+    con: &Connection,
+) -> Result<usize, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "DELETE FROM episode_actions WHERE action IN ('play', 'new')
+        AND episode_id IN (SELECT id FROM episodes WHERE url = ?);",
+    )?;
+    stmt.execute(params![url])?;
+
+    let mut stmt = con.prepare_cached(
+        "INSERT OR REPLACE INTO episode_actions (episode_id, action, timestamp, started, position, total)
+        SELECT id, 'play', :timestamp, MIN(COALESCE(last_position, 0), :position), :position, duration
+        FROM episodes WHERE url = :url;",
+    )?;
+    stmt.execute(named_params![
+        ":timestamp": Utc::now().timestamp(),
+        ":position": position,
+        ":url": url,
+    ])
+}
+
+/// Record that the episode got marked as played (played until the end) or unplayed ([`EpisodeActionKind::New`])
+pub fn insert_played(
+    episode_id: PodcastDBId,
+    played: bool,
+    con: &Connection,
+) -> Result<usize, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "DELETE FROM episode_actions WHERE action IN ('play', 'new') AND episode_id = ?;",
+    )?;
+    stmt.execute(params![episode_id])?;
+
+    let query = if played {
+        "INSERT INTO episode_actions (episode_id, action, timestamp, started, position, total)
+        SELECT id, 'play', :timestamp, CASE WHEN duration IS NULL THEN NULL ELSE 0 END, duration, duration
+        FROM episodes WHERE id = :epid;"
+    } else {
+        "INSERT INTO episode_actions (episode_id, action, timestamp)
+        SELECT id, 'new', :timestamp FROM episodes WHERE id = :epid;"
+    };
+    let mut stmt = con.prepare_cached(query)?;
+    stmt.execute(named_params![
+        ":timestamp": Utc::now().timestamp(),
+        ":epid": episode_id,
+    ])
+}
+
+/// Record that the episode got downloaded or its download got deleted
+pub fn insert_file_action(
+    episode_id: PodcastDBId,
+    action: EpisodeActionKind,
+    con: &Connection,
+) -> Result<usize, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "INSERT OR REPLACE INTO episode_actions (episode_id, action, timestamp) VALUES (?, ?, ?);",
+    )?;
+    stmt.execute(params![episode_id, action.name(), Utc::now().timestamp()])
+}
+
+/// Get all episode actions that still need to be uploaded, oldest first
+pub fn get_episode_actions(con: &Connection) -> Result<Vec<EpisodeActionDB>, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "SELECT episode_actions.*, podcasts.url AS podcast_url, episodes.url AS episode_url, episodes.guid
+        FROM episode_actions
+        JOIN episodes ON episode_actions.episode_id = episodes.id
+        JOIN podcasts ON episodes.podcast_id = podcasts.id
+        ORDER BY episode_actions.timestamp;",
+    )?;
+    let actions = stmt
+        .query_map([], EpisodeActionDB::try_from_row_named)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(actions)
+}
+
+/// Delete uploaded episode actions, keeping the ones that got replaced since
+pub fn delete_episode_actions(
+    actions: &[EpisodeActionDB],
+    con: &Connection,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "DELETE FROM episode_actions WHERE episode_id = ? AND action = ? AND timestamp <= ?;",
+    )?;
+    for action in actions {
+        stmt.execute(params![
+            action.episode_id,
+            action.action.name(),
+            action.timestamp
+        ])?;
+    }
+
+    Ok(())
+}
+
+/// Record that the feed with `url` got subscribed to
+pub fn insert_subscription_add(url: &str, con: &Connection) -> Result<usize, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "INSERT OR REPLACE INTO subscription_changes (url, action) VALUES (?, 'add');",
+    )?;
+    stmt.execute(params![url])
+}
+
+/// Record that the podcast got removed
+///
+/// Needs to be called before the podcast is deleted, as its url is taken from there.
+pub fn insert_subscription_remove(
+    podcast_id: PodcastDBId,
+    con: &Connection,
+) -> Result<usize, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "INSERT OR REPLACE INTO subscription_changes (url, action)
+        SELECT url, 'remove' FROM podcasts WHERE id = ?;",
+    )?;
+    stmt.execute(params![podcast_id])
+}
+
+/// Get all subscription changes that still need to be uploaded, as `(url, added)`
+pub fn get_subscription_changes(con: &Connection) -> Result<Vec<(String, bool)>, rusqlite::Error> {
+    let mut stmt = con.prepare_cached("SELECT url, action FROM subscription_changes;")?;
+    let changes = stmt
+        .query_map([], |row| {
+            Ok((row.get("url")?, row.get::<_, String>("action")? == "add"))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(changes)
+}
+
+/// Delete uploaded subscription changes, keeping the ones that got changed since
+pub fn delete_subscription_changes(
+    changes: &[(String, bool)],
+    con: &Connection,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt =
+        con.prepare_cached("DELETE FROM subscription_changes WHERE url = ? AND action = ?;")?;
+    for (url, added) in changes {
+        stmt.execute(params![url, if *added { "add" } else { "remove" }])?;
+    }
+
+    Ok(())
+}
+
+/// Get the `(subscriptions_since, actions_since)` timestamps of the last sync, `0` if never synced
+pub fn get_sync_state(con: &Connection) -> Result<(i64, i64), rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "SELECT subscriptions_since, actions_since FROM gpodder_sync WHERE id = 0;",
+    )?;
+    let state = stmt
+        .query_row([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?;
+
+    Ok(state.unwrap_or((0, 0)))
+}
+
+/// Store the timestamps of the last sync
+pub fn set_sync_state(
+    subscriptions_since: i64,
+    actions_since: i64,
+    con: &Connection,
+) -> Result<usize, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "INSERT OR REPLACE INTO gpodder_sync (id, subscriptions_since, actions_since) VALUES (0, ?, ?);",
+    )?;
+    stmt.execute(params![subscriptions_since, actions_since])
+}
+
+/// Get the feeds subscribed to on other devices that are not added locally yet
+pub fn get_pending_adds(con: &Connection) -> Result<Vec<String>, rusqlite::Error> {
+    let mut stmt = con.prepare_cached("SELECT url FROM gpodder_pending_adds;")?;
+    let urls = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(urls)
+}
+
+/// Replace the feeds subscribed to on other devices that are not added locally yet
+pub fn set_pending_adds(urls: &[String], con: &Connection) -> Result<(), rusqlite::Error> {
+    con.execute("DELETE FROM gpodder_pending_adds;", [])?;
+    let mut stmt =
+        con.prepare_cached("INSERT OR IGNORE INTO gpodder_pending_adds (url) VALUES (?);")?;
+    for url in urls {
+        stmt.execute(params![url])?;
+    }
+
+    Ok(())
+}
+
+/// Find the episode the gpodder API refers to, by the urls and optionally the guid, returning its id and duration
+pub fn find_episode(
+    podcast_url: &str,
+    episode_url: &str,
+    guid: Option<&str>,
+    con: &Connection,
+) -> Result<Option<(PodcastDBId, Option<i64>)>, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "SELECT episodes.id, episodes.duration FROM episodes
+        JOIN podcasts ON episodes.podcast_id = podcasts.id
+        WHERE podcasts.url = :podcast_url AND (episodes.url = :episode_url OR episodes.guid = :guid);",
+    )?;
+    stmt.query_row(
+        named_params![
+            ":podcast_url": podcast_url,
+            ":episode_url": episode_url,
+            // a empty guid would match all episodes without one
+            ":guid": guid.filter(|v| !v.is_empty()),
+        ],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+}
+
+/// Set the position and played status from a remote episode action, without recording it as a local action
+pub fn update_remote_position(
+    episode_id: PodcastDBId,
+    position: i64,
+    played: bool,
+    con: &Connection,
+) -> Result<usize, rusqlite::Error> {
+    let mut stmt = con.prepare_cached(
+        "UPDATE episodes SET last_position = :position, played = :played,
+        played_at = CASE WHEN :played THEN COALESCE(played_at, :now) ELSE NULL END
+        WHERE id = :epid;",
+    )?;
+    stmt.execute(named_params![
+        ":position": position,
+        ":played": played,
+        ":now": Utc::now().timestamp(),
+        ":epid": episode_id,
+    ])
+}
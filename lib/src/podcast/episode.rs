@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
 
+use crate::chapters::Chapter;
 use crate::utils::StringUtils;
 
 use super::{Menuable, EPISODE_DURATION_LENGTH, EPISODE_PUBDATE_LENGTH};
@@ -22,8 +23,18 @@ pub struct Episode {
     pub duration: Option<i64>,
     pub path: Option<PathBuf>,
     pub played: bool,
+    /// When the episode was last marked as played
+    pub played_at: Option<DateTime<Utc>>,
     pub last_position: Option<i64>,
     pub image_url: Option<String>,
+    /// The file size in bytes as reported by the feed, if known
+    pub enclosure_length: Option<u64>,
+    /// The url of the `<podcast:chapters>` JSON document, if the feed has one
+    pub chapters_url: Option<String>,
+    /// The chapters, fetched from `chapters_url` when the episode got downloaded
+    pub chapters: Vec<Chapter>,
+    /// The url of the `<podcast:transcript>` to cache with the download, if the feed has one in a supported format
+    pub transcript_url: Option<String>,
 }
 
 impl Episode {
@@ -110,4 +121,10 @@ pub struct EpisodeNoId {
     pub pubdate: Option<DateTime<Utc>>,
     pub duration: Option<i64>,
     pub image_url: Option<String>,
+    /// The file size in bytes as reported by the feed, if known
+    pub enclosure_length: Option<u64>,
+    /// The url of the `<podcast:chapters>` JSON document, if the feed has one
+    pub chapters_url: Option<String>,
+    /// The url of the `<podcast:transcript>` to cache with the download, if the feed has one in a supported format
+    pub transcript_url: Option<String>,
 }
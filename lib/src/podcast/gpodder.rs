@@ -0,0 +1,664 @@
+//! Sync of subscriptions and episode actions with a gpodder.net compatible server, using the gpodder API v2
+//!
+//! <https://gpoddernet.readthedocs.io/en/latest/api/reference/>
+//!
+//! Local changes are recorded by [`Database`] and uploaded on the next sync.
+//! From other devices, `play` (position and played status) and `new` (unplayed) actions are applied,
+//! `download` and `delete` are only uploaded, as downloads are specific to a device.
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime};
+use reqwest::{Client, ClientBuilder, Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::db::{Database, EpisodeActionDB};
+use crate::config::v2::server::GpodderSettings;
+
+/// The format of episode action timestamps, always in UTC
+const ACTION_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// The kind of a episode action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EpisodeActionKind {
+    Download,
+    Delete,
+    Play,
+    New,
+    /// Actions we do not use, like `flattr`
+    #[serde(other)]
+    Other,
+}
+
+impl EpisodeActionKind {
+    /// Get the name used in the API and the database
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Download => "download",
+            Self::Delete => "delete",
+            Self::Play => "play",
+            Self::New => "new",
+            Self::Other => "other",
+        }
+    }
+
+    /// Get the kind from a name returned by [`name`](Self::name), [`None`] if unknown
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Download, Self::Delete, Self::Play, Self::New]
+            .into_iter()
+            .find(|v| v.name() == name)
+    }
+}
+
+/// A episode action as used by the API, episodes are identified by their (enclosure) url and the url of their feed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpisodeAction {
+    pub podcast: String,
+    pub episode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    pub action: EpisodeActionKind,
+    /// In the format of [`ACTION_TIMESTAMP_FORMAT`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Position in seconds playback started at, only for [`EpisodeActionKind::Play`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started: Option<i64>,
+    /// Position in seconds playback stopped at, only for [`EpisodeActionKind::Play`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<i64>,
+    /// Duration of the episode in seconds, only for [`EpisodeActionKind::Play`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+impl EpisodeAction {
+    fn from_db(value: &EpisodeActionDB, device_id: &str) -> Self {
+        Self {
+            podcast: value.podcast_url.clone(),
+            episode: value.episode_url.clone(),
+            guid: Some(value.guid.clone()).filter(|v| !v.is_empty()),
+            device: Some(device_id.to_string()),
+            action: value.action,
+            timestamp: DateTime::from_timestamp(value.timestamp, 0)
+                .map(|v| v.format(ACTION_TIMESTAMP_FORMAT).to_string()),
+            started: value.started,
+            position: value.position,
+            total: value.total,
+        }
+    }
+
+    /// Whether this is a action that gets applied from other devices, see [`apply_remote_action`]
+    fn changes_position(&self) -> bool {
+        matches!(
+            self.action,
+            EpisodeActionKind::Play | EpisodeActionKind::New
+        )
+    }
+}
+
+/// Subscription changes of a device since a timestamp
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionChanges {
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// The timestamp to use as `since` for the next request
+    pub timestamp: i64,
+}
+
+/// Episode actions of all devices since a timestamp
+#[derive(Debug, Clone, Deserialize)]
+pub struct EpisodeActions {
+    #[serde(default)]
+    pub actions: Vec<EpisodeAction>,
+    /// The timestamp to use as `since` for the next request
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct SubscriptionUpload<'a> {
+    add: Vec<&'a str>,
+    remove: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct DeviceUpdate<'a> {
+    caption: &'a str,
+    #[serde(rename = "type")]
+    kind: &'a str,
+}
+
+/// Client for the gpodder API v2, authenticating every request with HTTP basic auth
+#[derive(Debug, Clone)]
+pub struct GpodderClient {
+    agent: Client,
+    base_url: String,
+    username: String,
+    password: String,
+    device_id: String,
+}
+
+impl GpodderClient {
+    /// Create a new client for the server in `settings`
+    ///
+    /// # Errors
+    ///
+    /// - if the http client cannot be built
+    pub fn new(settings: &GpodderSettings) -> Result<Self> {
+        let agent = ClientBuilder::new()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(60))
+            .build()
+            .context("build http client")?;
+
+        Ok(Self {
+            agent,
+            base_url: settings.url.trim_end_matches('/').to_string(),
+            username: settings.username.clone(),
+            password: settings.password.clone(),
+            device_id: settings.device_id.clone(),
+        })
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.agent
+            .request(method, format!("{}/api/2/{path}", self.base_url))
+            .basic_auth(&self.username, Some(&self.password))
+    }
+
+    /// Create or update this device, as subscriptions are stored per device
+    pub async fn register_device(&self) -> Result<()> {
+        self.request(
+            Method::POST,
+            &format!("devices/{}/{}.json", self.username, self.device_id),
+        )
+        .json(&DeviceUpdate {
+            caption: "termusic",
+            kind: "desktop",
+        })
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Get the subscription changes of this device since the timestamp `since`, `0` gets all subscriptions
+    pub async fn get_subscription_changes(&self, since: i64) -> Result<SubscriptionChanges> {
+        let response = self
+            .request(
+                Method::GET,
+                &format!("subscriptions/{}/{}.json", self.username, self.device_id),
+            )
+            .query(&[("since", since)])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(response.json().await?)
+    }
+
+    /// Upload subscription changes of this device
+    pub async fn upload_subscription_changes(
+        &self,
+        add: Vec<&str>,
+        remove: Vec<&str>,
+    ) -> Result<()> {
+        self.request(
+            Method::POST,
+            &format!("subscriptions/{}/{}.json", self.username, self.device_id),
+        )
+        .json(&SubscriptionUpload { add, remove })
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Get the latest episode action of every episode since the timestamp `since`
+    pub async fn get_episode_actions(&self, since: i64) -> Result<EpisodeActions> {
+        let response = self
+            .request(Method::GET, &format!("episodes/{}.json", self.username))
+            .query(&[
+                ("since", since.to_string().as_str()),
+                ("aggregated", "true"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(response.json().await?)
+    }
+
+    /// Upload episode actions
+    pub async fn upload_episode_actions(&self, actions: &[EpisodeAction]) -> Result<()> {
+        self.request(Method::POST, &format!("episodes/{}.json", self.username))
+            .json(actions)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// What a sync changed
+///
+/// Subscription changes from other devices are not applied, as new feeds need to be fetched first.
+/// Added feeds are returned again by every sync until they exist locally.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncOutcome {
+    /// Feeds subscribed to on other devices (now or on a previous sync), which are not subscribed to locally
+    pub add_feeds: Vec<String>,
+    /// Feeds removed on other devices, which are still subscribed to locally
+    pub remove_feeds: Vec<String>,
+    /// Feeds which had episode actions from other devices applied
+    pub updated_feeds: Vec<String>,
+    /// How many local changes got uploaded
+    pub uploaded: usize,
+}
+
+/// Sync the podcast database in `db_path` with the server
+///
+/// A separate connection is used, so that the database does not stay locked while waiting on the server.
+/// Local changes win over the changes from other devices, unless those are newer.
+///
+/// # Errors
+///
+/// - if the database cannot be opened or modified
+/// - if any request fails
+pub async fn sync(client: &GpodderClient, db_path: &Path) -> Result<SyncOutcome> {
+    let db = Database::new(db_path)?;
+    let (subscriptions_since, actions_since) = db.get_gpodder_state()?;
+    let mut outcome = SyncOutcome::default();
+
+    client
+        .register_device()
+        .await
+        .context("registering the device")?;
+
+    let remote_subscriptions = client
+        .get_subscription_changes(subscriptions_since)
+        .await
+        .context("getting subscription changes")?;
+    let local: HashSet<String> = db.get_podcasts()?.into_iter().map(|v| v.url).collect();
+    let mut changes = db.get_subscription_changes()?;
+    if subscriptions_since == 0 {
+        // the first sync uploads all local subscriptions
+        for url in &local {
+            if !changes.iter().any(|(v, _)| v == url) {
+                changes.push((url.clone(), true));
+            }
+        }
+    }
+
+    let changed: HashSet<&str> = changes.iter().map(|(url, _)| url.as_str()).collect();
+    // adding a feed can fail, so they are kept until they exist locally
+    let mut add_feeds = db.get_gpodder_pending_adds()?;
+    for url in &remote_subscriptions.add {
+        if !add_feeds.contains(url) {
+            add_feeds.push(url.clone());
+        }
+    }
+    add_feeds.retain(|url| {
+        !local.contains(url)
+            && !changed.contains(url.as_str())
+            && !remote_subscriptions.remove.contains(url)
+    });
+    db.set_gpodder_pending_adds(&add_feeds)?;
+    outcome.add_feeds = add_feeds;
+    outcome.remove_feeds = remote_subscriptions
+        .remove
+        .iter()
+        .filter(|url| local.contains(*url) && !changed.contains(url.as_str()))
+        .cloned()
+        .collect();
+
+    if !changes.is_empty() {
+        let (add, remove): (Vec<_>, Vec<_>) = changes.iter().partition(|(_, added)| *added);
+        client
+            .upload_subscription_changes(
+                add.into_iter().map(|(url, _)| url.as_str()).collect(),
+                remove.into_iter().map(|(url, _)| url.as_str()).collect(),
+            )
+            .await
+            .context("uploading subscription changes")?;
+        db.remove_subscription_changes(&changes)?;
+        outcome.uploaded += changes.len();
+    }
+
+    let remote_actions = client
+        .get_episode_actions(actions_since)
+        .await
+        .context("getting episode actions")?;
+    let pending = db.get_episode_actions()?;
+    // actions can be fetched again (see below), so only the newest of each episode is applied
+    let mut newest: HashMap<(&str, &str), Option<i64>> = HashMap::new();
+    for action in remote_actions
+        .actions
+        .iter()
+        .filter(|v| v.changes_position())
+    {
+        let timestamp = action.timestamp.as_deref().and_then(parse_action_timestamp);
+        newest
+            .entry((action.podcast.as_str(), action.episode.as_str()))
+            .and_modify(|v| *v = (*v).max(timestamp))
+            .or_insert(timestamp);
+    }
+    for action in &remote_actions.actions {
+        let timestamp = action.timestamp.as_deref().and_then(parse_action_timestamp);
+        if newest.get(&(action.podcast.as_str(), action.episode.as_str())) != Some(&timestamp) {
+            continue;
+        }
+        if apply_remote_action(&db, client.device_id(), action, &pending)?
+            && !outcome.updated_feeds.contains(&action.podcast)
+        {
+            outcome.updated_feeds.push(action.podcast.clone());
+        }
+    }
+
+    if !pending.is_empty() {
+        let actions: Vec<EpisodeAction> = pending
+            .iter()
+            .map(|v| EpisodeAction::from_db(v, client.device_id()))
+            .collect();
+        client
+            .upload_episode_actions(&actions)
+            .await
+            .context("uploading episode actions")?;
+        db.remove_episode_actions(&pending)?;
+        outcome.uploaded += pending.len();
+    }
+
+    // the actions of feeds that are not added yet are fetched again on the next sync
+    let waiting = remote_actions
+        .actions
+        .iter()
+        .any(|action| outcome.add_feeds.contains(&action.podcast));
+    let actions_since = if waiting {
+        actions_since
+    } else {
+        remote_actions.timestamp
+    };
+    db.set_gpodder_state(remote_subscriptions.timestamp, actions_since)?;
+
+    Ok(outcome)
+}
+
+/// Apply a episode action from another device, returns whether anything changed
+fn apply_remote_action(
+    db: &Database,
+    device_id: &str,
+    action: &EpisodeAction,
+    pending: &[EpisodeActionDB],
+) -> Result<bool> {
+    // our own actions from previous syncs
+    if action.device.as_deref() == Some(device_id) {
+        return Ok(false);
+    }
+    let Some((episode_id, duration)) =
+        db.find_synced_episode(&action.podcast, &action.episode, action.guid.as_deref())?
+    else {
+        return Ok(false);
+    };
+
+    let timestamp = action.timestamp.as_deref().and_then(parse_action_timestamp);
+    let local_is_newer = pending.iter().any(|v| {
+        v.episode_id == episode_id
+            && matches!(v.action, EpisodeActionKind::Play | EpisodeActionKind::New)
+            && timestamp.map_or(true, |timestamp| v.timestamp >= timestamp)
+    });
+    if local_is_newer {
+        return Ok(false);
+    }
+
+    match action.action {
+        EpisodeActionKind::Play => {
+            let Some(position) = action.position.and_then(|v| u64::try_from(v).ok()) else {
+                return Ok(false);
+            };
+            let total = action
+                .total
+                .filter(|v| *v > 0)
+                .or(duration)
+                .and_then(|v| u64::try_from(v).ok());
+            let played = total.is_some_and(|total| position >= total);
+            // finished episodes start from the beginning when played again
+            let position = if played { 0 } else { position };
+            db.set_synced_position(episode_id, Duration::from_secs(position), played)?;
+        }
+        EpisodeActionKind::New => db.set_synced_position(episode_id, Duration::ZERO, false)?,
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Parse a episode action timestamp as unix timestamp, some servers send RFC 3339 instead of the documented format
+fn parse_action_timestamp(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|v| v.timestamp())
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, &format!("{ACTION_TIMESTAMP_FORMAT}%.f"))
+                .map(|v| v.and_utc().timestamp())
+        })
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::num::NonZeroU32;
+    use std::path::PathBuf;
+    use std::thread::JoinHandle;
+
+    use chrono::Utc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::podcast::episode::EpisodeNoId;
+    use crate::podcast::{FeedValidators, PodcastNoId};
+
+    /// A minimal HTTP stand-in answering one request per response in order
+    ///
+    /// Returns the url and a handle resolving to the request lines (without query) and bodies.
+    fn serve(responses: Vec<&'static str>) -> (String, JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut len = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length: ") {
+                        len = value.parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap();
+                let path = parts.next().unwrap().split('?').next().unwrap();
+                requests.push((format!("{method} {path}"), String::from_utf8(body).unwrap()));
+
+                let _ = stream.write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                        response.len()
+                    )
+                    .as_bytes(),
+                );
+            }
+
+            requests
+        });
+
+        (url, handle)
+    }
+
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("termusic-gpodder-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn episode(url: &str) -> EpisodeNoId {
+        EpisodeNoId {
+            title: url.to_string(),
+            url: url.to_string(),
+            guid: String::new(),
+            description: String::new(),
+            pubdate: None,
+            duration: Some(600),
+            image_url: None,
+            enclosure_length: None,
+            chapters_url: None,
+            transcript_url: None,
+        }
+    }
+
+    #[test]
+    fn should_parse_action_timestamps() {
+        assert_eq!(
+            parse_action_timestamp("2024-01-01T10:00:00"),
+            Some(1_704_103_200)
+        );
+        assert_eq!(
+            parse_action_timestamp("2024-01-01T10:00:00.5"),
+            Some(1_704_103_200)
+        );
+        assert_eq!(
+            parse_action_timestamp("2024-01-01T11:00:00+01:00"),
+            Some(1_704_103_200)
+        );
+        assert_eq!(parse_action_timestamp("yesterday"), None);
+    }
+
+    #[tokio::test]
+    async fn should_sync_with_server() {
+        let dir = test_dir();
+        let db = Database::new(&dir).unwrap();
+        db.insert_podcast(&PodcastNoId {
+            title: "Example".to_string(),
+            url: "https://example.com/feed.rss".to_string(),
+            description: None,
+            author: None,
+            explicit: None,
+            last_checked: Utc::now(),
+            episodes: vec![
+                episode("https://example.com/2.mp3"),
+                episode("https://example.com/1.mp3"),
+            ],
+            image_url: None,
+            validators: FeedValidators::default(),
+        })
+        .unwrap();
+        let episodes = db.get_podcasts().unwrap().remove(0).episodes;
+        let id_of = |url: &str| episodes.iter().find(|ep| ep.url == url).unwrap().id;
+        db.set_played_status(id_of("https://example.com/1.mp3"), true)
+            .unwrap();
+
+        let (url, server) = serve(vec![
+            "{}",
+            r#"{"add": ["https://example.com/feed.rss", "https://example.com/other.rss"], "remove": [], "timestamp": 100}"#,
+            r#"{"timestamp": 101, "update_urls": []}"#,
+            r#"{"actions": [{"podcast": "https://example.com/feed.rss", "episode": "https://example.com/2.mp3", "device": "phone",
+                "action": "play", "timestamp": "2024-01-01T10:00:00", "started": 0, "position": 120, "total": 600},
+                {"podcast": "https://example.com/feed.rss", "episode": "https://example.com/2.mp3", "device": "tablet",
+                "action": "play", "timestamp": "2024-01-01T09:00:00", "started": 0, "position": 60, "total": 600},
+                {"podcast": "https://example.com/other.rss", "episode": "https://example.com/other.mp3", "device": "phone",
+                "action": "play", "timestamp": "2024-01-01T10:00:00", "started": 0, "position": 30, "total": 600}], "timestamp": 200}"#,
+            r#"{"timestamp": 201, "update_urls": []}"#,
+        ]);
+        let client = GpodderClient::new(&GpodderSettings {
+            url: format!("{url}/"),
+            username: "user".to_string(),
+            password: "secret".to_string(),
+            device_id: "termusic".to_string(),
+            sync_interval_minutes: NonZeroU32::new(30).unwrap(),
+        })
+        .unwrap();
+
+        let outcome = sync(&client, &dir).await.unwrap();
+        assert_eq!(
+            outcome,
+            SyncOutcome {
+                add_feeds: vec!["https://example.com/other.rss".to_string()],
+                remove_feeds: Vec::new(),
+                updated_feeds: vec!["https://example.com/feed.rss".to_string()],
+                uploaded: 2,
+            }
+        );
+
+        let requests = server.join().unwrap();
+        let lines: Vec<&str> = requests.iter().map(|(line, _)| line.as_str()).collect();
+        assert_eq!(
+            lines,
+            [
+                "POST /api/2/devices/user/termusic.json",
+                "GET /api/2/subscriptions/user/termusic.json",
+                "POST /api/2/subscriptions/user/termusic.json",
+                "GET /api/2/episodes/user.json",
+                "POST /api/2/episodes/user.json",
+            ]
+        );
+        assert_eq!(
+            requests[2].1,
+            r#"{"add":["https://example.com/feed.rss"],"remove":[]}"#
+        );
+        let uploaded: Vec<EpisodeAction> = serde_json::from_str(&requests[4].1).unwrap();
+        assert_eq!(uploaded.len(), 1);
+        assert_eq!(uploaded[0].episode, "https://example.com/1.mp3");
+        assert_eq!(uploaded[0].action, EpisodeActionKind::Play);
+        assert_eq!(uploaded[0].device.as_deref(), Some("termusic"));
+        assert_eq!(
+            (uploaded[0].started, uploaded[0].position, uploaded[0].total),
+            (Some(0), Some(600), Some(600))
+        );
+
+        let episodes = db.get_podcasts().unwrap().remove(0).episodes;
+        let synced = episodes
+            .iter()
+            .find(|ep| ep.url == "https://example.com/2.mp3")
+            .unwrap();
+        assert_eq!(synced.last_position, Some(120));
+        assert!(!synced.played);
+        assert!(db.get_episode_actions().unwrap().is_empty());
+        assert!(db.get_subscription_changes().unwrap().is_empty());
+        // "other.rss" is not added yet, so its actions are fetched again next time
+        assert_eq!(db.get_gpodder_state().unwrap(), (100, 0));
+        assert_eq!(
+            db.get_gpodder_pending_adds().unwrap(),
+            ["https://example.com/other.rss"]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
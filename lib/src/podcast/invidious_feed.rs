@@ -0,0 +1,275 @@
+//! Parsing of youtube channels and playlists from the invidious api, see [`crate::invidious::YoutubeFeed`]
+//!
+//! <https://docs.invidious.io/api/>
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::{EpisodeNoId, FeedValidators, PodcastNoId};
+
+/// The path of the channel api, the feed url is `{domain}/api/v1/channels/{id}`
+const CHANNEL_PATH: &str = "/api/v1/channels/";
+/// The path of the playlist api, the feed url is `{domain}/api/v1/playlists/{id}`
+const PLAYLIST_PATH: &str = "/api/v1/playlists/";
+
+/// The `itag` of the m4a audio stream, which every video has
+const AUDIO_ITAG: u32 = 140;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Channel {
+    author: String,
+    description: Option<String>,
+    #[serde(default)]
+    author_thumbnails: Vec<Thumbnail>,
+    #[serde(default)]
+    latest_videos: Vec<Video>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Playlist {
+    title: String,
+    author: Option<String>,
+    description: Option<String>,
+    playlist_thumbnail: Option<String>,
+    #[serde(default)]
+    videos: Vec<Video>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Video {
+    title: String,
+    video_id: String,
+    length_seconds: Option<i64>,
+    /// Unix timestamp, only set for channel videos
+    published: Option<i64>,
+    description: Option<String>,
+    #[serde(default)]
+    video_thumbnails: Vec<Thumbnail>,
+    #[serde(default)]
+    live_now: bool,
+    #[serde(default)]
+    is_upcoming: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Thumbnail {
+    url: String,
+    #[serde(default)]
+    width: u32,
+}
+
+/// Get whether `url` is the feed of a youtube channel or playlist on a invidious instance
+pub fn is_feed_url(url: &str) -> bool {
+    url.contains(CHANNEL_PATH) || url.contains(PLAYLIST_PATH)
+}
+
+/// Parse the invidious channel or playlist at `url` into a podcast
+///
+/// The episode urls point to the audio streams of the videos, proxied through the same instance.
+pub fn parse_feed_data(body: &[u8], url: &str) -> Result<PodcastNoId> {
+    let domain = domain(url);
+
+    let (title, author, description, image_url, videos) = if url.contains(CHANNEL_PATH) {
+        let channel: Channel = serde_json::from_slice(body).context("parsing invidious channel")?;
+        let image_url = largest_thumbnail(&channel.author_thumbnails, domain);
+        (
+            channel.author.clone(),
+            Some(channel.author),
+            channel.description,
+            image_url,
+            channel.latest_videos,
+        )
+    } else {
+        let playlist: Playlist =
+            serde_json::from_slice(body).context("parsing invidious playlist")?;
+        let image_url = playlist.playlist_thumbnail.map(|v| absolute_url(v, domain));
+        (
+            playlist.title,
+            playlist.author,
+            playlist.description,
+            image_url,
+            playlist.videos,
+        )
+    };
+
+    Ok(PodcastNoId {
+        title,
+        url: url.to_string(),
+        description: description.filter(|v| !v.is_empty()),
+        author,
+        explicit: None,
+        last_checked: Utc::now(),
+        episodes: videos
+            .into_iter()
+            // live streams and premieres do not have a finished audio stream yet
+            .filter(|v| !v.live_now && !v.is_upcoming)
+            .map(|v| parse_episode_data(v, domain))
+            .collect(),
+        image_url,
+        validators: FeedValidators::default(),
+    })
+}
+
+/// Convert a video into a episode, using the video id as guid
+fn parse_episode_data(video: Video, domain: &str) -> EpisodeNoId {
+    EpisodeNoId {
+        title: video.title,
+        url: format!(
+            "{domain}/latest_version?id={}&itag={AUDIO_ITAG}&local=true",
+            urlencoding::encode(&video.video_id)
+        ),
+        image_url: largest_thumbnail(&video.video_thumbnails, domain),
+        guid: video.video_id,
+        description: video.description.unwrap_or_default(),
+        pubdate: video.published.and_then(|v| DateTime::from_timestamp(v, 0)),
+        duration: video.length_seconds.filter(|v| *v > 0),
+        enclosure_length: None,
+        chapters_url: None,
+        transcript_url: None,
+    }
+}
+
+/// Get the instance part of a feed url, like `https://yewtu.be`
+fn domain(url: &str) -> &str {
+    url.find("/api/v1/").map_or(url, |idx| &url[..idx])
+}
+
+/// Get the url of the widest thumbnail
+fn largest_thumbnail(thumbnails: &[Thumbnail], domain: &str) -> Option<String> {
+    thumbnails
+        .iter()
+        .max_by_key(|v| v.width)
+        .map(|v| absolute_url(v.url.clone(), domain))
+}
+
+/// Newer invidious versions return thumbnail urls relative to the instance, or without a scheme
+fn absolute_url(url: String, domain: &str) -> String {
+    if url.starts_with("//") {
+        format!("https:{url}")
+    } else if url.starts_with('/') {
+        format!("{domain}{url}")
+    } else {
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use pretty_assertions::assert_eq;
+
+    use super::{is_feed_url, parse_feed_data};
+
+    const CHANNEL: &str = r#"{
+    "author": "Example Channel",
+    "authorId": "UCabc123",
+    "description": "About examples",
+    "authorThumbnails": [
+        { "url": "//yt3.example.com/small.jpg", "width": 32, "height": 32 },
+        { "url": "//yt3.example.com/large.jpg", "width": 512, "height": 512 }
+    ],
+    "latestVideos": [
+        {
+            "type": "video",
+            "title": "Video 2",
+            "videoId": "vid2",
+            "lengthSeconds": 3723,
+            "published": 1704182400,
+            "description": "The second one",
+            "videoThumbnails": [{ "quality": "high", "url": "/vi/vid2/hqdefault.jpg", "width": 480, "height": 360 }]
+        },
+        {
+            "type": "video",
+            "title": "Live",
+            "videoId": "live",
+            "lengthSeconds": 0,
+            "published": 1704268800,
+            "liveNow": true
+        }
+    ]
+}"#;
+
+    const PLAYLIST: &str = r#"{
+    "type": "playlist",
+    "title": "Example Playlist",
+    "playlistId": "PLabc123",
+    "author": "Example Channel",
+    "description": "",
+    "videos": [
+        { "title": "Video 1", "videoId": "vid1", "author": "Example Channel", "lengthSeconds": 60, "index": 0 }
+    ]
+}"#;
+
+    #[test]
+    fn should_detect_feed_urls() {
+        assert!(is_feed_url("https://yewtu.be/api/v1/channels/UCabc123"));
+        assert!(is_feed_url("https://yewtu.be/api/v1/playlists/PLabc123"));
+        assert!(!is_feed_url("https://example.com/feed.xml"));
+    }
+
+    #[test]
+    fn should_parse_channel() {
+        let podcast = parse_feed_data(
+            CHANNEL.as_bytes(),
+            "https://yewtu.be/api/v1/channels/UCabc123",
+        )
+        .unwrap();
+
+        assert_eq!(podcast.title, "Example Channel");
+        assert_eq!(podcast.author.as_deref(), Some("Example Channel"));
+        assert_eq!(podcast.description.as_deref(), Some("About examples"));
+        assert_eq!(
+            podcast.image_url.as_deref(),
+            Some("https://yt3.example.com/large.jpg")
+        );
+        // the live stream is skipped
+        assert_eq!(podcast.episodes.len(), 1);
+
+        let episode = &podcast.episodes[0];
+        assert_eq!(episode.title, "Video 2");
+        assert_eq!(episode.guid, "vid2");
+        assert_eq!(
+            episode.url,
+            "https://yewtu.be/latest_version?id=vid2&itag=140&local=true"
+        );
+        assert_eq!(
+            episode.image_url.as_deref(),
+            Some("https://yewtu.be/vi/vid2/hqdefault.jpg")
+        );
+        assert_eq!(episode.description, "The second one");
+        assert_eq!(
+            episode.pubdate,
+            Some(
+                DateTime::parse_from_rfc3339("2024-01-02T08:00:00Z")
+                    .unwrap()
+                    .into()
+            )
+        );
+        assert_eq!(episode.duration, Some(3723));
+    }
+
+    #[test]
+    fn should_parse_playlist() {
+        let podcast = parse_feed_data(
+            PLAYLIST.as_bytes(),
+            "https://yewtu.be/api/v1/playlists/PLabc123",
+        )
+        .unwrap();
+
+        assert_eq!(podcast.title, "Example Playlist");
+        assert_eq!(podcast.author.as_deref(), Some("Example Channel"));
+        assert_eq!(podcast.description, None);
+        assert_eq!(podcast.episodes.len(), 1);
+
+        let episode = &podcast.episodes[0];
+        assert_eq!(
+            episode.url,
+            "https://yewtu.be/latest_version?id=vid1&itag=140&local=true"
+        );
+        assert_eq!(episode.pubdate, None);
+        assert_eq!(episode.duration, Some(60));
+    }
+}
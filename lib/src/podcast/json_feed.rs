@@ -0,0 +1,196 @@
+//! Parsing of JSON Feed 1.0 and 1.1
+//!
+//! <https://www.jsonfeed.org/version/1.1/>
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{EpisodeNoId, FeedValidators, PodcastNoId};
+
+/// The prefix of the `version` every JSON Feed has
+const VERSION_PREFIX: &str = "https://jsonfeed.org/version/";
+
+#[derive(Debug, Deserialize)]
+struct JsonFeed {
+    version: String,
+    title: String,
+    description: Option<String>,
+    icon: Option<String>,
+    favicon: Option<String>,
+    /// Version 1.0
+    author: Option<JsonAuthor>,
+    /// Version 1.1
+    #[serde(default)]
+    authors: Vec<JsonAuthor>,
+    #[serde(default)]
+    items: Vec<JsonItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonAuthor {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonItem {
+    /// Should be a string, but some feeds use numbers
+    id: Value,
+    title: Option<String>,
+    summary: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    image: Option<String>,
+    date_published: Option<String>,
+    #[serde(default)]
+    attachments: Vec<JsonAttachment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonAttachment {
+    url: String,
+    size_in_bytes: Option<u64>,
+    duration_in_seconds: Option<f64>,
+}
+
+/// Parse a JSON Feed into a podcast, the first attachment of a item is used as the episode file
+pub fn parse_feed_data(body: &[u8], url: &str) -> Result<PodcastNoId> {
+    let feed: JsonFeed = serde_json::from_slice(body).context("parsing JSON Feed")?;
+    if !feed.version.starts_with(VERSION_PREFIX) {
+        bail!("Unknown JSON Feed version: {}", feed.version);
+    }
+
+    let author = feed
+        .authors
+        .into_iter()
+        .chain(feed.author)
+        .find_map(|author| author.name);
+
+    Ok(PodcastNoId {
+        title: feed.title,
+        url: url.to_string(),
+        description: feed.description,
+        author,
+        explicit: None,
+        last_checked: Utc::now(),
+        episodes: feed.items.into_iter().map(parse_episode_data).collect(),
+        image_url: feed.icon.or(feed.favicon),
+        validators: FeedValidators::default(),
+    })
+}
+
+/// Convert a JSON Feed item into a episode, the `url` is empty if there is no attachment
+fn parse_episode_data(item: JsonItem) -> EpisodeNoId {
+    let attachment = item.attachments.into_iter().next();
+    let guid = match item.id {
+        Value::String(id) => id,
+        Value::Null => String::new(),
+        id => id.to_string(),
+    };
+
+    // durations are seconds, so they realistically fit into a i64
+    #[allow(clippy::cast_possible_truncation)]
+    let duration = attachment
+        .as_ref()
+        .and_then(|v| v.duration_in_seconds)
+        .map(|v| v.round() as i64);
+
+    let enclosure_length = attachment
+        .as_ref()
+        .and_then(|v| v.size_in_bytes)
+        .filter(|v| *v > 0);
+
+    EpisodeNoId {
+        title: item.title.unwrap_or_default(),
+        url: attachment.map(|v| v.url).unwrap_or_default(),
+        guid,
+        description: item
+            .content_html
+            .or(item.content_text)
+            .or(item.summary)
+            .unwrap_or_default(),
+        pubdate: item
+            .date_published
+            .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+            .map(|v| v.with_timezone(&Utc)),
+        duration,
+        image_url: item.image,
+        enclosure_length,
+        // JSON Feed has no equivalent of "<podcast:chapters>" and "<podcast:transcript>"
+        chapters_url: None,
+        transcript_url: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use pretty_assertions::assert_eq;
+
+    use super::parse_feed_data;
+
+    const FEED: &str = r#"{
+    "version": "https://jsonfeed.org/version/1.1",
+    "title": "Example Podcast",
+    "description": "About examples",
+    "icon": "https://example.com/icon.png",
+    "authors": [{ "name": "Jane Doe" }],
+    "items": [
+        {
+            "id": "2",
+            "title": "Episode 2",
+            "content_html": "<p>The second one</p>",
+            "date_published": "2024-01-02T09:00:00+01:00",
+            "attachments": [
+                { "url": "https://example.com/2.mp3", "mime_type": "audio/mpeg", "size_in_bytes": 1234, "duration_in_seconds": 3723 }
+            ]
+        },
+        {
+            "id": 1,
+            "content_text": "A post without audio"
+        }
+    ]
+}"#;
+
+    #[test]
+    fn should_parse_json_feed() {
+        let podcast = parse_feed_data(FEED.as_bytes(), "https://example.com/feed.json").unwrap();
+
+        assert_eq!(podcast.title, "Example Podcast");
+        assert_eq!(podcast.url, "https://example.com/feed.json");
+        assert_eq!(podcast.description.as_deref(), Some("About examples"));
+        assert_eq!(podcast.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            podcast.image_url.as_deref(),
+            Some("https://example.com/icon.png")
+        );
+        assert_eq!(podcast.episodes.len(), 2);
+
+        let episode = &podcast.episodes[0];
+        assert_eq!(episode.title, "Episode 2");
+        assert_eq!(episode.url, "https://example.com/2.mp3");
+        assert_eq!(episode.guid, "2");
+        assert_eq!(episode.description, "<p>The second one</p>");
+        assert_eq!(
+            episode.pubdate,
+            Some(
+                DateTime::parse_from_rfc3339("2024-01-02T08:00:00Z")
+                    .unwrap()
+                    .into()
+            )
+        );
+        assert_eq!(episode.duration, Some(3723));
+        assert_eq!(episode.enclosure_length, Some(1234));
+
+        let item = &podcast.episodes[1];
+        assert_eq!(item.guid, "1");
+        assert_eq!(item.url, "");
+        assert_eq!(item.description, "A post without audio");
+        assert_eq!(item.pubdate, None);
+    }
+
+    #[test]
+    fn should_reject_unknown_version() {
+        assert!(parse_feed_data(br#"{"version": "1", "title": "x"}"#, "").is_err());
+    }
+}
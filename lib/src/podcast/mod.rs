@@ -1,36 +1,53 @@
 // Thanks to the author of shellcaster(https://github.com/jeff-hughes/shellcaster). Most parts of following code are taken from it.
 
+mod atom;
 #[allow(unused)]
 pub mod db;
 #[allow(clippy::module_name_repetitions)]
 pub mod episode;
+pub mod gpodder;
+mod invidious_feed;
+mod json_feed;
 // repetetive name, but will do for now
 #[allow(clippy::module_inception)]
 mod podcast;
+pub mod transcript;
 
+use crate::chapters::{parse_json_chapters, Chapter};
 use crate::config::v2::server::PodcastSettings;
 use crate::taskpool::TaskPool;
 use crate::types::{Msg, PCMsg};
 use db::Database;
 use episode::{Episode, EpisodeNoId};
 #[allow(clippy::module_name_repetitions)]
-pub use podcast::{Podcast, PodcastNoId};
+pub use podcast::{FeedSettings, FeedValidators, Podcast, PodcastNoId};
+use transcript::{parse_transcript, transcript_path, TranscriptFormat};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bytes::Buf;
 use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use opml::{Body, Head, Outline, OPML};
+use quick_xml::events::Event;
+use quick_xml::Reader;
 use regex::Regex;
-use reqwest::ClientBuilder;
+use reqwest::header::{
+    HeaderMap, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    RANGE,
+};
+use reqwest::{ClientBuilder, StatusCode};
 use rfc822_sanitizer::parse_from_rfc2822_with_fallback;
 use rss::{Channel, Item};
 use sanitize_filename::{sanitize_with_options, Options};
-use std::fs::File;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
 use std::io::Write as _;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Sender};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// The namespace of the Podcasting 2.0 elements, like `<podcast:chapters>` and `<podcast:transcript>`
+const PODCAST_NAMESPACE: &str = "https://podcastindex.org/namespace/1.0";
 
 // How many columns we need, minimum, before we display the
 // (unplayed/total) after the podcast title
@@ -51,7 +68,7 @@ lazy_static! {
 
     /// Regex for removing "A", "An", and "The" from the beginning of
     /// podcast titles
-    static ref RE_ARTICLES: Regex = Regex::new(r"^(a|an|the) ").expect("Regex error");
+    pub(crate) static ref RE_ARTICLES: Regex = Regex::new(r"^(a|an|the) ").expect("Regex error");
 }
 
 /// Defines interface used for both podcasts and episodes, to be
@@ -68,6 +85,8 @@ pub struct PodcastFeed {
     pub id: Option<i64>,
     pub url: String,
     pub title: Option<String>,
+    /// Validators of the last fetch, to skip the download if the feed did not change
+    pub validators: FeedValidators,
 }
 
 impl PodcastFeed {
@@ -76,6 +95,18 @@ impl PodcastFeed {
             id,
             url: url.to_string(),
             title,
+            validators: FeedValidators::default(),
+        }
+    }
+}
+
+impl From<&Podcast> for PodcastFeed {
+    fn from(value: &Podcast) -> Self {
+        Self {
+            id: Some(value.id),
+            url: value.url.clone(),
+            title: Some(value.title.clone()),
+            validators: value.validators.clone(),
         }
     }
 }
@@ -86,8 +117,8 @@ impl PodcastFeed {
 pub fn check_feed(feed: PodcastFeed, max_retries: usize, tp: &TaskPool, tx_to_main: Sender<Msg>) {
     tp.execute(async move {
         let _ = tx_to_main.send(Msg::Podcast(PCMsg::FetchPodcastStart(feed.url.clone())));
-        match get_feed_data(&feed.url, max_retries).await {
-            Ok(pod) => match feed.id {
+        match get_feed_data(&feed, max_retries).await {
+            Ok(Some(pod)) => match feed.id {
                 Some(id) => {
                     let _ = tx_to_main.send(Msg::Podcast(PCMsg::SyncData((id, pod))));
                 }
@@ -95,6 +126,15 @@ pub fn check_feed(feed: PodcastFeed, max_retries: usize, tp: &TaskPool, tx_to_ma
                     let _ = tx_to_main.send(Msg::Podcast(PCMsg::NewData(pod)));
                 }
             },
+            Ok(None) => {
+                if let Some(id) = feed.id {
+                    let _ = tx_to_main.send(Msg::Podcast(PCMsg::SyncUnchanged(id, feed.url)));
+                } else {
+                    // new feeds are fetched without validators, so this only happens with misbehaving servers
+                    error!("Feed \"{}\" was not modified, but is not stored", feed.url);
+                    let _ = tx_to_main.send(Msg::Podcast(PCMsg::Error(feed.url.clone(), feed)));
+                }
+            }
             Err(err) => {
                 error!("get_feed_data had a Error: {:#?}", err);
                 let _ = tx_to_main.send(Msg::Podcast(PCMsg::Error(feed.url.to_string(), feed)));
@@ -103,15 +143,25 @@ pub fn check_feed(feed: PodcastFeed, max_retries: usize, tp: &TaskPool, tx_to_ma
     });
 }
 
-/// Given a URL, this attempts to pull the data about a podcast and its
+/// Given a feed, this attempts to pull the data about a podcast and its
 /// episodes from an RSS feed.
-async fn get_feed_data(url: &str, mut max_retries: usize) -> Result<PodcastNoId> {
+///
+/// Returns `None` if the feed was not modified since the fetch `feed.validators` are from.
+async fn get_feed_data(feed: &PodcastFeed, mut max_retries: usize) -> Result<Option<PodcastNoId>> {
     let agent = ClientBuilder::new()
         .connect_timeout(Duration::from_secs(5))
         .build()?;
 
     let resp: reqwest::Response = loop {
-        let response = agent.get(url).send().await;
+        let mut request = agent.get(&feed.url);
+        if let Some(etag) = &feed.validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &feed.validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let response = request.send().await;
         if let Ok(resp) = response {
             break resp;
         }
@@ -121,8 +171,84 @@ async fn get_feed_data(url: &str, mut max_retries: usize) -> Result<PodcastNoId>
         }
     };
 
-    let channel = Channel::read_from(resp.bytes().await?.reader())?;
-    Ok(parse_feed_data(channel, url))
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    let validators = feed_validators(resp.headers());
+    let body = resp.bytes().await?;
+    let format = if invidious_feed::is_feed_url(&feed.url) {
+        FeedFormat::Invidious
+    } else {
+        FeedFormat::sniff(&body)
+    };
+    let mut podcast = match format {
+        FeedFormat::Rss => {
+            let channel = Channel::read_from(body.reader()).context("parsing RSS feed")?;
+            parse_feed_data(channel, &feed.url)
+        }
+        FeedFormat::Atom => atom::parse_feed_data(&body, &feed.url)?,
+        FeedFormat::JsonFeed => json_feed::parse_feed_data(&body, &feed.url)?,
+        FeedFormat::Invidious => invidious_feed::parse_feed_data(&body, &feed.url)?,
+    };
+    podcast.validators = validators;
+
+    Ok(Some(podcast))
+}
+
+/// The supported feed formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FeedFormat {
+    Rss,
+    Atom,
+    JsonFeed,
+    /// A youtube channel or playlist from a invidious instance, detected by the url instead of the content
+    Invidious,
+}
+
+impl FeedFormat {
+    /// Detect the format from the content, as the `Content-Type` is often not set correctly
+    ///
+    /// JSON is detected by a starting `{`, XML by its root element being `feed` (Atom), everything else is treated as RSS.
+    fn sniff(body: &[u8]) -> Self {
+        let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+        if body.iter().find(|v| !v.is_ascii_whitespace()) == Some(&b'{') {
+            return Self::JsonFeed;
+        }
+
+        let mut reader = Reader::from_reader(body);
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e) | Event::Empty(ref e)) => {
+                    return if e.local_name().as_ref() == b"feed" {
+                        Self::Atom
+                    } else {
+                        Self::Rss
+                    };
+                }
+                // let the RSS parser report the error
+                Ok(Event::Eof) | Err(_) => return Self::Rss,
+                // skip the declaration, comments, doctype, etc
+                Ok(_) => buf.clear(),
+            }
+        }
+    }
+}
+
+/// Get the validators from the response headers, ignoring values that are not valid strings
+fn feed_validators(headers: &HeaderMap) -> FeedValidators {
+    let get = |name| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(ToString::to_string)
+    };
+
+    FeedValidators {
+        etag: get(ETAG),
+        last_modified: get(LAST_MODIFIED),
+    }
 }
 
 /// Given a Channel with the RSS feed data, this parses the data about a
@@ -152,11 +278,12 @@ fn parse_feed_data(channel: Channel, url: &str) -> PodcastNoId {
         image_url = itunes.image().map(std::string::ToString::to_string);
     }
 
+    let podcast_prefix = podcast_prefix(channel.namespaces()).to_string();
     let mut episodes = Vec::new();
     let items = channel.into_items();
     if !items.is_empty() {
         for item in &items {
-            episodes.push(parse_episode_data(item));
+            episodes.push(parse_episode_data(item, &podcast_prefix));
         }
     }
 
@@ -169,6 +296,7 @@ fn parse_feed_data(channel: Channel, url: &str) -> PodcastNoId {
         last_checked,
         episodes,
         image_url,
+        validators: FeedValidators::default(),
     }
 }
 
@@ -177,12 +305,18 @@ fn parse_feed_data(channel: Channel, url: &str) -> PodcastNoId {
 /// podcast RSS feeds that a feed should adhere to, but this does try to
 /// make some attempt to account for the possibility that a feed might
 /// not be valid according to the spec.
-fn parse_episode_data(item: &Item) -> EpisodeNoId {
+///
+/// `podcast_prefix` is the prefix the feed uses for [`PODCAST_NAMESPACE`].
+fn parse_episode_data(item: &Item, podcast_prefix: &str) -> EpisodeNoId {
     let title = item.title().unwrap_or("").to_string();
     let url = match item.enclosure() {
         Some(enc) => enc.url().to_string(),
         None => String::new(),
     };
+    let enclosure_length = item
+        .enclosure()
+        .and_then(|enc| enc.length().parse().ok())
+        .filter(|v| *v > 0);
     let guid = match item.guid() {
         Some(guid) => guid.value().to_string(),
         None => String::new(),
@@ -200,6 +334,17 @@ fn parse_episode_data(item: &Item) -> EpisodeNoId {
         image_url = itunes.image().map(std::string::ToString::to_string);
     }
 
+    let chapters_url = item
+        .extensions()
+        .get(podcast_prefix)
+        .and_then(|v| v.get("chapters"))
+        .and_then(|v| v.iter().find_map(|ext| json_chapters_url(ext.attrs())));
+    let transcript_url = item
+        .extensions()
+        .get(podcast_prefix)
+        .and_then(|v| v.get("transcript"))
+        .and_then(|v| transcript_url(v.iter().map(|ext| ext.attrs())));
+
     EpisodeNoId {
         title,
         url,
@@ -208,9 +353,43 @@ fn parse_episode_data(item: &Item) -> EpisodeNoId {
         pubdate,
         duration,
         image_url,
+        enclosure_length,
+        chapters_url,
+        transcript_url,
     }
 }
 
+/// Find the prefix the feed declared for [`PODCAST_NAMESPACE`], defaulting to `podcast`
+fn podcast_prefix(namespaces: &BTreeMap<String, String>) -> &str {
+    namespaces
+        .iter()
+        .find(|(_, uri)| uri.trim_end_matches('/') == PODCAST_NAMESPACE)
+        .map_or("podcast", |(prefix, _)| prefix.as_str())
+}
+
+/// Get the `url` of a `<podcast:chapters>` element from its attributes, if it is the JSON format
+fn json_chapters_url(attrs: &BTreeMap<String, String>) -> Option<String> {
+    let is_json = attrs.get("type").map_or(true, |v| v.contains("json"));
+    attrs
+        .get("url")
+        .filter(|v| is_json && !v.is_empty())
+        .cloned()
+}
+
+/// Get the `url` of the preferred `<podcast:transcript>` element from their attributes, if any has a supported format
+fn transcript_url<'a, I: Iterator<Item = &'a BTreeMap<String, String>>>(
+    elements: I,
+) -> Option<String> {
+    elements
+        .filter_map(|attrs| {
+            let format = TranscriptFormat::from_mime(attrs.get("type")?)?;
+            let url = attrs.get("url").filter(|v| !v.is_empty())?;
+            Some((format, url))
+        })
+        .min_by_key(|(format, _)| *format)
+        .map(|(_, url)| url.clone())
+}
+
 /// Given a string representing an episode duration, this attempts to
 /// convert to an integer representing the duration in seconds. Covers
 /// formats HH:MM:SS, MM:SS, and SS. If the duration cannot be converted
@@ -338,7 +517,7 @@ pub fn import_from_opml(db_path: &Path, config: &PodcastSettings, file: &Path) -
                 }
             }
 
-            Msg::Podcast(PCMsg::SyncData((_id, _pod))) => {
+            Msg::Podcast(PCMsg::SyncData(_) | PCMsg::SyncUnchanged(..)) => {
                 msg_counter += 1;
             }
             _ => {}
@@ -438,6 +617,14 @@ pub struct EpData {
     pub url: String,
     pub pubdate: Option<DateTime<Utc>>,
     pub file_path: Option<PathBuf>,
+    /// The file size in bytes as reported by the feed, if known
+    pub enclosure_length: Option<u64>,
+    /// The url of the `<podcast:chapters>` JSON document, if the feed has one
+    pub chapters_url: Option<String>,
+    /// The chapters fetched from `chapters_url`, set once the download completed
+    pub chapters: Vec<Chapter>,
+    /// The url of the `<podcast:transcript>`, which gets cached next to the downloaded file
+    pub transcript_url: Option<String>,
 }
 
 /// This is the function the main controller uses to indicate new files to download.
@@ -459,41 +646,252 @@ pub fn download_list(
         let dest2 = dest.to_path_buf();
         tp.execute(async move {
             let _ = tx.send(Msg::Podcast(PCMsg::DLStart(ep.clone())));
-            let result = download_file(ep, dest2, max_retries).await;
+            let result = download_file(ep, dest2, max_retries, &tx).await;
             let _ = tx.send(Msg::Podcast(result));
         });
     }
 }
 
+/// Minimal time between [`PCMsg::DLProgress`] messages of a single download
+const DOWNLOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Why a single download attempt failed
+#[derive(Debug)]
+enum DownloadError {
+    /// The request or response failed, the attempt may be retried
+    Response(anyhow::Error),
+    FileCreate(std::io::Error),
+    FileWrite(std::io::Error),
+}
+
 /// Downloads a file to a local filepath, returning `DownloadMsg` variant
 /// indicating success or failure.
+///
+/// The file is downloaded into a `.part` file first, which is resumed with a `Range` request on retries (and on later downloads
+/// of the same episode) and only renamed to the final path once the size has been verified.
+///
+/// The chapters from `chapters_url` and the transcript from `transcript_url` are fetched afterwards,
+/// failing to do so does not fail the download.
+///
+/// Sends [`PCMsg::DLProgress`] to `tx` while downloading.
 async fn download_file(
     mut ep_data: EpData,
     destination_path: PathBuf,
-    mut max_retries: usize,
+    max_retries: usize,
+    tx: &Sender<Msg>,
 ) -> PCMsg {
     let agent = ClientBuilder::new()
         .connect_timeout(Duration::from_secs(10))
         .build()
         .expect("reqwest client build failed");
 
-    let response: reqwest::Response = loop {
-        let response = agent.get(&ep_data.url).send().await;
-        if let Ok(resp) = response {
-            break resp;
+    let mut file_name = sanitize_with_options(
+        &ep_data.title,
+        Options {
+            truncate: true,
+            windows: true, // for simplicity, we'll just use Windows-friendly paths for everyone
+            replacement: "",
+        },
+    );
+
+    if let Some(pubdate) = ep_data.pubdate {
+        file_name = format!("{file_name}_{}", pubdate.format("%Y%m%d_%H%M%S"));
+    }
+
+    let part_path = destination_path.join(format!("{file_name}.part"));
+
+    let mut attempts = 0;
+    let ext = loop {
+        match download_attempt(&agent, &ep_data, &part_path, tx).await {
+            Ok(ext) => break ext,
+            Err(DownloadError::Response(err)) => {
+                attempts += 1;
+                warn!(
+                    "Downloading \"{}\" failed (attempt {attempts}): {err:#}",
+                    ep_data.title
+                );
+                if attempts >= max_retries {
+                    return PCMsg::DLResponseError(ep_data);
+                }
+            }
+            Err(DownloadError::FileCreate(err)) => {
+                error!("Could not create \"{}\": {err}", part_path.display());
+                return PCMsg::DLFileCreateError(ep_data);
+            }
+            Err(DownloadError::FileWrite(err)) => {
+                error!("Could not write \"{}\": {err}", part_path.display());
+                return PCMsg::DLFileWriteError(ep_data);
+            }
         }
-        max_retries -= 1;
-        if max_retries == 0 {
-            return PCMsg::DLResponseError(ep_data);
+    };
+
+    let file_path = destination_path.join(format!("{file_name}.{ext}"));
+    if std::fs::rename(&part_path, &file_path).is_err() {
+        return PCMsg::DLFileWriteError(ep_data);
+    }
+
+    if let Some(url) = &ep_data.chapters_url {
+        match fetch_chapters(&agent, url).await {
+            Ok(chapters) => ep_data.chapters = chapters,
+            Err(err) => warn!(
+                "Fetching the chapters of \"{}\" failed: {err:#}",
+                ep_data.title
+            ),
+        }
+    }
+
+    if let Some(url) = &ep_data.transcript_url {
+        if let Err(err) = fetch_transcript(&agent, url, &file_path).await {
+            warn!(
+                "Fetching the transcript of \"{}\" failed: {err:#}",
+                ep_data.title
+            );
+        }
+    }
+
+    ep_data.file_path = Some(file_path);
+
+    PCMsg::DLComplete(ep_data)
+}
+
+/// Fetch and parse a `<podcast:chapters>` JSON document
+async fn fetch_chapters(agent: &reqwest::Client, url: &str) -> Result<Vec<Chapter>> {
+    let response = agent.get(url).send().await?.error_for_status()?;
+    let body = response.bytes().await?;
+
+    parse_json_chapters(&body)
+}
+
+/// Fetch a `<podcast:transcript>` and cache it next to the episode downloaded to `episode_path`
+async fn fetch_transcript(agent: &reqwest::Client, url: &str, episode_path: &Path) -> Result<()> {
+    let response = agent.get(url).send().await?.error_for_status()?;
+    let body = response.text().await?;
+
+    // only cache transcripts that can actually be shown
+    let format = TranscriptFormat::sniff(&body);
+    parse_transcript(&body, format)?;
+
+    let path = transcript_path(episode_path, format);
+    std::fs::write(&path, body).with_context(|| format!("writing \"{}\"", path.display()))?;
+
+    Ok(())
+}
+
+/// Download (or resume) the episode into `part_path`, returning the file extension to use on success
+async fn download_attempt(
+    agent: &reqwest::Client,
+    ep_data: &EpData,
+    part_path: &Path,
+    tx: &Sender<Msg>,
+) -> Result<&'static str, DownloadError> {
+    let offset = std::fs::metadata(part_path).map_or(0, |v| v.len());
+
+    let mut request = agent.get(&ep_data.url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+    let mut response = request
+        .send()
+        .await
+        .map_err(|err| DownloadError::Response(err.into()))?;
+
+    let status = response.status();
+    let (mut dst, mut downloaded, total) = if status == StatusCode::PARTIAL_CONTENT && offset > 0 {
+        let range = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_content_range);
+        let Some((start, total)) = range.filter(|(start, _)| *start == Some(offset)) else {
+            // cannot be appended, start over on the next attempt
+            let _ = std::fs::remove_file(part_path);
+            return Err(DownloadError::Response(anyhow!(
+                "Unexpected Content-Range {range:?} for offset {offset}"
+            )));
+        };
+        let total = total.or_else(|| response.content_length().map(|v| v + offset));
+        let dst = OpenOptions::new()
+            .append(true)
+            .open(part_path)
+            .map_err(DownloadError::FileCreate)?;
+
+        (dst, start.unwrap_or(offset), total)
+    } else if status.is_success() {
+        // either a new download or the server does not support ranges
+        let dst = File::create(part_path).map_err(DownloadError::FileCreate)?;
+
+        (dst, 0, response.content_length())
+    } else {
+        if status == StatusCode::RANGE_NOT_SATISFIABLE {
+            // the part file does not match the file anymore, start over on the next attempt
+            let _ = std::fs::remove_file(part_path);
         }
+        return Err(DownloadError::Response(anyhow!("HTTP status {status}")));
     };
 
-    // figure out the file type
-    let ext = if let Some(content_type) = response
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-    {
+    let ext = file_extension(response.headers());
+    let mut last_progress = Instant::now();
+    // the enclosure length from the feed is often only approximate, so it is only a hint for the progress
+    let progress_total = total.or(ep_data.enclosure_length);
+
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(err) => return Err(DownloadError::Response(err.into())),
+        };
+
+        dst.write_all(&chunk).map_err(DownloadError::FileWrite)?;
+
+        downloaded += chunk.len() as u64;
+        if last_progress.elapsed() >= DOWNLOAD_PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            let _ = tx.send(Msg::Podcast(PCMsg::DLProgress(
+                ep_data.clone(),
+                downloaded,
+                progress_total,
+            )));
+        }
+    }
+    dst.flush().map_err(DownloadError::FileWrite)?;
+
+    verify_download_size(downloaded, total).map_err(|err| {
+        if total.is_some_and(|total| downloaded > total) {
+            // more than the server said there is, the part file cannot be trusted anymore
+            let _ = std::fs::remove_file(part_path);
+        }
+        DownloadError::Response(err)
+    })?;
+
+    Ok(ext)
+}
+
+/// Check that the download is complete
+///
+/// Only the size reported by the server with `Content-Length` or `Content-Range` is enforced, it has to match exactly.
+fn verify_download_size(downloaded: u64, total: Option<u64>) -> Result<()> {
+    match total {
+        Some(total) if downloaded != total => {
+            bail!("Downloaded {downloaded} bytes, but the server reported {total} bytes")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parse a `Content-Range: bytes START-END/TOTAL` header into `(START, TOTAL)`, both of which may be `*`
+fn parse_content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range
+        .split_once('-')
+        .and_then(|(start, _)| start.parse().ok());
+    let total = total.parse().ok();
+
+    Some((start, total))
+}
+
+/// Figure out the file extension from the `Content-Type`, defaulting to `mp3`
+fn file_extension(headers: &HeaderMap) -> &'static str {
+    if let Some(content_type) = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
         match content_type {
             "audio/x-m4a" | "audio/mp4" => "m4a",
             "audio/x-matroska" => "mka",
@@ -510,36 +908,331 @@ async fn download_file(
     } else {
         error!("The response doesn't contain a content type, using \"mp3\" as fallback!");
         "mp3"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+    use std::thread::JoinHandle;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        download_file, get_feed_data, parse_content_range, parse_feed_data, verify_download_size,
+        EpData, FeedFormat, FeedValidators, PCMsg, PodcastFeed,
     };
 
-    let mut file_name = sanitize_with_options(
-        &ep_data.title,
-        Options {
-            truncate: true,
-            windows: true, // for simplicity, we'll just use Windows-friendly paths for everyone
-            replacement: "",
-        },
-    );
+    #[test]
+    fn should_sniff_feed_format() {
+        assert_eq!(
+            FeedFormat::sniff(br#"<?xml version="1.0"?><rss version="2.0"><channel/></rss>"#),
+            FeedFormat::Rss
+        );
+        assert_eq!(
+            FeedFormat::sniff(
+                b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n<!-- comment -->\n<feed xmlns=\"http://www.w3.org/2005/Atom\"></feed>"
+            ),
+            FeedFormat::Atom
+        );
+        assert_eq!(
+            FeedFormat::sniff(br#"<a:feed xmlns:a="http://www.w3.org/2005/Atom"/>"#),
+            FeedFormat::Atom
+        );
+        assert_eq!(
+            FeedFormat::sniff(b"\n  {\"version\": \"https://jsonfeed.org/version/1.1\"}"),
+            FeedFormat::JsonFeed
+        );
+        assert_eq!(FeedFormat::sniff(b"not a feed"), FeedFormat::Rss);
+    }
 
-    if let Some(pubdate) = ep_data.pubdate {
-        file_name = format!("{file_name}_{}", pubdate.format("%Y%m%d_%H%M%S"));
+    #[test]
+    fn should_parse_rss_chapters_url() {
+        let feed = br#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:podcast="https://podcastindex.org/namespace/1.0">
+  <channel>
+    <title>Example Podcast</title>
+    <item>
+      <title>Episode 2</title>
+      <enclosure url="https://example.com/2.mp3" length="1234" type="audio/mpeg"/>
+      <podcast:chapters url="https://example.com/2.json" type="application/json+chapters"/>
+    </item>
+    <item>
+      <title>Episode 1</title>
+      <enclosure url="https://example.com/1.mp3" length="1234" type="audio/mpeg"/>
+      <podcast:chapters url="https://example.com/1.psc" type="application/psc+xml"/>
+    </item>
+  </channel>
+</rss>"#;
+        let channel = rss::Channel::read_from(&feed[..]).unwrap();
+        let podcast = parse_feed_data(channel, "https://example.com/feed.rss");
+
+        assert_eq!(
+            podcast.episodes[0].chapters_url.as_deref(),
+            Some("https://example.com/2.json")
+        );
+        assert_eq!(podcast.episodes[1].chapters_url, None);
     }
 
-    let mut file_path = destination_path;
-    file_path.push(format!("{file_name}.{ext}"));
+    #[test]
+    fn should_parse_rss_transcript_url() {
+        let feed = br#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:pc="https://podcastindex.org/namespace/1.0">
+  <channel>
+    <title>Example Podcast</title>
+    <item>
+      <title>Episode 2</title>
+      <enclosure url="https://example.com/2.mp3" length="1234" type="audio/mpeg"/>
+      <pc:transcript url="https://example.com/2.html" type="text/html"/>
+      <pc:transcript url="https://example.com/2.json" type="application/json"/>
+      <pc:transcript url="https://example.com/2.srt" type="application/x-subrip"/>
+    </item>
+    <item>
+      <title>Episode 1</title>
+      <enclosure url="https://example.com/1.mp3" length="1234" type="audio/mpeg"/>
+      <pc:transcript url="https://example.com/1.txt" type="text/plain"/>
+    </item>
+  </channel>
+</rss>"#;
+        let channel = rss::Channel::read_from(&feed[..]).unwrap();
+        let podcast = parse_feed_data(channel, "https://example.com/feed.rss");
 
-    let Ok(mut dst) = File::create(&file_path) else {
-        return PCMsg::DLFileCreateError(ep_data);
-    };
+        assert_eq!(
+            podcast.episodes[0].transcript_url.as_deref(),
+            Some("https://example.com/2.srt")
+        );
+        assert_eq!(podcast.episodes[1].transcript_url, None);
+    }
 
-    ep_data.file_path = Some(file_path);
+    #[test]
+    fn should_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 100-999/1000"),
+            Some((Some(100), Some(1000)))
+        );
+        assert_eq!(
+            parse_content_range("bytes 100-999/*"),
+            Some((Some(100), None))
+        );
+        assert_eq!(
+            parse_content_range("bytes */1000"),
+            Some((None, Some(1000)))
+        );
+        assert_eq!(parse_content_range("items 1-2/3"), None);
+    }
 
-    let Ok(bytes) = response.bytes().await else {
-        return PCMsg::DLFileCreateError(ep_data);
-    };
+    #[test]
+    fn should_verify_download_size() {
+        assert!(verify_download_size(10, Some(10)).is_ok());
+        assert!(verify_download_size(9, Some(10)).is_err());
+        assert!(verify_download_size(11, Some(10)).is_err());
+        assert!(verify_download_size(10, None).is_ok());
+    }
+
+    /// The `ETag` sent by [`serve`]
+    const SERVE_ETAG: &str = "\"termusic-test\"";
+    /// The `Last-Modified` sent by [`serve`]
+    const SERVE_LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+    /// A minimal HTTP stand-in serving `body` for `connections` requests, supporting `Range: bytes=N-`
+    /// and conditional requests matching [`SERVE_ETAG`] or [`SERVE_LAST_MODIFIED`]
+    ///
+    /// The first `truncated` responses close the connection after half of the body.
+    /// Returns the url and a handle resolving to the requested range starts.
+    fn serve(
+        body: Vec<u8>,
+        connections: usize,
+        truncated: usize,
+    ) -> (String, JoinHandle<Vec<Option<u64>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/episode.mp3", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let mut ranges = Vec::new();
+            for idx in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut range = None;
+                let mut if_none_match = None;
+                let mut if_modified_since = None;
+                for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let line = line.to_lowercase();
+                    if let Some(value) = line.strip_prefix("range: bytes=") {
+                        range = value.trim_end_matches('-').parse::<u64>().ok();
+                    } else if let Some(value) = line.strip_prefix("if-none-match: ") {
+                        if_none_match = Some(value.to_string());
+                    } else if let Some(value) = line.strip_prefix("if-modified-since: ") {
+                        if_modified_since = Some(value.to_string());
+                    }
+                }
+                ranges.push(range);
+
+                // "If-None-Match" takes precedence over "If-Modified-Since"
+                let not_modified = match if_none_match {
+                    Some(etag) => etag == SERVE_ETAG,
+                    None => {
+                        if_modified_since.is_some_and(|v| v == SERVE_LAST_MODIFIED.to_lowercase())
+                    }
+                };
+                if not_modified {
+                    let _ =
+                        stream.write_all(b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n");
+                    continue;
+                }
+
+                let len = body.len();
+                let start = range.map_or(0, |v| usize::try_from(v).unwrap());
+                let head = match range {
+                    Some(_) => format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{}/{len}\r\n",
+                        len - 1
+                    ),
+                    None => "HTTP/1.1 200 OK\r\n".to_string(),
+                };
+                let rest = &body[start..];
+                let send = if idx < truncated {
+                    &rest[..rest.len() / 2]
+                } else {
+                    rest
+                };
+                let _ = stream.write_all(
+                    format!(
+                        "{head}Content-Type: audio/mpeg\r\nContent-Length: {}\r\nETag: {SERVE_ETAG}\r\n\
+                        Last-Modified: {SERVE_LAST_MODIFIED}\r\nConnection: close\r\n\r\n",
+                        rest.len()
+                    )
+                    .as_bytes(),
+                );
+                let _ = stream.write_all(send);
+            }
+
+            ranges
+        });
+
+        (url, handle)
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("termusic-download-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn ep_data(url: String) -> EpData {
+        EpData {
+            id: 1,
+            pod_id: 1,
+            title: "episode".to_string(),
+            url,
+            pubdate: None,
+            file_path: None,
+            enclosure_length: None,
+            chapters_url: None,
+            chapters: Vec::new(),
+            transcript_url: None,
+        }
+    }
+
+    fn body() -> Vec<u8> {
+        (0..10_000u32).map(|v| (v % 251) as u8).collect()
+    }
+
+    async fn download(url: String, dir: &Path, max_retries: usize) -> PCMsg {
+        let (tx, _rx) = mpsc::channel();
+        download_file(ep_data(url), dir.to_path_buf(), max_retries, &tx).await
+    }
+
+    #[tokio::test]
+    async fn should_resume_interrupted_download() {
+        let dir = test_dir("resume");
+        let (url, server) = serve(body(), 2, 1);
+
+        let PCMsg::DLComplete(ep) = download(url, &dir, 3).await else {
+            panic!("download failed");
+        };
+
+        assert_eq!(server.join().unwrap(), vec![None, Some(5000)]);
+        assert_eq!(
+            ep.file_path.as_deref(),
+            Some(dir.join("episode.mp3").as_path())
+        );
+        assert_eq!(std::fs::read(dir.join("episode.mp3")).unwrap(), body());
+        assert!(!dir.join("episode.part").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_resume_existing_part_file() {
+        let dir = test_dir("existing");
+        std::fs::write(dir.join("episode.part"), &body()[..3000]).unwrap();
+        let (url, server) = serve(body(), 1, 0);
+
+        assert!(matches!(download(url, &dir, 3).await, PCMsg::DLComplete(_)));
+
+        assert_eq!(server.join().unwrap(), vec![Some(3000)]);
+        assert_eq!(std::fs::read(dir.join("episode.mp3")).unwrap(), body());
+        assert!(!dir.join("episode.part").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_keep_part_file_on_failure() {
+        let dir = test_dir("failure");
+        let (url, server) = serve(body(), 2, 2);
+
+        assert!(matches!(
+            download(url, &dir, 2).await,
+            PCMsg::DLResponseError(_)
+        ));
+
+        assert_eq!(server.join().unwrap(), vec![None, Some(5000)]);
+        assert!(!dir.join("episode.mp3").exists());
+        assert_eq!(
+            std::fs::read(dir.join("episode.part")).unwrap(),
+            &body()[..7500]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_skip_unmodified_feed() {
+        let feed_body =
+            br#"<?xml version="1.0"?><rss version="2.0"><channel><title>Feed</title></channel></rss>"#;
+        let (url, server) = serve(feed_body.to_vec(), 4, 0);
+
+        // the first fetch has no validators yet
+        let mut feed = PodcastFeed::new(Some(1), &url, None);
+        let podcast = get_feed_data(&feed, 1).await.unwrap().unwrap();
+        assert_eq!(
+            podcast.validators,
+            FeedValidators {
+                etag: Some(SERVE_ETAG.to_string()),
+                last_modified: Some(SERVE_LAST_MODIFIED.to_string()),
+            }
+        );
+
+        feed.validators = podcast.validators;
+        assert!(get_feed_data(&feed, 1).await.unwrap().is_none());
+
+        // "If-Modified-Since" alone
+        feed.validators.etag = None;
+        assert!(get_feed_data(&feed, 1).await.unwrap().is_none());
+
+        // a changed feed is fetched again, even if it is not newer
+        feed.validators.etag = Some("\"other\"".to_string());
+        assert!(get_feed_data(&feed, 1).await.unwrap().is_some());
 
-    match std::io::copy(&mut bytes.reader(), &mut dst) {
-        Ok(_) => PCMsg::DLComplete(ep_data),
-        Err(_) => PCMsg::DLFileWriteError(ep_data),
+        assert_eq!(server.join().unwrap(), vec![None; 4]);
     }
 }
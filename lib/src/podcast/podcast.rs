@@ -1,7 +1,8 @@
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 
 use chrono::{DateTime, Utc};
 
+use crate::config::v2::server::PodcastSettings;
 use crate::utils::StringUtils;
 
 use super::{
@@ -23,6 +24,8 @@ pub struct Podcast {
     pub last_checked: DateTime<Utc>,
     pub episodes: Vec<Episode>,
     pub image_url: Option<String>,
+    pub settings: FeedSettings,
+    pub validators: FeedValidators,
 }
 
 impl Podcast {
@@ -33,6 +36,82 @@ impl Podcast {
             .map(|ep| usize::from(!ep.is_played()))
             .sum()
     }
+
+    /// Get the downloaded episodes that should be deleted, according to the feed's (or the default) retention settings
+    ///
+    /// Those are all downloads after the newest `keep_downloaded` and all downloads played more than `delete_played_after_days` ago.
+    pub fn expired_downloads(
+        &self,
+        defaults: &PodcastSettings,
+        now: DateTime<Utc>,
+    ) -> Vec<&Episode> {
+        let keep = self.settings.keep_downloaded(defaults);
+        let max_age = match self.settings.delete_played_after_days(defaults) {
+            0 => None,
+            days => Some(chrono::Duration::days(i64::from(days))),
+        };
+
+        let mut downloaded: Vec<&Episode> = self
+            .episodes
+            .iter()
+            .filter(|ep| ep.path.is_some())
+            .collect();
+        // newest first, episodes without a date count as the oldest
+        downloaded.sort_by_key(|ep| Reverse(ep.pubdate));
+
+        downloaded
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, ep)| {
+                let over_limit = keep != 0 && *idx >= keep as usize;
+                let played_expired = max_age.is_some_and(|max_age| {
+                    ep.played && ep.played_at.is_some_and(|at| now - at >= max_age)
+                });
+
+                over_limit || played_expired
+            })
+            .map(|(_, ep)| ep)
+            .collect()
+    }
+}
+
+/// The HTTP validators returned with a feed, to only download it again if it changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedValidators {
+    /// The `ETag` header, send as `If-None-Match`
+    pub etag: Option<String>,
+    /// The `Last-Modified` header, send as `If-Modified-Since`
+    pub last_modified: Option<String>,
+}
+
+/// Settings for a single feed, `None` uses the default from [`PodcastSettings`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeedSettings {
+    /// Download new episodes after the feed got refreshed
+    pub auto_download: Option<bool>,
+    /// Only keep the newest this many downloaded episodes, `0` keeps all
+    pub keep_downloaded: Option<u32>,
+    /// Delete downloaded episodes this many days after they have been played, `0` disables it
+    pub delete_played_after_days: Option<u32>,
+    /// Playback speed for the episodes of this feed, in the same format as `player.speed`
+    ///
+    /// There is no default for this, `None` keeps the current speed.
+    pub playback_speed: Option<i32>,
+}
+
+impl FeedSettings {
+    pub fn auto_download(&self, defaults: &PodcastSettings) -> bool {
+        self.auto_download.unwrap_or(defaults.auto_download)
+    }
+
+    pub fn keep_downloaded(&self, defaults: &PodcastSettings) -> u32 {
+        self.keep_downloaded.unwrap_or(defaults.keep_downloaded)
+    }
+
+    pub fn delete_played_after_days(&self, defaults: &PodcastSettings) -> u32 {
+        self.delete_played_after_days
+            .unwrap_or(defaults.delete_played_after_days)
+    }
 }
 
 impl Menuable for Podcast {
@@ -100,4 +179,135 @@ pub struct PodcastNoId {
     pub last_checked: DateTime<Utc>,
     pub episodes: Vec<EpisodeNoId>,
     pub image_url: Option<String>,
+    pub validators: FeedValidators,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::{DateTime, Duration, Utc};
+
+    use super::{FeedSettings, FeedValidators, Podcast};
+    use crate::config::v2::server::PodcastSettings;
+    use crate::podcast::episode::Episode;
+
+    fn episode(
+        id: i64,
+        days_ago: i64,
+        downloaded: bool,
+        played_days_ago: Option<i64>,
+        now: DateTime<Utc>,
+    ) -> Episode {
+        Episode {
+            id,
+            pubdate: Some(now - Duration::days(days_ago)),
+            path: downloaded.then(|| PathBuf::from(format!("/tmp/{id}.mp3"))),
+            played: played_days_ago.is_some(),
+            played_at: played_days_ago.map(|days| now - Duration::days(days)),
+            ..Default::default()
+        }
+    }
+
+    fn podcast(episodes: Vec<Episode>, settings: FeedSettings) -> Podcast {
+        Podcast {
+            id: 1,
+            title: String::new(),
+            sort_title: String::new(),
+            url: String::new(),
+            description: None,
+            author: None,
+            explicit: None,
+            last_checked: Utc::now(),
+            episodes,
+            image_url: None,
+            settings,
+            validators: FeedValidators::default(),
+        }
+    }
+
+    fn expired_ids(podcast: &Podcast, defaults: &PodcastSettings, now: DateTime<Utc>) -> Vec<i64> {
+        podcast
+            .expired_downloads(defaults, now)
+            .iter()
+            .map(|ep| ep.id)
+            .collect()
+    }
+
+    #[test]
+    fn should_keep_all_downloads_by_default() {
+        let now = Utc::now();
+        let podcast = podcast(
+            vec![
+                episode(1, 1, true, Some(100), now),
+                episode(2, 2, true, None, now),
+            ],
+            FeedSettings::default(),
+        );
+
+        assert!(expired_ids(&podcast, &PodcastSettings::default(), now).is_empty());
+    }
+
+    #[test]
+    fn should_expire_downloads_over_limit() {
+        let now = Utc::now();
+        // not sorted on purpose
+        let podcast = podcast(
+            vec![
+                episode(3, 3, true, None, now),
+                episode(1, 1, true, None, now),
+                episode(4, 4, false, None, now),
+                episode(2, 2, true, None, now),
+                episode(5, 5, true, None, now),
+            ],
+            FeedSettings::default(),
+        );
+        let defaults = PodcastSettings {
+            keep_downloaded: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(expired_ids(&podcast, &defaults, now), vec![3, 5]);
+    }
+
+    #[test]
+    fn should_expire_played_downloads() {
+        let now = Utc::now();
+        let podcast = podcast(
+            vec![
+                episode(1, 1, true, Some(1), now),
+                episode(2, 2, true, Some(10), now),
+                episode(3, 3, false, Some(10), now),
+                episode(4, 4, true, None, now),
+            ],
+            FeedSettings {
+                delete_played_after_days: Some(7),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            expired_ids(&podcast, &PodcastSettings::default(), now),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn should_prefer_feed_settings() {
+        let defaults = PodcastSettings {
+            auto_download: true,
+            keep_downloaded: 5,
+            ..Default::default()
+        };
+        let settings = FeedSettings {
+            auto_download: Some(false),
+            keep_downloaded: None,
+            delete_played_after_days: Some(3),
+            playback_speed: None,
+        };
+
+        assert!(!settings.auto_download(&defaults));
+        assert_eq!(settings.keep_downloaded(&defaults), 5);
+        assert_eq!(settings.delete_played_after_days(&defaults), 3);
+    }
 }
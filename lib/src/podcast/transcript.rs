@@ -0,0 +1,292 @@
+//! Podcasting 2.0 transcripts (`<podcast:transcript>`), shown as a timeline like synced lyrics
+//!
+//! Supported are the SRT, WebVTT and JSON formats
+//! (<https://github.com/Podcastindex-org/podcast-namespace/blob/main/transcripts/transcripts.md>),
+//! which get cached next to the downloaded episode.
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::songtag::lrc::Lyric;
+
+/// The transcript formats that have timing information, in order of preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TranscriptFormat {
+    WebVtt,
+    Srt,
+    Json,
+}
+
+impl TranscriptFormat {
+    pub const ALL: [Self; 3] = [Self::WebVtt, Self::Srt, Self::Json];
+
+    /// Get the format from the `type` attribute of a `<podcast:transcript>` element
+    ///
+    /// Returns [`None`] for formats without timing information, like `text/html` and `text/plain`.
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        match mime.trim().to_ascii_lowercase().as_str() {
+            "text/vtt" => Some(Self::WebVtt),
+            "application/srt" | "application/x-subrip" | "text/srt" => Some(Self::Srt),
+            "application/json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from the content, as servers rarely send a useful `Content-Type` for these
+    pub fn sniff(data: &str) -> Self {
+        let data = data.trim_start_matches('\u{feff}').trim_start();
+        if data.starts_with("WEBVTT") {
+            Self::WebVtt
+        } else if data.starts_with('{') {
+            Self::Json
+        } else {
+            Self::Srt
+        }
+    }
+
+    /// The file extension used for the cached transcript
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::WebVtt => "vtt",
+            Self::Srt => "srt",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// The root object of a JSON transcript, only the parts we use
+#[derive(Debug, Deserialize)]
+struct JsonTranscript {
+    #[serde(default)]
+    segments: Vec<JsonSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonSegment {
+    start_time: f64,
+    #[serde(default)]
+    speaker: Option<String>,
+    body: String,
+}
+
+/// Parse a transcript into a [`Lyric`] timeline
+pub fn parse_transcript(data: &str, format: TranscriptFormat) -> Result<Lyric> {
+    let captions = match format {
+        TranscriptFormat::WebVtt | TranscriptFormat::Srt => parse_cues(data),
+        TranscriptFormat::Json => parse_json(data)?,
+    };
+
+    if captions.is_empty() {
+        bail!("Transcript does not contain any timed lines");
+    }
+
+    Ok(Lyric::from_captions(captions))
+}
+
+/// Parse the cues of a SRT or WebVTT file, which only differ in their header and the millisecond separator
+fn parse_cues(data: &str) -> Vec<(Duration, String)> {
+    let mut captions = Vec::new();
+    let mut lines = data.lines().map(str::trim);
+
+    while let Some(line) = lines.next() {
+        let Some((start, _)) = line.split_once("-->") else {
+            continue;
+        };
+        let Some(start) = parse_timestamp(start.trim()) else {
+            continue;
+        };
+
+        // the cue text lasts until the next empty line
+        let text = lines
+            .by_ref()
+            .take_while(|v| !v.is_empty())
+            .map(strip_tags)
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if !text.is_empty() {
+            captions.push((start, text));
+        }
+    }
+
+    captions
+}
+
+/// Parse a JSON transcript, prefixing the speaker whenever it changes
+fn parse_json(data: &str) -> Result<Vec<(Duration, String)>> {
+    let transcript: JsonTranscript =
+        serde_json::from_str(data).context("parsing JSON transcript")?;
+
+    let mut last_speaker: Option<String> = None;
+    let captions = transcript
+        .segments
+        .into_iter()
+        .filter(|v| !v.body.trim().is_empty())
+        // negative, infinite or too large start times (which would panic in "from_secs_f64") are invalid
+        .filter_map(|v| Some((Duration::try_from_secs_f64(v.start_time).ok()?, v)))
+        .map(|(start, v)| {
+            let body = v.body.trim();
+            let text = match v.speaker.filter(|v| !v.is_empty()) {
+                Some(speaker) if last_speaker.as_ref() != Some(&speaker) => {
+                    let text = format!("{speaker}: {body}");
+                    last_speaker = Some(speaker);
+                    text
+                }
+                _ => body.to_string(),
+            };
+
+            (start, text)
+        })
+        .collect();
+
+    Ok(captions)
+}
+
+/// Parse a cue timestamp like `01:02:03,456` (SRT), `01:02:03.456` or `02:03.456` (WebVTT)
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let (hms, millis) = value.split_once([',', '.']).unwrap_or((value, "0"));
+
+    let mut secs: u64 = 0;
+    for part in hms.split(':') {
+        secs = secs.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+    }
+    let millis: u64 = format!("{millis:0<3}").get(..3)?.parse().ok()?;
+
+    Duration::from_secs(secs).checked_add(Duration::from_millis(millis))
+}
+
+/// Remove WebVTT / SRT formatting tags like `<v Speaker>` or `<i>`
+fn strip_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for ch in line.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => (),
+        }
+    }
+
+    text.trim().to_string()
+}
+
+/// The path the transcript of the episode downloaded to `episode_path` is cached at
+pub fn transcript_path(episode_path: &Path, format: TranscriptFormat) -> PathBuf {
+    episode_path.with_extension(format.extension())
+}
+
+/// Read the cached transcript of the episode downloaded to `episode_path`, if there is one
+pub fn read_cached_transcript(episode_path: &Path) -> Option<Lyric> {
+    TranscriptFormat::ALL.into_iter().find_map(|format| {
+        let path = transcript_path(episode_path, format);
+        let data = std::fs::read_to_string(&path).ok()?;
+        parse_transcript(&data, format)
+            .map_err(|err| warn!("Error reading transcript \"{}\": {err:#}", path.display()))
+            .ok()
+    })
+}
+
+/// Remove the cached transcript of the episode downloaded to `episode_path`, if there is one
+pub fn remove_cached_transcript(episode_path: &Path) {
+    for format in TranscriptFormat::ALL {
+        let _ = std::fs::remove_file(transcript_path(episode_path, format));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Get the text shown at `secs`, [`Lyric::get_text`] shows lines 2 seconds early
+    fn text_at(lyric: &Lyric, secs: u64) -> String {
+        lyric
+            .get_text(Duration::from_secs(secs).saturating_sub(Duration::from_secs(2)))
+            .unwrap()
+    }
+
+    #[test]
+    fn should_parse_srt() {
+        let data = "1\r\n00:00:01,000 --> 00:00:04,000\r\nHello and <i>welcome</i>\r\nto the show\r\n\r\n2\r\n01:00:10,500 --> 01:00:12,000\r\nGoodbye\r\n";
+        assert_eq!(TranscriptFormat::sniff(data), TranscriptFormat::Srt);
+
+        let lyric = parse_transcript(data, TranscriptFormat::Srt).unwrap();
+        assert_eq!(text_at(&lyric, 5), "Hello and welcome to the show");
+        assert_eq!(text_at(&lyric, 3611), "Goodbye");
+    }
+
+    #[test]
+    fn should_parse_webvtt() {
+        let data = "WEBVTT\n\nNOTE a comment\n\n00:05.000 --> 00:08.000 align:start\n<v Alice>Hi there\n\nintro\n00:00:20.000 --> 00:00:25.000\nSecond cue\n";
+        assert_eq!(TranscriptFormat::sniff(data), TranscriptFormat::WebVtt);
+
+        let lyric = parse_transcript(data, TranscriptFormat::WebVtt).unwrap();
+        assert_eq!(text_at(&lyric, 6), "Hi there");
+        assert_eq!(text_at(&lyric, 21), "Second cue");
+    }
+
+    #[test]
+    fn should_parse_json() {
+        let data = r#"{
+            "version": "1.0.0",
+            "segments": [
+                { "speaker": "Alice", "startTime": 0.5, "endTime": 1.0, "body": "Hello" },
+                { "speaker": "Alice", "startTime": 10.0, "endTime": 11.0, "body": "again" },
+                { "speaker": "Bob", "startTime": 20.0, "endTime": 21.0, "body": "Hi" },
+                { "startTime": 30.0, "endTime": 31.0, "body": " " }
+            ]
+        }"#;
+        assert_eq!(TranscriptFormat::sniff(data), TranscriptFormat::Json);
+
+        let lyric = parse_transcript(data, TranscriptFormat::Json).unwrap();
+        assert_eq!(lyric.unsynced_captions.len(), 3);
+        assert_eq!(text_at(&lyric, 1), "Alice: Hello");
+        assert_eq!(text_at(&lyric, 10), "again");
+        assert_eq!(text_at(&lyric, 35), "Bob: Hi");
+
+        assert!(parse_transcript(r#"{"segments": []}"#, TranscriptFormat::Json).is_err());
+    }
+
+    #[test]
+    fn should_skip_invalid_json_start_times() {
+        let data = r#"{
+            "segments": [
+                { "startTime": 1e300, "body": "Too late" },
+                { "startTime": -5.0, "body": "Too early" },
+                { "startTime": 10.0, "body": "Valid" }
+            ]
+        }"#;
+
+        let lyric = parse_transcript(data, TranscriptFormat::Json).unwrap();
+        assert_eq!(lyric.unsynced_captions.len(), 1);
+        assert_eq!(text_at(&lyric, 10), "Valid");
+    }
+
+    #[test]
+    fn should_not_overflow_timestamps() {
+        assert_eq!(
+            parse_timestamp("01:00:10,500"),
+            Some(Duration::from_millis(3_610_500))
+        );
+        assert_eq!(parse_timestamp("18446744073709551615:59"), None);
+        assert_eq!(parse_timestamp("18446744073709551615,999"), None);
+    }
+
+    #[test]
+    fn should_get_format_from_mime() {
+        assert_eq!(
+            TranscriptFormat::from_mime("application/x-subrip"),
+            Some(TranscriptFormat::Srt)
+        );
+        assert_eq!(
+            TranscriptFormat::from_mime("text/VTT"),
+            Some(TranscriptFormat::WebVtt)
+        );
+        assert_eq!(TranscriptFormat::from_mime("text/html"), None);
+    }
+}
@@ -72,6 +72,28 @@ pub struct UnsyncedCaption {
 const EOL: &str = "\n";
 
 impl Lyric {
+    /// Create a [`Lyric`] from already timed lines, like from a podcast transcript
+    pub fn from_captions<I: IntoIterator<Item = (Duration, String)>>(captions: I) -> Self {
+        let mut unsynced_captions: Vec<UnsyncedCaption> = captions
+            .into_iter()
+            .map(|(time, text)| UnsyncedCaption {
+                time_stamp: time.as_millis().try_into().unwrap_or(i64::MAX),
+                text,
+            })
+            .collect();
+        unsynced_captions.sort_by(|b, a| b.time_stamp.cmp(&a.time_stamp));
+
+        let mut lyric = Self {
+            offset: 0,
+            lang_extension: None,
+            unsynced_captions,
+        };
+
+        lyric.merge_adjacent();
+
+        lyric
+    }
+
     // GetText will fetch lyric by time in seconds
     pub fn get_text(&self, time: Duration) -> Option<String> {
         if self.unsynced_captions.is_empty() {
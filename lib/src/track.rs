@@ -1,5 +1,7 @@
+use crate::chapters::{read_id3_chapters, Chapter};
 use crate::library_db::const_unknown::{UNKNOWN_ARTIST, UNKNOWN_TITLE};
 use crate::podcast::episode::Episode;
+use crate::podcast::transcript::read_cached_transcript;
 /**
  * MIT License
  *
@@ -91,6 +93,8 @@ pub struct Track {
     // Disc
     // Comment
     pub podcast_localfile: Option<String>,
+    /// Chapters of the track, sorted by their start
+    chapters: Vec<Chapter>,
 }
 
 impl PartialEq for Track {
@@ -112,9 +116,17 @@ impl Track {
     pub fn from_episode(ep: &Episode) -> Self {
         let lyric_frames: Vec<Lyrics> = Vec::new();
         let mut podcast_localfile: Option<String> = None;
+        let mut chapters = ep.chapters.clone();
+        let mut parsed_lyric = None;
         if let Some(path) = &ep.path {
             if path.exists() {
                 podcast_localfile = Some(path.to_string_lossy().to_string());
+                // the transcript is shown like synced lyrics
+                parsed_lyric = read_cached_transcript(path);
+                // prefer the chapters from the feed, as they are likely more up-to-date
+                if chapters.is_empty() {
+                    chapters = read_id3_chapters(path);
+                }
             }
         }
 
@@ -127,13 +139,14 @@ impl Track {
             last_modified: SystemTime::now(),
             lyric_frames,
             lyric_selected_index: 0,
-            parsed_lyric: None,
+            parsed_lyric,
             picture: None,
             album_photo: ep.image_url.clone(),
             file_type: None,
             genre: None,
             media_type: MediaType::Podcast,
             podcast_localfile,
+            chapters,
         }
     }
 
@@ -159,6 +172,11 @@ impl Track {
             }
         }
 
+        // chapter frames are practically only used in mp3 files
+        if !for_db && song.file_type == Some(FileType::Mpeg) {
+            song.chapters = read_id3_chapters(path);
+        }
+
         let parent_folder = get_parent_folder(path);
 
         if let Ok(files) = std::fs::read_dir(parent_folder) {
@@ -226,6 +244,11 @@ impl Track {
         track
     }
 
+    /// Create a track that only knows its location, like for a file that could not be read
+    pub fn new_placeholder(location: LocationType, media_type: MediaType) -> Self {
+        Self::new(location, media_type)
+    }
+
     fn new(location: LocationType, media_type: MediaType) -> Self {
         let duration = Duration::from_secs(0);
         let lyric_frames: Vec<Lyrics> = Vec::new();
@@ -258,6 +281,7 @@ impl Track {
             genre: None,
             media_type,
             podcast_localfile: None,
+            chapters: Vec::new(),
         }
     }
 
@@ -412,6 +436,11 @@ impl Track {
         }
     }
 
+    /// Get the chapters of the track, sorted by their start
+    pub fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
     pub const fn duration(&self) -> Duration {
         self.duration
     }
@@ -14,6 +14,7 @@ pub enum Msg {
     DeleteConfirmCloseOk,
     DeleteConfirmShow,
     Download(DLMsg),
+    Equalizer(EQMsg),
     ErrorPopupClose,
     GeneralSearch(GSMsg),
     HelpPopupShow,
@@ -33,6 +34,8 @@ pub enum Msg {
     PlayerSpeedDown,
     PlayerSeekForward,
     PlayerSeekBackward,
+    PlayerChapterNext,
+    PlayerChapterPrevious,
     Playlist(PLMsg),
     Podcast(PCMsg),
     QuitPopupCloseCancel,
@@ -51,6 +54,20 @@ pub enum Msg {
     None,
 }
 
+/// Messages of the equalizer popup, gains are in tenths of a dB as `Msg` cannot contain floats
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EQMsg {
+    PopupShow,
+    PopupClose,
+    ToggleEnabled,
+    NextPreset,
+    /// Adjust the gain of the band at the index
+    AdjustGain(usize, i32),
+    /// Set the gain of all bands of the current preset to 0
+    Reset,
+    RemovePreset,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum XYWHMsg {
     Hide,
@@ -81,6 +98,8 @@ pub enum DLMsg {
 pub enum LyricMsg {
     LyricTextAreaBlurUp,
     LyricTextAreaBlurDown,
+    /// A row with a timestamp got selected, like in a episode description
+    SeekToTimestamp(std::time::Duration),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -359,6 +378,8 @@ pub enum PCMsg {
     PodcastAddPopupCloseOk(String),
     PodcastAddPopupCloseCancel,
     SyncData((i64, PodcastNoId)),
+    /// The feed with the id and url was not modified since the last sync
+    SyncUnchanged(i64, String),
     NewData(PodcastNoId),
     Error(String, PodcastFeed),
     PodcastSelected(usize),
@@ -371,6 +392,8 @@ pub enum PCMsg {
     FetchPodcastStart(String),
     EpisodeDownload(usize),
     DLStart(EpData),
+    /// Downloaded bytes and the total bytes, if known
+    DLProgress(EpData, u64, Option<u64>),
     DLComplete(EpData),
     DLResponseError(EpData),
     DLFileCreateError(EpData),
@@ -498,6 +521,7 @@ pub enum Id {
     DeleteConfirmInputPopup,
     DownloadSpinner,
     Episode,
+    EqualizerPopup,
     ErrorPopup,
     GeneralSearchInput,
     GeneralSearchTable,
@@ -35,18 +35,26 @@ mod mpv_backend;
 mod rusty_backend;
 
 mod discord;
+pub mod loudness;
 mod mpris;
 pub mod playlist;
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-pub use playlist::{Playlist, Status};
+pub use playlist::{new_shared_playlist, Playlist, SharedPlaylist, Status};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use termusiclib::chapters::{next_chapter_start, previous_chapter_start};
 use termusiclib::config::v2::server::config_extra::ServerConfigVersionedDefaulted;
-use termusiclib::config::{new_shared_server_settings, ServerOverlay, SharedServerSettings};
+use termusiclib::config::v2::server::{EqualizerBand, LoopMode};
+#[cfg(any(feature = "mpv", feature = "gst"))]
+use termusiclib::config::ServerOverlay;
+use termusiclib::config::SharedServerSettings;
 use termusiclib::library_db::DataBase;
-use termusiclib::player::{PlayerProgress, PlayerTimeUnit, TrackChangedInfo, UpdateEvents};
+use termusiclib::player::{
+    EqualizerSet, PlayerProgress, PlayerTimeUnit, PlaylistAddTrack, PlaylistMoveTrack,
+    PlaylistRemoveTrack, PlaylistSwapTrack, TrackChangedInfo, UpdateEvents, UpdatePlaylistEvents,
+};
 use termusiclib::podcast::db::Database as DBPod;
 use termusiclib::track::{MediaType, Track};
 use termusiclib::utils::get_app_config_path;
@@ -88,13 +96,17 @@ pub type PlayerCmdSender = UnboundedSender<PlayerCmd>;
 
 impl Backend {
     /// Create a new Backend based on `backend`([`BackendSelect`])
-    fn new_select(backend: BackendSelect, config: &ServerOverlay, cmd_tx: PlayerCmdSender) -> Self {
+    fn new_select(
+        backend: BackendSelect,
+        config: &SharedServerSettings,
+        cmd_tx: PlayerCmdSender,
+    ) -> Self {
         match backend {
             #[cfg(feature = "mpv")]
-            BackendSelect::Mpv => Self::new_mpv(config, cmd_tx),
+            BackendSelect::Mpv => Self::new_mpv(&config.read(), cmd_tx),
             #[cfg(feature = "gst")]
-            BackendSelect::GStreamer => Self::new_gstreamer(config, cmd_tx),
-            BackendSelect::Rusty => Self::new_rusty(config, cmd_tx),
+            BackendSelect::GStreamer => Self::new_gstreamer(&config.read(), cmd_tx),
+            BackendSelect::Rusty => Self::new_rusty(config.clone(), cmd_tx),
         }
     }
 
@@ -111,7 +123,9 @@ impl Backend {
     // }
 
     /// Explicitly choose Backend [`RustyBackend`](rusty_backend::RustyBackend)
-    fn new_rusty(config: &ServerOverlay, cmd_tx: PlayerCmdSender) -> Self {
+    ///
+    /// Takes the shared config, as the ReplayGain settings are read for every new track
+    fn new_rusty(config: SharedServerSettings, cmd_tx: PlayerCmdSender) -> Self {
         info!("Using Backend \"rusty\"");
         Self::Rusty(rusty_backend::RustyBackend::new(config, cmd_tx))
     }
@@ -159,7 +173,12 @@ pub enum PlayerCmd {
     CycleLoop,
     Eos,
     GetProgress,
-    PlaySelected,
+    /// Only used by clients to request the whole playlist, ignored by the server
+    GetPlaylist,
+    /// Play the track at the given playlist index
+    PlaySelected(usize),
+    /// Stop playback, until something is explicitly played again
+    Stop,
     SkipPrevious,
     Pause,
     Play,
@@ -177,14 +196,64 @@ pub enum PlayerCmd {
     TogglePause,
     VolumeDown,
     VolumeUp,
+
+    PlaylistAddTrack(PlaylistAddTrack),
+    PlaylistRemoveTrack(PlaylistRemoveTrack),
+    PlaylistSwapTrack(PlaylistSwapTrack),
+    PlaylistMoveTrack(PlaylistMoveTrack),
+    PlaylistClear,
+    PlaylistShuffle,
+    PlaylistRemoveDeletedTracks,
+    PlaylistSetLoopMode(LoopMode),
+
+    SetVolume(Volume),
+    SetSpeed(Speed),
+    /// Seek to a absolute position in the current track
+    SeekTo(Duration),
+    /// Seek to the start of the next chapter of the current track
+    ChapterNext,
+    /// Seek to the start of the current chapter, or the previous one if the current just started
+    ChapterPrevious,
+    /// The equalizer settings in the config changed, apply the active bands
+    EqualizerChanged,
+    /// Only used by clients to change the equalizer, ignored by the player
+    EqualizerSet(EqualizerSet),
+    /// Only used by clients to remove a equalizer preset by name, ignored by the player
+    EqualizerRemovePreset(String),
+    /// The output device in the config changed, move playback to it
+    OutputDeviceChanged,
+
+    /// Only used by clients to add a podcast feed by url, ignored by the player
+    PodcastAdd(String),
+    /// Only used by clients to refresh podcast feeds (all if empty), ignored by the player
+    PodcastRefresh(Vec<i64>),
+    /// Only used by clients to download podcast episodes, ignored by the player
+    PodcastDownload(Vec<i64>),
+    /// Only used by clients to delete the downloaded files of podcast episodes, ignored by the player
+    PodcastDeleteFiles(Vec<i64>),
+    /// Only used by clients to mark podcast episodes as played or unplayed, ignored by the player
+    PodcastSetPlayed(Vec<i64>, bool),
+    /// Only used by clients to remove podcast feeds and their downloaded episodes, ignored by the player
+    PodcastRemove(Vec<i64>),
+    /// Only used by clients to request all podcasts and their episodes, ignored by the player
+    PodcastReload,
 }
 
 pub type StreamTX = broadcast::Sender<UpdateEvents>;
 
+/// Get the names of the audio output devices, which can be used as `player.output_device`
+///
+/// # Errors
+///
+/// - if the devices of the audio host could not be queried
+pub fn output_devices() -> Result<Vec<String>> {
+    rusty_backend::output_device_names().context("query audio output devices")
+}
+
 #[allow(clippy::module_name_repetitions)]
 pub struct GeneralPlayer {
     pub backend: Backend,
-    pub playlist: Playlist,
+    pub playlist: SharedPlaylist,
     pub config: SharedServerSettings,
     pub current_track_updated: bool,
     pub mpris: Option<mpris::Mpris>,
@@ -193,6 +262,10 @@ pub struct GeneralPlayer {
     pub db_podcast: DBPod,
     pub cmd_tx: PlayerCmdSender,
     pub stream_tx: StreamTX,
+    /// The speed before a podcast feed with its own speed started playing, to restore it afterwards
+    speed_before_feed: Option<Speed>,
+    /// Whether playback got stopped by [`PlayerCmd::Stop`], to not start playing again on the next tick
+    user_stopped: bool,
 }
 
 impl GeneralPlayer {
@@ -204,19 +277,18 @@ impl GeneralPlayer {
     /// - if config path creation fails
     pub fn new_backend(
         backend: BackendSelect,
-        config: ServerOverlay,
+        config: SharedServerSettings,
         cmd_tx: PlayerCmdSender,
         stream_tx: StreamTX,
+        playlist: SharedPlaylist,
     ) -> Result<Self> {
         let backend = Backend::new_select(backend, &config, cmd_tx.clone());
 
         let db_path = get_app_config_path().with_context(|| "failed to get podcast db path.")?;
 
         let db_podcast = DBPod::new(&db_path).with_context(|| "error connecting to podcast db.")?;
-        let db = DataBase::new(&config)?;
+        let db = DataBase::new(&config.read())?;
 
-        let config = new_shared_server_settings(config);
-        let playlist = Playlist::new(config.clone()).unwrap_or_default();
         let mpris = if config.read().settings.player.use_mediacontrols {
             Some(mpris::Mpris::new(cmd_tx.clone()))
         } else {
@@ -239,6 +311,8 @@ impl GeneralPlayer {
             cmd_tx,
             stream_tx,
             current_track_updated: false,
+            speed_before_feed: None,
+            user_stopped: false,
         })
     }
 
@@ -249,11 +323,12 @@ impl GeneralPlayer {
     /// - if connecting to the database fails
     /// - if config path creation fails
     pub fn new(
-        config: ServerOverlay,
+        config: SharedServerSettings,
         cmd_tx: PlayerCmdSender,
         stream_tx: StreamTX,
+        playlist: SharedPlaylist,
     ) -> Result<Self> {
-        Self::new_backend(BackendSelect::Rusty, config, cmd_tx, stream_tx)
+        Self::new_backend(BackendSelect::Rusty, config, cmd_tx, stream_tx, playlist)
     }
 
     /// Reload the config from file, on fail continue to use the old
@@ -265,13 +340,15 @@ impl GeneralPlayer {
         info!("Reloading config");
         let mut config = self.config.write();
         let parsed = ServerConfigVersionedDefaulted::from_config_path()?.into_settings();
+        let output_device_changed =
+            config.settings.player.output_device != parsed.player.output_device;
         config.settings = parsed;
 
         if config.settings.player.use_mediacontrols && self.mpris.is_none() {
             // start mpris if new config has it enabled, but is not active yet
             let mut mpris = mpris::Mpris::new(self.cmd_tx.clone());
             // actually set the metadata of the currently playing track, otherwise the controls will work but no title or coverart will be set until next track
-            if let Some(track) = self.playlist.current_track() {
+            if let Some(track) = self.playlist.read().current_track() {
                 mpris.add_and_play(track);
             }
             // the same for volume
@@ -287,7 +364,7 @@ impl GeneralPlayer {
             let mut discord = discord::Rpc::default();
 
             // actually set the metadata of the currently playing track, otherwise the controls will work but no title or coverart will be set until next track
-            if let Some(track) = self.playlist.current_track() {
+            if let Some(track) = self.playlist.read().current_track() {
                 discord.update(track);
             }
 
@@ -297,6 +374,13 @@ impl GeneralPlayer {
             self.discord.take();
         }
 
+        drop(config);
+        self.equalizer_changed();
+        // switching rebuilds the stream, so only do it if necessary
+        if output_device_changed {
+            self.output_device_changed();
+        }
+
         info!("Config Reloaded");
 
         Ok(())
@@ -314,6 +398,9 @@ impl GeneralPlayer {
         let new_gapless = !self.backend.as_player().gapless();
         self.backend.as_player_mut().set_gapless(new_gapless);
         self.config.write().settings.player.gapless = new_gapless;
+        self.send_stream_ev(UpdateEvents::GaplessChanged {
+            gapless: new_gapless,
+        });
         new_gapless
     }
 
@@ -323,25 +410,28 @@ impl GeneralPlayer {
     ///
     /// if `current_track_index` in playlist is above u32
     pub fn start_play(&mut self) {
-        if self.playlist.is_stopped() | self.playlist.is_paused() {
-            self.playlist.set_status(Status::Running);
-        }
-
-        self.playlist.proceed();
+        {
+            let mut playlist = self.playlist.write();
+            if playlist.is_stopped() | playlist.is_paused() {
+                playlist.set_status(Status::Running);
+            }
 
-        if let Some(track) = self.playlist.current_track() {
-            let track = track.clone();
+            playlist.proceed();
+        }
 
+        let current_track = self.playlist.read().current_track().cloned();
+        if let Some(track) = current_track {
             info!("Starting Track {:#?}", track);
 
-            if self.playlist.has_next_track() {
-                self.playlist.set_next_track(None);
+            if self.playlist.read().has_next_track() {
+                self.playlist.write().set_next_track(None);
                 self.current_track_updated = true;
                 info!("gapless next track played");
                 #[allow(irrefutable_let_patterns)]
                 if let Backend::Rusty(ref mut backend) = self.backend {
                     backend.message_on_end();
                 }
+                self.apply_feed_speed(&track);
                 self.add_and_play_mpris_discord();
                 return;
             }
@@ -352,6 +442,7 @@ impl GeneralPlayer {
             };
             Handle::current().block_on(wait);
 
+            self.apply_feed_speed(&track);
             self.add_and_play_mpris_discord();
             self.player_restore_last_position();
             #[allow(irrefutable_let_patterns)]
@@ -360,17 +451,47 @@ impl GeneralPlayer {
             }
 
             self.send_stream_ev(UpdateEvents::TrackChanged(TrackChangedInfo {
-                current_track_index: u32::try_from(self.playlist.get_current_track_index())
+                current_track_index: u32::try_from(self.playlist.read().get_current_track_index())
                     .unwrap(),
                 current_track_updated: self.current_track_updated,
                 title: self.media_info().media_title,
                 progress: self.get_progress(),
+                track: Some((&track).into()),
             }));
         }
     }
 
+    /// Switch to the playback speed of the podcast feed of `track`, if set, otherwise restore the speed from before
+    fn apply_feed_speed(&mut self, track: &Track) {
+        let feed_speed = if track.media_type == MediaType::Podcast {
+            self.db_podcast
+                .get_feed_playback_speed(track)
+                .unwrap_or_else(|err| {
+                    error!(
+                        "Getting the podcast playback speed failed, Error: {:#?}",
+                        err
+                    );
+                    None
+                })
+        } else {
+            None
+        };
+
+        if let Some(speed) = feed_speed {
+            if self.speed_before_feed.is_none() {
+                self.speed_before_feed = Some(self.speed());
+            }
+            let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+            if self.speed() != speed {
+                self.set_speed(speed);
+            }
+        } else if let Some(speed) = self.speed_before_feed.take() {
+            self.set_speed(speed);
+        }
+    }
+
     fn add_and_play_mpris_discord(&mut self) {
-        if let Some(track) = self.playlist.current_track() {
+        if let Some(track) = self.playlist.read().current_track() {
             if let Some(ref mut mpris) = self.mpris {
                 mpris.add_and_play(track);
             }
@@ -380,26 +501,36 @@ impl GeneralPlayer {
             }
         }
     }
+    /// Enqueue the next track of the playlist, if it follows without a gap, either because gapless is enabled or because it is crossfaded
     pub fn enqueue_next_from_playlist(&mut self) {
-        if self.playlist.next_track().is_some() {
+        if self.playlist.read().next_track().is_some() {
             return;
         }
 
-        let track = match self.playlist.fetch_next_track() {
-            Some(t) => t.clone(),
-            None => return,
+        // only peek, as the next track must stay unset if it does not get enqueued
+        let Some((index, track)) = self
+            .playlist
+            .read()
+            .peek_next_track()
+            .map(|(index, track)| (index, track.clone()))
+        else {
+            return;
         };
 
-        self.playlist.set_next_track(Some(&track));
+        if !self.config.read().settings.player.gapless && !self.crossfades_into(&track) {
+            return;
+        }
+
+        self.playlist.write().set_next_track_at(index);
         self.enqueue_next(&track);
 
         info!("Next track enqueued: {:#?}", track);
     }
 
     pub fn next(&mut self) {
-        if self.playlist.current_track().is_some() {
+        if self.playlist.read().current_track().is_some() {
             info!("skip route 1 which is in most cases.");
-            self.playlist.set_next_track(None);
+            self.playlist.write().set_next_track(None);
             self.skip_one();
         } else {
             info!("skip route 2 cause no current track.");
@@ -410,18 +541,151 @@ impl GeneralPlayer {
         }
     }
     pub fn previous(&mut self) {
-        self.playlist.previous();
-        self.playlist.proceed_false();
+        {
+            let mut playlist = self.playlist.write();
+            playlist.previous();
+            playlist.proceed_false();
+        }
         self.next();
     }
 
+    /// Play the track at the given playlist `index`, ignored if the index is out of bounds
+    pub fn play_selected(&mut self, index: usize) {
+        {
+            let mut playlist = self.playlist.write();
+            if index >= playlist.len() {
+                warn!(
+                    "Not playing index {index}, as it is out of bounds for playlist with length {}",
+                    playlist.len()
+                );
+                return;
+            }
+            playlist.set_current_track_index(index);
+            playlist.proceed_false();
+        }
+        self.user_stopped = false;
+        self.next();
+    }
+
+    /// Handle [`PlayerCmd::Stop`]
+    pub fn user_stop(&mut self) {
+        self.user_stopped = true;
+        <Self as PlayerTrait>::stop(self);
+
+        self.send_stream_ev(UpdateEvents::PlayStateChanged {
+            playing: Status::Stopped.as_u32(),
+        });
+    }
+
+    /// Whether playback got stopped by [`PlayerCmd::Stop`] and should not start again automatically
+    #[must_use]
+    pub fn is_user_stopped(&self) -> bool {
+        self.user_stopped
+    }
+
+    /// Handle [`PlayerCmd::PlaylistAddTrack`]
+    ///
+    /// # Errors
+    ///
+    /// see [`Playlist::add_tracks`]
+    pub fn playlist_add_tracks(&mut self, info: &PlaylistAddTrack) -> Result<()> {
+        let added = self.playlist.write().add_tracks(info, &self.db_podcast)?;
+        if !added.tracks.is_empty() {
+            self.send_playlist_ev(UpdatePlaylistEvents::AddTrack(added));
+        }
+
+        Ok(())
+    }
+
+    /// Handle [`PlayerCmd::PlaylistRemoveTrack`]
+    ///
+    /// # Errors
+    ///
+    /// see [`Playlist::remove_tracks`]
+    pub fn playlist_remove_tracks(&mut self, info: PlaylistRemoveTrack) -> Result<()> {
+        self.playlist.write().remove_tracks(info)?;
+        self.send_playlist_ev(UpdatePlaylistEvents::RemoveTrack(info));
+
+        Ok(())
+    }
+
+    /// Handle [`PlayerCmd::PlaylistSwapTrack`]
+    ///
+    /// # Errors
+    ///
+    /// see [`Playlist::swap`]
+    pub fn playlist_swap_tracks(&mut self, info: PlaylistSwapTrack) -> Result<()> {
+        self.playlist.write().swap(info)?;
+        self.send_playlist_ev(UpdatePlaylistEvents::SwapTracks(info));
+
+        Ok(())
+    }
+
+    /// Handle [`PlayerCmd::PlaylistMoveTrack`]
+    ///
+    /// # Errors
+    ///
+    /// see [`Playlist::move_track`]
+    pub fn playlist_move_track(&mut self, info: PlaylistMoveTrack) -> Result<()> {
+        self.playlist.write().move_track(info)?;
+        self.send_playlist_ev(UpdatePlaylistEvents::MoveTrack(info));
+
+        Ok(())
+    }
+
+    /// Handle [`PlayerCmd::PlaylistClear`]
+    pub fn playlist_clear(&mut self) {
+        self.playlist.write().clear();
+        self.send_playlist_ev(UpdatePlaylistEvents::Cleared);
+    }
+
+    /// Handle [`PlayerCmd::PlaylistShuffle`]
+    pub fn playlist_shuffle(&mut self) {
+        self.playlist.write().shuffle();
+        self.send_playlist_reloaded();
+    }
+
+    /// Handle [`PlayerCmd::PlaylistRemoveDeletedTracks`]
+    pub fn playlist_remove_deleted_tracks(&mut self) {
+        self.playlist.write().remove_deleted_items();
+        self.send_playlist_reloaded();
+    }
+
+    /// Handle [`PlayerCmd::ReloadPlaylist`], loading the playlist from the playlist file again
+    ///
+    /// # Errors
+    ///
+    /// see [`Playlist::reload_tracks`]
+    pub fn playlist_reload(&mut self) -> Result<()> {
+        self.playlist.write().reload_tracks()?;
+        self.send_playlist_reloaded();
+
+        Ok(())
+    }
+
+    /// Handle [`PlayerCmd::PlaylistSetLoopMode`], also storing the mode in the config
+    pub fn playlist_set_loop_mode(&mut self, mode: LoopMode) {
+        self.playlist.write().set_loop_mode(mode);
+        self.config.write().settings.player.loop_mode = mode;
+        self.send_stream_ev(UpdateEvents::LoopModeChanged { mode });
+    }
+
+    /// Handle [`PlayerCmd::CycleLoop`], also storing the mode in the config
+    pub fn playlist_cycle_loop_mode(&mut self) {
+        let mode = self.playlist.write().cycle_loop_mode();
+        self.config.write().settings.player.loop_mode = mode;
+        self.send_stream_ev(UpdateEvents::LoopModeChanged { mode });
+    }
+
     /// Resume playback if paused, pause playback if running
     pub fn toggle_pause(&mut self) {
-        match self.playlist.status() {
+        let status = self.playlist.read().status();
+        match status {
             Status::Running => {
                 <Self as PlayerTrait>::pause(self);
             }
-            Status::Stopped => {}
+            // the next tick starts playing again
+            Status::Stopped => self.user_stopped = false,
             Status::Paused => {
                 <Self as PlayerTrait>::resume(self);
             }
@@ -430,7 +694,8 @@ impl GeneralPlayer {
 
     /// Pause playback if running
     pub fn pause(&mut self) {
-        match self.playlist.status() {
+        let status = self.playlist.read().status();
+        match status {
             Status::Running => {
                 <Self as PlayerTrait>::pause(self);
             }
@@ -440,8 +705,11 @@ impl GeneralPlayer {
 
     /// Resume playback if paused
     pub fn play(&mut self) {
-        match self.playlist.status() {
-            Status::Running | Status::Stopped => {}
+        let status = self.playlist.read().status();
+        match status {
+            Status::Running => {}
+            // the next tick starts playing again
+            Status::Stopped => self.user_stopped = false,
             Status::Paused => {
                 <Self as PlayerTrait>::resume(self);
             }
@@ -451,7 +719,7 @@ impl GeneralPlayer {
     ///
     /// if the underlying "seek" returns a error (which current never happens)
     pub fn seek_relative(&mut self, forward: bool) {
-        let track_len = if let Some(track) = self.playlist.current_track() {
+        let track_len = if let Some(track) = self.playlist.read().current_track() {
             track.duration().as_secs()
         } else {
             // fallback to 5 instead of not seeking at all
@@ -472,9 +740,59 @@ impl GeneralPlayer {
         self.seek(offset).expect("Error in player seek.");
     }
 
+    /// Handle [`PlayerCmd::EqualizerChanged`], applying the active bands from the config
+    pub fn equalizer_changed(&mut self) {
+        let bands = self
+            .config
+            .read()
+            .settings
+            .player
+            .equalizer
+            .active_bands()
+            .to_vec();
+        self.set_equalizer(&bands);
+    }
+
+    /// Handle [`PlayerCmd::OutputDeviceChanged`], moving playback to the device from the config
+    pub fn output_device_changed(&mut self) {
+        let device = self.config.read().settings.player.output_device.clone();
+        self.set_output_device(device.as_deref());
+    }
+
+    /// Seek to the start of the next chapter, ignored if the current track has no chapters or is in its last chapter
+    pub fn chapter_next(&mut self) {
+        let position = self.position().unwrap_or_default();
+        let start = self
+            .playlist
+            .read()
+            .current_track()
+            .and_then(|track| next_chapter_start(track.chapters(), position));
+
+        if let Some(start) = start {
+            self.seek_to(start);
+        }
+    }
+
+    /// Seek to the start of the current chapter, or the previous chapter if the current one just started
+    ///
+    /// Ignored if the current track has no chapters.
+    pub fn chapter_previous(&mut self) {
+        let position = self.position().unwrap_or_default();
+        let start = self
+            .playlist
+            .read()
+            .current_track()
+            .and_then(|track| previous_chapter_start(track.chapters(), position));
+
+        if let Some(start) = start {
+            self.seek_to(start);
+        }
+    }
+
     #[allow(clippy::cast_sign_loss)]
     pub fn player_save_last_position(&mut self) {
-        let Some(track) = self.playlist.current_track() else {
+        let playlist = self.playlist.read();
+        let Some(track) = playlist.current_track() else {
             info!("Not saving Last position as there is no current track");
             return;
         };
@@ -518,7 +836,7 @@ impl GeneralPlayer {
     }
 
     pub fn player_restore_last_position(&mut self) {
-        let Some(track) = self.playlist.current_track() else {
+        let Some(track) = self.playlist.read().current_track().cloned() else {
             info!("Not restoring Last position as there is no current track");
             return;
         };
@@ -535,13 +853,13 @@ impl GeneralPlayer {
         {
             match track.media_type {
                 MediaType::Music => {
-                    if let Ok(last_pos) = self.db.get_last_position(track) {
+                    if let Ok(last_pos) = self.db.get_last_position(&track) {
                         self.seek_to(last_pos);
                         restored = true;
                     }
                 }
                 MediaType::Podcast => {
-                    if let Ok(last_pos) = self.db_podcast.get_last_position(track) {
+                    if let Ok(last_pos) = self.db_podcast.get_last_position(&track) {
                         self.seek_to(last_pos);
                         restored = true;
                     }
@@ -556,10 +874,8 @@ impl GeneralPlayer {
         }
 
         if restored {
-            if let Some(track) = self.playlist.current_track() {
-                if let Err(err) = self.db.set_last_position(track, Duration::from_secs(0)) {
-                    error!("Resetting last_position failed, Error: {:#?}", err);
-                }
+            if let Err(err) = self.db.set_last_position(&track, Duration::from_secs(0)) {
+                error!("Resetting last_position failed, Error: {:#?}", err);
             }
         }
     }
@@ -571,6 +887,17 @@ impl GeneralPlayer {
             debug!("Stream Event not send: No Receivers");
         }
     }
+
+    /// Send a [`UpdateEvents::PlaylistChanged`] stream event
+    fn send_playlist_ev(&self, ev: UpdatePlaylistEvents) {
+        self.send_stream_ev(UpdateEvents::PlaylistChanged(ev));
+    }
+
+    /// Send the whole current playlist, for changes that are not worth sending as a delta
+    fn send_playlist_reloaded(&self) {
+        let tracks = self.playlist.read().as_grpc_playlist_tracks();
+        self.send_playlist_ev(UpdatePlaylistEvents::Reloaded(tracks));
+    }
 }
 
 #[async_trait]
@@ -595,7 +922,7 @@ impl PlayerTrait for GeneralPlayer {
     }
     /// This function should not be used directly, use GeneralPlayer::pause
     fn pause(&mut self) {
-        self.playlist.set_status(Status::Paused);
+        self.playlist.write().set_status(Status::Paused);
         self.get_player_mut().pause();
         if let Some(ref mut mpris) = self.mpris {
             mpris.pause();
@@ -610,7 +937,7 @@ impl PlayerTrait for GeneralPlayer {
     }
     /// This function should not be used directly, use GeneralPlayer::play
     fn resume(&mut self) {
-        self.playlist.set_status(Status::Running);
+        self.playlist.write().set_status(Status::Running);
         self.get_player_mut().resume();
         if let Some(ref mut mpris) = self.mpris {
             mpris.resume();
@@ -653,9 +980,9 @@ impl PlayerTrait for GeneralPlayer {
     }
 
     fn stop(&mut self) {
-        self.playlist.set_status(Status::Stopped);
-        self.playlist.set_next_track(None);
-        self.playlist.clear_current_track();
+        self.playlist.write().set_status(Status::Stopped);
+        self.playlist.write().set_next_track(None);
+        self.playlist.write().clear_current_track();
         self.get_player_mut().stop();
     }
 
@@ -675,6 +1002,14 @@ impl PlayerTrait for GeneralPlayer {
         self.get_player_mut().skip_one();
     }
 
+    fn set_equalizer(&mut self, bands: &[EqualizerBand]) {
+        self.get_player_mut().set_equalizer(bands);
+    }
+
+    fn set_output_device(&mut self, device: Option<&str>) {
+        self.get_player_mut().set_output_device(device);
+    }
+
     fn position(&self) -> Option<PlayerTimeUnit> {
         self.get_player().position()
     }
@@ -683,6 +1018,10 @@ impl PlayerTrait for GeneralPlayer {
         self.get_player_mut().enqueue_next(track);
     }
 
+    fn crossfades_into(&self, next: &Track) -> bool {
+        self.get_player().crossfades_into(next)
+    }
+
     fn media_info(&self) -> MediaInfo {
         self.get_player().media_info()
     }
@@ -757,6 +1096,14 @@ pub trait PlayerTrait {
     fn gapless(&self) -> bool;
     fn set_gapless(&mut self, to: bool);
     fn skip_one(&mut self);
+    /// Set the bands of the equalizer, empty to disable it
+    ///
+    /// Only supported by the rusty backend, ignored by the others.
+    fn set_equalizer(&mut self, _bands: &[EqualizerBand]) {}
+    /// Move playback to the output device with the name, or the default device if `None`, keeping the position
+    ///
+    /// Only supported by the rusty backend, ignored by the others.
+    fn set_output_device(&mut self, _device: Option<&str>) {}
     /// Quickly access the position.
     ///
     /// This should ALWAYS match up with [`PlayerTrait::get_progress`]'s `.position`!
@@ -765,6 +1112,12 @@ pub trait PlayerTrait {
     }
     /// Add the given URI to be played, but do not skip currently playing track
     fn enqueue_next(&mut self, track: &Track);
+    /// Get whether the transition from the current track to `next` would be crossfaded
+    ///
+    /// Only supported by the rusty backend, the others never crossfade.
+    fn crossfades_into(&self, _next: &Track) -> bool {
+        false
+    }
     /// Get info of the current media
     fn media_info(&self) -> MediaInfo;
 }
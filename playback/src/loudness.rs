@@ -0,0 +1,381 @@
+//! EBU R128 loudness analysis of library tracks, used for normalization of tracks without ReplayGain tags
+//!
+//! <https://tech.ebu.ch/docs/r/r128.pdf>, measured as per <https://www.itu.int/rec/R-REC-BS.1770>
+use std::f64::consts::PI;
+use std::fmt::Display;
+use std::fs::File;
+use std::path::Path;
+use std::thread::JoinHandle;
+
+use anyhow::{Context, Result};
+use symphonia::core::io::{MediaSourceStream, MediaSourceStreamOptions};
+use termusiclib::library_db::{DataBase, TrackLoudness};
+
+use crate::rusty_backend::decoder::buffered_source::BufferedSource;
+use crate::rusty_backend::{Source, Symphonia};
+
+/// Blocks with a loudness below this are ignored, in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Blocks more than this below the absolute-gated loudness are ignored, in LU
+const RELATIVE_GATE: f64 = -10.0;
+/// The gating blocks are 400ms long and overlap by 75%, so a block is made of 4 steps of 100ms
+const STEPS_PER_BLOCK: usize = 4;
+/// The oversampling factor for the true peak
+const OVERSAMPLING: usize = 4;
+/// The amount of input samples each phase of the true peak interpolation filter uses
+const INTERPOLATION_TAPS: usize = 12;
+
+/// Analyze the loudness of the file at `path`, by decoding it completely
+///
+/// # Errors
+///
+/// - if the file cannot be opened or decoded
+pub fn analyze_file(path: &Path) -> Result<TrackLoudness> {
+    let file = File::open(path).context("Failed to open music file")?;
+    let mss = MediaSourceStream::new(
+        Box::new(BufferedSource::new_default_size(file)),
+        MediaSourceStreamOptions::default(),
+    );
+    let mut decoder =
+        Symphonia::new(mss, false).map_err(|err| anyhow::anyhow!("error decoding: {err:?}"))?;
+
+    let channels = usize::from(decoder.channels());
+    let mut meter = LoudnessMeter::new(channels, decoder.sample_rate());
+    let mut frame = Vec::with_capacity(channels);
+
+    for sample in &mut decoder {
+        frame.push(f64::from(sample) / f64::from(i16::MAX));
+        if frame.len() == channels {
+            meter.push_frame(&frame);
+            frame.clear();
+        }
+    }
+
+    Ok(meter.finish())
+}
+
+/// Analyze the tracks in the database, only the ones not analyzed yet or all if `all` is set
+///
+/// `progress` is called before each track with the index, the total amount and the file.
+/// Tracks that fail to decode are logged and skipped.
+///
+/// # Errors
+///
+/// - if reading from or writing to the database fails
+pub fn analyze_library<F: FnMut(usize, usize, &str)>(
+    db: &mut DataBase,
+    all: bool,
+    mut progress: F,
+) -> Result<()> {
+    let files = db.get_files_to_analyze(all)?;
+    let total = files.len();
+
+    for (idx, file) in files.iter().enumerate() {
+        progress(idx, total, file);
+
+        match analyze_file(Path::new(file)) {
+            Ok(loudness) => db.set_loudness(file, loudness)?,
+            Err(err) => warn!("Error analyzing loudness of \"{file}\": {err:#}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Spawn a thread that analyzes all tracks not analyzed yet, after the `scans` of the library are done
+///
+/// # Errors
+///
+/// - if the thread cannot be spawned
+pub fn spawn_analyzer<E: Display + Send + 'static>(
+    mut db: DataBase,
+    scans: Vec<JoinHandle<Result<(), E>>>,
+) -> std::io::Result<JoinHandle<()>> {
+    std::thread::Builder::new()
+        .name("loudness analyzer".into())
+        .spawn(move || {
+            for scan in scans {
+                if let Ok(Err(err)) = scan.join() {
+                    error!("Error in library scan: {err}");
+                }
+            }
+
+            let res = analyze_library(&mut db, false, |idx, total, file| {
+                debug!("Analyzing loudness {}/{total}: \"{file}\"", idx + 1);
+            });
+            match res {
+                Ok(()) => info!("Loudness analysis done"),
+                Err(err) => error!("Error in loudness analysis: {err:#}"),
+            }
+        })
+}
+
+/// A biquad filter in direct form 1, for a single channel
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+
+        output
+    }
+}
+
+/// The K-weighting of BS.1770 for `sample_rate`, a high-shelf followed by a high-pass
+///
+/// The coefficients are derived for any sample rate the same way libebur128 does.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = f64::from(sample_rate);
+
+    let f0 = 1681.974_450_955_533;
+    let gain = 3.999_843_853_973_347;
+    let q = 0.707_175_236_955_419_6;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.135_470_876_024_44;
+    let q = 0.500_327_037_323_877_3;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, highpass]
+}
+
+/// Get the weight of channel `idx` in the sum of the channels, expecting the common 5.1 order for more than 4 channels
+fn channel_weight(idx: usize, channels: usize) -> f64 {
+    if channels < 5 {
+        return 1.0;
+    }
+
+    match idx {
+        // low frequency effects are not part of the loudness
+        3 => 0.0,
+        // surround channels
+        4 | 5 => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// The windowed-sinc filter to interpolate [`OVERSAMPLING`] samples for each input sample, split into its phases
+#[allow(clippy::cast_precision_loss)]
+fn interpolation_filter() -> Vec<Vec<f64>> {
+    let len = OVERSAMPLING * INTERPOLATION_TAPS + 1;
+    let center = len / 2;
+
+    let mut phases = vec![Vec::with_capacity(INTERPOLATION_TAPS + 1); OVERSAMPLING];
+    for idx in 0..len {
+        let pos = idx as f64;
+        let t = (pos - center as f64) / OVERSAMPLING as f64;
+        let sinc = if idx == center {
+            1.0
+        } else {
+            (PI * t).sin() / (PI * t)
+        };
+        // hann window
+        let window = 0.5 - 0.5 * (2.0 * PI * pos / (len - 1) as f64).cos();
+
+        phases[idx % OVERSAMPLING].push(sinc * window);
+    }
+
+    phases
+}
+
+/// A meter for the integrated loudness and true peak of a track
+#[derive(Debug)]
+struct LoudnessMeter {
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    /// The amount of frames of a step
+    step_len: usize,
+    /// The weighted sum of the squared samples of the current step
+    step_sum: f64,
+    step_frames: usize,
+    /// The mean square of each finished step
+    steps: Vec<f64>,
+
+    interpolation: Vec<Vec<f64>>,
+    /// The last input samples of each channel, newest first
+    history: Vec<Vec<f64>>,
+    peak: f64,
+}
+
+impl LoudnessMeter {
+    fn new(channels: usize, sample_rate: u32) -> Self {
+        let interpolation = interpolation_filter();
+        let history_len = interpolation.iter().map(Vec::len).max().unwrap_or_default();
+
+        Self {
+            filters: vec![k_weighting(sample_rate); channels],
+            weights: (0..channels).map(|v| channel_weight(v, channels)).collect(),
+            step_len: (sample_rate as usize / 10).max(1),
+            step_sum: 0.0,
+            step_frames: 0,
+            steps: Vec::new(),
+
+            interpolation,
+            history: vec![vec![0.0; history_len]; channels],
+            peak: 0.0,
+        }
+    }
+
+    /// Add a frame, with a sample for each channel
+    #[allow(clippy::cast_precision_loss)]
+    fn push_frame(&mut self, frame: &[f64]) {
+        for (channel, sample) in frame.iter().enumerate() {
+            let [shelf, highpass] = &mut self.filters[channel];
+            let filtered = highpass.process(shelf.process(*sample));
+            self.step_sum += self.weights[channel] * filtered * filtered;
+
+            self.push_peak(channel, *sample);
+        }
+
+        self.step_frames += 1;
+        if self.step_frames == self.step_len {
+            self.steps.push(self.step_sum / self.step_len as f64);
+            self.step_sum = 0.0;
+            self.step_frames = 0;
+        }
+    }
+
+    /// Oversample the channel to find peaks between the samples
+    fn push_peak(&mut self, channel: usize, sample: f64) {
+        let history = &mut self.history[channel];
+        history.rotate_right(1);
+        history[0] = sample;
+
+        for phase in &self.interpolation {
+            let value: f64 = phase.iter().zip(history.iter()).map(|(h, x)| h * x).sum();
+            self.peak = self.peak.max(value.abs());
+        }
+    }
+
+    /// Get the loudness of all gating blocks, as mean square
+    #[allow(clippy::cast_precision_loss)]
+    fn blocks(&self) -> Vec<f64> {
+        self.steps
+            .windows(STEPS_PER_BLOCK)
+            .map(|v| v.iter().sum::<f64>() / STEPS_PER_BLOCK as f64)
+            .collect()
+    }
+
+    /// Get the measured loudness
+    fn finish(mut self) -> TrackLoudness {
+        // flush the interpolation filter, to also get the peaks around the last samples
+        let flush = vec![0.0; self.history.first().map_or(0, Vec::len)];
+        for channel in 0..self.history.len() {
+            for sample in &flush {
+                self.push_peak(channel, *sample);
+            }
+        }
+
+        TrackLoudness {
+            integrated: gated_loudness(&self.blocks()),
+            true_peak: self.peak,
+        }
+    }
+}
+
+/// Convert a mean square to loudness in LUFS
+fn to_lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Get the integrated loudness of the blocks after the absolute and relative gate, [`None`] if no block is above them
+#[allow(clippy::cast_precision_loss)]
+fn gated_loudness(blocks: &[f64]) -> Option<f64> {
+    let mean_above = |threshold: f64| {
+        let (sum, count) = blocks
+            .iter()
+            .filter(|v| to_lufs(**v) > threshold)
+            .fold((0.0, 0_usize), |(sum, count), v| (sum + v, count + 1));
+        (count > 0).then(|| sum / count as f64)
+    };
+
+    let relative_gate = to_lufs(mean_above(ABSOLUTE_GATE)?) + RELATIVE_GATE;
+
+    mean_above(relative_gate.max(ABSOLUTE_GATE)).map(to_lufs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::LoudnessMeter;
+
+    /// Measure a stereo sine with `amplitude`, `frequency` and `phase` for `seconds`, returning the integrated loudness and true peak
+    #[allow(clippy::cast_precision_loss)]
+    fn measure_sine(amplitude: f64, frequency: f64, phase: f64, seconds: usize) -> (f64, f64) {
+        let rate = 48000;
+        let mut meter = LoudnessMeter::new(2, rate);
+        for idx in 0..(rate as usize * seconds) {
+            let t = idx as f64 / f64::from(rate);
+            let sample = amplitude * (2.0 * PI * frequency * t + phase).sin();
+            meter.push_frame(&[sample, sample]);
+        }
+        let loudness = meter.finish();
+
+        (loudness.integrated.unwrap(), loudness.true_peak)
+    }
+
+    #[test]
+    fn should_measure_reference_sine() {
+        // EBU Tech 3341 case 1 and 2: a stereo 1kHz sine at -23dBFS / -33dBFS has a loudness of -23 / -33 LUFS
+        for level in [-23.0, -33.0] {
+            let (integrated, peak) = measure_sine(10f64.powf(level / 20.0), 1000.0, 0.0, 5);
+            assert!((integrated - level).abs() < 0.1, "{integrated} != {level}");
+            assert!((20.0 * peak.log10() - level).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn should_find_true_peak() {
+        // the samples of a sine at a quarter of the sample rate shifted by 45 degrees only reach ~0.707 of the amplitude
+        let (_, peak) = measure_sine(0.5, 12000.0, PI / 4.0, 1);
+        assert!((peak - 0.5).abs() < 0.02, "{peak}");
+    }
+
+    #[test]
+    fn should_ignore_silence() {
+        let mut meter = LoudnessMeter::new(1, 44100);
+        for _ in 0..44100 {
+            meter.push_frame(&[0.0]);
+        }
+        let loudness = meter.finish();
+
+        assert_eq!(loudness.integrated, None);
+        assert_eq!(loudness.true_peak, 0.0);
+    }
+}
@@ -1,4 +1,5 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use parking_lot::RwLock;
 use pathdiff::diff_paths;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
@@ -7,10 +8,15 @@ use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use termusiclib::config::v2::server::LoopMode;
 use termusiclib::config::SharedServerSettings;
+use termusiclib::player::{
+    PlaylistAddTrack, PlaylistMoveTrack, PlaylistRemoveTrack, PlaylistSwapTrack,
+    PlaylistTrackSource, PlaylistTracks,
+};
 use termusiclib::podcast::{db::Database as DBPod, episode::Episode};
-use termusiclib::track::MediaType;
+use termusiclib::track::{LocationType, MediaType};
 use termusiclib::{
     track::Track,
     utils::{filetype_supported, get_app_config_path, get_parent_folder},
@@ -54,6 +60,15 @@ impl std::fmt::Display for Status {
     }
 }
 
+/// A [`Playlist`] that can be accessed by multiple threads, like the player loop and the gRPC service
+pub type SharedPlaylist = Arc<RwLock<Playlist>>;
+
+/// Create a new [`SharedPlaylist`] without having to also depend on [`parking_lot`]
+#[must_use]
+pub fn new_shared_playlist(playlist: Playlist) -> SharedPlaylist {
+    Arc::new(RwLock::new(playlist))
+}
+
 #[derive(Default, Debug)]
 pub struct Playlist {
     tracks: Vec<Track>,
@@ -64,14 +79,13 @@ pub struct Playlist {
     next_track: Option<Track>,
     status: Status,
     loop_mode: LoopMode,
-    config: SharedServerSettings,
     need_proceed_to_next: bool,
 }
 
 impl Playlist {
     /// # Errors
     /// errors could happen when reading files
-    pub fn new(config: SharedServerSettings) -> Result<Self> {
+    pub fn new(config: &SharedServerSettings) -> Result<Self> {
         let (current_track_index, tracks) = Self::load()?;
         // TODO: shouldnt "loop_mode" be combined with the config ones?
         let loop_mode = config.read().settings.player.loop_mode;
@@ -86,12 +100,30 @@ impl Playlist {
             current_track_index,
             current_track,
             played_index: Vec::new(),
-            config,
             next_track_index: 0,
             need_proceed_to_next: false,
         })
     }
 
+    /// Create a new empty playlist, without loading anything from the playlist file
+    ///
+    /// Used by clients which get the playlist from the server instead
+    pub fn new_empty(config: &SharedServerSettings) -> Self {
+        let loop_mode = config.read().settings.player.loop_mode;
+
+        Self {
+            tracks: Vec::new(),
+            next_track: None,
+            status: Status::Stopped,
+            loop_mode,
+            current_track_index: 0,
+            current_track: None,
+            played_index: Vec::new(),
+            next_track_index: 0,
+            need_proceed_to_next: false,
+        }
+    }
+
     pub fn proceed(&mut self) {
         debug!("need to proceed to next: {}", self.need_proceed_to_next);
         if self.need_proceed_to_next {
@@ -205,7 +237,8 @@ impl Playlist {
 
     pub fn next(&mut self) {
         self.played_index.push(self.current_track_index);
-        if self.config.read().settings.player.gapless && self.has_next_track() {
+        // the next track is only set if it was enqueued, either gapless or crossfaded
+        if self.has_next_track() {
             self.current_track_index = self.next_track_index;
             return;
         }
@@ -313,9 +346,22 @@ impl Playlist {
         result
     }
 
-    pub fn fetch_next_track(&mut self) -> Option<&Track> {
-        self.next_track_index = self.get_next_track_index();
-        self.tracks.get(self.next_track_index)
+    /// Get the track that would be played after the current one, together with its index
+    ///
+    /// Nothing is changed, use [`Self::set_next_track_at`] to make it the next track.
+    #[must_use]
+    pub fn peek_next_track(&self) -> Option<(usize, &Track)> {
+        let index = self.get_next_track_index();
+        self.tracks.get(index).map(|track| (index, track))
+    }
+
+    /// Set the track at `index` as the next track, ignored if the index is out of bounds
+    pub fn set_next_track_at(&mut self, index: usize) {
+        let Some(track) = self.tracks.get(index) else {
+            return;
+        };
+        self.next_track = Some(track.clone());
+        self.next_track_index = index;
     }
 
     pub fn set_status(&mut self, status: Status) {
@@ -358,6 +404,15 @@ impl Playlist {
         self.loop_mode
     }
 
+    #[must_use]
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    pub fn set_loop_mode(&mut self, mode: LoopMode) {
+        self.loop_mode = mode;
+    }
+
     /// Export the current playlist to a `.m3u` playlist file
     ///
     /// might be confused with [save](Self::save)
@@ -419,6 +474,189 @@ impl Playlist {
         Ok(())
     }
 
+    /// Insert the tracks from `info` at the requested index, appending if the index is past the end
+    ///
+    /// Unsupported filetypes are skipped, like in [`add_playlist`](Self::add_playlist).
+    /// Either all other tracks are added, or none if any of them could not be resolved.
+    ///
+    /// Returns what was actually added, with the index clamped to the playlist and skipped tracks removed.
+    ///
+    /// # Errors
+    /// - if a track could not be read from a local file
+    /// - if a podcast episode could not be found in the database
+    pub fn add_tracks(
+        &mut self,
+        info: &PlaylistAddTrack,
+        db_podcast: &DBPod,
+    ) -> Result<PlaylistAddTrack> {
+        let mut tracks = Vec::with_capacity(info.tracks.len());
+        let mut sources = Vec::with_capacity(info.tracks.len());
+        for source in &info.tracks {
+            if let PlaylistTrackSource::Path(path) = source {
+                if !filetype_supported(path) {
+                    error!("unsupported filetype: {:#?}", path);
+                    continue;
+                }
+            }
+            tracks.push(track_from_source(source, db_podcast)?);
+            sources.push(source.clone());
+        }
+
+        let at_index = usize::min(info.at_index as usize, self.tracks.len());
+        let amount = tracks.len();
+
+        if !self.tracks.is_empty() && at_index <= self.current_track_index {
+            self.current_track_index += amount;
+        }
+        if self.has_next_track() && at_index <= self.next_track_index {
+            self.next_track_index += amount;
+        }
+
+        self.tracks.splice(at_index..at_index, tracks);
+
+        // the index is at most the length of the playlist, which is sent as u32
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(PlaylistAddTrack {
+            at_index: at_index as u32,
+            tracks: sources,
+        })
+    }
+
+    /// Remove the range of tracks described by `info`
+    ///
+    /// # Errors
+    /// - if the range is not within the playlist
+    pub fn remove_tracks(&mut self, info: PlaylistRemoveTrack) -> Result<()> {
+        let at_index = info.at_index as usize;
+        let end_index = at_index + info.amount as usize;
+        if end_index > self.tracks.len() {
+            bail!(
+                "Range {at_index}..{end_index} is out of bounds for playlist with length {}",
+                self.tracks.len()
+            );
+        }
+
+        self.tracks.drain(at_index..end_index);
+
+        // same behavior as "remove": a removed current track moves the index to the track before it
+        if at_index <= self.current_track_index {
+            let removed_before = usize::min(end_index, self.current_track_index + 1) - at_index;
+            self.current_track_index = self.current_track_index.saturating_sub(removed_before);
+        }
+        if self.has_next_track() && at_index <= self.next_track_index {
+            let removed_before = usize::min(end_index, self.next_track_index + 1) - at_index;
+            self.next_track_index = self.next_track_index.saturating_sub(removed_before);
+        }
+
+        Ok(())
+    }
+
+    /// Swap the tracks at the two indexes described by `info`
+    ///
+    /// # Errors
+    /// - if any of the indexes is not within the playlist
+    pub fn swap(&mut self, info: PlaylistSwapTrack) -> Result<()> {
+        let index_a = info.index_a as usize;
+        let index_b = info.index_b as usize;
+        if index_a >= self.tracks.len() || index_b >= self.tracks.len() {
+            bail!(
+                "Indexes {index_a} and {index_b} are out of bounds for playlist with length {}",
+                self.tracks.len()
+            );
+        }
+
+        self.tracks.swap(index_a, index_b);
+
+        if self.current_track_index == index_a {
+            self.current_track_index = index_b;
+        } else if self.current_track_index == index_b {
+            self.current_track_index = index_a;
+        }
+        if self.next_track_index == index_a {
+            self.next_track_index = index_b;
+        } else if self.next_track_index == index_b {
+            self.next_track_index = index_a;
+        }
+
+        Ok(())
+    }
+
+    /// Move the track at `from_index` to `to_index`, shifting the tracks in between
+    ///
+    /// # Errors
+    /// - if any of the indexes is not within the playlist
+    pub fn move_track(&mut self, info: PlaylistMoveTrack) -> Result<()> {
+        let from = info.from_index as usize;
+        let to = info.to_index as usize;
+        if from >= self.tracks.len() || to >= self.tracks.len() {
+            bail!(
+                "Indexes {from} and {to} are out of bounds for playlist with length {}",
+                self.tracks.len()
+            );
+        }
+
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+
+        let moved_index = |index: usize| {
+            if index == from {
+                to
+            } else if from < index && index <= to {
+                index - 1
+            } else if to <= index && index < from {
+                index + 1
+            } else {
+                index
+            }
+        };
+        self.current_track_index = moved_index(self.current_track_index);
+        self.next_track_index = moved_index(self.next_track_index);
+
+        Ok(())
+    }
+
+    /// Get the current playlist in the form used for gRPC
+    ///
+    /// # Panics
+    ///
+    /// if `current_track_index` in playlist is above u32
+    #[must_use]
+    pub fn as_grpc_playlist_tracks(&self) -> PlaylistTracks {
+        PlaylistTracks {
+            current_track_index: u32::try_from(self.current_track_index).unwrap(),
+            tracks: self
+                .tracks
+                .iter()
+                .filter_map(PlaylistTrackSource::from_track)
+                .map(Into::into)
+                .collect(),
+        }
+    }
+
+    /// Replace the tracks and current index with what was received from the server
+    ///
+    /// Tracks that cannot be resolved (like files that are not readable) are kept as placeholders,
+    /// so that the indexes stay the same as on the server.
+    ///
+    /// # Errors
+    /// - if the received data is malformed
+    pub fn load_from_grpc(&mut self, info: PlaylistTracks, db_podcast: &DBPod) -> Result<()> {
+        let mut tracks = Vec::with_capacity(info.tracks.len());
+        for id in info.tracks {
+            let source = PlaylistTrackSource::try_from(id)?;
+            let track = track_from_source(&source, db_podcast).unwrap_or_else(|err| {
+                error!("Error resolving track {source:#?}: {err:#}");
+                placeholder_from_source(&source)
+            });
+            tracks.push(track);
+        }
+
+        self.tracks = tracks;
+        self.current_track_index = info.current_track_index as usize;
+
+        Ok(())
+    }
+
     #[must_use]
     pub fn tracks(&self) -> &Vec<Track> {
         &self.tracks
@@ -525,6 +763,42 @@ impl Playlist {
     }
 }
 
+/// Resolve a given [`PlaylistTrackSource`] into a full [`Track`]
+///
+/// # Errors
+/// - if reading the local file fails
+/// - if the podcast episode could not be found in the database
+fn track_from_source(source: &PlaylistTrackSource, db_podcast: &DBPod) -> Result<Track> {
+    match source {
+        PlaylistTrackSource::Path(path) => Track::read_from_path(path, false),
+        PlaylistTrackSource::Url(url) => Ok(Track::new_radio(url)),
+        PlaylistTrackSource::PodcastUrl(url) => {
+            let podcasts = db_podcast
+                .get_podcasts()
+                .with_context(|| "failed to get podcasts from db.")?;
+            podcasts
+                .iter()
+                .flat_map(|pod| pod.episodes.iter())
+                .find(|ep| ep.url == url.as_str())
+                .map(Track::from_episode)
+                .ok_or_else(|| anyhow!("Podcast episode with url \"{url}\" not found"))
+        }
+    }
+}
+
+/// Create a track for `source` without reading anything
+fn placeholder_from_source(source: &PlaylistTrackSource) -> Track {
+    match source {
+        PlaylistTrackSource::Path(path) => {
+            Track::new_placeholder(PathBuf::from(path).into(), MediaType::Music)
+        }
+        PlaylistTrackSource::Url(url) => Track::new_radio(url),
+        PlaylistTrackSource::PodcastUrl(url) => {
+            Track::new_placeholder(LocationType::Uri(url.clone()), MediaType::Podcast)
+        }
+    }
+}
+
 const PLAYLIST_SAVE_FILENAME: &str = "playlist.log";
 
 fn get_playlist_path() -> Result<PathBuf> {
@@ -533,3 +807,222 @@ fn get_playlist_path() -> Result<PathBuf> {
 
     Ok(path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termusiclib::config::{new_shared_server_settings, ServerOverlay};
+
+    fn playlist_with_radios(amount: usize) -> Playlist {
+        let config = new_shared_server_settings(ServerOverlay::default());
+        let mut playlist = Playlist::new_empty(&config);
+        playlist.tracks = (0..amount)
+            .map(|i| Track::new_radio(&format!("http://radio{i}")))
+            .collect();
+
+        playlist
+    }
+
+    fn urls(playlist: &Playlist) -> Vec<&str> {
+        playlist.tracks.iter().filter_map(Track::file).collect()
+    }
+
+    #[test]
+    fn should_remove_tracks_and_move_current_index() {
+        let mut playlist = playlist_with_radios(5);
+        playlist.set_current_track_index(3);
+
+        playlist
+            .remove_tracks(PlaylistRemoveTrack {
+                at_index: 1,
+                amount: 2,
+            })
+            .unwrap();
+
+        assert_eq!(
+            urls(&playlist),
+            &["http://radio0", "http://radio3", "http://radio4"]
+        );
+        assert_eq!(playlist.get_current_track_index(), 1);
+
+        // removing the current track moves the index to the track before it
+        playlist
+            .remove_tracks(PlaylistRemoveTrack {
+                at_index: 1,
+                amount: 1,
+            })
+            .unwrap();
+        assert_eq!(playlist.get_current_track_index(), 0);
+    }
+
+    #[test]
+    fn should_only_advance_to_set_next_track() {
+        let mut playlist = playlist_with_radios(3);
+        playlist.loop_mode = LoopMode::Playlist;
+        playlist.set_current_track_index(0);
+
+        // peeking alone does not change where "next" goes
+        assert_eq!(playlist.peek_next_track().map(|(i, _)| i), Some(1));
+        playlist.next();
+        assert_eq!(playlist.get_current_track_index(), 1);
+
+        playlist.set_next_track_at(0);
+        playlist.next();
+        assert_eq!(playlist.get_current_track_index(), 0);
+    }
+
+    #[test]
+    fn should_not_remove_tracks_out_of_bounds() {
+        let mut playlist = playlist_with_radios(2);
+
+        assert!(playlist
+            .remove_tracks(PlaylistRemoveTrack {
+                at_index: 1,
+                amount: 2,
+            })
+            .is_err());
+        assert_eq!(playlist.len(), 2);
+    }
+
+    #[test]
+    fn should_swap_tracks_and_follow_current_index() {
+        let mut playlist = playlist_with_radios(3);
+        playlist.set_current_track_index(0);
+
+        playlist
+            .swap(PlaylistSwapTrack {
+                index_a: 0,
+                index_b: 2,
+            })
+            .unwrap();
+
+        assert_eq!(
+            urls(&playlist),
+            &["http://radio2", "http://radio1", "http://radio0"]
+        );
+        assert_eq!(playlist.get_current_track_index(), 2);
+
+        assert!(playlist
+            .swap(PlaylistSwapTrack {
+                index_a: 0,
+                index_b: 3,
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn should_move_track_and_follow_current_index() {
+        let mut playlist = playlist_with_radios(4);
+        playlist.set_current_track_index(1);
+
+        playlist
+            .move_track(PlaylistMoveTrack {
+                from_index: 0,
+                to_index: 2,
+            })
+            .unwrap();
+        assert_eq!(
+            urls(&playlist),
+            &[
+                "http://radio1",
+                "http://radio2",
+                "http://radio0",
+                "http://radio3"
+            ]
+        );
+        assert_eq!(playlist.get_current_track_index(), 0);
+
+        playlist
+            .move_track(PlaylistMoveTrack {
+                from_index: 0,
+                to_index: 3,
+            })
+            .unwrap();
+        assert_eq!(
+            urls(&playlist),
+            &[
+                "http://radio2",
+                "http://radio0",
+                "http://radio3",
+                "http://radio1"
+            ]
+        );
+        assert_eq!(playlist.get_current_track_index(), 3);
+
+        playlist
+            .move_track(PlaylistMoveTrack {
+                from_index: 2,
+                to_index: 0,
+            })
+            .unwrap();
+        assert_eq!(
+            urls(&playlist),
+            &[
+                "http://radio3",
+                "http://radio2",
+                "http://radio0",
+                "http://radio1"
+            ]
+        );
+        assert_eq!(playlist.get_current_track_index(), 3);
+
+        assert!(playlist
+            .move_track(PlaylistMoveTrack {
+                from_index: 0,
+                to_index: 4,
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn should_keep_unresolved_tracks_from_grpc() {
+        let dir =
+            std::env::temp_dir().join(format!("termusic-playlist-grpc-{}", std::process::id()));
+        let db_podcast = DBPod::new(&dir).unwrap();
+        let sources = vec![
+            PlaylistTrackSource::Path("/does/not/exist.mp3".to_string()),
+            PlaylistTrackSource::Url("http://radio0".to_string()),
+            PlaylistTrackSource::PodcastUrl("http://unknown.example/ep.mp3".to_string()),
+        ];
+
+        let mut playlist = playlist_with_radios(0);
+        playlist
+            .load_from_grpc(
+                PlaylistTracks {
+                    current_track_index: 2,
+                    tracks: sources.iter().cloned().map(Into::into).collect(),
+                },
+                &db_podcast,
+            )
+            .unwrap();
+
+        assert_eq!(playlist.get_current_track_index(), 2);
+        let loaded: Vec<PlaylistTrackSource> = playlist
+            .tracks()
+            .iter()
+            .filter_map(PlaylistTrackSource::from_track)
+            .collect();
+        assert_eq!(loaded, sources);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn should_roundtrip_grpc_sources() {
+        let playlist = playlist_with_radios(2);
+        let grpc = playlist.as_grpc_playlist_tracks();
+
+        let sources: Vec<PlaylistTrackSource> = grpc
+            .tracks
+            .into_iter()
+            .map(|v| PlaylistTrackSource::try_from(v).unwrap())
+            .collect();
+        assert_eq!(
+            sources,
+            &[
+                PlaylistTrackSource::Url("http://radio0".to_string()),
+                PlaylistTrackSource::Url("http://radio1".to_string())
+            ]
+        );
+    }
+}
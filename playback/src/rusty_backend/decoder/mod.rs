@@ -1,6 +1,8 @@
 pub mod buffered_source;
 pub mod read_seek_source;
+pub mod replaygain;
 
+use self::replaygain::ReplayGain;
 use super::Source;
 use std::{fmt, num::NonZeroU64, time::Duration};
 use symphonia::{
@@ -84,6 +86,7 @@ pub struct Symphonia {
     track_id: u32,
     time_base: Option<TimeBase>,
     seek_required_ts: Option<NonZeroU64>,
+    replaygain: ReplayGain,
 
     media_title_tx: MediaTitleTxWrap,
 }
@@ -169,6 +172,7 @@ impl Symphonia {
         let duration = Self::get_duration(&track.codec_params);
         let track_id = track.id;
         let time_base = track.codec_params.time_base;
+        let replaygain = find_replaygain(&mut *probed.format, &mut probed.metadata);
         let mut media_title_tx = MediaTitleTxWrap::new();
 
         let media_title_rx = if media_title {
@@ -205,6 +209,7 @@ impl Symphonia {
                 track_id,
                 time_base,
                 seek_required_ts: None,
+                replaygain,
 
                 media_title_tx,
             },
@@ -212,6 +217,12 @@ impl Symphonia {
         )))
     }
 
+    /// Get the ReplayGain tags found when the track was opened
+    #[inline]
+    pub fn replaygain(&self) -> &ReplayGain {
+        &self.replaygain
+    }
+
     fn get_duration(params: &CodecParameters) -> Option<Duration> {
         params.n_frames.and_then(|n_frames| {
             params.time_base.map(|tb| {
@@ -519,6 +530,23 @@ fn do_inline_metdata(media_title_tx: &mut MediaTitleTxWrap, format: &mut dyn For
     }
 }
 
+/// Find the ReplayGain tags, preferring the container standard tags like [`do_container_metdata`]
+fn find_replaygain(format: &mut dyn FormatReader, probed: &mut ProbedMetadata) -> ReplayGain {
+    format
+        .metadata()
+        .current()
+        .map(ReplayGain::from_metadata)
+        .filter(|v| !v.is_empty())
+        .or_else(|| {
+            probed
+                .get()
+                .as_ref()
+                .and_then(|m| m.current())
+                .map(ReplayGain::from_metadata)
+        })
+        .unwrap_or_default()
+}
+
 #[inline]
 fn find_title_metadata(metadata: &MetadataRevision) -> Option<&String> {
     metadata
@@ -0,0 +1,282 @@
+//! Reading of ReplayGain tags and calculating the volume factor from them
+//!
+//! <https://wiki.hydrogenaud.io/index.php?title=ReplayGain_2.0_specification>
+use symphonia::core::meta::{MetadataRevision, StandardTagKey, Tag, Value};
+use termusiclib::config::v2::server::{PlayerSettings, ReplayGainMode};
+use termusiclib::library_db::TrackLoudness;
+
+/// The loudness ReplayGain 2.0 normalizes to, in LUFS
+const REFERENCE_LOUDNESS: f64 = -18.0;
+
+/// The ReplayGain tags of a track, gains in dB and peaks as linear sample amplitude
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Collect the ReplayGain tags from `metadata`
+    ///
+    /// Non-standard keys like ID3 `TXXX:REPLAYGAIN_TRACK_GAIN` or `----:com.apple.iTunes:replaygain_track_gain` are also considered.
+    pub fn from_metadata(metadata: &MetadataRevision) -> Self {
+        let mut replaygain = Self::default();
+
+        for tag in metadata.tags() {
+            let Some(value) = tag_value(tag) else {
+                continue;
+            };
+
+            let field = match tag_key(tag) {
+                Some(StandardTagKey::ReplayGainTrackGain) => &mut replaygain.track_gain,
+                Some(StandardTagKey::ReplayGainTrackPeak) => &mut replaygain.track_peak,
+                Some(StandardTagKey::ReplayGainAlbumGain) => &mut replaygain.album_gain,
+                Some(StandardTagKey::ReplayGainAlbumPeak) => &mut replaygain.album_peak,
+                _ => continue,
+            };
+            field.get_or_insert(value);
+        }
+
+        replaygain
+    }
+
+    /// Get the track values from the analyzed loudness, for tracks without ReplayGain tags
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn from_loudness(loudness: TrackLoudness) -> Self {
+        Self {
+            track_gain: loudness.integrated.map(|v| (REFERENCE_LOUDNESS - v) as f32),
+            track_peak: Some(loudness.true_peak as f32),
+            album_gain: None,
+            album_peak: None,
+        }
+    }
+
+    /// Get whether no gain tag was found
+    pub fn is_empty(&self) -> bool {
+        self.track_gain.is_none() && self.album_gain.is_none()
+    }
+
+    /// Get the linear volume factor to apply for `mode`, with `preamp` dB added to the gain
+    ///
+    /// [`ReplayGainMode::Album`] falls back to the track values if there is no album gain.
+    /// The factor is limited so that the peak does not clip, and is `1.0` if there is no gain for the mode.
+    pub fn factor(&self, mode: ReplayGainMode, preamp: f32) -> f32 {
+        let (gain, peak) = match mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (self.track_gain, self.track_peak),
+            // "Auto" should already be resolved at this point, see "ReplayGainMode::resolve"
+            ReplayGainMode::Album | ReplayGainMode::Auto => {
+                if self.album_gain.is_some() {
+                    (self.album_gain, self.album_peak)
+                } else {
+                    (self.track_gain, self.track_peak)
+                }
+            }
+        };
+
+        let Some(gain) = gain else {
+            return 1.0;
+        };
+
+        let factor = 10f32.powf((gain + preamp) / 20.0);
+
+        match peak {
+            Some(peak) if peak > 0.0 => factor.min(1.0 / peak),
+            _ => factor,
+        }
+    }
+}
+
+/// The ReplayGain settings to apply to newly decoded tracks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGainOptions {
+    pub mode: ReplayGainMode,
+    pub preamp: f32,
+    /// The values from the loudness analysis, used if the track has no ReplayGain tags
+    pub fallback: Option<ReplayGain>,
+}
+
+impl ReplayGainOptions {
+    /// Get the options from the current settings, resolving [`ReplayGainMode::Auto`] with the current loop mode
+    pub fn from_settings(settings: &PlayerSettings) -> Self {
+        Self {
+            mode: settings.replaygain.resolve(settings.loop_mode),
+            preamp: settings.replaygain_preamp,
+            fallback: None,
+        }
+    }
+
+    /// Get the linear volume factor for `replaygain`, or for the fallback if there are no tags
+    pub fn factor(&self, replaygain: &ReplayGain) -> f32 {
+        let replaygain = match &self.fallback {
+            Some(fallback) if replaygain.is_empty() => fallback,
+            _ => replaygain,
+        };
+
+        replaygain.factor(self.mode, self.preamp)
+    }
+}
+
+/// Get the ReplayGain [`StandardTagKey`] of the tag, also for tags symphonia did not map
+fn tag_key(tag: &Tag) -> Option<StandardTagKey> {
+    if tag.std_key.is_some() {
+        return tag.std_key;
+    }
+
+    // the key may have a prefix like "TXXX:" or "----:com.apple.iTunes:"
+    let key = tag.key.to_ascii_lowercase();
+    let key = key.rsplit(':').next().unwrap_or_default();
+
+    match key {
+        "replaygain_track_gain" => Some(StandardTagKey::ReplayGainTrackGain),
+        "replaygain_track_peak" => Some(StandardTagKey::ReplayGainTrackPeak),
+        "replaygain_album_gain" => Some(StandardTagKey::ReplayGainAlbumGain),
+        "replaygain_album_peak" => Some(StandardTagKey::ReplayGainAlbumPeak),
+        _ => None,
+    }
+}
+
+/// Parse the value of a ReplayGain tag, like `-6.54 dB` or `0.988553`
+#[allow(clippy::cast_possible_truncation)]
+fn tag_value(tag: &Tag) -> Option<f32> {
+    match &tag.value {
+        Value::String(v) => parse_value(v),
+        Value::Float(v) => Some(*v as f32),
+        _ => None,
+    }
+}
+
+/// Parse a ReplayGain value string, ignoring the unit
+fn parse_value(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value
+        .get(value.len().saturating_sub(2)..)
+        .filter(|v| v.eq_ignore_ascii_case("db"))
+        .map_or(value, |_| &value[..value.len() - 2]);
+
+    value
+        .trim()
+        .trim_start_matches('+')
+        .parse()
+        .ok()
+        .filter(|v: &f32| v.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use symphonia::core::meta::{MetadataBuilder, StandardTagKey, Tag, Value};
+    use termusiclib::config::v2::server::ReplayGainMode;
+    use termusiclib::library_db::TrackLoudness;
+
+    use super::{parse_value, ReplayGain, ReplayGainOptions};
+
+    #[test]
+    fn should_parse_values() {
+        assert_eq!(parse_value("-6.54 dB"), Some(-6.54));
+        assert_eq!(parse_value("+2.10 dB"), Some(2.1));
+        assert_eq!(parse_value("-1.5dB"), Some(-1.5));
+        assert_eq!(parse_value(" 0.988553 "), Some(0.988_553));
+        assert_eq!(parse_value("dB"), None);
+        assert_eq!(parse_value("loud"), None);
+    }
+
+    #[test]
+    fn should_read_tags() {
+        let mut builder = MetadataBuilder::new();
+        builder
+            .add_tag(Tag::new(
+                Some(StandardTagKey::ReplayGainTrackGain),
+                "REPLAYGAIN_TRACK_GAIN",
+                Value::from("-6.00 dB"),
+            ))
+            .add_tag(Tag::new(
+                None,
+                "TXXX:REPLAYGAIN_TRACK_PEAK",
+                Value::from("0.5"),
+            ))
+            .add_tag(Tag::new(
+                None,
+                "----:com.apple.iTunes:replaygain_album_gain",
+                Value::from("-3.00 dB"),
+            ))
+            .add_tag(Tag::new(
+                Some(StandardTagKey::TrackTitle),
+                "TITLE",
+                Value::from("-1.00 dB"),
+            ));
+        let metadata = builder.metadata();
+
+        assert_eq!(
+            ReplayGain::from_metadata(&metadata),
+            ReplayGain {
+                track_gain: Some(-6.0),
+                track_peak: Some(0.5),
+                album_gain: Some(-3.0),
+                album_peak: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_calculate_factor() {
+        let replaygain = ReplayGain {
+            track_gain: Some(-20.0),
+            track_peak: Some(0.5),
+            album_gain: Some(0.0),
+            album_peak: None,
+        };
+
+        assert_eq!(replaygain.factor(ReplayGainMode::Off, 0.0), 1.0);
+        assert!((replaygain.factor(ReplayGainMode::Track, 0.0) - 0.1).abs() < 1e-6);
+        assert!((replaygain.factor(ReplayGainMode::Track, 20.0) - 1.0).abs() < 1e-6);
+        assert_eq!(replaygain.factor(ReplayGainMode::Album, 0.0), 1.0);
+        // limited by the peak, +40dB would be a factor of 100
+        assert_eq!(replaygain.factor(ReplayGainMode::Track, 40.0), 2.0);
+
+        // album falls back to the track values
+        let track_only = ReplayGain {
+            album_gain: None,
+            ..replaygain
+        };
+        assert!((track_only.factor(ReplayGainMode::Album, 0.0) - 0.1).abs() < 1e-6);
+
+        assert_eq!(
+            ReplayGain::default().factor(ReplayGainMode::Track, 6.0),
+            1.0
+        );
+    }
+
+    #[test]
+    fn should_fall_back_to_loudness() {
+        let options = ReplayGainOptions {
+            mode: ReplayGainMode::Album,
+            preamp: 0.0,
+            fallback: Some(ReplayGain::from_loudness(TrackLoudness {
+                integrated: Some(-8.0),
+                true_peak: 0.5,
+            })),
+        };
+
+        // -10dB to reach the reference of -18 LUFS
+        assert!((options.factor(&ReplayGain::default()) - 0.316_227_8).abs() < 1e-6);
+        // tags take precedence
+        let tagged = ReplayGain {
+            track_gain: Some(0.0),
+            ..ReplayGain::default()
+        };
+        assert_eq!(options.factor(&tagged), 1.0);
+
+        // too quiet to be measured
+        let silent = ReplayGainOptions {
+            fallback: Some(ReplayGain::from_loudness(TrackLoudness {
+                integrated: None,
+                true_peak: 0.0,
+            })),
+            ..options
+        };
+        assert_eq!(silent.factor(&ReplayGain::default()), 1.0);
+    }
+}
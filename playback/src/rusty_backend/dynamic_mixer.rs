@@ -151,6 +151,15 @@ impl<S> DynamicMixer<S>
 where
     S: Sample + Send + 'static,
 {
+    /// Take all playing and pending sources out of the mixer, for example to add them to the mixer of another device
+    pub fn take_sources(&mut self) -> Vec<Box<dyn Source<Item = S> + Send>> {
+        let mut sources = std::mem::take(&mut self.current_sources);
+        sources.append(&mut self.input.pending_sources.lock().unwrap());
+        self.input.has_pending.store(false, Ordering::SeqCst);
+
+        sources
+    }
+
     // Samples from the #next() function are interlaced for each of the channels.
     // We need to ensure we start playing sources so that their samples are
     // in-step with the modulo of the samples produced so far. Otherwise, the
@@ -187,3 +196,28 @@ where
         sum
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::mixer;
+    use crate::rusty_backend::buffer::SamplesBuffer;
+
+    #[test]
+    fn should_take_playing_and_pending_sources() {
+        let (controller, mut output) = mixer::<f32>(1, 1000);
+        controller.add(SamplesBuffer::new(1, 1000, vec![0.5f32; 10]));
+        // start playing the first source
+        assert!(output.next().is_some());
+        controller.add(SamplesBuffer::new(1, 1000, vec![0.25f32; 10]));
+
+        let sources = output.take_sources();
+        // nothing is left to play in the mixer
+        assert!(output.next().is_none());
+
+        // the playing source continues where it was, the pending one from the start
+        let remaining: Vec<usize> = sources.into_iter().map(Iterator::count).collect();
+        assert_eq!(remaining, [9, 10]);
+    }
+}
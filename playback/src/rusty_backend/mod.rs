@@ -19,14 +19,18 @@ pub use decoder::Symphonia;
 pub use sink::Sink;
 pub use source::Source;
 use std::num::{NonZeroU16, NonZeroUsize};
-pub use stream::OutputStream;
-use termusiclib::config::ServerOverlay;
+pub use stream::{output_device_names, OutputStream};
+use stream::{OutputStreamHandle, PlayError};
+use termusiclib::config::v2::server::{EqualizerBand, ReplayGainMode};
+use termusiclib::config::SharedServerSettings;
+use termusiclib::library_db::DataBase;
 use tokio::runtime::Handle;
 
 use crate::{MediaInfo, Speed, Volume};
 
 use self::decoder::buffered_source::BufferedSource;
 use self::decoder::read_seek_source::ReadSeekSource;
+use self::decoder::replaygain::{ReplayGain, ReplayGainOptions};
 use self::decoder::{MediaTitleRx, MediaTitleType};
 
 use super::{PlayerCmd, PlayerProgress, PlayerTrait};
@@ -63,12 +67,12 @@ pub type ArcTotalDuration = Arc<Mutex<TotalDuration>>;
 pub enum PlayerInternalCmd {
     MessageOnEnd,
     /// Enqueue a new track to be played, and skip to it
-    /// (Track, gapless)
-    Play(Box<Track>, bool),
+    /// (Track, gapless, replaygain)
+    Play(Box<Track>, bool, ReplayGainOptions),
     Progress(Duration),
     /// Enqueue a new track to be played, but do not skip current track
-    /// (Track, gapless)
-    QueueNext(Box<Track>, bool),
+    /// (Track, gapless, replaygain, crossfade)
+    QueueNext(Box<Track>, bool, ReplayGainOptions, Option<Duration>),
     Resume,
     SeekAbsolute(Duration),
     SeekRelative(i64),
@@ -77,12 +81,21 @@ pub enum PlayerInternalCmd {
     Stop,
     TogglePause,
     Volume(u16),
+    /// Set the bands of the equalizer, empty to disable it
+    Equalizer(Vec<EqualizerBand>),
+    /// Move playback to the output device with the name, or the default device if `None`
+    OutputDevice(Option<String>),
     Eos,
 }
 pub struct RustyBackend {
     volume: Arc<AtomicU16>,
     speed: i32,
     gapless: bool,
+    config: SharedServerSettings,
+    /// The library, to get the analyzed loudness of tracks without ReplayGain tags
+    db: Option<DataBase>,
+    /// The last played or enqueued track, to decide whether to crossfade into the next
+    last_queued: Option<QueuedTrack>,
     command_tx: Sender<PlayerInternalCmd>,
     position: Arc<Mutex<Duration>>,
     total_duration: ArcTotalDuration,
@@ -99,14 +112,19 @@ pub struct RustyBackend {
 impl RustyBackend {
     #[allow(clippy::similar_names)]
     #[allow(clippy::too_many_lines)]
-    pub fn new(config: &ServerOverlay, cmd_tx: crate::PlayerCmdSender) -> Self {
+    pub fn new(config: SharedServerSettings, cmd_tx: crate::PlayerCmdSender) -> Self {
         let (picmd_tx, picmd_rx): (Sender<PlayerInternalCmd>, Receiver<PlayerInternalCmd>) =
             mpsc::channel();
         let picmd_tx_local = picmd_tx.clone();
-        let volume = Arc::new(AtomicU16::from(config.settings.player.volume));
+        let config_read = config.read();
+        let volume = Arc::new(AtomicU16::from(config_read.settings.player.volume));
         let volume_local = volume.clone();
-        let speed = config.settings.player.speed;
-        let gapless = config.settings.player.gapless;
+        let speed = config_read.settings.player.speed;
+        let gapless = config_read.settings.player.gapless;
+        let db = DataBase::new(&config_read)
+            .inspect_err(|err| error!("Error opening library for loudness values: {err:#}"))
+            .ok();
+        drop(config_read);
         let position = Arc::new(Mutex::new(Duration::default()));
         let total_duration = Arc::new(Mutex::new(None));
         let total_duration_local = total_duration.clone();
@@ -118,6 +136,7 @@ impl RustyBackend {
         // let radio_downloaded_local = radio_downloaded.clone();
         // this should likely be a parameter, but works for now
         let tokio_handle = Handle::current();
+        let config_local = config.clone();
 
         std::thread::Builder::new()
             .name("playback player loop".into())
@@ -132,6 +151,7 @@ impl RustyBackend {
                     position_local,
                     volume_local,
                     speed,
+                    config_local,
                 ));
             })
             .expect("failed to spawn thread");
@@ -141,6 +161,9 @@ impl RustyBackend {
             volume,
             speed,
             gapless,
+            config,
+            db,
+            last_queued: None,
             command_tx: picmd_tx,
             position,
             media_title,
@@ -159,14 +182,84 @@ impl RustyBackend {
     pub fn message_on_end(&self) {
         self.command(PlayerInternalCmd::MessageOnEnd);
     }
+
+    /// Get the ReplayGain options for `track` from the current config, with the analyzed loudness as fallback
+    fn replaygain(&mut self, track: &Track) -> ReplayGainOptions {
+        let mut options = ReplayGainOptions::from_settings(&self.config.read().settings.player);
+
+        if options.mode != ReplayGainMode::Off && track.media_type == MediaType::Music {
+            if let (Some(db), Some(file)) = (self.db.as_mut(), track.file()) {
+                match db.get_loudness(file) {
+                    Ok(loudness) => options.fallback = loudness.map(ReplayGain::from_loudness),
+                    Err(err) => error!("Error getting loudness of \"{file}\": {err}"),
+                }
+            }
+        }
+
+        options
+    }
+
+    /// Get the crossfade duration from the current config, if `next` should be crossfaded into
+    fn crossfade(&self, next: &QueuedTrack) -> Option<Duration> {
+        let duration = Duration::from_secs(self.config.read().settings.player.crossfade_secs);
+
+        self.last_queued
+            .as_ref()
+            .and_then(|previous| crossfade_between(duration, previous, next))
+    }
+}
+
+/// The parts of a queued track that decide whether to crossfade
+#[derive(Debug, Clone)]
+struct QueuedTrack {
+    media_type: MediaType,
+    album: Option<String>,
+}
+
+impl From<&Track> for QueuedTrack {
+    fn from(value: &Track) -> Self {
+        Self {
+            media_type: value.media_type,
+            album: value.album().map(ToString::to_string),
+        }
+    }
+}
+
+/// Get whether to crossfade from `previous` to `next` with `duration`
+///
+/// Only music is crossfaded, and not within the same album as it is likely meant to be played gapless.
+fn crossfade_between(
+    duration: Duration,
+    previous: &QueuedTrack,
+    next: &QueuedTrack,
+) -> Option<Duration> {
+    if duration.is_zero()
+        || previous.media_type != MediaType::Music
+        || next.media_type != MediaType::Music
+    {
+        return None;
+    }
+
+    let same_album = previous
+        .album
+        .as_deref()
+        .is_some_and(|album| !album.is_empty() && next.album.as_deref() == Some(album));
+    if same_album {
+        return None;
+    }
+
+    Some(duration)
 }
 
 #[async_trait]
 impl PlayerTrait for RustyBackend {
     async fn add_and_play(&mut self, track: &Track) {
+        let replaygain = self.replaygain(track);
+        self.last_queued = Some(track.into());
         self.command(PlayerInternalCmd::Play(
             Box::new(track.clone()),
             self.gapless,
+            replaygain,
         ));
         self.resume();
     }
@@ -242,13 +335,33 @@ impl PlayerTrait for RustyBackend {
         self.command(PlayerInternalCmd::Skip);
     }
 
+    fn set_equalizer(&mut self, bands: &[EqualizerBand]) {
+        self.command(PlayerInternalCmd::Equalizer(bands.to_vec()));
+    }
+
+    fn set_output_device(&mut self, device: Option<&str>) {
+        self.command(PlayerInternalCmd::OutputDevice(
+            device.map(ToString::to_string),
+        ));
+    }
+
     fn enqueue_next(&mut self, track: &Track) {
+        let replaygain = self.replaygain(track);
+        let next = QueuedTrack::from(track);
+        let crossfade = self.crossfade(&next);
+        self.last_queued = Some(next);
         self.command(PlayerInternalCmd::QueueNext(
             Box::new(track.clone()),
             self.gapless,
+            replaygain,
+            crossfade,
         ));
     }
 
+    fn crossfades_into(&self, next: &Track) -> bool {
+        self.crossfade(&next.into()).is_some()
+    }
+
     fn media_info(&self) -> MediaInfo {
         let media_title_r = self.media_title.lock();
         if media_title_r.is_empty() {
@@ -261,19 +374,32 @@ impl PlayerTrait for RustyBackend {
     }
 }
 
+/// Append the `decoder` to the `sink`, amplified by its ReplayGain
+fn append_with_replaygain(sink: &Sink, decoder: Symphonia, replaygain: ReplayGainOptions) {
+    let factor = replaygain.factor(decoder.replaygain());
+    if (factor - 1.0).abs() > f32::EPSILON {
+        info!(
+            "Applying ReplayGain factor {factor} ({:?})",
+            replaygain.mode
+        );
+    }
+    sink.append(decoder.amplify(factor));
+}
+
 /// Append the `media_source` to the `sink`, while allowing different functions to run with `func` with a [`MediaTitleRx`]
 fn append_to_sink_inner_media_title<F: FnOnce(&mut Symphonia, MediaTitleRx)>(
     media_source: Box<dyn MediaSource>,
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    replaygain: ReplayGainOptions,
     func: F,
 ) {
     let mss = MediaSourceStream::new(media_source, MediaSourceStreamOptions::default());
     match Symphonia::new_with_media_title(mss, gapless) {
         Ok((mut decoder, rx)) => {
             func(&mut decoder, rx);
-            sink.append(decoder);
+            append_with_replaygain(sink, decoder, replaygain);
         }
         Err(e) => error!("error decoding '{trace}' is: {e:?}"),
     }
@@ -285,13 +411,14 @@ fn append_to_sink_inner<F: FnOnce(&mut Symphonia)>(
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    replaygain: ReplayGainOptions,
     func: F,
 ) {
     let mss = MediaSourceStream::new(media_source, MediaSourceStreamOptions::default());
     match Symphonia::new(mss, gapless) {
         Ok(mut decoder) => {
             func(&mut decoder);
-            sink.append(decoder);
+            append_with_replaygain(sink, decoder, replaygain);
         }
         Err(e) => error!("error decoding '{trace}' is: {e:?}"),
     }
@@ -305,6 +432,7 @@ fn append_to_sink<MT: Fn(MediaTitleType) + Send + 'static>(
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    replaygain: ReplayGainOptions,
     total_duration_local: &ArcTotalDuration,
     media_title_fn: MT,
 ) {
@@ -313,6 +441,7 @@ fn append_to_sink<MT: Fn(MediaTitleType) + Send + 'static>(
         trace,
         sink,
         gapless,
+        replaygain,
         |decoder, mut media_title_rx| {
             std::mem::swap(
                 &mut *total_duration_local.lock(),
@@ -336,9 +465,10 @@ fn append_to_sink_no_duration(
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    replaygain: ReplayGainOptions,
     total_duration_local: &ArcTotalDuration,
 ) {
-    append_to_sink_inner(media_source, trace, sink, gapless, |_| {
+    append_to_sink_inner(media_source, trace, sink, gapless, replaygain, |_| {
         // remove old stale duration
         total_duration_local.lock().take();
     });
@@ -354,6 +484,7 @@ fn append_to_sink_queue<MT: Fn(MediaTitleType) + Send + 'static>(
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    replaygain: ReplayGainOptions,
     // total_duration_local: &ArcTotalDuration,
     next_duration_opt: &mut Option<Duration>,
     media_title_fn: MT,
@@ -363,6 +494,7 @@ fn append_to_sink_queue<MT: Fn(MediaTitleType) + Send + 'static>(
         trace,
         sink,
         gapless,
+        replaygain,
         |decoder, mut media_title_rx| {
             std::mem::swap(next_duration_opt, &mut decoder.total_duration());
             // rely on EOS message to set next duration
@@ -387,10 +519,11 @@ fn append_to_sink_queue_no_duration(
     trace: &str,
     sink: &Sink,
     gapless: bool,
+    replaygain: ReplayGainOptions,
     // total_duration_local: &ArcTotalDuration,
     next_duration_opt: &mut Option<Duration>,
 ) {
-    append_to_sink_inner(media_source, trace, sink, gapless, |_| {
+    append_to_sink_inner(media_source, trace, sink, gapless, replaygain, |_| {
         // remove potential old stale duration
         next_duration_opt.take();
         // rely on EOS message to set next duration
@@ -425,16 +558,32 @@ async fn player_thread(
     position: Arc<Mutex<Duration>>,
     volume_inside: Arc<AtomicU16>,
     mut speed_inside: i32,
+    config: SharedServerSettings,
 ) {
     let mut is_radio = false;
+    // the sink of the previous track while it fades out for a crossfade
+    let mut fading_sink: Option<Sink> = None;
 
     // option to store enqueued's duration
     // note that the current implementation is only meant to have 1 enqueued next after the current playing song
     let mut next_duration_opt = None;
-    let (_stream, handle) = OutputStream::try_default().unwrap();
-    let mut sink = Sink::try_new(&handle, picmd_tx.clone(), pcmd_tx.clone()).unwrap();
-    sink.set_speed(speed_inside as f32 / 10.0);
-    sink.set_volume(f32::from(volume_inside.load(Ordering::SeqCst)) / 100.0);
+    let output_device = config.read().settings.player.output_device.clone();
+    let (mut stream, mut handle) = OutputStream::try_from_name(output_device.as_deref()).unwrap();
+    let mut equalizer = config
+        .read()
+        .settings
+        .player
+        .equalizer
+        .active_bands()
+        .to_vec();
+    let new_sink = |handle: &OutputStreamHandle, speed: i32, equalizer: &[EqualizerBand]| {
+        let sink = Sink::try_new(handle, picmd_tx.clone(), pcmd_tx.clone())?;
+        sink.set_speed(speed as f32 / 10.0);
+        sink.set_volume(f32::from(volume_inside.load(Ordering::SeqCst)) / 100.0);
+        sink.set_equalizer(equalizer);
+        Ok::<_, PlayError>(sink)
+    };
+    let mut sink = new_sink(&handle, speed_inside, &equalizer).unwrap();
     loop {
         let Ok(cmd) = picmd_rx.recv() else {
             // only error can be a disconnect (no more senders)
@@ -442,10 +591,12 @@ async fn player_thread(
         };
 
         match cmd {
-            PlayerInternalCmd::Play(track, gapless) => {
+            PlayerInternalCmd::Play(track, gapless, replaygain) => {
+                fading_sink = None;
                 if let Err(err) = queue_next(
                     &track,
                     gapless,
+                    replaygain,
                     &sink,
                     &mut is_radio,
                     &total_duration,
@@ -461,11 +612,30 @@ async fn player_thread(
             }
             PlayerInternalCmd::TogglePause => {
                 sink.toggle_playback();
+                if let Some(fading_sink) = &fading_sink {
+                    fading_sink.toggle_playback();
+                }
             }
-            PlayerInternalCmd::QueueNext(track, gapless) => {
+            PlayerInternalCmd::QueueNext(track, gapless, replaygain, crossfade) => {
+                // the remaining time of the current track is the most the crossfade can take
+                let crossfade = crossfade
+                    .zip(*total_duration.lock())
+                    .map(|(crossfade, total)| crossfade.min(total.saturating_sub(sink.elapsed())))
+                    .filter(|v| !v.is_zero() && !sink.empty());
+                if let Some(crossfade) = crossfade {
+                    // both sinks are played at the same time by the mixer of the output stream
+                    match new_sink(&handle, speed_inside, &equalizer) {
+                        Ok(next_sink) => {
+                            info!("Crossfading over {crossfade:?}");
+                            fading_sink = Some(crossfade_handover(&mut sink, next_sink, crossfade));
+                        }
+                        Err(err) => error!("Failed to create sink for crossfade: {err:?}"),
+                    }
+                }
                 if let Err(err) = queue_next(
                     &track,
                     gapless,
+                    replaygain,
                     &sink,
                     &mut is_radio,
                     &total_duration,
@@ -481,20 +651,57 @@ async fn player_thread(
             }
             PlayerInternalCmd::Resume => {
                 sink.play();
+                if let Some(fading_sink) = &fading_sink {
+                    fading_sink.play();
+                }
             }
             PlayerInternalCmd::Speed(speed) => {
                 speed_inside = speed;
                 sink.set_speed(speed_inside as f32 / 10.0);
+                if let Some(fading_sink) = &fading_sink {
+                    fading_sink.set_speed(speed_inside as f32 / 10.0);
+                }
             }
             PlayerInternalCmd::Stop => {
-                sink = Sink::try_new(&handle, picmd_tx.clone(), pcmd_tx.clone()).unwrap();
-                sink.set_speed(speed_inside as f32 / 10.0);
-                sink.set_volume(f32::from(volume_inside.load(Ordering::SeqCst)) / 100.0);
+                fading_sink = None;
+                sink = new_sink(&handle, speed_inside, &equalizer).unwrap();
             }
             PlayerInternalCmd::Volume(volume) => {
                 sink.set_volume(f32::from(volume) / 100.0);
+                if let Some(fading_sink) = &fading_sink {
+                    fading_sink.set_volume(f32::from(volume) / 100.0);
+                }
                 volume_inside.store(volume, Ordering::SeqCst);
             }
+            PlayerInternalCmd::Equalizer(bands) => {
+                equalizer = bands;
+                sink.set_equalizer(&equalizer);
+                if let Some(fading_sink) = &fading_sink {
+                    fading_sink.set_equalizer(&equalizer);
+                }
+            }
+            PlayerInternalCmd::OutputDevice(device) => {
+                // the sinks keep playing, as their sources are moved to the new device
+                match stream.switch_device(device.as_deref()) {
+                    Ok(new_handle) => {
+                        info!("Switched output device to {device:?}");
+                        handle = new_handle;
+                    }
+                    Err(err) => error!("Failed to switch output device to {device:?}: {err}"),
+                }
+            }
+            PlayerInternalCmd::Skip if fading_sink.is_some() => {
+                // the current track is the fading one, skip it like below so that its "message_on_end" sends the EOS,
+                // the sink itself is dropped on that EOS
+                if let Some(fading_sink) = &fading_sink {
+                    fading_sink.skip_one();
+                    if fading_sink.is_paused() {
+                        fading_sink.play();
+                    }
+                }
+                // the next track is discarded, as it gets played from the start after the EOS
+                sink = new_sink(&handle, speed_inside, &equalizer).unwrap();
+            }
             PlayerInternalCmd::Skip => {
                 // the sink can be empty, if for example nothing could be enqueued, so a "skip_one" would be a no-op and never send EOS, which is required to go to the next track
                 if sink.empty() {
@@ -516,9 +723,11 @@ async fn player_thread(
                 if !is_radio {
                     if let Some(d) = *total_duration.lock() {
                         let progress = new_position.as_secs_f64() / d.as_secs_f64();
-                        if progress >= 0.5
-                            && d.saturating_sub(new_position) < Duration::from_secs(2)
-                        {
+                        // a crossfade needs the next track to be enqueued earlier
+                        let about_to_finish =
+                            Duration::from_secs(config.read().settings.player.crossfade_secs)
+                                .max(Duration::from_secs(2));
+                        if progress >= 0.5 && d.saturating_sub(new_position) < about_to_finish {
                             if let Err(e) = pcmd_tx.send(PlayerCmd::AboutToFinish) {
                                 error!("command AboutToFinish sent failed: {e}");
                             }
@@ -560,6 +769,8 @@ async fn player_thread(
             }
 
             PlayerInternalCmd::Eos => {
+                // the previous track finished fading out, if there was a crossfade
+                fading_sink = None;
                 // replace the current total_duration with the next one
                 // this is only present when QueueNext was used; which is only used if gapless is enabled
                 if next_duration_opt.is_some() {
@@ -570,11 +781,26 @@ async fn player_thread(
     }
 }
 
+/// Hand playback over from `sink` to `next_sink`, crossfading over `crossfade`
+///
+/// The next appended source of `next_sink` fades in, while the current source of `sink` fades out and sends the EOS when done.
+///
+/// Returns the fading sink, which has to be kept alive until that EOS.
+fn crossfade_handover(sink: &mut Sink, next_sink: Sink, crossfade: Duration) -> Sink {
+    next_sink.set_fade_in(crossfade);
+    // rely on EOS message of the current track to set next duration
+    sink.message_on_end();
+    sink.fade_out(crossfade);
+
+    std::mem::replace(sink, next_sink)
+}
+
 /// Queue the given track into the [`Sink`], while also setting all of the other variables
 #[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 async fn queue_next(
     track: &Track,
     gapless: bool,
+    replaygain: ReplayGainOptions,
     sink: &Sink,
 
     is_radio: &mut bool,
@@ -599,6 +825,7 @@ async fn queue_next(
                     &file_path,
                     sink,
                     gapless,
+                    replaygain,
                     next_duration_opt,
                     common_media_title_cb(media_title.clone()),
                 );
@@ -608,6 +835,7 @@ async fn queue_next(
                     &file_path,
                     sink,
                     gapless,
+                    replaygain,
                     total_duration,
                     common_media_title_cb(media_title.clone()),
                 );
@@ -627,6 +855,7 @@ async fn queue_next(
                         &file_path,
                         sink,
                         gapless,
+                        replaygain,
                         next_duration_opt,
                         common_media_title_cb(media_title.clone()),
                     );
@@ -636,6 +865,7 @@ async fn queue_next(
                         &file_path,
                         sink,
                         gapless,
+                        replaygain,
                         total_duration,
                         common_media_title_cb(media_title.clone()),
                     );
@@ -663,6 +893,7 @@ async fn queue_next(
                     &url,
                     sink,
                     gapless,
+                    replaygain,
                     next_duration_opt,
                     common_media_title_cb(media_title.clone()),
                 );
@@ -672,6 +903,7 @@ async fn queue_next(
                     &url,
                     sink,
                     gapless,
+                    replaygain,
                     total_duration,
                     common_media_title_cb(media_title.clone()),
                 );
@@ -746,10 +978,18 @@ async fn queue_next(
                     &url,
                     sink,
                     gapless,
+                    replaygain,
                     next_duration_opt,
                 );
             } else {
-                append_to_sink_no_duration(media_source, &url, sink, gapless, total_duration);
+                append_to_sink_no_duration(
+                    media_source,
+                    &url,
+                    sink,
+                    gapless,
+                    replaygain,
+                    total_duration,
+                );
             }
 
             Ok(())
@@ -767,3 +1007,90 @@ async fn queue_next(
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+    use termusiclib::track::MediaType;
+
+    use super::buffer::SamplesBuffer;
+    use super::{crossfade_between, crossfade_handover, PlayerInternalCmd, QueuedTrack, Sink};
+    use crate::PlayerCmd;
+
+    fn music(album: Option<&str>) -> QueuedTrack {
+        QueuedTrack {
+            media_type: MediaType::Music,
+            album: album.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn should_crossfade_between_albums() {
+        let duration = Duration::from_secs(5);
+
+        assert_eq!(
+            crossfade_between(duration, &music(Some("A")), &music(Some("B"))),
+            Some(duration)
+        );
+        assert_eq!(
+            crossfade_between(duration, &music(None), &music(None)),
+            Some(duration)
+        );
+        assert_eq!(
+            crossfade_between(duration, &music(Some("")), &music(Some(""))),
+            Some(duration)
+        );
+        assert_eq!(
+            crossfade_between(Duration::ZERO, &music(Some("A")), &music(Some("B"))),
+            None
+        );
+    }
+
+    #[test]
+    fn should_not_crossfade_gapless() {
+        let duration = Duration::from_secs(5);
+
+        assert_eq!(
+            crossfade_between(duration, &music(Some("A")), &music(Some("A"))),
+            None
+        );
+
+        let podcast = QueuedTrack {
+            media_type: MediaType::Podcast,
+            album: None,
+        };
+        assert_eq!(crossfade_between(duration, &music(None), &podcast), None);
+        assert_eq!(crossfade_between(duration, &podcast, &music(None)), None);
+    }
+
+    #[test]
+    fn should_hand_over_sink_on_crossfade() {
+        let (picmd_tx, picmd_rx) = std::sync::mpsc::channel();
+        let (pcmd_tx, mut pcmd_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let (mut sink, mut output) = Sink::new_idle(picmd_tx.clone(), pcmd_tx.clone());
+        // 10 seconds of samples
+        sink.append(SamplesBuffer::new(1, 1000, vec![0.5f32; 10_000]));
+        let (next_sink, _next_output) = Sink::new_idle(picmd_tx, pcmd_tx);
+
+        let fading_sink = crossfade_handover(&mut sink, next_sink, Duration::from_millis(100));
+        assert!(sink.empty());
+        assert!(!fading_sink.empty());
+
+        // the fading source stops after the crossfade instead of playing the full 10 seconds
+        let played = output
+            .by_ref()
+            .take(1000)
+            .filter(|v| v.abs() > f32::EPSILON)
+            .count();
+        assert!(played < 200, "played {played} samples");
+        assert!(fading_sink.empty());
+
+        let eos = std::iter::from_fn(|| picmd_rx.recv_timeout(Duration::from_secs(5)).ok())
+            .find(|cmd| matches!(cmd, PlayerInternalCmd::Eos));
+        assert!(eos.is_some());
+        assert!(matches!(pcmd_rx.try_recv(), Ok(PlayerCmd::Eos)));
+    }
+}
@@ -3,6 +3,7 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
 use std::time::Duration;
+use termusiclib::config::v2::server::EqualizerBand;
 use tokio::sync::mpsc::UnboundedSender;
 
 use super::stream::{OutputStreamHandle, PlayError};
@@ -23,8 +24,13 @@ pub struct Sink {
     elapsed: Arc<RwLock<Duration>>,
     message_tx: Sender<PlayerInternalCmd>,
     cmd_tx: crate::PlayerCmdSender,
+    /// Fade in duration for the next appended source
+    fade_in: Mutex<Duration>,
 }
 
+/// The duration the sources are limited to when not fading out, long enough to never be reached
+const UNLIMITED_DURATION: Duration = Duration::from_secs(60 * 60 * 24 * 365 * 100);
+
 struct Controls {
     pause: AtomicBool,
     volume: Mutex<f32>,
@@ -32,6 +38,9 @@ struct Controls {
     stopped: AtomicBool,
     speed: Mutex<f32>,
     to_clear: Mutex<u32>,
+    fade_out: Mutex<Option<Duration>>,
+    fading: AtomicBool,
+    equalizer: Mutex<Vec<EqualizerBand>>,
 }
 
 impl Sink {
@@ -66,12 +75,16 @@ impl Sink {
                 seek: Mutex::new(None),
                 speed: Mutex::new(1.0),
                 to_clear: Mutex::new(0),
+                fade_out: Mutex::new(None),
+                fading: AtomicBool::new(false),
+                equalizer: Mutex::new(Vec::new()),
             }),
             sound_count: Arc::new(AtomicUsize::new(0)),
             detached: false,
             elapsed: Arc::new(RwLock::new(Duration::from_secs(0))),
             message_tx: tx,
             cmd_tx,
+            fade_in: Mutex::new(Duration::ZERO),
         };
         (sink, queue_rx)
     }
@@ -94,20 +107,30 @@ impl Sink {
         }
 
         let controls = self.controls.clone();
+        let controls_progress = self.controls.clone();
         let controls_tempo = self.controls.clone();
 
         let start_played = AtomicBool::new(false);
 
         let tx = self.message_tx.clone();
         let elapsed = self.elapsed.clone();
+        let fade_in = std::mem::take(&mut *self.fade_in.lock());
         let source = source
+            // the equalizer filters in f32
+            .convert_samples::<f32>()
+            .fade_in(fade_in)
+            .equalizer()
+            .take_duration(UNLIMITED_DURATION)
             .speed(1.0)
             .pausable(false)
             .amplify(1.0)
             .skippable()
             .stoppable()
             .periodic_access(Duration::from_millis(500), move |src| {
-                tx.send(PlayerInternalCmd::Progress(src.elapsed())).ok();
+                // the progress of a fading out source is not the current one anymore
+                if !controls_progress.fading.load(Ordering::SeqCst) {
+                    tx.send(PlayerInternalCmd::Progress(src.elapsed())).ok();
+                }
             })
             .periodic_access(Duration::from_millis(5), move |src| {
                 let src = src.inner_mut();
@@ -137,6 +160,12 @@ impl Sink {
                             .set_factor(*controls.speed.lock());
                     }
 
+                    let take = amp.inner_mut().inner_mut().inner_mut();
+                    if let Some(fade_out) = controls.fade_out.lock().take() {
+                        take.fade_out(fade_out);
+                    }
+                    take.inner_mut().set_bands(&controls.equalizer.lock());
+
                     start_played.store(true, Ordering::SeqCst);
                 }
             })
@@ -174,6 +203,12 @@ impl Sink {
         *self.controls.volume.lock() = value;
     }
 
+    /// Changes the bands of the equalizer, an empty slice disables it.
+    #[inline]
+    pub fn set_equalizer(&self, bands: &[EqualizerBand]) {
+        *self.controls.equalizer.lock() = bands.to_vec();
+    }
+
     /// Gets the speed of the sound.
     ///
     /// The value `1.0` is the "normal" speed (unfiltered input). Any value other than `1.0` will
@@ -223,6 +258,19 @@ impl Sink {
         }
         *self.controls.seek.lock() = Some(seek_time);
     }
+    /// Fade out the current source over `duration` and stop it afterwards, for crossfading
+    ///
+    /// The progress of the source is not reported anymore.
+    pub fn fade_out(&self, duration: Duration) {
+        self.controls.fading.store(true, Ordering::SeqCst);
+        *self.controls.fade_out.lock() = Some(duration);
+    }
+
+    /// Fade in the next appended source over `duration`, for crossfading
+    pub fn set_fade_in(&self, duration: Duration) {
+        *self.fade_in.lock() = duration;
+    }
+
     /// Toggles playback of the sink
     pub fn toggle_playback(&self) {
         if self.is_paused() {
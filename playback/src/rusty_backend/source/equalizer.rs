@@ -0,0 +1,275 @@
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use termusiclib::config::v2::server::EqualizerBand;
+
+use super::Source;
+
+/// Internal function that builds a `Equalizer` object.
+pub fn equalizer<I>(input: I) -> Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    Equalizer {
+        input,
+        bands: Vec::new(),
+        filters: Vec::new(),
+        sample_rate: 0,
+        channels: 0,
+        channel: 0,
+    }
+}
+
+/// Filter that applies a peaking biquad filter for each equalizer band.
+#[derive(Clone, Debug)]
+pub struct Equalizer<I> {
+    input: I,
+    bands: Vec<EqualizerBand>,
+    /// The filters of the bands that have a effect at the current sample rate
+    filters: Vec<PeakingFilter>,
+    sample_rate: u32,
+    channels: u16,
+    /// The channel of the next sample
+    channel: usize,
+}
+
+#[allow(unused)]
+impl<I> Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    /// Modifies the bands, an empty slice disables the equalizer.
+    ///
+    /// Does nothing if the bands did not change, which makes it cheap to call periodically.
+    #[inline]
+    pub fn set_bands(&mut self, bands: &[EqualizerBand]) {
+        if self.bands == bands {
+            return;
+        }
+
+        self.bands = bands.to_vec();
+        self.update_filters();
+    }
+
+    /// Returns a reference to the inner source.
+    #[inline]
+    pub fn inner(&self) -> &I {
+        &self.input
+    }
+
+    /// Returns a mutable reference to the inner source.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    /// Returns the inner source.
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+
+    /// Calculate the filters for the current bands and format
+    ///
+    /// The state of existing filters is kept so that changing the gain while playing does not click.
+    fn update_filters(&mut self) {
+        let channels = usize::from(self.channels);
+        let nyquist = f64::from(self.sample_rate) / 2.0;
+        let mut old_filters = std::mem::take(&mut self.filters).into_iter();
+
+        self.filters = self
+            .bands
+            .iter()
+            // bands without gain do not change anything, and bands above nyquist cannot be represented
+            .filter(|band| {
+                band.is_valid() && band.gain != 0.0 && f64::from(band.frequency) < nyquist
+            })
+            .map(|band| {
+                let mut filter = PeakingFilter::new(band, self.sample_rate, channels);
+                if let Some(old) = old_filters.next() {
+                    if old.state.len() == channels {
+                        filter.state = old.state;
+                    }
+                }
+
+                filter
+            })
+            .collect();
+    }
+}
+
+impl<I> Iterator for Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            let sample_rate = self.input.sample_rate();
+            let channels = self.input.channels();
+            if sample_rate != self.sample_rate || channels != self.channels {
+                self.sample_rate = sample_rate;
+                self.channels = channels;
+                // the state of a different format does not apply anymore
+                self.filters.clear();
+                self.update_filters();
+            }
+        }
+
+        let sample = self.input.next()?;
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % usize::from(self.channels.max(1));
+
+        if self.filters.is_empty() {
+            return Some(sample);
+        }
+
+        let value = self
+            .filters
+            .iter_mut()
+            .fold(f64::from(sample), |value, filter| {
+                filter.process(channel, value)
+            });
+
+        #[allow(clippy::cast_possible_truncation)]
+        Some(value as f32)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for Equalizer<I> where I: Source<Item = f32> + ExactSizeIterator {}
+
+impl<I> Source for Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.input.elapsed()
+    }
+
+    fn seek(&mut self, time: Duration) -> Option<Duration> {
+        self.input.seek(time)
+    }
+}
+
+/// A peaking biquad filter in transposed direct form 2, with the state of each channel
+///
+/// The coefficients are from the "Audio EQ Cookbook" by Robert Bristow-Johnson.
+#[derive(Clone, Debug)]
+struct PeakingFilter {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: Vec<[f64; 2]>,
+}
+
+impl PeakingFilter {
+    fn new(band: &EqualizerBand, sample_rate: u32, channels: usize) -> Self {
+        let gain = 10f64.powf(f64::from(band.gain) / 40.0);
+        let w0 = 2.0 * PI * f64::from(band.frequency) / f64::from(sample_rate);
+        let alpha = w0.sin() / (2.0 * f64::from(band.q));
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha / gain;
+
+        Self {
+            b: [
+                (1.0 + alpha * gain) / a0,
+                -2.0 * cos / a0,
+                (1.0 - alpha * gain) / a0,
+            ],
+            a: [-2.0 * cos / a0, (1.0 - alpha / gain) / a0],
+            state: vec![[0.0; 2]; channels],
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, channel: usize, input: f64) -> f64 {
+        let state = &mut self.state[channel];
+        let output = self.b[0] * input + state[0];
+        state[0] = self.b[1] * input - self.a[0] * output + state[1];
+        state[1] = self.b[2] * input - self.a[1] * output;
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use termusiclib::config::v2::server::EqualizerBand;
+
+    use crate::rusty_backend::buffer::SamplesBuffer;
+    use crate::rusty_backend::Source;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// Get the peak of a stereo sine with `frequency` after the warm-up of the filters
+    #[allow(clippy::cast_precision_loss)]
+    fn filtered_peak(frequency: f32, bands: &[EqualizerBand]) -> f32 {
+        let data: Vec<f32> = (0..SAMPLE_RATE)
+            .flat_map(|idx| {
+                let value = 0.25 * (2.0 * PI * frequency * idx as f32 / SAMPLE_RATE as f32).sin();
+                [value, value]
+            })
+            .collect();
+        let mut source = SamplesBuffer::new(2, SAMPLE_RATE, data).equalizer();
+        source.set_bands(bands);
+
+        source
+            .skip(SAMPLE_RATE as usize)
+            .fold(0.0, |peak: f32, v| peak.max(v.abs()))
+    }
+
+    #[test]
+    fn should_boost_band() {
+        let bands = [EqualizerBand {
+            frequency: 1000.0,
+            gain: 6.0,
+            q: EqualizerBand::OCTAVE_Q,
+        }];
+
+        // +6dB doubles the amplitude at the center frequency
+        assert!((filtered_peak(1000.0, &bands) - 0.5).abs() < 0.01);
+        // far away frequencies are not affected
+        assert!((filtered_peak(50.0, &bands) - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn should_pass_through_without_gain() {
+        let flat = [EqualizerBand {
+            frequency: 1000.0,
+            gain: 0.0,
+            q: EqualizerBand::OCTAVE_Q,
+        }];
+
+        assert!((filtered_peak(1000.0, &[]) - 0.25).abs() < 1e-6);
+        assert!((filtered_peak(1000.0, &flat) - 0.25).abs() < 1e-6);
+    }
+}
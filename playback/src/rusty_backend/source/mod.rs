@@ -8,7 +8,8 @@ pub use self::amplify::Amplify;
 pub use self::delay::Delay;
 pub use self::done::Done;
 pub use self::empty::Empty;
-// pub use self::fadein::FadeIn;
+pub use self::equalizer::Equalizer;
+pub use self::fadein::FadeIn;
 #[allow(clippy::module_name_repetitions)]
 #[allow(unused_imports)]
 pub use self::mix_source::MixSource;
@@ -20,7 +21,7 @@ pub use self::scaletempo::TempoStretch;
 pub use self::skippable::Skippable;
 pub use self::speed::Speed;
 pub use self::stoppable::Stoppable;
-// pub use self::take::TakeDuration;
+pub use self::take::TakeDuration;
 pub use self::uniform::UniformSourceIterator;
 pub use self::zero::Zero;
 
@@ -28,7 +29,8 @@ mod amplify;
 mod delay;
 mod done;
 mod empty;
-// mod fadein;
+mod equalizer;
+mod fadein;
 mod mix_source;
 // mod http;
 mod pausable;
@@ -39,7 +41,7 @@ pub mod scaletempo;
 mod skippable;
 mod speed;
 mod stoppable;
-// mod take;
+mod take;
 mod uniform;
 mod zero;
 
@@ -137,14 +139,14 @@ where
 
     fn elapsed(&mut self) -> Duration;
 
-    // /// Takes a certain duration of this source and then stops.
-    // #[inline]
-    // fn take_duration(self, duration: Duration) -> TakeDuration<Self>
-    // where
-    //     Self: Sized,
-    // {
-    //     take::take_duration(self, duration)
-    // }
+    /// Takes a certain duration of this source and then stops.
+    #[inline]
+    fn take_duration(self, duration: Duration) -> TakeDuration<Self>
+    where
+        Self: Sized,
+    {
+        take::take_duration(self, duration)
+    }
 
     /// Immediately skips a certain duration of this source.
     ///
@@ -159,14 +161,24 @@ where
         amplify::amplify(self, value)
     }
 
-    // /// Fades in the sound.
-    // #[inline]
-    // fn fade_in(self, duration: Duration) -> FadeIn<Self>
-    // where
-    //     Self: Sized,
-    // {
-    //     fadein::fadein(self, duration)
-    // }
+    /// Applies the bands of a equalizer, initially none, see [`Equalizer::set_bands`].
+    #[inline]
+    fn equalizer(self) -> Equalizer<Self>
+    where
+        Self: Sized,
+        Self: Source<Item = f32>,
+    {
+        equalizer::equalizer(self)
+    }
+
+    /// Fades in the sound.
+    #[inline]
+    fn fade_in(self, duration: Duration) -> FadeIn<Self>
+    where
+        Self: Sized,
+    {
+        fadein::fadein(self, duration)
+    }
 
     /// Calls the `access` closure on `Self` the first time the source is iterated and every
     /// time `period` elapses.
@@ -102,6 +102,13 @@ where
     pub fn clear_filter(&mut self) {
         self.filter = None;
     }
+
+    /// Fade out over `duration` starting now, and stop afterwards
+    pub fn fade_out(&mut self, duration: Duration) {
+        self.remaining_duration = duration;
+        self.requested_duration = duration;
+        self.set_filter_fadeout();
+    }
 }
 
 impl<I> Iterator for TakeDuration<I>
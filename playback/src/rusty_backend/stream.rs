@@ -2,10 +2,16 @@ use std::sync::{Arc, Weak};
 use std::{error, fmt};
 
 use super::decoder;
-use super::dynamic_mixer::{self, DynamicMixerController};
+use super::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
 use super::source::Source;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Sample, SupportedStreamConfig};
+use parking_lot::Mutex;
+
+/// The output side of the mixer, shared with the callback of the `cpal::Stream` so that its sources can be moved to another device
+type SharedMixer = Arc<Mutex<DynamicMixer<f32>>>;
+/// The input and output of a mixer, and the `cpal::Stream` playing it
+type MixerStream = (Arc<DynamicMixerController<f32>>, SharedMixer, cpal::Stream);
 
 /// `cpal::Stream` container. Also see the more useful `OutputStreamHandle`.
 ///
@@ -13,6 +19,7 @@ use cpal::{Sample, SupportedStreamConfig};
 #[allow(clippy::module_name_repetitions)]
 pub struct OutputStream {
     mixer: Arc<DynamicMixerController<f32>>,
+    output: SharedMixer,
     _stream: cpal::Stream,
 }
 
@@ -40,10 +47,11 @@ impl OutputStream {
         device: &cpal::Device,
         config: SupportedStreamConfig,
     ) -> Result<(Self, OutputStreamHandle), StreamError> {
-        let (mixer, stream) = device.try_new_output_stream_config(config)?;
+        let (mixer, output, stream) = device.try_new_output_stream_config(config)?;
         stream.play()?;
         let out = Self {
             mixer,
+            output,
             _stream: stream,
         };
         let handle = OutputStreamHandle {
@@ -73,6 +81,61 @@ impl OutputStream {
                 .ok_or(original_err)
         })
     }
+
+    /// Return a new stream & handle using the output device with `name`.
+    ///
+    /// Falls back to [`OutputStream::try_default`] if `name` is `None` or if the device does not work,
+    /// for example because it is unplugged.
+    pub fn try_from_name(name: Option<&str>) -> Result<(Self, OutputStreamHandle), StreamError> {
+        let Some(name) = name else {
+            return Self::try_default();
+        };
+
+        find_device(name)
+            .and_then(|device| Self::try_from_device(&device))
+            .or_else(|err| {
+                warn!("Output device \"{name}\" is not available, using the default device: {err}");
+                Self::try_default()
+            })
+    }
+
+    /// Move playback to the output device with `name`, or to the default output device if `None`.
+    ///
+    /// The playing sources are moved to the new device, so playback continues where it was.
+    /// Handles of the previous device do not work anymore, use the returned one instead.
+    pub fn switch_device(&mut self, name: Option<&str>) -> Result<OutputStreamHandle, StreamError> {
+        let device = match name {
+            Some(name) => find_device(name)?,
+            None => cpal::default_host()
+                .default_output_device()
+                .ok_or(StreamError::NoDevice)?,
+        };
+        let (stream, handle) = Self::try_from_device(&device)?;
+
+        for source in self.output.lock().take_sources() {
+            stream.mixer.add(source);
+        }
+        // drops the previous device's stream
+        *self = stream;
+
+        Ok(handle)
+    }
+}
+
+/// Get the names of all output devices of the default host
+pub fn output_device_names() -> Result<Vec<String>, StreamError> {
+    Ok(cpal::default_host()
+        .output_devices()?
+        .filter_map(|device| device.name().ok())
+        .collect())
+}
+
+/// Find the output device with `name` on the default host
+fn find_device(name: &str) -> Result<cpal::Device, StreamError> {
+    cpal::default_host()
+        .output_devices()?
+        .find(|device| device.name().is_ok_and(|v| v == name))
+        .ok_or(StreamError::NoDevice)
 }
 
 impl OutputStreamHandle {
@@ -138,6 +201,7 @@ pub enum StreamError {
     DefaultStreamConfigError(cpal::DefaultStreamConfigError),
     BuildStreamError(cpal::BuildStreamError),
     SupportedStreamConfigsError(cpal::SupportedStreamConfigsError),
+    DevicesError(cpal::DevicesError),
     NoDevice,
 }
 
@@ -153,6 +217,12 @@ impl From<cpal::SupportedStreamConfigsError> for StreamError {
     }
 }
 
+impl From<cpal::DevicesError> for StreamError {
+    fn from(err: cpal::DevicesError) -> Self {
+        Self::DevicesError(err)
+    }
+}
+
 impl From<cpal::BuildStreamError> for StreamError {
     fn from(err: cpal::BuildStreamError) -> Self {
         Self::BuildStreamError(err)
@@ -172,6 +242,7 @@ impl fmt::Display for StreamError {
             Self::BuildStreamError(e) => e.fmt(f),
             Self::DefaultStreamConfigError(e) => e.fmt(f),
             Self::SupportedStreamConfigsError(e) => e.fmt(f),
+            Self::DevicesError(e) => e.fmt(f),
             Self::NoDevice => write!(f, "NoDevice"),
         }
     }
@@ -184,6 +255,7 @@ impl error::Error for StreamError {
             Self::BuildStreamError(e) => Some(e),
             Self::DefaultStreamConfigError(e) => Some(e),
             Self::SupportedStreamConfigsError(e) => Some(e),
+            Self::DevicesError(e) => Some(e),
             Self::NoDevice => None,
         }
     }
@@ -194,12 +266,12 @@ pub(crate) trait CpalDeviceExt {
     fn new_output_stream_with_format(
         &self,
         format: cpal::SupportedStreamConfig,
-    ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), cpal::BuildStreamError>;
+    ) -> Result<MixerStream, cpal::BuildStreamError>;
 
     fn try_new_output_stream_config(
         &self,
         config: cpal::SupportedStreamConfig,
-    ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), StreamError>;
+    ) -> Result<MixerStream, StreamError>;
 }
 
 impl CpalDeviceExt for cpal::Device {
@@ -207,9 +279,11 @@ impl CpalDeviceExt for cpal::Device {
     fn new_output_stream_with_format(
         &self,
         format: cpal::SupportedStreamConfig,
-    ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), cpal::BuildStreamError> {
-        let (mixer_tx, mut mixer_rx) =
+    ) -> Result<MixerStream, cpal::BuildStreamError> {
+        let (mixer_tx, mixer_rx) =
             dynamic_mixer::mixer::<f32>(format.channels(), format.sample_rate().0);
+        let mixer_rx = Arc::new(Mutex::new(mixer_rx));
+        let output = mixer_rx.clone();
 
         let error_callback = |err| error!("an error occurred on output stream: {err}");
 
@@ -217,6 +291,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::F32 => self.build_output_stream::<f32, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     data.iter_mut()
                         .for_each(|d| *d = mixer_rx.next().unwrap_or(0f32));
                 },
@@ -226,6 +301,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::F64 => self.build_output_stream::<f64, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(0f64, Sample::from_sample);
                     }
@@ -236,6 +312,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::I8 => self.build_output_stream::<i8, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(0i8, Sample::from_sample);
                     }
@@ -246,6 +323,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::I16 => self.build_output_stream::<i16, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(0i16, Sample::from_sample);
                     }
@@ -256,6 +334,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::I32 => self.build_output_stream::<i32, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(0i32, Sample::from_sample);
                     }
@@ -266,6 +345,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::I64 => self.build_output_stream::<i64, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(0i64, Sample::from_sample);
                     }
@@ -276,6 +356,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::U8 => self.build_output_stream::<u8, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(u8::MAX / 2, Sample::from_sample);
                     }
@@ -286,6 +367,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::U16 => self.build_output_stream::<u16, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(u16::MAX / 2, Sample::from_sample);
                     }
@@ -296,6 +378,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::U32 => self.build_output_stream::<u32, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(u32::MAX / 2, Sample::from_sample);
                     }
@@ -306,6 +389,7 @@ impl CpalDeviceExt for cpal::Device {
             cpal::SampleFormat::U64 => self.build_output_stream::<u64, _, _>(
                 &format.config(),
                 move |data, _| {
+                    let mut mixer_rx = mixer_rx.lock();
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(u64::MAX / 2, Sample::from_sample);
                     }
@@ -315,13 +399,13 @@ impl CpalDeviceExt for cpal::Device {
             ),
             _ => return Err(cpal::BuildStreamError::StreamConfigNotSupported),
         }
-        .map(|stream| (mixer_tx, stream))
+        .map(|stream| (mixer_tx, output, stream))
     }
 
     fn try_new_output_stream_config(
         &self,
         config: SupportedStreamConfig,
-    ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), StreamError> {
+    ) -> Result<MixerStream, StreamError> {
         self.new_output_stream_with_format(config).or_else(|err| {
             // look through all supported formats to see if another works
             supported_output_formats(self)?
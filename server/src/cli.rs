@@ -107,6 +107,12 @@ pub enum Action {
         #[arg(value_name = "FILE")]
         file: PathBuf,
     },
+    /// Analyze the loudness of the library tracks, used for normalization of tracks without ReplayGain tags.
+    AnalyzeLoudness {
+        /// Analyze all tracks again, instead of only the ones not analyzed yet
+        #[arg(long)]
+        all: bool,
+    },
 }
 
 const DEFAULT_LOGFILE_FILENAME: &str = "termusic-server.log";
@@ -0,0 +1,837 @@
+//! Execution of single MPD commands
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use termusiclib::config::v2::server::LoopMode;
+use termusiclib::library_db::{SearchCriteria, TrackDB};
+use termusiclib::player::{PlaylistAddTrack, PlaylistRemoveTrack, PlaylistTrackSource};
+use termusiclib::track::Track;
+use termusiclib::utils::filetype_supported;
+use termusicplayback::{PlayerCmd, Status};
+
+use super::protocol::{parse_bool, parse_range, Ack, AckCode, AckResult, Response};
+use super::{MpdContext, Session};
+
+/// All supported commands, as reported by `commands`
+const COMMANDS: &[&str] = &[
+    "add",
+    "addid",
+    "clear",
+    "close",
+    "command_list_begin",
+    "command_list_end",
+    "command_list_ok_begin",
+    "commands",
+    "consume",
+    "currentsong",
+    "delete",
+    "deleteid",
+    "find",
+    "idle",
+    "list",
+    "listplaylists",
+    "next",
+    "noidle",
+    "notcommands",
+    "outputs",
+    "password",
+    "pause",
+    "ping",
+    "play",
+    "playid",
+    "playlistid",
+    "playlistinfo",
+    "plchanges",
+    "previous",
+    "random",
+    "repeat",
+    "search",
+    "seek",
+    "seekcur",
+    "seekid",
+    "setvol",
+    "shuffle",
+    "single",
+    "stats",
+    "status",
+    "stop",
+    "tagtypes",
+    "urlhandlers",
+    "volume",
+];
+
+/// How long `addid` waits for the player to add the track
+const ADD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Commands that can be used without being authenticated
+const COMMANDS_UNAUTHENTICATED: &[&str] = &["close", "commands", "notcommands", "password", "ping"];
+
+/// Execute a single command, returning the response body without the final `OK`
+pub fn execute(ctx: &MpdContext, session: &mut Session, args: &[String]) -> AckResult<String> {
+    let command = args[0].as_str();
+    let args = &args[1..];
+
+    if !session.authenticated && !COMMANDS_UNAUTHENTICATED.contains(&command) {
+        return Err(Ack::new(
+            AckCode::Permission,
+            format!("you don't have permission for \"{command}\""),
+        ));
+    }
+
+    let mut resp = Response::default();
+    match command {
+        "ping" | "notcommands" | "listplaylists" => (),
+        "password" => {
+            let given = arg(args, 0)?;
+            if session.token.as_deref().is_some_and(|v| v != given) {
+                return Err(Ack::new(AckCode::Password, "incorrect password"));
+            }
+            session.authenticated = true;
+        }
+        "commands" => {
+            for command in COMMANDS {
+                resp.field("command", command);
+            }
+        }
+        "tagtypes" => {
+            // "tagtypes" with arguments changes the enabled tags, which is not supported, but always successful
+            if args.is_empty() {
+                for tag in ["Artist", "Album", "Title", "Genre"] {
+                    resp.field("tagtype", tag);
+                }
+            }
+        }
+        "urlhandlers" => {
+            resp.field("handler", "http://")
+                .field("handler", "https://");
+        }
+        "outputs" => {
+            resp.field("outputid", 0)
+                .field("outputname", "termusic")
+                .field("plugin", "termusic")
+                .field("outputenabled", 1);
+        }
+        "status" => status(ctx, &mut resp),
+        "stats" => stats(ctx, &mut resp)?,
+        "currentsong" => {
+            let playlist = ctx.playlist.read();
+            if let Some(track) = playlist.current_track() {
+                let index = playlist.get_current_track_index();
+                write_track(&mut resp, track, index, ctx.song_ids.id_of(index));
+            }
+        }
+        "play" | "playid" => match args.first() {
+            Some(pos) => {
+                let pos = if command == "playid" {
+                    resolve_id(ctx, pos)?
+                } else {
+                    parse_index(pos)?
+                };
+                check_index(ctx, pos)?;
+                command_send(ctx, PlayerCmd::PlaySelected(pos));
+            }
+            None => {
+                let (status, index) = {
+                    let playlist = ctx.playlist.read();
+                    (playlist.status(), playlist.get_current_track_index())
+                };
+                match status {
+                    Status::Paused => command_send(ctx, PlayerCmd::Play),
+                    Status::Stopped => command_send(ctx, PlayerCmd::PlaySelected(index)),
+                    Status::Running => (),
+                }
+            }
+        },
+        "pause" => match args.first() {
+            Some(state) if parse_bool(state)? => command_send(ctx, PlayerCmd::Pause),
+            Some(_) => command_send(ctx, PlayerCmd::Play),
+            None => command_send(ctx, PlayerCmd::TogglePause),
+        },
+        "stop" => command_send(ctx, PlayerCmd::Stop),
+        "next" => command_send(ctx, PlayerCmd::SkipNext),
+        "previous" => command_send(ctx, PlayerCmd::SkipPrevious),
+        "setvol" => {
+            let volume = parse_u32(arg(args, 0)?)?.min(100);
+            command_send(ctx, PlayerCmd::SetVolume(volume_u16(volume)));
+        }
+        "volume" => {
+            let change = parse_i64(arg(args, 0)?)?;
+            let current = i64::from(ctx.config.read().settings.player.volume);
+            let volume = (current + change).clamp(0, 100);
+            command_send(ctx, PlayerCmd::SetVolume(volume_u16(volume)));
+        }
+        "seekcur" => {
+            let time = arg(args, 0)?;
+            let position = if time.starts_with(['+', '-']) {
+                let current = ctx
+                    .player_stats
+                    .lock()
+                    .progress
+                    .position
+                    .unwrap_or_default();
+                let change = parse_f64(time)?;
+                parse_duration(current.as_secs_f64() + change)?
+            } else {
+                parse_duration(parse_f64(time)?)?
+            };
+            command_send(ctx, PlayerCmd::SeekTo(position));
+        }
+        "seek" | "seekid" => {
+            let pos = if command == "seekid" {
+                resolve_id(ctx, arg(args, 0)?)?
+            } else {
+                parse_index(arg(args, 0)?)?
+            };
+            let position = parse_duration(parse_f64(arg(args, 1)?)?)?;
+            if pos != ctx.playlist.read().get_current_track_index() {
+                return Err(Ack::arg("Seeking is only supported in the current song"));
+            }
+            command_send(ctx, PlayerCmd::SeekTo(position));
+        }
+        // the playlist always repeats, "single" repeats the current track
+        "repeat" => {
+            if !parse_bool(arg(args, 0)?)? {
+                return Err(unsupported());
+            }
+        }
+        "consume" => {
+            if parse_bool(arg(args, 0)?)? {
+                return Err(unsupported());
+            }
+        }
+        // "random" and "single" are both loop modes, so only disabling the active one changes the mode
+        "random" | "single" => {
+            let mode = if command == "random" {
+                LoopMode::Random
+            } else {
+                LoopMode::Single
+            };
+            let enable = match arg(args, 0)? {
+                "oneshot" => return Err(unsupported()),
+                state => parse_bool(state)?,
+            };
+            if enable {
+                command_send(ctx, PlayerCmd::PlaylistSetLoopMode(mode));
+            } else if ctx.playlist.read().loop_mode() == mode {
+                command_send(ctx, PlayerCmd::PlaylistSetLoopMode(LoopMode::Playlist));
+            }
+        }
+        "add" | "addid" => {
+            let tracks = resolve_uri(ctx, arg(args, 0)?)?;
+            let at_index = match args.get(1) {
+                Some(pos) => parse_u32(pos)?,
+                None => u32::MAX,
+            };
+            command_send(
+                ctx,
+                PlayerCmd::PlaylistAddTrack(PlaylistAddTrack {
+                    at_index,
+                    tracks: tracks.clone(),
+                }),
+            );
+            if command == "addid" {
+                let Some(id) = ctx.song_ids.wait_added(&tracks, ADD_TIMEOUT) else {
+                    return Err(Ack::new(AckCode::NoExist, "Failed to add the song"));
+                };
+                resp.field("Id", id);
+            }
+        }
+        "delete" | "deleteid" => {
+            let (start, end) = if command == "deleteid" {
+                let pos = resolve_id(ctx, arg(args, 0)?)?;
+                let pos = u32::try_from(pos).unwrap_or(u32::MAX);
+                (pos, pos.saturating_add(1))
+            } else {
+                let (start, end) = parse_range(arg(args, 0)?)?;
+                let len = u32::try_from(ctx.playlist.read().len()).unwrap_or(u32::MAX);
+                (start, end.unwrap_or(len).min(len))
+            };
+            if start >= end {
+                return Err(Ack::new(AckCode::NoExist, "Bad song index"));
+            }
+            command_send(
+                ctx,
+                PlayerCmd::PlaylistRemoveTrack(PlaylistRemoveTrack {
+                    at_index: start,
+                    amount: end - start,
+                }),
+            );
+        }
+        "clear" => command_send(ctx, PlayerCmd::PlaylistClear),
+        "shuffle" => command_send(ctx, PlayerCmd::PlaylistShuffle),
+        "playlistinfo" | "playlistid" | "plchanges" => {
+            // "plchanges" takes a version first, there are no deltas, so always send everything
+            let range = if command == "plchanges" {
+                args.get(1)
+            } else {
+                args.first()
+            };
+            let (start, end) = match range {
+                Some(range) => parse_range(range)?,
+                None => (0, None),
+            };
+            let playlist = ctx.playlist.read();
+            let ids = ctx.song_ids.ids();
+            for (index, track) in playlist.tracks().iter().enumerate() {
+                let index_u32 = u32::try_from(index).unwrap_or(u32::MAX);
+                if index_u32 >= start && end.map_or(true, |end| index_u32 < end) {
+                    write_track(&mut resp, track, index, ids.get(index).copied());
+                }
+            }
+        }
+        "search" | "find" => {
+            let filters = parse_filters(args)?;
+            let exact = command == "find";
+            let records = ctx.db.lock().get_all_records().map_err(internal_error)?;
+            for record in records
+                .iter()
+                .filter(|record| filters.iter().all(|v| v.matches(record, exact)))
+            {
+                write_record(&mut resp, record);
+            }
+        }
+        "list" => {
+            let (criteria, key) = match arg(args, 0)?.to_lowercase().as_str() {
+                "artist" | "albumartist" => (SearchCriteria::Artist, "Artist"),
+                "album" => (SearchCriteria::Album, "Album"),
+                "genre" => (SearchCriteria::Genre, "Genre"),
+                other => return Err(Ack::arg(format!("Unsupported tag type: {other}"))),
+            };
+            let values = ctx
+                .db
+                .lock()
+                .get_criterias(&criteria)
+                .map_err(internal_error)?;
+            for value in values {
+                resp.field(key, value);
+            }
+        }
+        _ => {
+            return Err(Ack::new(
+                AckCode::Unknown,
+                format!("unknown command \"{command}\""),
+            ))
+        }
+    }
+
+    Ok(resp.into_inner())
+}
+
+fn command_send(ctx: &MpdContext, cmd: PlayerCmd) {
+    if let Err(err) = ctx.cmd_tx.send(cmd) {
+        error!("Error sending command from MPD: {err}");
+    }
+}
+
+fn unsupported() -> Ack {
+    Ack::new(AckCode::Unknown, "unsupported")
+}
+
+fn internal_error(err: impl std::fmt::Display) -> Ack {
+    Ack::new(AckCode::NoExist, err.to_string())
+}
+
+fn arg(args: &[String], index: usize) -> AckResult<&str> {
+    args.get(index)
+        .map(String::as_str)
+        .ok_or_else(|| Ack::arg("too few arguments"))
+}
+
+fn parse_u32(arg: &str) -> AckResult<u32> {
+    arg.parse()
+        .map_err(|_| Ack::arg(format!("Integer expected: {arg}")))
+}
+
+fn parse_i64(arg: &str) -> AckResult<i64> {
+    arg.parse()
+        .map_err(|_| Ack::arg(format!("Integer expected: {arg}")))
+}
+
+fn parse_f64(arg: &str) -> AckResult<f64> {
+    arg.parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| Ack::arg(format!("Number expected: {arg}")))
+}
+
+fn parse_index(arg: &str) -> AckResult<usize> {
+    parse_u32(arg).map(|v| v as usize)
+}
+
+/// Convert seconds into a [`Duration`], negative values are clamped to `0`
+fn parse_duration(secs: f64) -> AckResult<Duration> {
+    Duration::try_from_secs_f64(secs.max(0.0))
+        .map_err(|_| Ack::arg(format!("Time out of range: {secs}")))
+}
+
+/// Get the position of the song with the id in `arg`
+fn resolve_id(ctx: &MpdContext, arg: &str) -> AckResult<usize> {
+    let id = parse_u32(arg)?;
+    ctx.song_ids
+        .pos_of(id)
+        .ok_or_else(|| Ack::new(AckCode::NoExist, format!("No such song: {id}")))
+}
+
+fn check_index(ctx: &MpdContext, index: usize) -> AckResult<()> {
+    if index >= ctx.playlist.read().len() {
+        return Err(Ack::new(AckCode::NoExist, "Bad song index"));
+    }
+
+    Ok(())
+}
+
+/// Convert a already clamped volume
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn volume_u16<T: Into<i64>>(volume: T) -> u16 {
+    volume.into().clamp(0, i64::from(u16::MAX)) as u16
+}
+
+fn status(ctx: &MpdContext, resp: &mut Response) {
+    let (status, loop_mode, len, index) = {
+        let playlist = ctx.playlist.read();
+        (
+            playlist.status(),
+            playlist.loop_mode(),
+            playlist.len(),
+            playlist.get_current_track_index(),
+        )
+    };
+    let volume = ctx.config.read().settings.player.volume;
+    let stats = ctx.player_stats.lock();
+
+    resp.field("volume", volume)
+        .field("repeat", 1)
+        .field("random", u8::from(loop_mode == LoopMode::Random))
+        .field("single", u8::from(loop_mode == LoopMode::Single))
+        .field("consume", 0)
+        .field("playlist", ctx.song_ids.version())
+        .field("playlistlength", len)
+        .field(
+            "state",
+            match status {
+                Status::Running => "play",
+                Status::Paused => "pause",
+                Status::Stopped => "stop",
+            },
+        );
+
+    if index < len {
+        resp.field("song", index);
+        if let Some(id) = ctx.song_ids.id_of(index) {
+            resp.field("songid", id);
+        }
+    }
+    if status != Status::Stopped {
+        let elapsed = stats.progress.position.unwrap_or_default();
+        let duration = stats.progress.total_duration.unwrap_or_default();
+        resp.field(
+            "time",
+            format!("{}:{}", elapsed.as_secs(), duration.as_secs()),
+        )
+        .field("elapsed", format!("{:.3}", elapsed.as_secs_f64()))
+        .field("duration", format!("{:.3}", duration.as_secs_f64()));
+    }
+}
+
+fn stats(ctx: &MpdContext, resp: &mut Response) -> AckResult<()> {
+    let mut db = ctx.db.lock();
+    let records = db.get_all_records().map_err(internal_error)?;
+    let artists = db
+        .get_criterias(&SearchCriteria::Artist)
+        .map_err(internal_error)?;
+    let albums = db
+        .get_criterias(&SearchCriteria::Album)
+        .map_err(internal_error)?;
+    let db_playtime: Duration = records.iter().map(|v| v.duration).sum();
+
+    resp.field("artists", artists.len())
+        .field("albums", albums.len())
+        .field("songs", records.len())
+        .field("uptime", 0)
+        .field("playtime", 0)
+        .field("db_playtime", db_playtime.as_secs());
+
+    Ok(())
+}
+
+fn write_track(resp: &mut Response, track: &Track, index: usize, id: Option<u32>) {
+    let file = PlaylistTrackSource::from_track(track).map(|v| match v {
+        PlaylistTrackSource::Path(v)
+        | PlaylistTrackSource::Url(v)
+        | PlaylistTrackSource::PodcastUrl(v) => v,
+    });
+    resp.field("file", file.unwrap_or_default());
+    write_tags(
+        resp,
+        track.artist(),
+        track.album(),
+        track.title(),
+        track.genre(),
+        track.duration(),
+    );
+    resp.field("Pos", index);
+    if let Some(id) = id {
+        resp.field("Id", id);
+    }
+}
+
+fn write_record(resp: &mut Response, record: &TrackDB) {
+    resp.field("file", &record.file);
+    write_tags(
+        resp,
+        Some(&record.artist),
+        Some(&record.album),
+        Some(&record.title),
+        Some(&record.genre),
+        record.duration,
+    );
+}
+
+fn write_tags(
+    resp: &mut Response,
+    artist: Option<&str>,
+    album: Option<&str>,
+    title: Option<&str>,
+    genre: Option<&str>,
+    duration: Duration,
+) {
+    for (key, value) in [
+        ("Artist", artist),
+        ("Album", album),
+        ("Title", title),
+        ("Genre", genre),
+    ] {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            resp.field(key, value);
+        }
+    }
+    resp.field("Time", duration.as_secs())
+        .field("duration", format!("{:.3}", duration.as_secs_f64()));
+}
+
+/// A single `TYPE WHAT` pair of `search` and `find`
+struct Filter {
+    tag: String,
+    value: String,
+}
+
+impl Filter {
+    fn matches(&self, record: &TrackDB, exact: bool) -> bool {
+        let fields: &[&str] = match self.tag.as_str() {
+            "artist" | "albumartist" => &[&record.artist],
+            "album" => &[&record.album],
+            "title" => &[&record.title],
+            "genre" => &[&record.genre],
+            "file" => &[&record.file],
+            // "any"
+            _ => &[
+                &record.artist,
+                &record.album,
+                &record.title,
+                &record.genre,
+                &record.file,
+            ],
+        };
+
+        if exact {
+            fields.iter().any(|v| *v == self.value)
+        } else {
+            let value = self.value.to_lowercase();
+            fields.iter().any(|v| v.to_lowercase().contains(&value))
+        }
+    }
+}
+
+/// Parse the `TYPE WHAT [...]` pairs of `search` and `find`
+fn parse_filters(args: &[String]) -> AckResult<Vec<Filter>> {
+    if args.first().is_some_and(|v| v.starts_with('(')) {
+        return Err(Ack::arg("Filter expressions are not supported"));
+    }
+    if args.is_empty() || args.len() % 2 != 0 {
+        return Err(Ack::arg("incorrect arguments"));
+    }
+
+    args.chunks_exact(2)
+        .map(|pair| {
+            let tag = pair[0].to_lowercase();
+            if ![
+                "artist",
+                "albumartist",
+                "album",
+                "title",
+                "genre",
+                "file",
+                "any",
+            ]
+            .contains(&tag.as_str())
+            {
+                return Err(Ack::arg(format!("Unsupported tag type: {}", pair[0])));
+            }
+            Ok(Filter {
+                tag,
+                value: pair[1].clone(),
+            })
+        })
+        .collect()
+}
+
+/// Resolve a uri given to `add`, paths are resolved against the music directories
+///
+/// Directories are added recursively, paths outside of the music directories are rejected.
+fn resolve_uri(ctx: &MpdContext, uri: &str) -> AckResult<Vec<PlaylistTrackSource>> {
+    if uri.starts_with("http://") || uri.starts_with("https://") {
+        return Ok(vec![PlaylistTrackSource::Url(uri.to_string())]);
+    }
+
+    let music_dirs: Vec<PathBuf> = {
+        let config = ctx.config.read();
+        config
+            .music_dir_overwrite
+            .iter()
+            .chain(config.settings.player.music_dirs.iter())
+            .cloned()
+            .collect()
+    };
+    // canonicalize to resolve any ".." and symlinks before checking that the path is in the music directory
+    let path = music_dirs.iter().find_map(|dir| {
+        let dir = dir.canonicalize().ok()?;
+        let path = dir.join(uri).canonicalize().ok()?;
+        path.starts_with(&dir).then_some(path)
+    });
+    let Some(path) = path else {
+        return Err(Ack::new(AckCode::NoExist, "No such directory"));
+    };
+
+    let mut files = Vec::new();
+    collect_files(&path, &mut files);
+
+    Ok(files
+        .into_iter()
+        .filter_map(|v| v.to_str().map(ToString::to_string))
+        .map(PlaylistTrackSource::Path)
+        .collect())
+}
+
+/// Collect all supported files in `path` recursively, sorted by path
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.filter_map(|v| v.ok().map(|v| v.path())).collect();
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            collect_files(&entry, files);
+        } else if filetype_supported(&entry.to_string_lossy()) {
+            files.push(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use termusiclib::config::v2::server::LoopMode;
+    use termusiclib::player::{
+        PlaylistAddTrack, PlaylistRemoveTrack, PlaylistTrackSource, UpdatePlaylistEvents,
+    };
+    use termusicplayback::PlayerCmd;
+
+    use super::execute;
+    use crate::mpd::protocol::{AckCode, AckResult};
+    use crate::mpd::test_utils::gen_context;
+    use crate::mpd::{MpdContext, Session};
+
+    fn run(ctx: &MpdContext, session: &mut Session, line: &str) -> AckResult<String> {
+        let args: Vec<String> = line.split(' ').map(ToString::to_string).collect();
+        execute(ctx, session, &args)
+    }
+
+    #[test]
+    fn should_require_password() {
+        let (ctx, _cmd_rx) = gen_context();
+        let mut session = Session {
+            authenticated: false,
+            token: Some("secret".to_string()),
+        };
+
+        assert_eq!(run(&ctx, &mut session, "ping").unwrap(), "");
+        assert_eq!(
+            run(&ctx, &mut session, "status").unwrap_err().code,
+            AckCode::Permission
+        );
+        assert_eq!(
+            run(&ctx, &mut session, "password wrong").unwrap_err().code,
+            AckCode::Password
+        );
+        run(&ctx, &mut session, "password secret").unwrap();
+        assert!(session.authenticated);
+        assert!(run(&ctx, &mut session, "status").is_ok());
+    }
+
+    #[test]
+    fn should_report_status() {
+        let (ctx, _cmd_rx) = gen_context();
+        let mut session = Session {
+            authenticated: true,
+            token: None,
+        };
+
+        let status = run(&ctx, &mut session, "status").unwrap();
+        assert!(status.contains("playlistlength: 0\n"));
+        assert!(status.contains("state: stop\n"));
+        assert_eq!(run(&ctx, &mut session, "search any foo").unwrap(), "");
+    }
+
+    #[test]
+    fn should_send_commands() {
+        let (ctx, mut cmd_rx) = gen_context();
+        let mut session = Session {
+            authenticated: true,
+            token: None,
+        };
+
+        run(&ctx, &mut session, "setvol 150").unwrap();
+        assert!(matches!(cmd_rx.try_recv(), Ok(PlayerCmd::SetVolume(100))));
+        run(&ctx, &mut session, "pause").unwrap();
+        assert!(matches!(cmd_rx.try_recv(), Ok(PlayerCmd::TogglePause)));
+        run(&ctx, &mut session, "stop").unwrap();
+        assert!(matches!(cmd_rx.try_recv(), Ok(PlayerCmd::Stop)));
+        run(&ctx, &mut session, "seekcur 1.5").unwrap();
+        assert!(matches!(
+            cmd_rx.try_recv(),
+            Ok(PlayerCmd::SeekTo(position)) if position == Duration::from_millis(1500)
+        ));
+
+        // "single 0" does not disable "random"
+        run(&ctx, &mut session, "single 0").unwrap();
+        run(&ctx, &mut session, "random 1").unwrap();
+        assert!(matches!(
+            cmd_rx.try_recv(),
+            Ok(PlayerCmd::PlaylistSetLoopMode(LoopMode::Random))
+        ));
+        run(&ctx, &mut session, "repeat 1").unwrap();
+        run(&ctx, &mut session, "consume 0").unwrap();
+        for line in ["repeat 0", "consume 1", "single oneshot"] {
+            assert_eq!(
+                run(&ctx, &mut session, line).unwrap_err().code,
+                AckCode::Unknown,
+                "{line} should be unsupported"
+            );
+        }
+
+        for line in ["seekcur 1e300", "seekcur +1e300", "seek 0 1e300"] {
+            assert_eq!(
+                run(&ctx, &mut session, line).unwrap_err().code,
+                AckCode::Arg,
+                "{line} should be out of range"
+            );
+        }
+
+        // no tracks in the playlist
+        assert_eq!(
+            run(&ctx, &mut session, "play 0").unwrap_err().code,
+            AckCode::NoExist
+        );
+        assert_eq!(
+            run(&ctx, &mut session, "delete 4294967295")
+                .unwrap_err()
+                .code,
+            AckCode::Arg
+        );
+        assert!(cmd_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn should_resolve_song_ids() {
+        let (ctx, mut cmd_rx) = gen_context();
+        let mut session = Session {
+            authenticated: true,
+            token: None,
+        };
+        ctx.song_ids
+            .apply(&UpdatePlaylistEvents::AddTrack(PlaylistAddTrack {
+                at_index: 0,
+                tracks: vec![PlaylistTrackSource::Url("http://a".to_string())],
+            }));
+        ctx.song_ids
+            .apply(&UpdatePlaylistEvents::AddTrack(PlaylistAddTrack {
+                at_index: 0,
+                tracks: vec![PlaylistTrackSource::Url("http://b".to_string())],
+            }));
+
+        // "http://a" got id 0 and is now at position 1
+        run(&ctx, &mut session, "deleteid 0").unwrap();
+        assert!(matches!(
+            cmd_rx.try_recv(),
+            Ok(PlayerCmd::PlaylistRemoveTrack(PlaylistRemoveTrack {
+                at_index: 1,
+                amount: 1
+            }))
+        ));
+        assert_eq!(
+            run(&ctx, &mut session, "seekid 2 0").unwrap_err().code,
+            AckCode::NoExist
+        );
+    }
+
+    #[test]
+    fn should_add_only_from_music_dir() {
+        let dir = std::env::temp_dir().join(format!("termusic-mpd-add-{}", std::process::id()));
+        let music_dir = dir.join("music");
+        std::fs::create_dir_all(music_dir.join("album")).unwrap();
+        std::fs::write(music_dir.join("album/song.mp3"), b"").unwrap();
+        std::fs::write(music_dir.join("album/cover.jpg"), b"").unwrap();
+        std::fs::write(dir.join("outside.mp3"), b"").unwrap();
+
+        let (ctx, mut cmd_rx) = gen_context();
+        ctx.config.write().settings.player.music_dirs = vec![music_dir.clone()];
+        let mut session = Session {
+            authenticated: true,
+            token: None,
+        };
+
+        let song = music_dir
+            .canonicalize()
+            .unwrap()
+            .join("album/song.mp3")
+            .to_string_lossy()
+            .to_string();
+        for uri in ["album", "album/song.mp3", song.as_str()] {
+            run(&ctx, &mut session, &format!("add {uri}")).unwrap();
+            let Ok(PlayerCmd::PlaylistAddTrack(PlaylistAddTrack { tracks, .. })) =
+                cmd_rx.try_recv()
+            else {
+                panic!("expected tracks to be added for {uri}");
+            };
+            assert_eq!(tracks, vec![PlaylistTrackSource::Path(song.clone())]);
+        }
+
+        let outside = dir.join("outside.mp3").to_string_lossy().to_string();
+        for uri in [
+            "/",
+            "..",
+            "../outside.mp3",
+            "album/../../outside.mp3",
+            &outside,
+        ] {
+            assert_eq!(
+                run(&ctx, &mut session, &format!("add {uri}"))
+                    .unwrap_err()
+                    .code,
+                AckCode::NoExist,
+                "{uri} should be rejected"
+            );
+        }
+        assert!(cmd_rx.try_recv().is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
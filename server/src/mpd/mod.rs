@@ -0,0 +1,410 @@
+//! MPD protocol frontend, so that MPD clients (like `mpc` or `ncmpcpp`) can control the server
+//!
+//! Commands are mapped onto [`PlayerCmd`](termusicplayback::PlayerCmd)s and the shared state of the player,
+//! only a subset of the protocol is supported.
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use termusiclib::config::v2::server::MpdSettings;
+use termusiclib::config::SharedServerSettings;
+use termusiclib::library_db::DataBase;
+use termusiclib::player::UpdateEvents;
+use termusicplayback::{PlayerCmdSender, SharedPlaylist, StreamTX};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::PlayerStats;
+use protocol::{tokenize, PROTOCOL_VERSION};
+
+mod commands;
+mod protocol;
+mod song_ids;
+
+pub use song_ids::SongIds;
+
+/// Everything a MPD connection needs to access the player
+#[derive(Clone)]
+pub struct MpdContext {
+    pub cmd_tx: PlayerCmdSender,
+    pub stream_tx: StreamTX,
+    pub playlist: SharedPlaylist,
+    pub player_stats: Arc<Mutex<PlayerStats>>,
+    pub config: SharedServerSettings,
+    pub db: Arc<Mutex<DataBase>>,
+    /// Stable ids of the tracks in `playlist`, and the playlist version
+    pub song_ids: Arc<SongIds>,
+}
+
+/// Bind the MPD listener, to fail early before starting anything else
+///
+/// # Errors
+///
+/// - if binding the address fails
+pub async fn bind(settings: &MpdSettings) -> Result<TcpListener> {
+    let addr = std::net::SocketAddr::from(settings);
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Error binding MPD address: {addr}"))?;
+    info!("MPD listening on {}", listener.local_addr()?);
+
+    Ok(listener)
+}
+
+/// Accept MPD connections on `listener` until the server exits
+pub fn spawn(listener: TcpListener, ctx: MpdContext) {
+    tokio::spawn(track_song_ids(
+        ctx.stream_tx.subscribe(),
+        ctx.song_ids.clone(),
+        ctx.playlist.clone(),
+    ));
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, addr) = match listener.accept().await {
+                Ok(v) => v,
+                Err(err) => {
+                    error!("Error accepting MPD connection: {err:#}");
+                    continue;
+                }
+            };
+            debug!("MPD client connected: {addr}");
+
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handle_connection(stream, ctx).await {
+                    warn!("MPD connection {addr} errored: {err:#}");
+                }
+                debug!("MPD client disconnected: {addr}");
+            });
+        }
+    });
+}
+
+/// Apply every playlist change to `ids`
+async fn track_song_ids(
+    mut events: broadcast::Receiver<UpdateEvents>,
+    ids: Arc<SongIds>,
+    playlist: SharedPlaylist,
+) {
+    loop {
+        match events.recv().await {
+            Ok(UpdateEvents::PlaylistChanged(ev)) => ids.apply(&ev),
+            Err(RecvError::Lagged(_)) => ids.reset(playlist.read().len()),
+            Ok(_) => (),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+/// State of a single MPD connection
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Whether the client has send the correct `password`, always `true` if no token is configured
+    pub authenticated: bool,
+    /// The configured token, required with `password`
+    pub token: Option<String>,
+}
+
+/// MPD "subsystems" reported by `idle`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Subsystem {
+    Player,
+    Mixer,
+    Options,
+    Playlist,
+}
+
+impl Subsystem {
+    const ALL: [Self; 4] = [Self::Player, Self::Mixer, Self::Options, Self::Playlist];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Player => "player",
+            Self::Mixer => "mixer",
+            Self::Options => "options",
+            Self::Playlist => "playlist",
+        }
+    }
+
+    fn from_event(ev: &UpdateEvents) -> &'static [Self] {
+        match ev {
+            UpdateEvents::MissedEvents { .. } => &Self::ALL,
+            UpdateEvents::VolumeChanged { .. } => &[Self::Mixer],
+            UpdateEvents::SpeedChanged { .. }
+            | UpdateEvents::LoopModeChanged { .. }
+            | UpdateEvents::GaplessChanged { .. } => &[Self::Options],
+            UpdateEvents::PlayStateChanged { .. } | UpdateEvents::TrackChanged(_) => {
+                &[Self::Player]
+            }
+            UpdateEvents::PlaylistChanged(_) => &[Self::Playlist],
+            UpdateEvents::PodcastFeedChanged { .. } | UpdateEvents::PodcastDownload(_) => &[],
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream, ctx: MpdContext) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    // subscribe for the whole connection, so that "idle" can report changes that happened between "idle"s
+    let mut events = ctx.stream_tx.subscribe();
+    let mut changed = BTreeSet::new();
+    let token = ctx.config.read().settings.com.token.clone();
+    let mut session = Session {
+        authenticated: token.is_none(),
+        token,
+    };
+    // "Some((list_ok, commands))" while in a command list
+    let mut command_list: Option<(bool, Vec<Vec<String>>)> = None;
+
+    writer
+        .write_all(format!("OK MPD {PROTOCOL_VERSION}\n").as_bytes())
+        .await?;
+
+    while let Some(line) = lines.next_line().await? {
+        let args = match tokenize(&line) {
+            Ok(v) => v,
+            Err(ack) => {
+                writer.write_all(ack.to_line(0, "").as_bytes()).await?;
+                continue;
+            }
+        };
+        let Some(command) = args.first() else {
+            continue;
+        };
+
+        if let Some((list_ok, list)) = &mut command_list {
+            if command == "command_list_end" {
+                let (list_ok, list) = (*list_ok, std::mem::take(list));
+                let response = run_blocking(&ctx, &mut session, move |ctx, session| {
+                    execute_list(ctx, session, list_ok, &list)
+                })
+                .await?;
+                writer.write_all(response.as_bytes()).await?;
+                command_list = None;
+            } else {
+                list.push(args);
+            }
+            continue;
+        }
+
+        let response = match command.as_str() {
+            "command_list_begin" => {
+                command_list = Some((false, Vec::new()));
+                continue;
+            }
+            "command_list_ok_begin" => {
+                command_list = Some((true, Vec::new()));
+                continue;
+            }
+            "close" => break,
+            "idle" if session.authenticated => {
+                let filter = args[1..]
+                    .iter()
+                    .filter_map(|v| Subsystem::ALL.into_iter().find(|s| s.as_str() == v))
+                    .collect();
+                let Some(response) = idle(&filter, &mut changed, &mut events, &mut lines).await?
+                else {
+                    break;
+                };
+                response
+            }
+            // only valid while idling, ignore it otherwise
+            "noidle" => continue,
+            _ => {
+                let cmd_args = args.clone();
+                let result = run_blocking(&ctx, &mut session, move |ctx, session| {
+                    commands::execute(ctx, session, &cmd_args)
+                })
+                .await?;
+                match result {
+                    Ok(body) => body + "OK\n",
+                    Err(ack) => ack.to_line(0, command),
+                }
+            }
+        };
+
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+/// Run `f` on the blocking thread pool, as commands may query the database or walk directories
+async fn run_blocking<T, F>(ctx: &MpdContext, session: &mut Session, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&MpdContext, &mut Session) -> T + Send + 'static,
+{
+    let ctx = ctx.clone();
+    let mut owned = std::mem::take(session);
+    let (value, owned) = tokio::task::spawn_blocking(move || {
+        let value = f(&ctx, &mut owned);
+        (value, owned)
+    })
+    .await?;
+    *session = owned;
+
+    Ok(value)
+}
+
+/// Execute all commands of a command list, stopping at the first error
+fn execute_list(
+    ctx: &MpdContext,
+    session: &mut Session,
+    list_ok: bool,
+    list: &[Vec<String>],
+) -> String {
+    let mut response = String::new();
+    for (index, args) in list.iter().enumerate() {
+        match commands::execute(ctx, session, args) {
+            Ok(body) => {
+                response.push_str(&body);
+                if list_ok {
+                    response.push_str("list_OK\n");
+                }
+            }
+            Err(ack) => {
+                response.push_str(&ack.to_line(index, &args[0]));
+                return response;
+            }
+        }
+    }
+    response.push_str("OK\n");
+
+    response
+}
+
+/// Wait until one of the subsystems in `filter` (or any, if empty) changed, or the client sends `noidle`
+///
+/// Returns `None` if the connection got closed.
+async fn idle(
+    filter: &BTreeSet<Subsystem>,
+    changed: &mut BTreeSet<Subsystem>,
+    events: &mut broadcast::Receiver<UpdateEvents>,
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+) -> Result<Option<String>> {
+    let is_wanted = |v: &Subsystem| filter.is_empty() || filter.contains(v);
+
+    // collect everything that happened since the last "idle"
+    loop {
+        match events.try_recv() {
+            Ok(ev) => changed.extend(Subsystem::from_event(&ev)),
+            Err(broadcast::error::TryRecvError::Lagged(_)) => changed.extend(Subsystem::ALL),
+            Err(_) => break,
+        }
+    }
+
+    while !changed.iter().any(is_wanted) {
+        tokio::select! {
+            ev = events.recv() => match ev {
+                Ok(ev) => changed.extend(Subsystem::from_event(&ev)),
+                Err(RecvError::Lagged(_)) => changed.extend(Subsystem::ALL),
+                Err(RecvError::Closed) => return Ok(None),
+            },
+            line = lines.next_line() => match line? {
+                // "noidle" (or anything else) ends the idle without changes
+                Some(_) => return Ok(Some("OK\n".to_string())),
+                None => return Ok(None),
+            },
+        }
+    }
+
+    let mut response = String::new();
+    changed.retain(|v| {
+        if is_wanted(v) {
+            response.push_str(&format!("changed: {}\n", v.as_str()));
+            false
+        } else {
+            true
+        }
+    });
+    response.push_str("OK\n");
+
+    Ok(Some(response))
+}
+
+#[cfg(test)]
+mod test_utils {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use termusiclib::config::{new_shared_server_settings, ServerOverlay};
+    use termusiclib::library_db::DataBase;
+    use termusicplayback::{new_shared_playlist, PlayerCmdReciever, Playlist};
+    use tokio::sync::{broadcast, mpsc};
+
+    use super::{MpdContext, SongIds};
+    use crate::PlayerStats;
+
+    /// Create a context with a empty playlist and library, and the receiver of the commands send by MPD
+    pub fn gen_context() -> (MpdContext, PlayerCmdReciever) {
+        let config = new_shared_server_settings(ServerOverlay::default());
+        let db = DataBase::new_in_memory(&config.read()).unwrap();
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+
+        let ctx = MpdContext {
+            cmd_tx,
+            stream_tx: broadcast::channel(10).0,
+            playlist: new_shared_playlist(Playlist::new_empty(&config)),
+            player_stats: Arc::new(Mutex::new(PlayerStats::new())),
+            config,
+            db: Arc::new(Mutex::new(db)),
+            song_ids: Arc::new(SongIds::new(0)),
+        };
+
+        (ctx, cmd_rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::handle_connection;
+    use super::test_utils::gen_context;
+
+    /// Send `input` over a new connection and get everything the server responded until it closed the connection
+    async fn converse(input: &str) -> String {
+        let (ctx, _cmd_rx) = gen_context();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, ctx).await.unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(input.as_bytes()).await.unwrap();
+        let mut output = String::new();
+        client.read_to_string(&mut output).await.unwrap();
+        server.await.unwrap();
+
+        output
+    }
+
+    #[tokio::test]
+    async fn should_respond_to_commands() {
+        let output = converse("ping\nfoo\ndelete 5\nclose\n").await;
+        assert_eq!(
+            output,
+            "OK MPD 0.23.0\nOK\nACK [5@0] {foo} unknown command \"foo\"\nACK [50@0] {delete} Bad song index\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_execute_command_lists() {
+        let output = converse(
+            "command_list_ok_begin\nping\nping\ncommand_list_end\ncommand_list_begin\nping\nfoo\nping\ncommand_list_end\nclose\n",
+        )
+        .await;
+        assert_eq!(
+            output,
+            "OK MPD 0.23.0\nlist_OK\nlist_OK\nOK\nACK [5@1] {foo} unknown command \"foo\"\n"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Parsing and formatting of the MPD text protocol
+//!
+//! See <https://mpd.readthedocs.io/en/latest/protocol.html>.
+use std::fmt::Write as _;
+
+/// Protocol version reported to clients
+pub const PROTOCOL_VERSION: &str = "0.23.0";
+
+/// Error codes used in `ACK` responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckCode {
+    Arg = 2,
+    Password = 3,
+    Permission = 4,
+    Unknown = 5,
+    NoExist = 50,
+}
+
+/// A failed command, send as a `ACK` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ack {
+    pub code: AckCode,
+    pub message: String,
+}
+
+impl Ack {
+    pub fn new(code: AckCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn arg(message: impl Into<String>) -> Self {
+        Self::new(AckCode::Arg, message)
+    }
+
+    /// Format as a full response line, `list_index` is the index in a command list (`0` otherwise)
+    pub fn to_line(&self, list_index: usize, command: &str) -> String {
+        format!(
+            "ACK [{}@{list_index}] {{{command}}} {}\n",
+            self.code as u8, self.message
+        )
+    }
+}
+
+pub type AckResult<T> = Result<T, Ack>;
+
+/// Split a command line into the command and its arguments
+///
+/// Arguments can be quoted with `"`, in which `\` escapes the next character.
+pub fn tokenize(line: &str) -> AckResult<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|v| v.is_whitespace()).is_some() {}
+
+        let Some(first) = chars.next() else {
+            break;
+        };
+
+        let mut token = String::new();
+        if first == '"' {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(char) => token.push(char),
+                        None => return Err(Ack::arg("Missing closing '\"'")),
+                    },
+                    Some(char) => token.push(char),
+                    None => return Err(Ack::arg("Missing closing '\"'")),
+                }
+            }
+        } else {
+            token.push(first);
+            while let Some(char) = chars.next_if(|v| !v.is_whitespace()) {
+                token.push(char);
+            }
+        }
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a single position or a `START:END` range (`END` exclusive and optional), into `(start, end)`
+pub fn parse_range(arg: &str) -> AckResult<(u32, Option<u32>)> {
+    let parse = |v: &str| {
+        v.parse::<u32>()
+            .map_err(|_| Ack::arg(format!("Integer expected: {v}")))
+    };
+
+    match arg.split_once(':') {
+        Some((start, "")) => Ok((parse(start)?, None)),
+        Some((start, end)) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if end < start {
+                return Err(Ack::arg(format!("Bad range: {arg}")));
+            }
+            Ok((start, Some(end)))
+        }
+        None => {
+            let pos = parse(arg)?;
+            let end = pos
+                .checked_add(1)
+                .ok_or_else(|| Ack::arg(format!("Bad range: {arg}")))?;
+            Ok((pos, Some(end)))
+        }
+    }
+}
+
+/// Parse a MPD boolean, which is `0` or `1`
+pub fn parse_bool(arg: &str) -> AckResult<bool> {
+    match arg {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(Ack::arg(format!("Boolean (0/1) expected: {arg}"))),
+    }
+}
+
+/// Builder for the key-value lines of a successful response
+#[derive(Debug, Default)]
+pub struct Response(String);
+
+impl Response {
+    pub fn field(&mut self, key: &str, value: impl std::fmt::Display) -> &mut Self {
+        // writing to a string cannot fail
+        let _ = writeln!(self.0, "{key}: {value}");
+        self
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_bool, parse_range, tokenize, Ack, AckCode};
+
+    #[test]
+    fn should_tokenize() {
+        assert_eq!(tokenize("status").unwrap(), vec!["status"]);
+        assert_eq!(tokenize("  setvol   40 ").unwrap(), vec!["setvol", "40"]);
+        assert_eq!(
+            tokenize(r#"search artist "some \"quoted\" name""#).unwrap(),
+            vec!["search", "artist", r#"some "quoted" name"#]
+        );
+        assert_eq!(tokenize(r#"add """#).unwrap(), vec!["add", ""]);
+        assert!(tokenize(r#"add "unclosed"#).is_err());
+    }
+
+    #[test]
+    fn should_parse_range() {
+        assert_eq!(parse_range("3").unwrap(), (3, Some(4)));
+        assert_eq!(parse_range("1:5").unwrap(), (1, Some(5)));
+        assert_eq!(parse_range("2:").unwrap(), (2, None));
+        assert!(parse_range("5:1").is_err());
+        assert!(parse_range("a").is_err());
+        // the end of a single position would overflow
+        assert!(parse_range(&u32::MAX.to_string()).is_err());
+    }
+
+    #[test]
+    fn should_parse_bool() {
+        assert!(parse_bool("1").unwrap());
+        assert!(!parse_bool("0").unwrap());
+        assert!(parse_bool("true").is_err());
+    }
+
+    #[test]
+    fn should_format_ack() {
+        assert_eq!(
+            Ack::new(AckCode::Unknown, "unknown command \"foo\"").to_line(0, "foo"),
+            "ACK [5@0] {foo} unknown command \"foo\"\n"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Stable song ids for the tracks in the playlist
+//!
+//! Tracks in the playlist have no identity of their own, so MPD ids are assigned here
+//! and kept up to date from the [`UpdatePlaylistEvents`] of the player.
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+use termusiclib::player::{PlaylistAddTrack, PlaylistTrackSource, UpdatePlaylistEvents};
+
+/// How many recent additions are kept for [`SongIds::wait_added`]
+const ADDED_HISTORY: usize = 16;
+
+/// Song ids of all tracks in the playlist and the playlist version, shared by all MPD connections
+#[derive(Debug, Default)]
+pub struct SongIds {
+    state: Mutex<State>,
+    /// Notified every time a change got applied
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The id of each track, in playlist order
+    ids: Vec<u32>,
+    next_id: u32,
+    /// Incremented on every playlist change, reported as `playlist` in `status`
+    version: u32,
+    /// The most recently added tracks with the id of the first of them
+    added: VecDeque<(Vec<PlaylistTrackSource>, u32)>,
+}
+
+impl State {
+    fn new_ids(&mut self, amount: usize) -> Vec<u32> {
+        (0..amount)
+            .map(|_| {
+                let id = self.next_id;
+                self.next_id = self.next_id.wrapping_add(1);
+                id
+            })
+            .collect()
+    }
+}
+
+impl SongIds {
+    /// Create ids for a playlist that currently has `len` tracks
+    pub fn new(len: usize) -> Self {
+        let ids = Self::default();
+        ids.reset(len);
+
+        ids
+    }
+
+    /// Assign new ids to all `len` tracks, for when the changes are not known
+    pub fn reset(&self, len: usize) {
+        let mut state = self.state.lock();
+        state.ids = state.new_ids(len);
+        state.version = state.version.wrapping_add(1);
+        drop(state);
+        self.changed.notify_all();
+    }
+
+    /// Apply a change of the playlist
+    ///
+    /// Moved tracks keep their id, a reloaded playlist (like after a shuffle) gets new ids.
+    pub fn apply(&self, ev: &UpdatePlaylistEvents) {
+        let mut state = self.state.lock();
+        match ev {
+            UpdatePlaylistEvents::AddTrack(PlaylistAddTrack { at_index, tracks }) => {
+                let at_index = usize::min(*at_index as usize, state.ids.len());
+                let new_ids = state.new_ids(tracks.len());
+                if let Some(&first) = new_ids.first() {
+                    if state.added.len() == ADDED_HISTORY {
+                        state.added.pop_front();
+                    }
+                    state.added.push_back((tracks.clone(), first));
+                }
+                state.ids.splice(at_index..at_index, new_ids);
+            }
+            UpdatePlaylistEvents::RemoveTrack(info) => {
+                let len = state.ids.len();
+                let start = usize::min(info.at_index as usize, len);
+                let end = usize::min(start + info.amount as usize, len);
+                state.ids.drain(start..end);
+            }
+            UpdatePlaylistEvents::SwapTracks(info) => {
+                let (a, b) = (info.index_a as usize, info.index_b as usize);
+                if a < state.ids.len() && b < state.ids.len() {
+                    state.ids.swap(a, b);
+                }
+            }
+            UpdatePlaylistEvents::MoveTrack(info) => {
+                let (from, to) = (info.from_index as usize, info.to_index as usize);
+                if from < state.ids.len() && to < state.ids.len() {
+                    let id = state.ids.remove(from);
+                    state.ids.insert(to, id);
+                }
+            }
+            UpdatePlaylistEvents::Cleared => state.ids.clear(),
+            UpdatePlaylistEvents::Reloaded(info) => state.ids = state.new_ids(info.tracks.len()),
+        }
+        state.version = state.version.wrapping_add(1);
+        drop(state);
+        self.changed.notify_all();
+    }
+
+    /// Get the current playlist version
+    pub fn version(&self) -> u32 {
+        self.state.lock().version
+    }
+
+    /// Get the id of the track at `pos`
+    pub fn id_of(&self, pos: usize) -> Option<u32> {
+        self.state.lock().ids.get(pos).copied()
+    }
+
+    /// Get the position of the track with `id`
+    pub fn pos_of(&self, id: u32) -> Option<usize> {
+        self.state.lock().ids.iter().position(|v| *v == id)
+    }
+
+    /// Get the ids of all tracks, in playlist order
+    pub fn ids(&self) -> Vec<u32> {
+        self.state.lock().ids.clone()
+    }
+
+    /// Wait until `tracks` got added to the playlist, returning the id of the first of them
+    ///
+    /// Returns `None` if they were not added within `timeout`, like when the tracks could not be read.
+    pub fn wait_added(&self, tracks: &[PlaylistTrackSource], timeout: Duration) -> Option<u32> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock();
+        loop {
+            if let Some(index) = state.added.iter().rposition(|(v, _)| v == tracks) {
+                return state.added.remove(index).map(|(_, id)| id);
+            }
+            if self.changed.wait_until(&mut state, deadline).timed_out() {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use termusiclib::player::{
+        PlaylistAddTrack, PlaylistMoveTrack, PlaylistRemoveTrack, PlaylistTrackSource,
+        UpdatePlaylistEvents,
+    };
+
+    use super::SongIds;
+
+    fn add(at_index: u32, urls: &[&str]) -> UpdatePlaylistEvents {
+        UpdatePlaylistEvents::AddTrack(PlaylistAddTrack {
+            at_index,
+            tracks: urls
+                .iter()
+                .map(|v| PlaylistTrackSource::Url((*v).to_string()))
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn should_keep_ids_stable() {
+        let ids = SongIds::new(2);
+        assert_eq!(ids.ids(), vec![0, 1]);
+
+        ids.apply(&add(1, &["http://a", "http://b"]));
+        assert_eq!(ids.ids(), vec![0, 2, 3, 1]);
+        ids.apply(&UpdatePlaylistEvents::RemoveTrack(PlaylistRemoveTrack {
+            at_index: 0,
+            amount: 1,
+        }));
+        assert_eq!(ids.ids(), vec![2, 3, 1]);
+        ids.apply(&UpdatePlaylistEvents::MoveTrack(PlaylistMoveTrack {
+            from_index: 2,
+            to_index: 0,
+        }));
+        assert_eq!(ids.ids(), vec![1, 2, 3]);
+        assert_eq!(ids.pos_of(3), Some(2));
+        assert_eq!(ids.pos_of(0), None);
+
+        ids.apply(&UpdatePlaylistEvents::Cleared);
+        ids.apply(&add(u32::MAX, &["http://c"]));
+        assert_eq!(ids.ids(), vec![4]);
+        assert_eq!(ids.version(), 6);
+    }
+
+    #[test]
+    fn should_return_added_ids() {
+        let ids = SongIds::new(0);
+        ids.apply(&add(0, &["http://a"]));
+        ids.apply(&add(0, &["http://b"]));
+
+        let tracks = [PlaylistTrackSource::Url("http://a".to_string())];
+        assert_eq!(ids.wait_added(&tracks, std::time::Duration::ZERO), Some(0));
+        // every addition is only returned once
+        assert_eq!(ids.wait_added(&tracks, std::time::Duration::ZERO), None);
+    }
+}
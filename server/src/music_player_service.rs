@@ -2,21 +2,40 @@ use anyhow::Result;
 use parking_lot::Mutex;
 use std::pin::Pin;
 use std::sync::Arc;
+use termusiclib::config::v2::server::{EqualizerBand, LoopMode};
+use termusiclib::config::SharedServerSettings;
+use termusiclib::invidious::podcast_feed_url;
+use termusiclib::library_db::{DataBase, Page, SearchCriteria};
 use termusiclib::player::music_player_server::MusicPlayer;
 use termusiclib::player::{
-    stream_updates, CycleLoopReply, CycleLoopRequest, EmptyReply, GetProgressRequest,
-    GetProgressResponse, PlaySelectedRequest, PlayerTime, ReloadConfigRequest,
-    ReloadPlaylistRequest, SeekBackwardRequest, SeekForwardRequest, SkipNextRequest,
-    SkipNextResponse, SkipPreviousRequest, SpeedDownRequest, SpeedReply, SpeedUpRequest,
-    StreamUpdates, ToggleGaplessReply, ToggleGaplessRequest, TogglePauseRequest,
-    TogglePauseResponse, UpdateMissedEvents, VolumeDownRequest, VolumeReply, VolumeUpRequest,
+    stream_updates, AddPodcastRequest, ClearPlaylistRequest, CurrentTrackReply, CycleLoopReply,
+    CycleLoopRequest, EmptyReply, EpisodeIds, EpisodeList, EqualizerReply, EqualizerSet,
+    GetCurrentTrackRequest, GetEpisodesRequest, GetEqualizerRequest, GetPlaylistRequest,
+    GetPodcastsRequest, GetProgressRequest, GetProgressResponse, LibraryCriteriaReply,
+    LibraryCriteriaRequest, LibrarySearchRequest, LibraryTracks, LibraryTracksRequest,
+    ListOutputDevicesRequest, LoopModeReply, NextChapterRequest, OutputDevicesReply, PauseRequest,
+    PlayIndexRequest, PlayRequest, PlaySelectedRequest, PlayerTime, PlaylistAddTrack,
+    PlaylistMoveTracks, PlaylistSwapTracks, PlaylistTracks, PlaylistTracksToAdd,
+    PlaylistTracksToRemove, PodcastList, PreviousChapterRequest, RefreshPodcastsRequest,
+    ReloadConfigRequest, ReloadPlaylistRequest, RemoveDeletedTracksRequest,
+    RemoveEqualizerPresetRequest, RemovePodcastRequest, SeekBackwardRequest, SeekForwardRequest,
+    SeekToRequest, SetEpisodesPlayedRequest, SetEqualizerRequest, SetLoopModeRequest,
+    SetOutputDeviceRequest, SetPodcastSettingsRequest, SetSpeedRequest, SetVolumeRequest,
+    ShufflePlaylistRequest, SkipNextRequest, SkipNextResponse, SkipPreviousRequest,
+    SpeedDownRequest, SpeedReply, SpeedUpRequest, StreamUpdates, ToggleGaplessReply,
+    ToggleGaplessRequest, TogglePauseRequest, TogglePauseResponse, UpdateMissedEvents,
+    VolumeDownRequest, VolumeReply, VolumeUpRequest,
+};
+use termusicplayback::{
+    BackendSelect, PlayerCmd, PlayerCmdSender, SharedPlaylist, Speed, StreamTX, MAX_SPEED,
+    MIN_SPEED,
 };
-use termusicplayback::{PlayerCmd, PlayerCmdSender, StreamTX};
 use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::{Stream, StreamExt};
 use tonic::{Request, Response, Status};
 
+use crate::podcast_service::PodcastService;
 use crate::PlayerStats;
 
 #[derive(Debug)]
@@ -24,16 +43,35 @@ pub struct MusicPlayerService {
     cmd_tx: PlayerCmdSender,
     stream_tx: StreamTX,
     pub(crate) player_stats: Arc<Mutex<PlayerStats>>,
+    playlist: SharedPlaylist,
+    db: Arc<Mutex<DataBase>>,
+    podcasts: PodcastService,
+    config: SharedServerSettings,
+    /// The backend the player uses, as some features are only supported by some backends
+    backend: BackendSelect,
 }
 
 impl MusicPlayerService {
-    pub fn new(cmd_tx: PlayerCmdSender, stream_tx: StreamTX) -> Self {
+    pub fn new(
+        cmd_tx: PlayerCmdSender,
+        stream_tx: StreamTX,
+        playlist: SharedPlaylist,
+        db: Arc<Mutex<DataBase>>,
+        podcasts: PodcastService,
+        config: SharedServerSettings,
+        backend: BackendSelect,
+    ) -> Self {
         let player_stats = Arc::new(Mutex::new(PlayerStats::new()));
 
         Self {
             cmd_tx,
             player_stats,
             stream_tx,
+            playlist,
+            db,
+            podcasts,
+            config,
+            backend,
         }
     }
 }
@@ -44,6 +82,31 @@ impl MusicPlayerService {
             error!("error {cmd:?}: {e}");
         }
     }
+
+    /// Query the available output devices, with the configured one
+    async fn output_devices_reply(&self) -> Result<OutputDevicesReply, Status> {
+        if self.backend != BackendSelect::Rusty {
+            return Err(Status::unimplemented(
+                "Output devices are only supported by the rusty backend",
+            ));
+        }
+
+        // enumerating the devices can block on the audio host
+        let devices = tokio::task::spawn_blocking(termusicplayback::output_devices)
+            .await
+            .map_err(|err| Status::internal(format!("{err:#}")))?
+            .map_err(|err| Status::internal(format!("{err:#}")))?;
+        let current = self
+            .config
+            .read()
+            .settings
+            .player
+            .output_device
+            .clone()
+            .unwrap_or_default();
+
+        Ok(OutputDevicesReply { devices, current })
+    }
 }
 
 #[tonic::async_trait]
@@ -72,10 +135,11 @@ impl MusicPlayer for MusicPlayerService {
 
     async fn play_selected(
         &self,
-        _request: Request<PlaySelectedRequest>,
+        request: Request<PlaySelectedRequest>,
     ) -> Result<Response<EmptyReply>, Status> {
         let reply = EmptyReply {};
-        self.command(&PlayerCmd::PlaySelected);
+        let index = request.into_inner().index as usize;
+        self.command(&PlayerCmd::PlaySelected(index));
 
         Ok(Response::new(reply))
     }
@@ -146,6 +210,32 @@ impl MusicPlayer for MusicPlayerService {
         Ok(Response::new(reply))
     }
 
+    async fn next_chapter(
+        &self,
+        _request: Request<NextChapterRequest>,
+    ) -> Result<Response<PlayerTime>, Status> {
+        self.command(&PlayerCmd::ChapterNext);
+        // This is to let the player seek within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let s = self.player_stats.lock();
+        let reply = s.as_playertime();
+
+        Ok(Response::new(reply))
+    }
+
+    async fn previous_chapter(
+        &self,
+        _request: Request<PreviousChapterRequest>,
+    ) -> Result<Response<PlayerTime>, Status> {
+        self.command(&PlayerCmd::ChapterPrevious);
+        // This is to let the player seek within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let s = self.player_stats.lock();
+        let reply = s.as_playertime();
+
+        Ok(Response::new(reply))
+    }
+
     async fn speed_down(
         &self,
         _request: Request<SpeedDownRequest>,
@@ -197,6 +287,30 @@ impl MusicPlayer for MusicPlayerService {
         Ok(Response::new(reply))
     }
 
+    async fn play(
+        &self,
+        _request: Request<PlayRequest>,
+    ) -> Result<Response<TogglePauseResponse>, Status> {
+        self.command(&PlayerCmd::Play);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let r = self.player_stats.lock();
+        let reply = TogglePauseResponse { status: r.status };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn pause(
+        &self,
+        _request: Request<PauseRequest>,
+    ) -> Result<Response<TogglePauseResponse>, Status> {
+        self.command(&PlayerCmd::Pause);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let r = self.player_stats.lock();
+        let reply = TogglePauseResponse { status: r.status };
+
+        Ok(Response::new(reply))
+    }
+
     async fn volume_down(
         &self,
         _request: Request<VolumeDownRequest>,
@@ -227,6 +341,435 @@ impl MusicPlayer for MusicPlayerService {
         Ok(Response::new(reply))
     }
 
+    async fn set_volume(
+        &self,
+        request: Request<SetVolumeRequest>,
+    ) -> Result<Response<VolumeReply>, Status> {
+        let volume = u16::try_from(request.into_inner().volume).unwrap_or(u16::MAX);
+        self.command(&PlayerCmd::SetVolume(volume));
+        // This is to let the player update volume within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let r = self.player_stats.lock();
+        let reply = VolumeReply {
+            volume: u32::from(r.volume),
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_speed(
+        &self,
+        request: Request<SetSpeedRequest>,
+    ) -> Result<Response<SpeedReply>, Status> {
+        let speed = check_speed(request.into_inner().speed)?;
+        self.command(&PlayerCmd::SetSpeed(speed));
+        // This is to let the player update speed within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let s = self.player_stats.lock();
+        let reply = SpeedReply { speed: s.speed };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn seek_to(
+        &self,
+        request: Request<SeekToRequest>,
+    ) -> Result<Response<PlayerTime>, Status> {
+        let Some(position) = request.into_inner().position else {
+            return Err(Status::invalid_argument("\"position\" is required"));
+        };
+        self.command(&PlayerCmd::SeekTo(position.into()));
+        // This is to let the player seek within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let s = self.player_stats.lock();
+        let reply = s.as_playertime();
+
+        Ok(Response::new(reply))
+    }
+
+    async fn play_index(
+        &self,
+        request: Request<PlayIndexRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let index = request.into_inner().index as usize;
+        self.command(&PlayerCmd::PlaySelected(index));
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn get_current_track(
+        &self,
+        _request: Request<GetCurrentTrackRequest>,
+    ) -> Result<Response<CurrentTrackReply>, Status> {
+        let playlist = self.playlist.read();
+        // protobuf does not support usize directly
+        #[allow(clippy::cast_possible_truncation)]
+        let reply = CurrentTrackReply {
+            track: playlist.current_track().map(Into::into),
+            current_track_index: playlist.get_current_track_index() as u32,
+        };
+
+        Ok(Response::new(reply))
+    }
+
+    async fn get_playlist(
+        &self,
+        _request: Request<GetPlaylistRequest>,
+    ) -> Result<Response<PlaylistTracks>, Status> {
+        let reply = self.playlist.read().as_grpc_playlist_tracks();
+
+        Ok(Response::new(reply))
+    }
+
+    async fn add_to_playlist(
+        &self,
+        request: Request<PlaylistTracksToAdd>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let info = PlaylistAddTrack::try_from(request.into_inner())
+            .map_err(|err| Status::invalid_argument(format!("{err:#}")))?;
+        self.command(&PlayerCmd::PlaylistAddTrack(info));
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn remove_from_playlist(
+        &self,
+        request: Request<PlaylistTracksToRemove>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.command(&PlayerCmd::PlaylistRemoveTrack(request.into_inner().into()));
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn swap_tracks(
+        &self,
+        request: Request<PlaylistSwapTracks>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.command(&PlayerCmd::PlaylistSwapTrack(request.into_inner().into()));
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn move_track(
+        &self,
+        request: Request<PlaylistMoveTracks>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.command(&PlayerCmd::PlaylistMoveTrack(request.into_inner().into()));
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn clear_playlist(
+        &self,
+        _request: Request<ClearPlaylistRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.command(&PlayerCmd::PlaylistClear);
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn shuffle_playlist(
+        &self,
+        _request: Request<ShufflePlaylistRequest>,
+    ) -> Result<Response<PlaylistTracks>, Status> {
+        self.command(&PlayerCmd::PlaylistShuffle);
+        // This is to let the player shuffle the playlist within loop
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let reply = self.playlist.read().as_grpc_playlist_tracks();
+
+        Ok(Response::new(reply))
+    }
+
+    async fn remove_deleted_tracks(
+        &self,
+        _request: Request<RemoveDeletedTracksRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.command(&PlayerCmd::PlaylistRemoveDeletedTracks);
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn set_loop_mode(
+        &self,
+        request: Request<SetLoopModeRequest>,
+    ) -> Result<Response<LoopModeReply>, Status> {
+        let mode = LoopMode::from_u32(request.into_inner().mode);
+        self.command(&PlayerCmd::PlaylistSetLoopMode(mode));
+
+        Ok(Response::new(LoopModeReply {
+            mode: mode.as_u32(),
+        }))
+    }
+
+    async fn get_library_criteria(
+        &self,
+        request: Request<LibraryCriteriaRequest>,
+    ) -> Result<Response<LibraryCriteriaReply>, Status> {
+        let request = request.into_inner();
+        let criteria = library_criteria(request.criteria)?;
+        let page: Page = request.page.map(Into::into).unwrap_or_default();
+        let (values, total) = self
+            .db
+            .lock()
+            .get_criterias_page(&criteria, page)
+            .map_err(|err| Status::internal(format!("Error querying library: {err}")))?;
+
+        Ok(Response::new(LibraryCriteriaReply { values, total }))
+    }
+
+    async fn get_library_tracks(
+        &self,
+        request: Request<LibraryTracksRequest>,
+    ) -> Result<Response<LibraryTracks>, Status> {
+        let request = request.into_inner();
+        let criteria = library_criteria(request.criteria)?;
+        let page: Page = request.page.map(Into::into).unwrap_or_default();
+        let (tracks, total) = self
+            .db
+            .lock()
+            .get_record_by_criteria_page(&request.value, &criteria, page)
+            .map_err(|err| Status::internal(format!("Error querying library: {err}")))?;
+
+        Ok(Response::new(LibraryTracks {
+            tracks: tracks.into_iter().map(Into::into).collect(),
+            total,
+        }))
+    }
+
+    async fn search_library(
+        &self,
+        request: Request<LibrarySearchRequest>,
+    ) -> Result<Response<LibraryTracks>, Status> {
+        let request = request.into_inner();
+        let page: Page = request.page.map(Into::into).unwrap_or_default();
+        let (tracks, total) = self
+            .db
+            .lock()
+            .search_records(&request.query, page)
+            .map_err(|err| Status::internal(format!("Error querying library: {err}")))?;
+
+        Ok(Response::new(LibraryTracks {
+            tracks: tracks.into_iter().map(Into::into).collect(),
+            total,
+        }))
+    }
+
+    async fn get_podcasts(
+        &self,
+        request: Request<GetPodcastsRequest>,
+    ) -> Result<Response<PodcastList>, Status> {
+        let podcasts = self.podcasts.podcasts().map_err(podcast_error)?;
+        let episodes = if request.into_inner().include_episodes {
+            podcasts
+                .iter()
+                .flat_map(|pod| pod.episodes.iter().map(Into::into))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Response::new(PodcastList {
+            podcasts: podcasts.iter().map(Into::into).collect(),
+            episodes,
+        }))
+    }
+
+    async fn get_episodes(
+        &self,
+        request: Request<GetEpisodesRequest>,
+    ) -> Result<Response<EpisodeList>, Status> {
+        let episodes = self
+            .podcasts
+            .episodes(request.into_inner().podcast_id)
+            .map_err(podcast_error)?;
+
+        Ok(Response::new(EpisodeList {
+            episodes: episodes.iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn add_podcast(
+        &self,
+        request: Request<AddPodcastRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        // youtube channels and playlists are subscribed to through a invidious instance
+        let url = podcast_feed_url(&request.into_inner().url)
+            .await
+            .map_err(|err| Status::unavailable(format!("{err:#}")))?;
+        self.podcasts
+            .add(&url)
+            .map_err(|err| Status::already_exists(format!("{err:#}")))?;
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn remove_podcast(
+        &self,
+        request: Request<RemovePodcastRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let request = request.into_inner();
+        // remove as many as possible, even if some fail
+        let failed: Vec<String> = request
+            .podcast_ids
+            .iter()
+            .filter_map(|id| {
+                self.podcasts
+                    .remove(*id, request.delete_files)
+                    .err()
+                    .map(|err| format!("{err:#}"))
+            })
+            .collect();
+        if !failed.is_empty() {
+            return Err(Status::internal(format!(
+                "Error removing podcasts: {}",
+                failed.join(", ")
+            )));
+        }
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn refresh_podcasts(
+        &self,
+        request: Request<RefreshPodcastsRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.podcasts
+            .refresh(&request.into_inner().podcast_ids)
+            .map_err(podcast_error)?;
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn download_episodes(
+        &self,
+        request: Request<EpisodeIds>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.podcasts
+            .download(&request.into_inner().episode_ids)
+            .map_err(podcast_error)?;
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn delete_episode_files(
+        &self,
+        request: Request<EpisodeIds>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        self.podcasts
+            .delete_files(&request.into_inner().episode_ids)
+            .map_err(podcast_error)?;
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn set_episodes_played(
+        &self,
+        request: Request<SetEpisodesPlayedRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let request = request.into_inner();
+        self.podcasts
+            .set_played(&request.episode_ids, request.played)
+            .map_err(podcast_error)?;
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn set_podcast_settings(
+        &self,
+        request: Request<SetPodcastSettingsRequest>,
+    ) -> Result<Response<EmptyReply>, Status> {
+        let request = request.into_inner();
+        self.podcasts
+            .set_settings(
+                request.podcast_id,
+                request.settings.unwrap_or_default().into(),
+            )
+            .map_err(podcast_error)?;
+
+        Ok(Response::new(EmptyReply {}))
+    }
+
+    async fn get_equalizer(
+        &self,
+        _request: Request<GetEqualizerRequest>,
+    ) -> Result<Response<EqualizerReply>, Status> {
+        let reply = (&self.config.read().settings.player.equalizer).into();
+
+        Ok(Response::new(reply))
+    }
+
+    async fn set_equalizer(
+        &self,
+        request: Request<SetEqualizerRequest>,
+    ) -> Result<Response<EqualizerReply>, Status> {
+        let set = EqualizerSet::from(request.into_inner());
+        if set.preset.is_empty() {
+            return Err(Status::invalid_argument("\"preset\" must not be empty"));
+        }
+        if !set.bands.iter().all(EqualizerBand::is_valid) {
+            return Err(Status::invalid_argument(
+                "\"bands\" need a positive frequency and q",
+            ));
+        }
+
+        let reply = {
+            let mut config = self.config.write();
+            let equalizer = &mut config.settings.player.equalizer;
+            equalizer.set(set.enabled, set.preset, set.bands);
+            (&*equalizer).into()
+        };
+        self.command(&PlayerCmd::EqualizerChanged);
+
+        Ok(Response::new(reply))
+    }
+
+    async fn remove_equalizer_preset(
+        &self,
+        request: Request<RemoveEqualizerPresetRequest>,
+    ) -> Result<Response<EqualizerReply>, Status> {
+        let preset = request.into_inner().preset;
+        let reply = {
+            let mut config = self.config.write();
+            let equalizer = &mut config.settings.player.equalizer;
+            if !equalizer.remove_preset(&preset) {
+                return Err(Status::not_found(format!(
+                    "Equalizer preset \"{preset}\" does not exist"
+                )));
+            }
+            (&*equalizer).into()
+        };
+        self.command(&PlayerCmd::EqualizerChanged);
+
+        Ok(Response::new(reply))
+    }
+
+    async fn list_output_devices(
+        &self,
+        _request: Request<ListOutputDevicesRequest>,
+    ) -> Result<Response<OutputDevicesReply>, Status> {
+        Ok(Response::new(self.output_devices_reply().await?))
+    }
+
+    async fn set_output_device(
+        &self,
+        request: Request<SetOutputDeviceRequest>,
+    ) -> Result<Response<OutputDevicesReply>, Status> {
+        let device = request.into_inner().device;
+        let mut reply = self.output_devices_reply().await?;
+        if !device.is_empty() && !reply.devices.contains(&device) {
+            return Err(Status::not_found(format!(
+                "Output device \"{device}\" does not exist"
+            )));
+        }
+
+        self.config.write().settings.player.output_device =
+            Some(device.clone()).filter(|v| !v.is_empty());
+        self.command(&PlayerCmd::OutputDeviceChanged);
+        reply.current = device;
+
+        Ok(Response::new(reply))
+    }
+
     type SubscribeServerUpdatesStream =
         Pin<Box<dyn Stream<Item = Result<termusiclib::player::StreamUpdates, Status>> + Send>>;
     async fn subscribe_server_updates(
@@ -253,3 +796,46 @@ impl MusicPlayer for MusicPlayerService {
         Ok(Response::new(Box::pin(receiver_stream)))
     }
 }
+
+/// Check that a requested `speed` is within [`MIN_SPEED`] and [`MAX_SPEED`]
+fn check_speed(speed: Speed) -> Result<Speed, Status> {
+    if (MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        Ok(speed)
+    } else {
+        Err(Status::invalid_argument(format!(
+            "\"speed\" must be between {MIN_SPEED} and {MAX_SPEED}, got {speed}"
+        )))
+    }
+}
+
+/// Convert the `criteria` of a library request
+fn library_criteria(value: u32) -> Result<SearchCriteria, Status> {
+    SearchCriteria::try_from(value).map_err(|err| Status::invalid_argument(format!("{err:#}")))
+}
+
+/// Convert a error of the [`PodcastService`]
+#[allow(clippy::needless_pass_by_value)] // for use in "map_err"
+fn podcast_error(err: anyhow::Error) -> Status {
+    Status::internal(format!("{err:#}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_speed;
+    use termusicplayback::{MAX_SPEED, MIN_SPEED};
+    use tonic::Code;
+
+    #[test]
+    fn should_reject_out_of_range_speed() {
+        assert_eq!(check_speed(MIN_SPEED).unwrap(), MIN_SPEED);
+        assert_eq!(check_speed(MAX_SPEED).unwrap(), MAX_SPEED);
+        assert_eq!(
+            check_speed(MIN_SPEED - 1).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            check_speed(MAX_SPEED + 1).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+}
@@ -0,0 +1,613 @@
+//! Podcast feed syncing and episode downloading, done by the server so that it also happens without a client
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use parking_lot::Mutex;
+use sanitize_filename::{sanitize_with_options, Options};
+use termusiclib::config::SharedServerSettings;
+use termusiclib::player::{
+    PodcastDownloadInfo, PodcastDownloadState, PodcastFeedState, UpdateEvents,
+};
+use termusiclib::podcast::db::{Database, PodcastDBId};
+use termusiclib::podcast::episode::Episode;
+use termusiclib::podcast::gpodder::{self, GpodderClient};
+use termusiclib::podcast::transcript::remove_cached_transcript;
+use termusiclib::podcast::{download_list, EpData, FeedSettings, Podcast, PodcastFeed};
+use termusiclib::taskpool::TaskPool;
+use termusiclib::types::{Msg, PCMsg};
+use termusiclib::utils::{create_podcast_dir, get_app_config_path};
+use termusicplayback::StreamTX;
+
+/// How often to check if the podcast feeds are due for a refresh
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Manages the podcast database, feed syncs and episode downloads
+///
+/// Progress is reported as [`UpdateEvents::PodcastFeedChanged`] and [`UpdateEvents::PodcastDownload`].
+#[derive(Debug, Clone)]
+pub struct PodcastService {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: SharedServerSettings,
+    db: Mutex<Database>,
+    taskpool: TaskPool,
+    /// Sender for the feed and download tasks, handled by [`PodcastService::handle_messages`]
+    tx: Sender<Msg>,
+    stream_tx: StreamTX,
+    /// Urls of the feeds currently syncing
+    syncing: Mutex<HashSet<String>>,
+    /// Urls of the episodes currently downloading
+    downloading: Mutex<HashSet<String>>,
+    /// When a feed was last tried to be refreshed by the automatic refresh, to not retry failing feeds every check
+    last_refresh: Mutex<HashMap<PodcastDBId, Instant>>,
+}
+
+impl PodcastService {
+    /// Open the podcast database and start handling feed sync and download results
+    ///
+    /// # Errors
+    ///
+    /// - if the podcast database cannot be opened
+    pub fn new(config: SharedServerSettings, stream_tx: StreamTX) -> Result<Self> {
+        let db_path = get_app_config_path().context("failed to get podcast db path.")?;
+        let db = Database::new(&db_path).context("error connecting to podcast db.")?;
+        let taskpool = TaskPool::new(usize::from(
+            config
+                .read()
+                .settings
+                .podcast
+                .concurrent_downloads_max
+                .get(),
+        ));
+        let (tx, rx) = mpsc::channel();
+
+        let service = Self {
+            inner: Arc::new(Inner {
+                config,
+                db: Mutex::new(db),
+                taskpool,
+                tx,
+                stream_tx,
+                syncing: Mutex::default(),
+                downloading: Mutex::default(),
+                last_refresh: Mutex::default(),
+            }),
+        };
+
+        let handler = service.clone();
+        std::thread::Builder::new()
+            .name("podcast handler".into())
+            .spawn(move || handler.handle_messages(&rx))
+            .context("spawn podcast handler thread")?;
+
+        Ok(service)
+    }
+
+    /// Periodically refresh all feeds which have not been refreshed in `podcast.refresh_interval_minutes`
+    pub fn spawn_auto_refresh(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REFRESH_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(err) = service.refresh_due() {
+                    error!("Error refreshing podcasts: {err:#}");
+                }
+            }
+        });
+    }
+
+    /// Periodically sync with the gpodder server in `podcast.gpodder`, if configured
+    pub fn spawn_gpodder_sync(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let interval = service
+                    .inner
+                    .config
+                    .read()
+                    .settings
+                    .podcast
+                    .gpodder
+                    .as_ref()
+                    .map(|v| Duration::from_secs(u64::from(v.sync_interval_minutes.get()) * 60));
+                let Some(interval) = interval else {
+                    // check again later, in case the config changes
+                    tokio::time::sleep(REFRESH_CHECK_INTERVAL).await;
+                    continue;
+                };
+
+                if let Err(err) = service.gpodder_sync().await {
+                    error!("Error syncing with gpodder: {err:#}");
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    /// Sync subscriptions and episode actions with the gpodder server, also adding and removing the feeds changed on other devices
+    async fn gpodder_sync(&self) -> Result<()> {
+        let settings = self.inner.config.read().settings.podcast.gpodder.clone();
+        let Some(settings) = settings else {
+            return Ok(());
+        };
+        let client = GpodderClient::new(&settings)?;
+        let db_path = get_app_config_path().context("failed to get podcast db path.")?;
+
+        let outcome = gpodder::sync(&client, &db_path).await?;
+        info!(
+            "Synced with gpodder: {} local changes uploaded, {} feeds added, {} feeds removed, {} feeds updated",
+            outcome.uploaded,
+            outcome.add_feeds.len(),
+            outcome.remove_feeds.len(),
+            outcome.updated_feeds.len()
+        );
+
+        for url in &outcome.add_feeds {
+            if let Err(err) = self.add(url) {
+                warn!("Error adding synced feed \"{url}\": {err:#}");
+            }
+        }
+        for pod in self.podcasts()? {
+            if outcome.remove_feeds.contains(&pod.url) {
+                if let Err(err) = self.remove(pod.id, false) {
+                    warn!("Error removing synced feed \"{}\": {err:#}", pod.url);
+                }
+            }
+        }
+        for url in outcome.updated_feeds {
+            self.send_feed_event(url, PodcastFeedState::Synced);
+        }
+
+        Ok(())
+    }
+
+    /// Refresh all feeds that are due for a refresh
+    fn refresh_due(&self) -> Result<()> {
+        let minutes = self
+            .inner
+            .config
+            .read()
+            .settings
+            .podcast
+            .refresh_interval_minutes;
+        if minutes == 0 {
+            return Ok(());
+        }
+        let interval = Duration::from_secs(u64::from(minutes) * 60);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let mut due = Vec::new();
+        let mut last_refresh = self.inner.last_refresh.lock();
+        for pod in self.podcasts()? {
+            let last_checked = u64::try_from(pod.last_checked.timestamp()).unwrap_or(0);
+            let since_checked = Duration::from_secs(now.saturating_sub(last_checked));
+            let since_tried = last_refresh
+                .get(&pod.id)
+                .map_or(Duration::MAX, Instant::elapsed);
+
+            if since_checked >= interval && since_tried >= interval {
+                last_refresh.insert(pod.id, Instant::now());
+                due.push(PodcastFeed::from(&pod));
+            }
+        }
+        drop(last_refresh);
+
+        if !due.is_empty() {
+            info!("Refreshing {} podcast feeds", due.len());
+        }
+        for feed in due {
+            self.check_feed(feed);
+        }
+
+        Ok(())
+    }
+
+    /// Get all podcasts, including their (not hidden) episodes
+    ///
+    /// # Errors
+    ///
+    /// - if reading the database fails
+    pub fn podcasts(&self) -> Result<Vec<Podcast>> {
+        self.inner.db.lock().get_podcasts()
+    }
+
+    /// Get all (not hidden) episodes of the given podcast
+    ///
+    /// # Errors
+    ///
+    /// - if reading the database fails
+    pub fn episodes(&self, podcast_id: PodcastDBId) -> Result<Vec<Episode>> {
+        self.inner.db.lock().get_episodes(podcast_id, false)
+    }
+
+    /// Add a new feed by its `url`, the podcast is stored once the feed synced successfully
+    ///
+    /// # Errors
+    ///
+    /// - if the feed already exists
+    pub fn add(&self, url: &str) -> Result<()> {
+        if self.podcasts()?.iter().any(|pod| pod.url == url) {
+            bail!("Podcast feed already exists: {url}");
+        }
+
+        self.check_feed(PodcastFeed::new(None, url, None));
+
+        Ok(())
+    }
+
+    /// Refresh the given podcasts, or all if `podcast_ids` is empty
+    ///
+    /// # Errors
+    ///
+    /// - if reading the database fails
+    pub fn refresh(&self, podcast_ids: &[PodcastDBId]) -> Result<()> {
+        for pod in self.podcasts()? {
+            if podcast_ids.is_empty() || podcast_ids.contains(&pod.id) {
+                self.check_feed(PodcastFeed::from(&pod));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove a podcast and all its episodes, optionally deleting all downloaded files
+    ///
+    /// # Errors
+    ///
+    /// - if the podcast does not exist
+    /// - if modifying the database fails
+    pub fn remove(&self, podcast_id: PodcastDBId, delete_files: bool) -> Result<()> {
+        let podcast = self.find_podcast(podcast_id)?;
+
+        if delete_files {
+            let ids: Vec<PodcastDBId> = podcast.episodes.iter().map(|ep| ep.id).collect();
+            self.delete_files(&ids)?;
+        }
+        self.inner.db.lock().remove_podcast(podcast_id)?;
+        self.send_feed_event(podcast.url, PodcastFeedState::Removed);
+
+        Ok(())
+    }
+
+    /// Download the given episodes, ignoring already downloaded or downloading episodes
+    ///
+    /// # Errors
+    ///
+    /// - if reading the database fails
+    /// - if the download directory cannot be created
+    pub fn download(&self, episode_ids: &[PodcastDBId]) -> Result<()> {
+        let max_retries = usize::from(
+            self.inner
+                .config
+                .read()
+                .settings
+                .podcast
+                .max_download_retries,
+        );
+
+        let mut by_podcast: HashMap<PodcastDBId, Vec<Episode>> = HashMap::new();
+        for ep in self.inner.db.lock().get_episodes_by_id(episode_ids)? {
+            by_podcast.entry(ep.pod_id).or_default().push(ep);
+        }
+
+        for (podcast_id, pod_episodes) in by_podcast {
+            let pod = self.find_podcast(podcast_id)?;
+            let episodes: Vec<EpData> = {
+                let mut downloading = self.inner.downloading.lock();
+                pod_episodes
+                    .iter()
+                    .filter(|ep| ep.path.is_none())
+                    // "insert" returns "false" if already downloading
+                    .filter(|ep| downloading.insert(ep.url.clone()))
+                    .map(|ep| EpData {
+                        id: ep.id,
+                        pod_id: ep.pod_id,
+                        title: ep.title.clone(),
+                        url: ep.url.clone(),
+                        pubdate: ep.pubdate,
+                        file_path: None,
+                        enclosure_length: ep.enclosure_length,
+                        chapters_url: ep.chapters_url.clone(),
+                        chapters: Vec::new(),
+                        transcript_url: ep.transcript_url.clone(),
+                    })
+                    .collect()
+            };
+            if episodes.is_empty() {
+                continue;
+            }
+
+            let dir_name = sanitize_with_options(
+                &pod.title,
+                Options {
+                    truncate: true,
+                    windows: true, // for simplicity, we'll just use Windows-friendly paths for everyone
+                    replacement: "",
+                },
+            );
+            let path = match create_podcast_dir(&self.inner.config.read(), dir_name) {
+                Ok(v) => v,
+                Err(err) => {
+                    let mut downloading = self.inner.downloading.lock();
+                    for ep in &episodes {
+                        downloading.remove(&ep.url);
+                    }
+                    return Err(err.context(format!("Could not create dir: {}", pod.title)));
+                }
+            };
+
+            download_list(
+                episodes,
+                &path,
+                max_retries,
+                &self.inner.taskpool,
+                &self.inner.tx,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Delete the downloaded files of the given episodes
+    ///
+    /// The files are removed from the database even if deleting them fails, like when they are already missing.
+    ///
+    /// # Errors
+    ///
+    /// - if reading or modifying the database fails
+    /// - if deleting any file fails
+    pub fn delete_files(&self, episode_ids: &[PodcastDBId]) -> Result<()> {
+        let mut removed = Vec::new();
+        let mut failed = Vec::new();
+        let episodes = self.inner.db.lock().get_episodes_by_id(episode_ids)?;
+        for ep in episodes {
+            let Some(path) = ep.path else {
+                continue;
+            };
+            if let Err(err) = std::fs::remove_file(&path) {
+                failed.push(format!("\"{}\": {err}", ep.title));
+            }
+            remove_cached_transcript(&path);
+            removed.push(ep.id);
+        }
+
+        self.inner.db.lock().remove_files(&removed)?;
+        if !failed.is_empty() {
+            bail!("Error deleting files: {}", failed.join(", "));
+        }
+
+        Ok(())
+    }
+
+    /// Replace the settings of the given podcast
+    ///
+    /// # Errors
+    ///
+    /// - if the podcast does not exist
+    /// - if modifying the database fails
+    pub fn set_settings(&self, podcast_id: PodcastDBId, settings: FeedSettings) -> Result<()> {
+        self.find_podcast(podcast_id)?;
+
+        self.inner.db.lock().set_feed_settings(podcast_id, settings)
+    }
+
+    /// Mark the given episodes as played or unplayed
+    ///
+    /// # Errors
+    ///
+    /// - if modifying the database fails
+    pub fn set_played(&self, episode_ids: &[PodcastDBId], played: bool) -> Result<()> {
+        self.inner
+            .db
+            .lock()
+            .set_all_played_status(episode_ids, played)
+    }
+
+    fn find_podcast(&self, podcast_id: PodcastDBId) -> Result<Podcast> {
+        self.inner
+            .db
+            .lock()
+            .get_podcast(podcast_id)?
+            .ok_or_else(|| anyhow!("No podcast with id {podcast_id}"))
+    }
+
+    /// Start syncing a feed, ignored if the feed is already syncing
+    fn check_feed(&self, feed: PodcastFeed) {
+        if !self.inner.syncing.lock().insert(feed.url.clone()) {
+            return;
+        }
+
+        let max_retries = usize::from(
+            self.inner
+                .config
+                .read()
+                .settings
+                .podcast
+                .max_download_retries,
+        );
+        termusiclib::podcast::check_feed(
+            feed,
+            max_retries,
+            &self.inner.taskpool,
+            self.inner.tx.clone(),
+        );
+    }
+
+    fn send_feed_event(&self, url: String, state: PodcastFeedState) {
+        // there might be no subscribers, which is not a error
+        let _ = self
+            .inner
+            .stream_tx
+            .send(UpdateEvents::PodcastFeedChanged { url, state });
+    }
+
+    fn send_download_event(&self, ep_data: EpData, state: PodcastDownloadState) {
+        // there might be no subscribers, which is not a error
+        let _ = self
+            .inner
+            .stream_tx
+            .send(UpdateEvents::PodcastDownload(PodcastDownloadInfo {
+                episode_id: ep_data.id,
+                url: ep_data.url,
+                title: ep_data.title,
+                state,
+            }));
+    }
+
+    /// Handle the results of the feed and download tasks, until all senders are dropped
+    fn handle_messages(&self, rx: &Receiver<Msg>) {
+        while let Ok(msg) = rx.recv() {
+            let Msg::Podcast(msg) = msg else {
+                continue;
+            };
+            if let Err(err) = self.handle_message(msg) {
+                error!("Error handling podcast result: {err:#}");
+            }
+        }
+    }
+
+    fn handle_message(&self, msg: PCMsg) -> Result<()> {
+        match msg {
+            PCMsg::FetchPodcastStart(url) => {
+                self.send_feed_event(url, PodcastFeedState::SyncStarted);
+            }
+            PCMsg::NewData(pod) => {
+                self.inner.syncing.lock().remove(&pod.url);
+                let res = self.inner.db.lock().insert_podcast(&pod);
+                self.finish_sync(pod.url, res.map(|_| ()))?;
+            }
+            PCMsg::SyncData((id, pod)) => {
+                self.inner.syncing.lock().remove(&pod.url);
+                let res = self.inner.db.lock().update_podcast(id, &pod);
+                let added_ids = res
+                    .as_ref()
+                    .map(|v| v.added_ids.clone())
+                    .unwrap_or_default();
+                self.finish_sync(pod.url, res.map(|_| ()))?;
+                self.apply_feed_settings(id, &added_ids)
+                    .context("applying podcast settings")?;
+            }
+            PCMsg::SyncUnchanged(id, url) => {
+                self.inner.syncing.lock().remove(&url);
+                debug!("Podcast feed \"{url}\" is unchanged");
+                let res = self.inner.db.lock().update_podcast_unchanged(id);
+                self.finish_sync(url, res.map(|_| ()))?;
+                // there are no new episodes, but played episodes may have expired since
+                self.apply_feed_settings(id, &[])
+                    .context("applying podcast settings")?;
+            }
+            PCMsg::Error(url, _feed) => {
+                self.inner.syncing.lock().remove(&url);
+                self.send_feed_event(url, PodcastFeedState::SyncFailed);
+            }
+            PCMsg::DLStart(ep_data) => {
+                self.send_download_event(ep_data, PodcastDownloadState::Started);
+            }
+            PCMsg::DLProgress(ep_data, downloaded, total) => {
+                self.send_download_event(
+                    ep_data,
+                    PodcastDownloadState::Progress { downloaded, total },
+                );
+            }
+            PCMsg::DLComplete(ep_data) => {
+                self.inner.downloading.lock().remove(&ep_data.url);
+                let res = match &ep_data.file_path {
+                    Some(path) => self.inner.db.lock().insert_file(ep_data.id, path),
+                    None => Err(anyhow!("Download completed without a file path")),
+                };
+                if !ep_data.chapters.is_empty() {
+                    if let Err(err) = self
+                        .inner
+                        .db
+                        .lock()
+                        .set_chapters(ep_data.id, &ep_data.chapters)
+                    {
+                        warn!(
+                            "Storing the chapters of \"{}\" failed: {err:#}",
+                            ep_data.title
+                        );
+                    }
+                }
+                let state = if res.is_ok() {
+                    PodcastDownloadState::Completed
+                } else {
+                    PodcastDownloadState::Failed
+                };
+                self.send_download_event(ep_data, state);
+                res?;
+            }
+            PCMsg::DLResponseError(ep_data)
+            | PCMsg::DLFileCreateError(ep_data)
+            | PCMsg::DLFileWriteError(ep_data) => {
+                warn!("Downloading episode \"{}\" failed", ep_data.title);
+                self.inner.downloading.lock().remove(&ep_data.url);
+                self.send_download_event(ep_data, PodcastDownloadState::Failed);
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Delete downloads according to the retention settings and download the newly added episodes, if enabled
+    fn apply_feed_settings(
+        &self,
+        podcast_id: PodcastDBId,
+        added_ids: &[PodcastDBId],
+    ) -> Result<()> {
+        let podcast = self.find_podcast(podcast_id)?;
+        let defaults = self.inner.config.read().settings.podcast.clone();
+
+        let expired: Vec<PodcastDBId> = podcast
+            .expired_downloads(&defaults, Utc::now())
+            .iter()
+            .map(|ep| ep.id)
+            .collect();
+        if !expired.is_empty() {
+            info!(
+                "Deleting {} expired downloads of \"{}\"",
+                expired.len(),
+                podcast.title
+            );
+            self.delete_files(&expired)?;
+        }
+
+        if !added_ids.is_empty() && podcast.settings.auto_download(&defaults) {
+            let keep = podcast.settings.keep_downloaded(&defaults);
+            // episodes are sorted newest first, dont download more than would be kept
+            let ids: Vec<PodcastDBId> = podcast
+                .episodes
+                .iter()
+                .filter(|ep| added_ids.contains(&ep.id))
+                .take(if keep == 0 { usize::MAX } else { keep as usize })
+                .map(|ep| ep.id)
+                .collect();
+            self.download(&ids)?;
+        }
+
+        Ok(())
+    }
+
+    /// Send the sync result for `url` and return the database error, if any
+    fn finish_sync(&self, url: String, res: Result<()>) -> Result<()> {
+        let state = if res.is_ok() {
+            PodcastFeedState::Synced
+        } else {
+            PodcastFeedState::SyncFailed
+        };
+        self.send_feed_event(url, state);
+
+        res.context("storing podcast feed")
+    }
+}
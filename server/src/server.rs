@@ -1,6 +1,9 @@
 mod cli;
 mod logger;
+mod mpd;
 mod music_player_service;
+mod podcast_service;
+mod transport;
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -10,20 +13,23 @@ use anyhow::{bail, Context, Result};
 use clap::Parser;
 use music_player_service::MusicPlayerService;
 use parking_lot::Mutex;
+use podcast_service::PodcastService;
 use termusiclib::config::v2::server::config_extra::ServerConfigVersionedDefaulted;
-use termusiclib::config::v2::server::ScanDepth;
-use termusiclib::config::ServerOverlay;
+use termusiclib::config::v2::server::{ReplayGainMode, ScanDepth};
+use termusiclib::config::{new_shared_server_settings, ServerOverlay, SharedServerSettings};
+use termusiclib::library_db::DataBase;
+use termusiclib::player::auth::ServerTokenInterceptor;
 use termusiclib::player::music_player_server::MusicPlayerServer;
 use termusiclib::player::{GetProgressResponse, PlayerProgress, PlayerTime};
 use termusiclib::track::MediaType;
 use termusiclib::{podcast, utils};
 use termusicplayback::{
-    Backend, BackendSelect, GeneralPlayer, PlayerCmd, PlayerCmdReciever, PlayerCmdSender,
-    PlayerTrait, SpeedSigned, Status, VolumeSigned,
+    loudness, new_shared_playlist, Backend, BackendSelect, GeneralPlayer, PlayerCmd,
+    PlayerCmdReciever, PlayerCmdSender, PlayerTrait, Playlist, SharedPlaylist, SpeedSigned, Status,
+    VolumeSigned, MAX_SPEED, MIN_SPEED,
 };
 use tokio::runtime::Handle;
 use tokio::sync::{broadcast, oneshot};
-use tonic::transport::server::TcpIncoming;
 use tonic::transport::Server;
 
 #[macro_use]
@@ -105,10 +111,73 @@ async fn actual_main() -> Result<()> {
     let (cmd_tx, cmd_rx) = tokio::sync::mpsc::unbounded_channel();
     let (stream_tx, _) = broadcast::channel(3);
 
-    let music_player_service: MusicPlayerService =
-        MusicPlayerService::new(cmd_tx.clone(), stream_tx.clone());
+    // bind before starting anything else, to fail early
+    let incoming = transport::bind(&config.settings.com).await?;
+    let tls_config = transport::tls_config(&config.settings.com)?;
+    if config.settings.com.token_in_plaintext() {
+        warn!(
+            "A token is set for non-loopback address {} without TLS, it will be sent in plaintext; consider setting \"com.tls\"",
+            config.settings.com.address
+        );
+    }
+    let auth_interceptor = ServerTokenInterceptor::new(config.settings.com.token.clone());
+    let mpd_listener = if config.settings.mpd.enabled {
+        Some(mpd::bind(&config.settings.mpd).await?)
+    } else {
+        None
+    };
+    let config = new_shared_server_settings(config);
+    let playlist = new_shared_playlist(Playlist::new(&config).unwrap_or_default());
+
+    let db = {
+        let config_read = config.read();
+        let mut db = DataBase::new(&config_read)?;
+        // keep the library up-to-date for clients that dont have access to the files themself
+        let scans = config_read
+            .settings
+            .player
+            .music_dirs
+            .iter()
+            .map(|dir| db.sync_database(dir))
+            .collect();
+        // the loudness is only used for normalization
+        if config_read.settings.player.replaygain != ReplayGainMode::Off {
+            loudness::spawn_analyzer(db.clone(), scans)?;
+        }
+        Arc::new(Mutex::new(db))
+    };
+
+    let podcasts = PodcastService::new(config.clone(), stream_tx.clone())?;
+    podcasts.spawn_auto_refresh();
+    podcasts.spawn_gpodder_sync();
+
+    let backend: BackendSelect = args.backend.into();
+    let music_player_service: MusicPlayerService = MusicPlayerService::new(
+        cmd_tx.clone(),
+        stream_tx.clone(),
+        playlist.clone(),
+        db.clone(),
+        podcasts,
+        config.clone(),
+        backend,
+    );
     let playerstats = music_player_service.player_stats.clone();
 
+    if let Some(listener) = mpd_listener {
+        mpd::spawn(
+            listener,
+            mpd::MpdContext {
+                cmd_tx: cmd_tx.clone(),
+                stream_tx: stream_tx.clone(),
+                song_ids: Arc::new(mpd::SongIds::new(playlist.read().len())),
+                playlist: playlist.clone(),
+                player_stats: playerstats.clone(),
+                config: config.clone(),
+                db,
+            },
+        );
+    }
+
     let cmd_tx_ctrlc = cmd_tx.clone();
     let cmd_tx_ticker = cmd_tx.clone();
 
@@ -119,17 +188,6 @@ async fn actual_main() -> Result<()> {
     })
     .expect("Error setting Ctrl-C handler");
 
-    let addr = std::net::SocketAddr::from(config.settings.com);
-
-    // workaround to print address once sever "actually" is started and address is known
-    // see https://github.com/hyperium/tonic/issues/351
-    let tcp_listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .with_context(|| format!("Error binding address: {}", addr))?;
-    info!("Server listening on {}", tcp_listener.local_addr().unwrap());
-    let tcp_stream =
-        TcpIncoming::from_listener(tcp_listener, true, None).map_err(|e| anyhow::anyhow!(e))?;
-
     let tokio_handle = Handle::current();
     let (player_handle_os_tx, player_handle_os_rx) = oneshot::channel();
     let player_handle = std::thread::Builder::new()
@@ -137,28 +195,40 @@ async fn actual_main() -> Result<()> {
         .spawn(move || {
             let _guard = tokio_handle.enter();
             let res = player_loop(
-                args.backend.into(),
+                backend,
                 cmd_tx,
                 cmd_rx,
                 config,
                 playerstats,
                 stream_tx,
+                playlist,
             );
             let _ = player_handle_os_tx.send(res);
         })?;
 
     ticker_thread(cmd_tx_ticker)?;
 
-    tokio::spawn(
-        Server::builder()
-            .add_service(MusicPlayerServer::new(music_player_service))
-            .serve_with_incoming(tcp_stream),
-    );
+    let mut server = Server::builder();
+    if let Some(tls_config) = tls_config {
+        server = server.tls_config(tls_config)?;
+        info!("TLS enabled");
+    }
+    let router = server.add_service(MusicPlayerServer::with_interceptor(
+        music_player_service,
+        auth_interceptor,
+    ));
+    match incoming {
+        transport::Incoming::Tcp(stream) => tokio::spawn(router.serve_with_incoming(stream)),
+        #[cfg(unix)]
+        transport::Incoming::Unix(stream) => tokio::spawn(router.serve_with_incoming(stream)),
+    };
 
-    info!("Server started and listening on {}", addr);
+    info!("Server started");
 
     // await the oneshot completing in a async fashion
-    player_handle_os_rx.await??;
+    let res = player_handle_os_rx.await;
+    transport::remove_socket();
+    res??;
     // do this *after* the oneshot, because this is a blocking operation
     // and by doing this after the oneshot we can be sure the thread is actually exited, or exiting
     let _ = player_handle.join();
@@ -171,27 +241,26 @@ fn player_loop(
     backend: BackendSelect,
     cmd_tx: PlayerCmdSender,
     mut cmd_rx: PlayerCmdReciever,
-    config: ServerOverlay,
+    config: SharedServerSettings,
     playerstats: Arc<Mutex<PlayerStats>>,
     stream_tx: termusicplayback::StreamTX,
+    playlist: SharedPlaylist,
 ) -> Result<()> {
-    let mut player = GeneralPlayer::new_backend(backend, config, cmd_tx, stream_tx)?;
+    let mut player = GeneralPlayer::new_backend(backend, config, cmd_tx, stream_tx, playlist)?;
     while let Some(cmd) = cmd_rx.blocking_recv() {
         #[allow(unreachable_patterns)]
         match cmd {
             PlayerCmd::AboutToFinish => {
                 info!("about to finish signal received");
-                if !player.playlist.is_empty()
-                    && !player.playlist.has_next_track()
-                    && player.config.read().settings.player.gapless
-                {
+                if !player.playlist.read().is_empty() && !player.playlist.read().has_next_track() {
                     player.enqueue_next_from_playlist();
                 }
             }
             PlayerCmd::Quit => {
                 info!("PlayerCmd::Quit received");
+                transport::remove_socket();
                 player.player_save_last_position();
-                if let Err(e) = player.playlist.save() {
+                if let Err(e) = player.playlist.write().save() {
                     error!("error when saving playlist: {e}");
                 };
                 if let Err(e) =
@@ -202,31 +271,54 @@ fn player_loop(
                 std::process::exit(0);
             }
             PlayerCmd::CycleLoop => {
-                player.config.write().settings.player.loop_mode = player.playlist.cycle_loop_mode();
+                player.playlist_cycle_loop_mode();
             }
             PlayerCmd::Eos => {
                 info!("Eos received");
-                if player.playlist.is_empty() {
+                if player.playlist.read().is_empty() {
                     player.stop();
                     continue;
                 }
                 debug!(
                     "current track index: {:?}",
-                    player.playlist.get_current_track_index()
+                    player.playlist.read().get_current_track_index()
                 );
-                player.playlist.clear_current_track();
+                player.playlist.write().clear_current_track();
                 player.start_play();
                 debug!(
                     "playing index is: {}",
-                    player.playlist.get_current_track_index()
+                    player.playlist.read().get_current_track_index()
                 );
             }
-            PlayerCmd::GetProgress | PlayerCmd::ProcessID => {}
-            PlayerCmd::PlaySelected => {
+            PlayerCmd::GetProgress
+            | PlayerCmd::GetPlaylist
+            | PlayerCmd::ProcessID
+            | PlayerCmd::PodcastAdd(_)
+            | PlayerCmd::PodcastRefresh(_)
+            | PlayerCmd::PodcastDownload(_)
+            | PlayerCmd::PodcastDeleteFiles(_)
+            | PlayerCmd::PodcastSetPlayed(..)
+            | PlayerCmd::PodcastRemove(_)
+            | PlayerCmd::PodcastReload
+            | PlayerCmd::EqualizerSet(_)
+            | PlayerCmd::EqualizerRemovePreset(_) => {}
+            PlayerCmd::EqualizerChanged => {
+                player.equalizer_changed();
+            }
+            PlayerCmd::OutputDeviceChanged => {
+                player.output_device_changed();
+            }
+            PlayerCmd::PlaySelected(index) => {
                 info!("play selected");
                 player.player_save_last_position();
-                player.playlist.proceed_false();
-                player.next();
+                player.play_selected(index);
+            }
+            PlayerCmd::Stop => {
+                info!("stop playback");
+                player.player_save_last_position();
+                player.user_stop();
+                let mut p_tick = playerstats.lock();
+                p_tick.status = player.playlist.read().status().as_u32();
             }
             PlayerCmd::SkipPrevious => {
                 info!("skip to previous track");
@@ -239,7 +331,9 @@ fn player_loop(
                 }
             }
             PlayerCmd::ReloadPlaylist => {
-                player.playlist.reload_tracks().ok();
+                if let Err(err) = player.playlist_reload() {
+                    error!("Error reloading playlist: {err:#?}");
+                }
             }
             PlayerCmd::SeekBackward => {
                 player.seek_relative(false);
@@ -255,6 +349,27 @@ fn player_loop(
                     p_tick.progress = progress
                 }
             }
+            PlayerCmd::SeekTo(position) => {
+                player.seek_to(position);
+                let mut p_tick = playerstats.lock();
+                if let Some(progress) = player.get_progress() {
+                    p_tick.progress = progress;
+                }
+            }
+            PlayerCmd::ChapterNext => {
+                player.chapter_next();
+                let mut p_tick = playerstats.lock();
+                if let Some(progress) = player.get_progress() {
+                    p_tick.progress = progress;
+                }
+            }
+            PlayerCmd::ChapterPrevious => {
+                player.chapter_previous();
+                let mut p_tick = playerstats.lock();
+                if let Some(progress) = player.get_progress() {
+                    p_tick.progress = progress;
+                }
+            }
             PlayerCmd::SkipNext => {
                 info!("skip to next track.");
                 player.player_save_last_position();
@@ -275,22 +390,29 @@ fn player_loop(
                 let mut p_tick = playerstats.lock();
                 p_tick.speed = new_speed;
             }
+            PlayerCmd::SetSpeed(speed) => {
+                let new_speed = player.set_speed(speed.clamp(MIN_SPEED, MAX_SPEED));
+                info!("after set speed: {}", new_speed);
+                player.config.write().settings.player.speed = new_speed;
+                let mut p_tick = playerstats.lock();
+                p_tick.speed = new_speed;
+            }
             PlayerCmd::Tick => {
                 // info!("tick received");
                 player.mpris_handle_events();
                 let mut p_tick = playerstats.lock();
-                p_tick.status = player.playlist.status().as_u32();
+                p_tick.status = player.playlist.read().status().as_u32();
                 // branch to auto-start playing if status is "stopped"(not paused) and playlist is not empty anymore
-                if player.playlist.status() == Status::Stopped {
-                    if player.playlist.is_empty() {
+                if player.playlist.read().status() == Status::Stopped {
+                    if player.playlist.read().is_empty() || player.is_user_stopped() {
                         continue;
                     }
                     debug!(
                         "current track index: {:?}",
-                        player.playlist.get_current_track_index()
+                        player.playlist.read().get_current_track_index()
                     );
-                    player.playlist.clear_current_track();
-                    player.playlist.proceed_false();
+                    player.playlist.write().clear_current_track();
+                    player.playlist.write().proceed_false();
                     player.start_play();
                     continue;
                 }
@@ -299,11 +421,12 @@ fn player_loop(
                     player.mpris_update_progress(&p_tick.progress);
                 }
                 if player.current_track_updated {
-                    p_tick.current_track_index = player.playlist.get_current_track_index() as u32;
+                    p_tick.current_track_index =
+                        player.playlist.read().get_current_track_index() as u32;
                     p_tick.current_track_updated = player.current_track_updated;
                     player.current_track_updated = false;
                 }
-                if let Some(track) = player.playlist.current_track() {
+                if let Some(track) = player.playlist.read().current_track() {
                     // if only one backend is enabled, rust will complain that it is the only thing that happens
                     #[allow(irrefutable_let_patterns)]
                     if MediaType::LiveRadio == track.media_type {
@@ -341,7 +464,7 @@ fn player_loop(
                 info!("player toggled pause");
                 player.toggle_pause();
                 let mut p_tick = playerstats.lock();
-                p_tick.status = player.playlist.status().as_u32();
+                p_tick.status = player.playlist.read().status().as_u32();
             }
             PlayerCmd::VolumeDown => {
                 info!("before volumedown: {}", player.volume());
@@ -361,11 +484,55 @@ fn player_loop(
                 p_tick.volume = new_volume;
                 player.mpris_volume_update();
             }
+            PlayerCmd::SetVolume(volume) => {
+                let new_volume = player.set_volume(volume);
+                player.config.write().settings.player.volume = new_volume;
+                info!("after set volume: {}", new_volume);
+                let mut p_tick = playerstats.lock();
+                p_tick.volume = new_volume;
+                player.mpris_volume_update();
+            }
             PlayerCmd::Pause => {
                 player.pause();
+                let mut p_tick = playerstats.lock();
+                p_tick.status = player.playlist.read().status().as_u32();
             }
             PlayerCmd::Play => {
-                player.resume();
+                player.play();
+                let mut p_tick = playerstats.lock();
+                p_tick.status = player.playlist.read().status().as_u32();
+            }
+            PlayerCmd::PlaylistAddTrack(info) => {
+                if let Err(err) = player.playlist_add_tracks(&info) {
+                    error!("Error adding tracks to playlist: {err:#?}");
+                }
+            }
+            PlayerCmd::PlaylistRemoveTrack(info) => {
+                if let Err(err) = player.playlist_remove_tracks(info) {
+                    error!("Error removing tracks from playlist: {err:#?}");
+                }
+            }
+            PlayerCmd::PlaylistSwapTrack(info) => {
+                if let Err(err) = player.playlist_swap_tracks(info) {
+                    error!("Error swapping tracks in playlist: {err:#?}");
+                }
+            }
+            PlayerCmd::PlaylistMoveTrack(info) => {
+                if let Err(err) = player.playlist_move_track(info) {
+                    error!("Error moving track in playlist: {err:#?}");
+                }
+            }
+            PlayerCmd::PlaylistClear => {
+                player.playlist_clear();
+            }
+            PlayerCmd::PlaylistShuffle => {
+                player.playlist_shuffle();
+            }
+            PlayerCmd::PlaylistRemoveDeletedTracks => {
+                player.playlist_remove_deleted_tracks();
+            }
+            PlayerCmd::PlaylistSetLoopMode(mode) => {
+                player.playlist_set_loop_mode(mode);
             }
         }
     }
@@ -446,6 +613,19 @@ fn execute_action(action: cli::Action, config: &ServerOverlay) -> Result<()> {
                 utils::get_app_config_path().context("getting app-config-path")?;
             podcast::export_to_opml(&config_dir_path, &path).context("export opml")?;
         }
+        cli::Action::AnalyzeLoudness { all } => {
+            let mut db = DataBase::new(config)?;
+            for dir in &config.settings.player.music_dirs {
+                if let Ok(Err(err)) = db.sync_database(dir).join() {
+                    bail!("Error scanning \"{}\": {err}", dir.display());
+                }
+            }
+
+            loudness::analyze_library(&mut db, all, |idx, total, file| {
+                println!("[{}/{total}] {file}", idx + 1);
+            })
+            .context("analyze loudness")?;
+        }
     };
 
     Ok(())
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use termusiclib::config::v2::server::ComSettings;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Identity, ServerTlsConfig};
+
+#[cfg(unix)]
+use tokio_stream::wrappers::UnixListenerStream;
+
+/// The path of the bound Unix domain socket, to remove it again on exit
+#[cfg(unix)]
+static BOUND_SOCKET: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+
+/// The bound listener the gRPC server will accept connections from
+pub enum Incoming {
+    Tcp(TcpIncoming),
+    #[cfg(unix)]
+    Unix(UnixListenerStream),
+}
+
+/// Bind the transport configured in `com`, either a Unix domain socket or TCP
+///
+/// # Errors
+///
+/// - if binding the address or socket fails
+/// - if a socket is configured on a platform that does not support them
+pub async fn bind(com: &ComSettings) -> Result<Incoming> {
+    if let Some(socket_path) = com.resolved_socket_path()? {
+        return bind_unix(&socket_path);
+    }
+
+    let addr = std::net::SocketAddr::from(com.clone());
+    // workaround to print address once sever "actually" is started and address is known
+    // see https://github.com/hyperium/tonic/issues/351
+    let tcp_listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Error binding address: {}", addr))?;
+    info!("Server listening on {}", tcp_listener.local_addr().unwrap());
+    let tcp_stream =
+        TcpIncoming::from_listener(tcp_listener, true, None).map_err(|e| anyhow::anyhow!(e))?;
+
+    Ok(Incoming::Tcp(tcp_stream))
+}
+
+/// Load the TLS identity configured in `com`, if any
+///
+/// TLS is only used for TCP, a Unix domain socket is already restricted to the current user.
+///
+/// # Errors
+///
+/// - if no key is configured
+/// - if reading the certificate or key fails
+pub fn tls_config(com: &ComSettings) -> Result<Option<ServerTlsConfig>> {
+    let Some(tls) = &com.tls else {
+        return Ok(None);
+    };
+
+    if com.socket_path.is_some() {
+        warn!("TLS is configured, but not used for Unix domain sockets");
+        return Ok(None);
+    }
+
+    let key_path = tls
+        .key
+        .as_ref()
+        .context("TLS is enabled, but \"com.tls.key\" is not set")?;
+    let cert = std::fs::read(&tls.cert)
+        .with_context(|| format!("Error reading TLS certificate {}", tls.cert.display()))?;
+    let key = std::fs::read(key_path)
+        .with_context(|| format!("Error reading TLS key {}", key_path.display()))?;
+
+    Ok(Some(
+        ServerTlsConfig::new().identity(Identity::from_pem(cert, key)),
+    ))
+}
+
+/// Remove the Unix domain socket bound by [`bind`], if any, as closing the listener does not remove the file
+pub fn remove_socket() {
+    #[cfg(unix)]
+    if let Some(path) = BOUND_SOCKET.get() {
+        if let Err(err) = std::fs::remove_file(path) {
+            warn!("Error removing socket {}: {err}", path.display());
+        }
+    }
+}
+
+/// Bind a Unix domain socket at `path`, only accessible by the current user
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path) -> Result<Incoming> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    if path.exists() {
+        // a socket file is not removed if the server did not exit cleanly, but dont take over a running server
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            anyhow::bail!(
+                "Another server is already listening on socket {}",
+                path.display()
+            );
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("Error removing stale socket {}", path.display()))?;
+    }
+
+    let parent = path
+        .parent()
+        .filter(|v| !v.as_os_str().is_empty())
+        .unwrap_or(std::path::Path::new("."));
+    std::fs::create_dir_all(parent)?;
+
+    // the socket is bound in a private directory and only moved into place once its permissions are set,
+    // so that it is never accessible by other users in between
+    let private_dir = parent.join(format!(".termusic-bind-{}", std::process::id()));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private_dir)
+        .with_context(|| format!("Error creating directory {}", private_dir.display()))?;
+    let res = bind_unix_private(path, &private_dir);
+    let _ = std::fs::remove_dir(&private_dir);
+    let listener = res?;
+
+    let _ = BOUND_SOCKET.set(path.to_path_buf());
+    info!("Server listening on {}", path.display());
+
+    Ok(Incoming::Unix(UnixListenerStream::new(listener)))
+}
+
+/// Bind a Unix domain socket in `private_dir`, restrict its permissions and move it to `path`
+#[cfg(unix)]
+fn bind_unix_private(
+    path: &std::path::Path,
+    private_dir: &std::path::Path,
+) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let private_path = private_dir.join("socket");
+    let res = tokio::net::UnixListener::bind(&private_path)
+        .with_context(|| format!("Error binding socket: {}", private_path.display()))
+        .and_then(|listener| {
+            std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))
+                .with_context(|| {
+                    format!(
+                        "Error setting permissions on socket {}",
+                        private_path.display()
+                    )
+                })?;
+            std::fs::rename(&private_path, path)
+                .with_context(|| format!("Error moving socket to {}", path.display()))?;
+            Ok(listener)
+        });
+    if res.is_err() {
+        let _ = std::fs::remove_file(&private_path);
+    }
+
+    res
+}
+
+#[cfg(not(unix))]
+fn bind_unix(path: &std::path::Path) -> Result<Incoming> {
+    anyhow::bail!(
+        "Unix domain sockets are not supported on this platform, cannot bind {}",
+        path.display()
+    );
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use termusiclib::config::v2::server::ComSettings;
+
+    use super::{bind, remove_socket, Incoming};
+
+    #[tokio::test]
+    async fn should_bind_private_socket() {
+        let dir = std::env::temp_dir().join(format!("termusic-transport-{}", std::process::id()));
+        let path = dir.join("server.sock");
+        let com = ComSettings {
+            socket_path: Some(path.clone()),
+            ..ComSettings::default()
+        };
+
+        let incoming = bind(&com).await.unwrap();
+        assert!(matches!(incoming, Incoming::Unix(_)));
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // only the socket is left, not the directory it was bound in
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // a running server is not taken over
+        assert!(bind(&com).await.is_err());
+
+        remove_socket();
+        assert!(!path.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -80,7 +80,7 @@ impl std::fmt::Display for Backend {
 }
 
 /// Subcommands for the binary
-#[derive(Subcommand, Debug)]
+#[derive(Subcommand, Debug, PartialEq)]
 pub enum Action {
     /// Import Podcast feeds from a opml file.
     Export {
@@ -92,6 +92,88 @@ pub enum Action {
         #[arg(value_name = "FILE")]
         file: PathBuf,
     },
+    /// Control the running server without starting the TUI.
+    #[command(flatten)]
+    Control(ControlAction),
+}
+
+/// Subcommands that control the running server and exit
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum ControlAction {
+    /// Resume playback if paused.
+    Play,
+    /// Pause playback if playing.
+    Pause,
+    /// Toggle between playing and paused.
+    Toggle,
+    /// Skip to the next track.
+    Next,
+    /// Skip to the previous track.
+    Prev,
+    /// Seek in the current track, to a absolute position or relatively with a leading `+` or `-`.
+    Seek {
+        #[arg(value_name = "SECONDS", allow_hyphen_values = true)]
+        position: ValueChange,
+    },
+    /// Set the volume, to a absolute value or relatively with a leading `+` or `-`.
+    Volume {
+        #[arg(value_name = "VOLUME", allow_hyphen_values = true)]
+        volume: ValueChange,
+    },
+    /// Print the player status and the current track.
+    Status {
+        /// Print the status as JSON.
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+        /// Keep running and print a new line whenever the status changes.
+        #[arg(long)]
+        follow: bool,
+        /// Print a single line with the given format,
+        /// available placeholders: `{status}`, `{title}`, `{artist}`, `{album}`, `{genre}`, `{location}`,
+        /// `{radio_title}`, `{position}`, `{duration}`, `{volume}`, `{speed}`, `{gapless}`, `{index}`.
+        #[arg(long)]
+        format: Option<String>,
+    },
+    /// Append files, directories, playlists or urls to the playlist.
+    Add {
+        #[arg(value_name = "PATH", required = true)]
+        items: Vec<String>,
+    },
+    /// List the audio output devices, or move playback to the device with the given name.
+    OutputDevice {
+        /// Name of the device to switch to, a empty name for the default device.
+        #[arg(value_name = "NAME")]
+        device: Option<String>,
+    },
+}
+
+/// A absolute value, or a change relative to the current value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueChange {
+    Absolute(u64),
+    Relative(i64),
+}
+
+impl ValueChange {
+    /// Apply this change to `current`, saturating at `0`
+    pub fn apply(self, current: u64) -> u64 {
+        match self {
+            Self::Absolute(v) => v,
+            Self::Relative(v) => current.saturating_add_signed(v),
+        }
+    }
+}
+
+impl std::str::FromStr for ValueChange {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with(['+', '-']) {
+            Ok(Self::Relative(s.parse()?))
+        } else {
+            Ok(Self::Absolute(s.parse()?))
+        }
+    }
 }
 
 const DEFAULT_LOGFILE_FILENAME: &str = "termusic-tui.log";
@@ -127,3 +209,55 @@ pub struct LogOptions {
 fn default_logfile_path() -> PathBuf {
     std::env::temp_dir().join(DEFAULT_LOGFILE_FILENAME)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Args, ControlAction, ValueChange};
+    use clap::Parser;
+
+    #[test]
+    fn should_parse_value_change() {
+        assert_eq!("40".parse(), Ok(ValueChange::Absolute(40)));
+        assert_eq!("+10".parse(), Ok(ValueChange::Relative(10)));
+        assert_eq!("-5".parse(), Ok(ValueChange::Relative(-5)));
+        assert!("ten".parse::<ValueChange>().is_err());
+
+        assert_eq!(ValueChange::Absolute(40).apply(70), 40);
+        assert_eq!(ValueChange::Relative(10).apply(70), 80);
+        assert_eq!(ValueChange::Relative(-10).apply(5), 0);
+    }
+
+    #[test]
+    fn should_parse_control_subcommands() {
+        let args = Args::try_parse_from(["termusic", "seek", "-10"]).unwrap();
+        assert_eq!(
+            args.action,
+            Some(Action::Control(ControlAction::Seek {
+                position: ValueChange::Relative(-10)
+            }))
+        );
+
+        let args = Args::try_parse_from(["termusic", "status", "--json"]).unwrap();
+        assert_eq!(
+            args.action,
+            Some(Action::Control(ControlAction::Status {
+                json: true,
+                follow: false,
+                format: None
+            }))
+        );
+        assert!(
+            Args::try_parse_from(["termusic", "status", "--json", "--format", "{title}"]).is_err()
+        );
+
+        assert!(Args::try_parse_from(["termusic", "add"]).is_err());
+
+        let args = Args::try_parse_from(["termusic", "output-device", "HDMI"]).unwrap();
+        assert_eq!(
+            args.action,
+            Some(Action::Control(ControlAction::OutputDevice {
+                device: Some("HDMI".to_string())
+            }))
+        );
+    }
+}
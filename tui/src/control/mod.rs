@@ -0,0 +1,140 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use termusiclib::player::{PlaylistAddTrack, PlaylistTrackSource};
+use termusiclib::track::Track;
+use termusiclib::utils::{filetype_supported, is_playlist, playlist_get_vec};
+
+use crate::cli::ControlAction;
+use crate::ui::Playback;
+
+mod status;
+
+/// Execute a single [`ControlAction`] against the server and print the result
+pub async fn execute(action: ControlAction, playback: &mut Playback) -> Result<()> {
+    match action {
+        ControlAction::Play => {
+            let status = playback.play().await?;
+            println!("{status}");
+        }
+        ControlAction::Pause => {
+            let status = playback.pause().await?;
+            println!("{status}");
+        }
+        ControlAction::Toggle => {
+            let status = playback.toggle_pause().await?;
+            println!("{status}");
+        }
+        ControlAction::Next => playback.skip_next().await?,
+        ControlAction::Prev => playback.skip_previous().await?,
+        ControlAction::Seek { position } => {
+            let current = playback
+                .get_progress()
+                .await?
+                .progress
+                .and_then(|v| v.position)
+                .map_or(0, |v| Duration::from(v).as_secs());
+            let progress = playback
+                .seek_to(Duration::from_secs(position.apply(current)))
+                .await?;
+            println!(
+                "{}",
+                Track::duration_formatted_short(&progress.position.unwrap_or_default())
+            );
+        }
+        ControlAction::Volume { volume } => {
+            let current = playback.get_progress().await?.volume;
+            let new = clamp_volume(volume.apply(u64::from(current)));
+            let volume = playback.set_volume(new).await?;
+            println!("{volume}");
+        }
+        ControlAction::Status {
+            json,
+            follow,
+            format,
+        } => status::print_status(playback, json, follow, format.as_deref()).await?,
+        ControlAction::Add { items } => {
+            let mut tracks = Vec::new();
+            for item in &items {
+                tracks.extend(sources_from_arg(item)?);
+            }
+            let count = tracks.len();
+            playback
+                .add_to_playlist(PlaylistAddTrack {
+                    at_index: u32::MAX,
+                    tracks,
+                })
+                .await?;
+            println!("Added {count} tracks");
+        }
+        ControlAction::OutputDevice { device } => {
+            let reply = match device {
+                Some(device) => playback.set_output_device(device).await?,
+                None => playback.list_output_devices().await?,
+            };
+            // mark the configured device, nothing is marked for the default device
+            for device in &reply.devices {
+                let marker = if *device == reply.current { '*' } else { ' ' };
+                println!("{marker} {device}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Clamp the volume to what the server accepts
+fn clamp_volume(volume: u64) -> u16 {
+    u16::try_from(volume.min(100)).unwrap_or(100)
+}
+
+/// Resolve a cli argument to the tracks to add
+///
+/// Directories add all their supported files (not recursive), playlists add all their entries.
+fn sources_from_arg(item: &str) -> Result<Vec<PlaylistTrackSource>> {
+    if item.starts_with("http") {
+        return Ok(vec![PlaylistTrackSource::Url(item.to_string())]);
+    }
+
+    let path = crate::get_path(Path::new(item))?;
+    let path_str = path.to_string_lossy().to_string();
+
+    if path.is_dir() {
+        let mut children: Vec<String> = std::fs::read_dir(&path)
+            .with_context(|| format!("reading directory {}", path.display()))?
+            .filter_map(std::result::Result::ok)
+            .map(|v| v.path())
+            .filter(|v| v.is_file())
+            .map(|v| v.to_string_lossy().to_string())
+            .filter(|v| filetype_supported(v))
+            .collect();
+        children.sort();
+
+        return Ok(children
+            .into_iter()
+            .map(PlaylistTrackSource::Path)
+            .collect());
+    }
+
+    if is_playlist(&path_str) {
+        let entries = playlist_get_vec(&path_str)
+            .with_context(|| format!("reading playlist {}", path.display()))?;
+        return Ok(entries
+            .into_iter()
+            .map(|v| {
+                if v.starts_with("http") {
+                    PlaylistTrackSource::Url(v)
+                } else {
+                    PlaylistTrackSource::Path(v)
+                }
+            })
+            .collect());
+    }
+
+    if !path.exists() {
+        anyhow::bail!("{} does not exist", path.display());
+    }
+
+    Ok(vec![PlaylistTrackSource::Path(path_str)])
+}
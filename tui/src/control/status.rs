@@ -0,0 +1,483 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use termusiclib::player::{GetProgressResponse, PlaylistTrackSource, UpdateEvents};
+use termusiclib::track::Track;
+use termusicplayback::Status;
+use tokio::time::MissedTickBehavior;
+use tokio_stream::StreamExt;
+
+use crate::ui::Playback;
+
+/// Format used for `--follow` if no `--format` is given
+const DEFAULT_FOLLOW_FORMAT: &str = "{status}: {artist} - {title} [{position}/{duration}]";
+/// How often the progress is polled with `--follow`, as there are no events for the position
+const FOLLOW_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Print the current player status, either human-readable, as JSON or with a custom format
+///
+/// With `follow`, a new line is printed whenever the output would change, until the server exits.
+pub async fn print_status(
+    playback: &mut Playback,
+    json: bool,
+    follow: bool,
+    format: Option<&str>,
+) -> Result<()> {
+    let format = match format {
+        Some(format) => Some(format.parse::<StatusFormat>()?),
+        None if follow && !json => Some(DEFAULT_FOLLOW_FORMAT.parse::<StatusFormat>()?),
+        None => None,
+    };
+    let render = |info: &StatusInfo| -> Result<String> {
+        if let Some(format) = &format {
+            Ok(format.render(info))
+        } else if follow {
+            // a single line per change, to be easily consumed line by line
+            Ok(serde_json::to_string(&info.to_json())?)
+        } else {
+            Ok(serde_json::to_string_pretty(&info.to_json())?)
+        }
+    };
+
+    if !follow {
+        let info = StatusInfo::fetch(playback).await?;
+        if json || format.is_some() {
+            println!("{}", render(&info)?);
+        } else {
+            info.print_human();
+        }
+
+        return Ok(());
+    }
+
+    // subscribe first, to not miss any changes between fetching and subscribing
+    let mut stream = playback.subscribe_to_stream_updates().await?;
+    let mut info = StatusInfo::fetch(playback).await?;
+    let mut last_line = render(&info)?;
+    println!("{last_line}");
+
+    let mut progress_interval = tokio::time::interval(FOLLOW_PROGRESS_INTERVAL);
+    progress_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // the first tick completes immediately, but the progress was just fetched
+    progress_interval.tick().await;
+
+    loop {
+        tokio::select! {
+            ev = stream.next() => {
+                let Some(ev) = ev else {
+                    break;
+                };
+                let ev = UpdateEvents::try_from(ev?)
+                    .context("Conversion from StreamUpdates to UpdateEvents failed!")?;
+
+                if !info.apply_event(ev) {
+                    info = StatusInfo::fetch(playback).await?;
+                }
+            }
+            _ = progress_interval.tick() => {
+                info.apply_progress(playback.get_progress().await?);
+            }
+        }
+
+        let line = render(&info)?;
+        if line != last_line {
+            println!("{line}");
+            last_line = line;
+        }
+    }
+
+    anyhow::bail!("Server closed the connection");
+}
+
+/// Everything `status` can print
+#[derive(Debug, Clone, Default, PartialEq)]
+struct StatusInfo {
+    status: Status,
+    position: Option<Duration>,
+    duration: Option<Duration>,
+    volume: u32,
+    /// Speed in tenths, `10` is `1.0x`
+    speed: i32,
+    gapless: bool,
+    current_track_index: u32,
+    track: Option<termusiclib::player::Track>,
+    radio_title: Option<String>,
+}
+
+impl StatusInfo {
+    /// Get the full status from the server
+    async fn fetch(playback: &mut Playback) -> Result<Self> {
+        let progress = playback.get_progress().await?;
+        let current = playback.get_current_track().await?;
+
+        let mut info = Self {
+            duration: current
+                .track
+                .as_ref()
+                .and_then(|v| v.duration)
+                .map(Duration::from),
+            current_track_index: current.current_track_index,
+            track: current.track,
+            ..Default::default()
+        };
+        info.apply_progress(progress);
+
+        Ok(info)
+    }
+
+    /// Apply a polled progress, which also contains the other player values
+    fn apply_progress(&mut self, progress: GetProgressResponse) {
+        let player_time = progress.progress.unwrap_or_default();
+
+        self.status = Status::from_u32(progress.status);
+        self.position = player_time.position.map(Duration::from);
+        if let Some(duration) = player_time.total_duration {
+            self.duration = Some(duration.into());
+        }
+        self.volume = progress.volume;
+        self.speed = progress.speed;
+        self.gapless = progress.gapless;
+        self.radio_title = Some(progress.radio_title).filter(|v| !v.is_empty());
+    }
+
+    /// Apply a stream event, returns `false` if the full status needs to be fetched again
+    fn apply_event(&mut self, ev: UpdateEvents) -> bool {
+        match ev {
+            UpdateEvents::MissedEvents { .. } => return false,
+            UpdateEvents::VolumeChanged { volume } => self.volume = u32::from(volume),
+            UpdateEvents::SpeedChanged { speed } => self.speed = speed,
+            UpdateEvents::PlayStateChanged { playing } => self.status = Status::from_u32(playing),
+            UpdateEvents::GaplessChanged { gapless } => self.gapless = gapless,
+            UpdateEvents::TrackChanged(info) => {
+                self.current_track_index = info.current_track_index;
+                if info.current_track_updated || info.track.is_some() {
+                    self.duration = info.track.as_ref().and_then(|v| v.duration).map(Into::into);
+                    self.track = info.track;
+                    self.radio_title = None;
+                }
+                if let Some(progress) = info.progress {
+                    self.position = progress.position;
+                    if progress.total_duration.is_some() {
+                        self.duration = progress.total_duration;
+                    }
+                }
+                if let Some(title) = info.title.filter(|v| !v.is_empty()) {
+                    self.radio_title = Some(title);
+                }
+            }
+            UpdateEvents::PlaylistChanged(_)
+            | UpdateEvents::LoopModeChanged { .. }
+            | UpdateEvents::PodcastFeedChanged { .. }
+            | UpdateEvents::PodcastDownload(_) => (),
+        }
+
+        true
+    }
+
+    fn location(&self) -> Option<String> {
+        let source = PlaylistTrackSource::try_from(self.track.as_ref()?.id.clone()?).ok()?;
+        match source {
+            PlaylistTrackSource::Path(v)
+            | PlaylistTrackSource::Url(v)
+            | PlaylistTrackSource::PodcastUrl(v) => Some(v),
+        }
+    }
+
+    /// Get the title of the track, falling back to the radio title or file name
+    fn title(&self) -> Option<String> {
+        if let Some(title) = self.track.as_ref().and_then(|v| v.title()) {
+            return Some(title.to_string());
+        }
+        if let Some(title) = &self.radio_title {
+            return Some(title.clone());
+        }
+
+        self.location().map(|v| {
+            Path::new(&v)
+                .file_name()
+                .map_or(v.clone(), |v| v.to_string_lossy().to_string())
+        })
+    }
+
+    fn speed_formatted(&self) -> String {
+        format!("{:.1}", f64::from(self.speed) / 10.0)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": self.status.to_string().to_lowercase(),
+            "position": self.position.map(|v| v.as_secs_f64()),
+            "duration": self.duration.map(|v| v.as_secs_f64()),
+            "volume": self.volume,
+            "speed": f64::from(self.speed) / 10.0,
+            "gapless": self.gapless,
+            "current_track_index": self.current_track_index,
+            "track": self.track.as_ref().map(|track| serde_json::json!({
+                "location": self.location(),
+                "title": track.title(),
+                "artist": track.artist(),
+                "album": track.album(),
+                "genre": track.genre(),
+            })),
+            "radio_title": self.radio_title,
+        })
+    }
+
+    fn print_human(&self) {
+        println!("Status: {}", self.status);
+        if let Some(track) = &self.track {
+            println!("Title: {}", track.title().unwrap_or("Unknown Title"));
+            println!("Artist: {}", track.artist().unwrap_or("Unknown Artist"));
+            println!("Album: {}", track.album().unwrap_or("Unknown Album"));
+        }
+        if let Some(radio_title) = &self.radio_title {
+            println!("Radio Title: {radio_title}");
+        }
+        println!(
+            "Position: {} / {}",
+            Track::duration_formatted_short(&self.position.unwrap_or_default()),
+            Track::duration_formatted_short(&self.duration.unwrap_or_default())
+        );
+        println!("Volume: {}", self.volume);
+        println!("Speed: {}", self.speed_formatted());
+        println!("Gapless: {}", self.gapless);
+    }
+}
+
+/// A placeholder in a [`StatusFormat`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Status,
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Location,
+    RadioTitle,
+    Position,
+    Duration,
+    Volume,
+    Speed,
+    Gapless,
+    Index,
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "status" => Self::Status,
+            "title" => Self::Title,
+            "artist" => Self::Artist,
+            "album" => Self::Album,
+            "genre" => Self::Genre,
+            "location" => Self::Location,
+            "radio_title" => Self::RadioTitle,
+            "position" => Self::Position,
+            "duration" => Self::Duration,
+            "volume" => Self::Volume,
+            "speed" => Self::Speed,
+            "gapless" => Self::Gapless,
+            "index" => Self::Index,
+            _ => anyhow::bail!("Unknown format placeholder \"{{{s}}}\""),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FormatPart {
+    Text(String),
+    Field(Field),
+}
+
+/// A parsed `--format` string, placeholders are written as `{name}`, literal braces as `{{` and `}}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StatusFormat(Vec<FormatPart>);
+
+impl FromStr for StatusFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            match char {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(char) => name.push(char),
+                            None => anyhow::bail!("Unclosed format placeholder \"{{{name}\""),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(FormatPart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(FormatPart::Field(name.parse()?));
+                }
+                char => text.push(char),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(FormatPart::Text(text));
+        }
+
+        Ok(Self(parts))
+    }
+}
+
+impl StatusFormat {
+    /// Render `info` with this format, unknown values are empty
+    fn render(&self, info: &StatusInfo) -> String {
+        let track = info.track.as_ref();
+        let mut out = String::new();
+
+        for part in &self.0 {
+            match part {
+                FormatPart::Text(text) => out.push_str(text),
+                FormatPart::Field(field) => {
+                    let value = match field {
+                        Field::Status => info.status.to_string(),
+                        Field::Title => info.title().unwrap_or_default(),
+                        Field::Artist => track
+                            .and_then(|v| v.artist())
+                            .unwrap_or_default()
+                            .to_string(),
+                        Field::Album => track
+                            .and_then(|v| v.album())
+                            .unwrap_or_default()
+                            .to_string(),
+                        Field::Genre => track
+                            .and_then(|v| v.genre())
+                            .unwrap_or_default()
+                            .to_string(),
+                        Field::Location => info.location().unwrap_or_default(),
+                        Field::RadioTitle => info.radio_title.clone().unwrap_or_default(),
+                        Field::Position => {
+                            Track::duration_formatted_short(&info.position.unwrap_or_default())
+                        }
+                        Field::Duration => {
+                            Track::duration_formatted_short(&info.duration.unwrap_or_default())
+                        }
+                        Field::Volume => info.volume.to_string(),
+                        Field::Speed => info.speed_formatted(),
+                        Field::Gapless => info.gapless.to_string(),
+                        Field::Index => info.current_track_index.to_string(),
+                    };
+                    out.push_str(&value);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use termusiclib::player::{GetProgressResponse, PlayerTime, TrackChangedInfo, UpdateEvents};
+    use termusicplayback::Status;
+
+    use super::{Field, FormatPart, StatusFormat, StatusInfo};
+
+    #[test]
+    fn should_parse_format() {
+        let format: StatusFormat = "{artist} - {title} {{x}}".parse().unwrap();
+        assert_eq!(
+            format.0,
+            vec![
+                FormatPart::Field(Field::Artist),
+                FormatPart::Text(" - ".to_string()),
+                FormatPart::Field(Field::Title),
+                FormatPart::Text(" {x}".to_string()),
+            ]
+        );
+
+        assert!("{unknown}".parse::<StatusFormat>().is_err());
+        assert!("{title".parse::<StatusFormat>().is_err());
+    }
+
+    #[test]
+    fn should_render_format() {
+        let info = StatusInfo {
+            status: Status::Running,
+            position: Some(Duration::from_secs(61)),
+            duration: Some(Duration::from_secs(3 * 60)),
+            volume: 40,
+            radio_title: Some("Some Radio".to_string()),
+            ..Default::default()
+        };
+        let format: StatusFormat = "{status}: {title} [{position}/{duration}] {volume}%"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            format.render(&info),
+            "Running: Some Radio [01:01/03:00] 40%"
+        );
+    }
+
+    #[test]
+    fn should_apply_events() {
+        let mut info = StatusInfo::default();
+
+        assert!(info.apply_event(UpdateEvents::VolumeChanged { volume: 20 }));
+        assert!(info.apply_event(UpdateEvents::PlayStateChanged {
+            playing: Status::Paused.as_u32()
+        }));
+        assert!(
+            info.apply_event(UpdateEvents::TrackChanged(TrackChangedInfo {
+                current_track_index: 2,
+                current_track_updated: true,
+                title: None,
+                progress: None,
+                track: None,
+            }))
+        );
+        assert_eq!(info.volume, 20);
+        assert_eq!(info.status, Status::Paused);
+        assert_eq!(info.current_track_index, 2);
+
+        assert!(!info.apply_event(UpdateEvents::MissedEvents { amount: 1 }));
+    }
+
+    #[test]
+    fn should_apply_progress() {
+        let mut info = StatusInfo {
+            duration: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+
+        info.apply_progress(GetProgressResponse {
+            progress: Some(PlayerTime {
+                position: Some(Duration::from_secs(5).into()),
+                total_duration: None,
+            }),
+            status: Status::Running.as_u32(),
+            volume: 30,
+            ..Default::default()
+        });
+        assert_eq!(info.position, Some(Duration::from_secs(5)));
+        // kept, as the progress does not know it yet
+        assert_eq!(info.duration, Some(Duration::from_secs(60)));
+        assert_eq!(info.status, Status::Running);
+        assert_eq!(info.volume, 30);
+    }
+}
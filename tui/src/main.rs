@@ -27,6 +27,7 @@
  * SOFTWARE.
  */
 mod cli;
+mod control;
 mod logger;
 mod ui;
 
@@ -38,17 +39,19 @@ use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use std::{error::Error, path::Path};
 use termusiclib::config::v2::server::config_extra::ServerConfigVersionedDefaulted;
-use termusiclib::config::v2::server::ScanDepth;
+use termusiclib::config::v2::server::{ComSettings, ScanDepth};
 use termusiclib::config::v2::tui::config_extra::TuiConfigVersionedDefaulted;
 use termusiclib::config::{
     new_shared_server_settings, new_shared_tui_settings, ServerOverlay, SharedServerSettings,
     SharedTuiSettings, TuiOverlay,
 };
+use termusiclib::player::auth::ClientTokenInterceptor;
 use termusiclib::player::music_player_client::MusicPlayerClient;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint};
 
 use sysinfo::{Pid, ProcessStatus, System};
 use termusiclib::{podcast, utils};
-use ui::UI;
+use ui::{PlayerClient, UI};
 
 #[macro_use]
 extern crate log;
@@ -82,7 +85,7 @@ async fn actual_main() -> Result<()> {
     let config = get_config(&args)?;
 
     if let Some(action) = args.action {
-        return execute_action(action, &config);
+        return execute_action(action, &config).await;
     }
 
     // launch the daemon if it isn't already
@@ -153,14 +156,17 @@ async fn actual_main() -> Result<()> {
     info!("Waiting until connected");
 
     let client = {
-        let addr = {
-            let config_read = config.tui.read();
-            SocketAddr::from(*config_read.settings.get_com().ok_or(anyhow::anyhow!(
+        let com = config
+            .tui
+            .read()
+            .settings
+            .get_com()
+            .ok_or(anyhow::anyhow!(
                 "Expected tui-com settings to be resolved at this point"
-            ))?)
-        };
+            ))?
+            .clone();
 
-        wait_till_connected(addr, pid).await?
+        wait_till_connected(&com, pid).await?
     };
     info!("Connected!");
 
@@ -179,10 +185,10 @@ const WAIT_INTERVAL: Duration = Duration::from_millis(100);
 /// - tonic errors anything other than `ConnectionRefused`
 /// - given PID does not exist anymore
 /// - timeout of [`WAIT_TIMEOUT`] reached
-async fn wait_till_connected(
-    socket: SocketAddr,
-    pid: u32,
-) -> Result<MusicPlayerClient<tonic::transport::Channel>> {
+async fn wait_till_connected(com: &ComSettings, pid: u32) -> Result<PlayerClient> {
+    let socket_path = com.resolved_socket_path()?;
+    let tcp_endpoint = tcp_endpoint(com)?;
+    let interceptor = ClientTokenInterceptor::new(com.token.as_deref())?;
     let mut sys = sysinfo::System::new();
     let sys_pid = Pid::from_u32(pid);
     let start_time = Instant::now();
@@ -203,11 +209,17 @@ async fn wait_till_connected(
             anyhow::bail!("Process {pid} exited before being able to connect!");
         }
 
-        match MusicPlayerClient::connect(format!("http://{socket}")).await {
+        let res = connect_channel(socket_path.as_deref(), &tcp_endpoint).await?;
+
+        match res {
             Err(err) => {
                 // downcast "tonic::transport::Error" to a "std::io::Error"(kind: Os)
                 if let Some(os_err) = find_source::<std::io::Error>(&err) {
-                    if os_err.kind() == std::io::ErrorKind::ConnectionRefused {
+                    // "NotFound" happens for a unix socket that is not created yet
+                    if matches!(
+                        os_err.kind(),
+                        std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotFound
+                    ) {
                         debug!("Connection refused found!");
                         tokio::time::sleep(WAIT_INTERVAL).await;
                         continue;
@@ -217,11 +229,75 @@ async fn wait_till_connected(
                 // return the error and stop if it is anything other than "Connection Refused"
                 anyhow::bail!(err);
             }
-            Ok(client) => return Ok(client),
+            Ok(channel) => return Ok(MusicPlayerClient::with_interceptor(channel, interceptor)),
         }
     }
 }
 
+/// Connect to the server once, without waiting for it to start
+async fn connect(com: &ComSettings) -> Result<PlayerClient> {
+    let socket_path = com.resolved_socket_path()?;
+    let tcp_endpoint = tcp_endpoint(com)?;
+    let interceptor = ClientTokenInterceptor::new(com.token.as_deref())?;
+    let channel = connect_channel(socket_path.as_deref(), &tcp_endpoint).await??;
+
+    Ok(MusicPlayerClient::with_interceptor(channel, interceptor))
+}
+
+/// Connect over the Unix domain socket at `socket_path` if set, otherwise to `tcp_endpoint`
+///
+/// The outer error is for unsupported configurations, the inner error is the connection error.
+async fn connect_channel(
+    socket_path: Option<&Path>,
+    tcp_endpoint: &Endpoint,
+) -> Result<Result<Channel, tonic::transport::Error>> {
+    Ok(match socket_path {
+        #[cfg(unix)]
+        Some(path) => connect_unix(path.to_path_buf()).await,
+        #[cfg(not(unix))]
+        Some(path) => bail!(
+            "Unix domain sockets are not supported on this platform, cannot connect to {}",
+            path.display()
+        ),
+        None => tcp_endpoint.connect().await,
+    })
+}
+
+/// Create the [`Endpoint`] to connect to the server over TCP, with TLS if configured
+fn tcp_endpoint(com: &ComSettings) -> Result<Endpoint> {
+    let addr = SocketAddr::from(com.clone());
+    let Some(tls) = &com.tls else {
+        return Ok(Endpoint::from_shared(format!("http://{addr}"))?);
+    };
+
+    let cert = std::fs::read(&tls.cert)
+        .with_context(|| format!("Error reading TLS certificate {}", tls.cert.display()))?;
+    let domain = tls
+        .domain
+        .clone()
+        .unwrap_or_else(|| com.address.to_string());
+    let tls_config = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(cert))
+        .domain_name(domain);
+
+    Ok(Endpoint::from_shared(format!("https://{addr}"))?.tls_config(tls_config)?)
+}
+
+/// Connect to the server over the Unix domain socket at `path`
+#[cfg(unix)]
+async fn connect_unix(path: PathBuf) -> Result<Channel, tonic::transport::Error> {
+    // the uri is required, but not used by the connector
+    Endpoint::from_static("http://[::]:50101")
+        .connect_with_connector(tower::service_fn(move |_: tonic::transport::Uri| {
+            let path = path.clone();
+            async move {
+                let stream = tokio::net::UnixStream::connect(path).await?;
+                Ok::<_, std::io::Error>(hyper_util::rt::TokioIo::new(stream))
+            }
+        }))
+        .await
+}
+
 /// Find a specific error in the [`Error::source`] chain
 fn find_source<E: Error + 'static>(err: &dyn Error) -> Option<&E> {
     let mut err = err.source();
@@ -288,7 +364,7 @@ fn get_path(dir: &Path) -> Result<PathBuf> {
     bail!("Error: non-existing directory '{}'", dir.display());
 }
 
-fn execute_action(action: cli::Action, config: &CombinedSettings) -> Result<()> {
+async fn execute_action(action: cli::Action, config: &CombinedSettings) -> Result<()> {
     match action {
         cli::Action::Import { file } => {
             println!("need to import from file {}", file.display());
@@ -311,6 +387,21 @@ fn execute_action(action: cli::Action, config: &CombinedSettings) -> Result<()>
                 utils::get_app_config_path().context("getting app-config-path")?;
             podcast::export_to_opml(&config_dir_path, &path).context("export opml")?;
         }
+        cli::Action::Control(action) => {
+            let com = config
+                .tui
+                .read()
+                .settings
+                .get_com()
+                .ok_or(anyhow::anyhow!(
+                    "Expected tui-com settings to be resolved at this point"
+                ))?
+                .clone();
+            let client = connect(&com)
+                .await
+                .context("Could not connect to the server, is it running?")?;
+            control::execute(action, &mut ui::Playback::new(client)).await?;
+        }
     };
 
     Ok(())
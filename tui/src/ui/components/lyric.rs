@@ -1,37 +1,32 @@
+use crate::ui::html::{find_timestamp, html_to_lines};
 use crate::ui::{model::TermusicLayout, Model};
+use std::time::Duration;
 use termusiclib::library_db::const_unknown::{UNKNOWN_ARTIST, UNKNOWN_TITLE};
 use termusiclib::podcast::episode::Episode;
-use termusiclib::track::MediaType;
+use termusiclib::track::{MediaType, Track};
 use termusiclib::types::{Id, LyricMsg, Msg};
+use termusicplayback::PlayerCmd;
 
 use anyhow::{anyhow, Result};
-use lazy_static::lazy_static;
-use regex::Regex;
 use termusiclib::config::SharedTuiSettings;
 use tui_realm_stdlib::Textarea;
 use tuirealm::command::{Cmd, CmdResult, Direction, Position};
 use tuirealm::event::{Key, KeyEvent, KeyModifiers, NoUserEvent};
 use tuirealm::props::{
-    Alignment, AttrValue, Attribute, BorderType, Borders, PropPayload, PropValue, TextSpan,
+    Alignment, AttrValue, Attribute, BorderType, Borders, PropPayload, PropValue, TextModifiers,
+    TextSpan,
 };
-use tuirealm::{Component, Event, MockComponent, State, StateValue};
+use tuirealm::ratatui::layout::Rect;
+use tuirealm::{Component, Event, Frame, MockComponent, State, StateValue};
 
-lazy_static! {
-    /// Regex for finding <br/> tags -- also captures any surrounding
-    /// line breaks
-    static ref RE_BR_TAGS: Regex = Regex::new(r"((\r\n)|\r|\n)*<br */?>((\r\n)|\r|\n)*").expect("Regex error");
+/// The rows moved by [`Cmd::Scroll`]
+const SCROLL_STEP: usize = 4;
 
-    /// Regex for finding HTML tags
-    static ref RE_HTML_TAGS: Regex = Regex::new(r"<[^<>]*>").expect("Regex error");
-
-    /// Regex for finding more than two line breaks
-    static ref RE_MULT_LINE_BREAKS: Regex = Regex::new(r"((\r\n)|\r|\n){3,}").expect("Regex error");
-}
-
-#[derive(MockComponent)]
 pub struct Lyric {
     component: Textarea,
     config: SharedTuiSettings,
+    /// The highlighted row, tracked here as [`Textarea`] does not expose it in its state
+    index: usize,
 }
 
 impl Lyric {
@@ -48,7 +43,7 @@ impl Lyric {
                 .foreground(config.settings.theme.lyric_foreground())
                 .title(" Lyrics ", Alignment::Left)
                 // .wrap(true)
-                .step(4)
+                .step(SCROLL_STEP)
                 .highlighted_str(&config.settings.theme.style.playlist.highlight_symbol)
                 .text_rows(&[TextSpan::new(format!(
                     "{}.",
@@ -56,7 +51,72 @@ impl Lyric {
                 ))])
         };
 
-        Self { component, config }
+        Self {
+            component,
+            config,
+            index: 0,
+        }
+    }
+
+    /// Get the number of rows currently shown
+    fn rows_len(&self) -> usize {
+        match self.component.query(Attribute::Text) {
+            Some(AttrValue::Payload(PropPayload::Vec(rows))) => rows.len(),
+            _ => 0,
+        }
+    }
+
+    /// Get the timestamp in the highlighted row, like the ones in episode descriptions
+    fn selected_timestamp(&self) -> Option<Duration> {
+        let Some(AttrValue::Payload(PropPayload::Vec(rows))) =
+            self.component.query(Attribute::Text)
+        else {
+            return None;
+        };
+
+        match rows.get(self.index)? {
+            // only the rows of descriptions are underlined, not for example the duration
+            PropValue::TextSpan(span) if span.modifiers.contains(TextModifiers::UNDERLINED) => {
+                find_timestamp(&span.content)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl MockComponent for Lyric {
+    fn view(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        self.component.view(frame, area);
+    }
+
+    fn query(&self, attr: Attribute) -> Option<AttrValue> {
+        self.component.query(attr)
+    }
+
+    fn attr(&mut self, attr: Attribute, value: AttrValue) {
+        self.component.attr(attr, value);
+        // the textarea keeps its index on new text, only limiting it to the new length
+        if attr == Attribute::Text {
+            self.index = self.index.min(self.rows_len().saturating_sub(1));
+        }
+    }
+
+    fn state(&self) -> State {
+        self.component.state()
+    }
+
+    fn perform(&mut self, cmd: Cmd) -> CmdResult {
+        let last = self.rows_len().saturating_sub(1);
+        match cmd {
+            Cmd::Move(Direction::Down) => self.index = (self.index + 1).min(last),
+            Cmd::Move(Direction::Up) => self.index = self.index.saturating_sub(1),
+            Cmd::Scroll(Direction::Down) => self.index = (self.index + SCROLL_STEP).min(last),
+            Cmd::Scroll(Direction::Up) => self.index = self.index.saturating_sub(SCROLL_STEP),
+            Cmd::GoTo(Position::Begin) => self.index = 0,
+            Cmd::GoTo(Position::End) => self.index = last,
+            _ => (),
+        }
+        self.component.perform(cmd)
     }
 }
 
@@ -89,6 +149,15 @@ impl Component<Msg, NoUserEvent> for Lyric {
                 code: Key::End,
                 modifiers: KeyModifiers::NONE,
             }) => self.perform(Cmd::GoTo(Position::End)),
+            Event::Keyboard(KeyEvent {
+                code: Key::Enter,
+                modifiers: KeyModifiers::NONE,
+            }) => {
+                if let Some(position) = self.selected_timestamp() {
+                    return Some(Msg::LyricMessage(LyricMsg::SeekToTimestamp(position)));
+                }
+                CmdResult::None
+            }
             Event::Keyboard(KeyEvent {
                 code: Key::Tab,
                 modifiers: KeyModifiers::NONE,
@@ -184,40 +253,8 @@ impl Model {
     }
 
     pub fn lyric_update_for_episode_after(&mut self, po_title: &str, ep: &Episode) {
-        // convert <br/> tags to a single line break
-        let br_to_lb = RE_BR_TAGS.replace_all(&ep.description, "\n");
-
-        // strip all HTML tags
-        let stripped_tags = RE_HTML_TAGS.replace_all(&br_to_lb, "");
-
-        // convert HTML entities (e.g., &amp;)
-        let decoded = match escaper::decode_html(&stripped_tags) {
-            Err(_) => stripped_tags.to_string(),
-            Ok(s) => s,
-        };
-
-        // remove anything more than two line breaks (i.e., one blank line)
-        let no_line_breaks = RE_MULT_LINE_BREAKS.replace_all(&decoded, "\n\n");
-
-        let (term_width, _) = viuer::terminal_size();
-        let term_width = usize::from(term_width);
-        let lyric_width = term_width * 3 / 5;
-        let lines_vec: Vec<_> = no_line_breaks.split('\n').collect();
-        let mut short_string_vec: Vec<_> = Vec::new();
-        for line in lines_vec {
-            let unicode_width = unicode_width::UnicodeWidthStr::width(line);
-            if unicode_width > lyric_width {
-                let mut string_tmp = textwrap::wrap(line, lyric_width);
-                short_string_vec.append(&mut string_tmp);
-            } else {
-                short_string_vec.push(std::borrow::Cow::Borrowed(line));
-            }
-        }
-
-        let lines_textspan_len = short_string_vec.len();
-        let lines_textspan = short_string_vec
-            .into_iter()
-            .map(|l| PropValue::TextSpan(TextSpan::from(l)));
+        let lines_textspan = description_rows(&ep.description);
+        let lines_textspan_len = lines_textspan.len();
 
         let mut final_vec: Vec<_> = Vec::with_capacity(7 + lines_textspan_len);
         final_vec.push(PropValue::TextSpan(TextSpan::from(po_title).bold()));
@@ -247,6 +284,89 @@ impl Model {
             .ok();
     }
 
+    /// Show the details of the podcast at `index`, until a episode is selected
+    pub fn lyric_update_for_podcast_details(&mut self, index: usize) {
+        let Some(podcast) = self.podcast.podcasts.get(index) else {
+            return;
+        };
+        let lines_textspan = podcast
+            .description
+            .as_deref()
+            .map(description_rows)
+            .unwrap_or_default();
+
+        let mut final_vec: Vec<_> = Vec::with_capacity(6 + lines_textspan.len());
+        final_vec.push(PropValue::TextSpan(TextSpan::from(&podcast.title).bold()));
+        if let Some(author) = &podcast.author {
+            final_vec.push(PropValue::TextSpan(
+                TextSpan::from(format!("By: {author}")).italic(),
+            ));
+        }
+        final_vec.push(PropValue::TextSpan(
+            TextSpan::from(format!(
+                "Episodes: {} ({} unplayed)",
+                podcast.episodes.len(),
+                podcast.num_unplayed()
+            ))
+            .italic(),
+        ));
+
+        if !lines_textspan.is_empty() {
+            final_vec.push(PropValue::TextSpan(TextSpan::from("   ")));
+            final_vec.push(PropValue::TextSpan(TextSpan::from("Description:").bold()));
+            final_vec.extend(lines_textspan);
+        }
+
+        self.app
+            .attr(
+                &Id::Lyric,
+                Attribute::Text,
+                AttrValue::Payload(PropPayload::Vec(final_vec)),
+            )
+            .ok();
+        self.lyric_update_title();
+    }
+
+    /// Seek to a timestamp selected in a episode description, if the described episode is the one playing
+    pub fn lyric_seek_to_timestamp(&mut self, position: Duration) {
+        let playing = self
+            .playlist
+            .current_track()
+            .filter(|track| track.media_type == MediaType::Podcast)
+            .and_then(Track::file)
+            .map(ToString::to_string);
+
+        // outside of the podcast layout, the description of the playing episode is shown
+        let described = if self.layout == TermusicLayout::Podcast {
+            self.lyric_selected_episode().map(|ep| ep.url.clone())
+        } else {
+            playing.clone()
+        };
+
+        if playing.is_some() && playing == described {
+            self.command(&PlayerCmd::SeekTo(position));
+        } else {
+            self.update_show_message_timeout(
+                "Timestamp",
+                "Play the episode to jump to its timestamps",
+                None,
+            );
+        }
+    }
+
+    /// Get the episode selected in the podcast layout
+    fn lyric_selected_episode(&self) -> Option<&Episode> {
+        let Ok(State::One(StateValue::Usize(episode_index))) = self.app.state(&Id::Episode) else {
+            return None;
+        };
+
+        self.podcast
+            .podcasts
+            .get(self.podcast.podcasts_index)?
+            .episodes
+            .get(episode_index)
+    }
+
     pub fn lyric_update(&mut self) {
         if self.layout == TermusicLayout::Podcast {
             if let Err(e) = self.lyric_update_for_podcast() {
@@ -264,7 +384,8 @@ impl Model {
             }
 
             let mut line = String::new();
-            if song.lyric_frames_is_empty() {
+            // podcast transcripts only have a parsed lyric
+            if song.lyric_frames_is_empty() && song.parsed_lyric().is_none() {
                 self.lyric_set_lyric("No lyrics available.");
                 return;
             }
@@ -348,7 +469,12 @@ impl Model {
                     lyric_title = format!(" Lyrics of {artist:^.20} - {title:^.20} ");
                 }
                 MediaType::Podcast => {
-                    lyric_title = " Details: ".to_string();
+                    if self.layout != TermusicLayout::Podcast && track.parsed_lyric().is_some() {
+                        let title = track.title().unwrap_or(UNKNOWN_TITLE);
+                        lyric_title = format!(" Transcript of {title:^.20} ");
+                    } else {
+                        lyric_title = " Details: ".to_string();
+                    }
                 }
                 MediaType::LiveRadio => {
                     lyric_title = " Live Radio ".to_string();
@@ -368,3 +494,28 @@ impl Model {
             .ok();
     }
 }
+
+/// Convert a HTML description into styled rows, wrapped to the width of the lyric area
+fn description_rows(description: &str) -> Vec<PropValue> {
+    let (term_width, _) = viuer::terminal_size();
+    let lyric_width = usize::from(term_width) * 3 / 5;
+
+    html_to_lines(description, lyric_width)
+        .into_iter()
+        .map(|line| {
+            let timestamp = line.timestamp();
+            let mut span = TextSpan::from(line.text);
+            if line.bold {
+                span = span.bold();
+            }
+            if line.italic {
+                span = span.italic();
+            }
+            // rows with a timestamp can be selected to seek to it
+            if timestamp.is_some() {
+                span = span.underlined();
+            }
+            PropValue::TextSpan(span)
+        })
+        .collect()
+}
@@ -57,7 +57,9 @@ pub use tag_editor::*;
 use termusiclib::config::v2::tui::keys::Keys;
 use termusiclib::config::SharedTuiSettings;
 
-use crate::ui::{ConfigEditorMsg, Id, IdConfigEditor, IdTagEditor, Model, Msg, PLMsg, XYWHMsg};
+use crate::ui::{
+    ConfigEditorMsg, EQMsg, Id, IdConfigEditor, IdTagEditor, Model, Msg, PLMsg, XYWHMsg,
+};
 use tui_realm_stdlib::Phantom;
 use tuirealm::event::NoUserEvent;
 use tuirealm::{Component, Event, MockComponent, Sub, SubClause, SubEventClause};
@@ -119,6 +121,12 @@ impl Component<Msg, NoUserEvent> for GlobalListener {
             Event::Keyboard(keyevent) if keyevent == keys.player_keys.seek_backward.get() => {
                 Some(Msg::PlayerSeekBackward)
             }
+            Event::Keyboard(keyevent) if keyevent == keys.player_keys.next_chapter.get() => {
+                Some(Msg::PlayerChapterNext)
+            }
+            Event::Keyboard(keyevent) if keyevent == keys.player_keys.previous_chapter.get() => {
+                Some(Msg::PlayerChapterPrevious)
+            }
             Event::Keyboard(keyevent) if keyevent == keys.player_keys.speed_up.get() => {
                 Some(Msg::PlayerSpeedUp)
             }
@@ -163,6 +171,9 @@ impl Component<Msg, NoUserEvent> for GlobalListener {
             Event::Keyboard(keyevent) if keyevent == keys.player_keys.save_playlist.get() => {
                 Some(Msg::SavePlaylistPopupShow)
             }
+            Event::Keyboard(keyevent) if keyevent == keys.player_keys.open_equalizer.get() => {
+                Some(Msg::Equalizer(EQMsg::PopupShow))
+            }
             Event::Keyboard(keyevent) if keyevent == keys.move_cover_art_keys.move_left.get() => {
                 Some(Msg::Xywh(XYWHMsg::MoveLeft))
             }
@@ -254,6 +265,14 @@ impl Model {
                 SubEventClause::Keyboard(keys.player_keys.seek_backward.get()),
                 Self::no_popup_mounted_clause(),
             ),
+            Sub::new(
+                SubEventClause::Keyboard(keys.player_keys.next_chapter.get()),
+                Self::no_popup_mounted_clause(),
+            ),
+            Sub::new(
+                SubEventClause::Keyboard(keys.player_keys.previous_chapter.get()),
+                Self::no_popup_mounted_clause(),
+            ),
             Sub::new(
                 SubEventClause::Keyboard(keys.lyric_keys.adjust_offset_forwards.get()),
                 Self::no_popup_mounted_clause(),
@@ -286,6 +305,10 @@ impl Model {
                 SubEventClause::Keyboard(keys.player_keys.save_playlist.get()),
                 Self::no_popup_mounted_clause(),
             ),
+            Sub::new(
+                SubEventClause::Keyboard(keys.player_keys.open_equalizer.get()),
+                Self::no_popup_mounted_clause(),
+            ),
             Sub::new(
                 SubEventClause::Keyboard(keys.select_view_keys.view_podcasts.get()),
                 Self::no_popup_mounted_clause(),
@@ -332,7 +355,10 @@ impl Model {
             Box::new(SubClause::IsMounted(Id::FeedDeleteConfirmRadioPopup)),
             Box::new(SubClause::Or(
                 Box::new(SubClause::IsMounted(Id::FeedDeleteConfirmInputPopup)),
-                Box::new(SubClause::IsMounted(Id::PodcastSearchTablePopup)),
+                Box::new(SubClause::Or(
+                    Box::new(SubClause::IsMounted(Id::PodcastSearchTablePopup)),
+                    Box::new(SubClause::IsMounted(Id::EqualizerPopup)),
+                )),
             )),
         )))
     }
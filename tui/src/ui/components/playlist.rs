@@ -9,6 +9,9 @@ use termusiclib::config::SharedTuiSettings;
 use termusiclib::library_db::const_unknown::{UNKNOWN_ALBUM, UNKNOWN_ARTIST, UNKNOWN_TITLE};
 use termusiclib::library_db::SearchCriteria;
 use termusiclib::library_db::TrackDB;
+use termusiclib::player::{
+    PlaylistAddTrack, PlaylistRemoveTrack, PlaylistSwapTrack, PlaylistTrackSource,
+};
 use termusiclib::track::Track;
 use termusiclib::types::{GSMsg, Id, Msg, PLMsg};
 use termusiclib::utils::{filetype_supported, get_parent_folder, is_playlist, playlist_get_vec};
@@ -258,12 +261,25 @@ impl Model {
 
     fn playlist_add_playlist(&mut self, current_node: &str) -> Result<()> {
         let vec = playlist_get_vec(current_node)?;
-        self.playlist.add_playlist(&vec)?;
-        self.player_sync_playlist()?;
-        self.playlist_sync();
+        self.playlist_add_sources(vec.iter().filter_map(|v| source_from_str(v)).collect());
         Ok(())
     }
 
+    /// Request the server to append the given tracks to the playlist
+    ///
+    /// The local playlist is updated once the server sends the change back.
+    fn playlist_add_sources(&mut self, tracks: Vec<PlaylistTrackSource>) {
+        if tracks.is_empty() {
+            return;
+        }
+        // always append, even if the local playlist is not up-to-date yet
+        let info = PlaylistAddTrack {
+            at_index: u32::MAX,
+            tracks,
+        };
+        self.command(&PlayerCmd::PlaylistAddTrack(info));
+    }
+
     pub fn playlist_add_episode(&mut self, episode_index: usize) -> Result<()> {
         if self.podcast.podcasts.is_empty() {
             return Ok(());
@@ -277,9 +293,8 @@ impl Model {
             .episodes
             .get(episode_index)
             .ok_or_else(|| anyhow!("get episode selected failed."))?;
-        self.playlist.add_episode(episode_selected);
-        self.player_sync_playlist()?;
-        self.playlist_sync();
+        let source = PlaylistTrackSource::PodcastUrl(episode_selected.url.clone());
+        self.playlist_add_sources(vec![source]);
         Ok(())
     }
 
@@ -290,13 +305,15 @@ impl Model {
         }
         if p.is_dir() {
             let new_items_vec = Self::library_dir_children(p);
-            self.playlist.add_playlist(&new_items_vec)?;
-            self.player_sync_playlist()?;
-            self.playlist_sync();
+            self.playlist_add_sources(
+                new_items_vec
+                    .iter()
+                    .filter_map(|v| source_from_str(v))
+                    .collect(),
+            );
             return Ok(());
         }
         self.playlist_add_item(current_node)?;
-        self.playlist_sync();
         Ok(())
     }
 
@@ -305,20 +322,16 @@ impl Model {
             self.playlist_add_playlist(current_node)?;
             return Ok(());
         }
-        self.playlist.add_playlist(&[current_node])?;
-        self.player_sync_playlist()?;
+        self.playlist_add_sources(source_from_str(current_node).into_iter().collect());
         Ok(())
     }
 
     pub fn playlist_add_all_from_db(&mut self, vec: &[TrackDB]) {
-        let vec2: Vec<&str> = vec.iter().map(|f| f.file.as_str()).collect();
-        if let Err(e) = self.playlist.add_playlist(&vec2) {
-            self.mount_error_popup(e.context("add all to playlist from database"));
-        }
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(e.context("player sync playlist"));
-        }
-        self.playlist_sync();
+        let sources = vec
+            .iter()
+            .filter_map(|f| source_from_str(&f.file))
+            .collect();
+        self.playlist_add_sources(sources);
     }
 
     pub fn playlist_add_random_album(&mut self) {
@@ -459,35 +472,35 @@ impl Model {
         if self.playlist.is_empty() {
             return;
         }
-        self.playlist.remove(index);
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(e.context("player sync playlist"));
-        }
-        self.playlist_sync();
+        // protobuf does not support usize directly
+        #[allow(clippy::cast_possible_truncation)]
+        let info = PlaylistRemoveTrack {
+            at_index: index as u32,
+            amount: 1,
+        };
+        self.command(&PlayerCmd::PlaylistRemoveTrack(info));
+    }
+
+    pub fn playlist_swap(&mut self, index_a: usize, index_b: usize) {
+        // protobuf does not support usize directly
+        #[allow(clippy::cast_possible_truncation)]
+        let info = PlaylistSwapTrack {
+            index_a: index_a as u32,
+            index_b: index_b as u32,
+        };
+        self.command(&PlayerCmd::PlaylistSwapTrack(info));
     }
 
     pub fn playlist_clear(&mut self) {
-        self.playlist.clear();
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(e.context("player sync playlist"));
-        }
-        self.playlist_sync();
+        self.command(&PlayerCmd::PlaylistClear);
     }
 
     pub fn playlist_shuffle(&mut self) {
-        self.playlist.shuffle();
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(e.context("player sync playlist"));
-        }
-        self.playlist_sync();
+        self.command(&PlayerCmd::PlaylistShuffle);
     }
 
     pub fn playlist_update_library_delete(&mut self) {
-        self.playlist.remove_deleted_items();
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(e.context("player sync playlist"));
-        }
-        self.playlist_sync();
+        self.command(&PlayerCmd::PlaylistRemoveDeletedTracks);
     }
 
     pub fn playlist_update_title(&mut self) {
@@ -517,10 +530,7 @@ impl Model {
     }
     pub fn playlist_play_selected(&mut self, index: usize) {
         self.playlist.set_current_track_index(index);
-        if let Err(e) = self.player_sync_playlist() {
-            self.mount_error_popup(e.context("player sync playlist"));
-        }
-        self.command(&PlayerCmd::PlaySelected);
+        self.command(&PlayerCmd::PlaySelected(index));
     }
 
     pub fn playlist_update_search(&mut self, input: &str) {
@@ -655,3 +665,17 @@ impl Model {
         Ok(())
     }
 }
+
+/// Classify a library or playlist entry into what the server can resolve
+///
+/// Local files that do not exist (anymore) are skipped.
+fn source_from_str(item: &str) -> Option<PlaylistTrackSource> {
+    if item.starts_with("http") {
+        return Some(PlaylistTrackSource::Url(item.to_string()));
+    }
+    if Path::new(item).exists() {
+        return Some(PlaylistTrackSource::Path(item.to_string()));
+    }
+
+    None
+}
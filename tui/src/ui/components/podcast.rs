@@ -2,13 +2,14 @@ use crate::ui::Model;
 use anyhow::{anyhow, bail, Result};
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::ClientBuilder;
-use sanitize_filename::{sanitize_with_options, Options};
 use serde_json::Value;
 use std::time::Duration;
 use termusiclib::config::SharedTuiSettings;
-use termusiclib::podcast::{download_list, EpData, PodcastFeed, PodcastNoId};
+use termusiclib::player::{PodcastDownloadInfo, PodcastDownloadState, PodcastFeedState};
+use termusiclib::podcast::{Podcast, PodcastFeed};
 use termusiclib::track::MediaType;
 use termusiclib::types::{Id, Msg, PCMsg};
+use termusicplayback::PlayerCmd;
 use tokio::runtime::Handle;
 use tui_realm_stdlib::List;
 use tuirealm::command::{Cmd, CmdResult, Direction, Position};
@@ -422,20 +423,7 @@ impl Model {
     }
 
     pub fn podcast_add(&mut self, url: &str) {
-        let feed = PodcastFeed::new(None, url, None);
-
-        crate::podcast::check_feed(
-            feed,
-            usize::from(
-                self.config_server
-                    .read()
-                    .settings
-                    .podcast
-                    .max_download_retries,
-            ),
-            &self.taskpool,
-            self.tx_to_main.clone(),
-        );
+        self.command(&PlayerCmd::PodcastAdd(url.to_string()));
     }
     pub fn podcast_sync_feeds_and_episodes(&mut self) {
         let mut table: TableBuilder = TableBuilder::default();
@@ -535,17 +523,13 @@ impl Model {
         let podcast_selected = self
             .podcast
             .podcasts
-            .get_mut(self.podcast.podcasts_index)
+            .get(self.podcast.podcasts_index)
             .ok_or_else(|| anyhow!("get podcast selected failed."))?;
         let ep = podcast_selected
             .episodes
-            .get_mut(index)
+            .get(index)
             .ok_or_else(|| anyhow!("get episode selected failed"))?;
-        ep.played = !ep.played;
-        self.podcast
-            .db_podcast
-            .set_played_status(ep.id, ep.played)?;
-        self.podcast_sync_feeds_and_episodes();
+        self.command(&PlayerCmd::PodcastSetPlayed(vec![ep.id], !ep.played));
 
         Ok(())
     }
@@ -562,53 +546,33 @@ impl Model {
         let podcast_selected = self
             .podcast
             .podcasts
-            .get_mut(self.podcast.podcasts_index)
+            .get(self.podcast.podcasts_index)
             .ok_or_else(|| anyhow!("get podcast selected failed."))?;
         let played = podcast_selected
             .episodes
             .get(ep_index)
             .ok_or_else(|| anyhow!("get first episode failed."))?
             .played;
-        let mut epid_vec = Vec::new();
-        for ep in &mut podcast_selected.episodes {
-            epid_vec.push(ep.id);
-            ep.played = !played;
-        }
-        self.podcast
-            .db_podcast
-            .set_all_played_status(&epid_vec, !played)?;
-        self.podcast_sync_feeds_and_episodes();
+        let epid_vec = podcast_selected.episodes.iter().map(|ep| ep.id).collect();
+        self.command(&PlayerCmd::PodcastSetPlayed(epid_vec, !played));
 
         Ok(())
     }
 
-    /// Handles the application logic for adding a new podcast, or
-    /// synchronizing data from the RSS feed of an existing podcast.
-    /// `pod_id` will be None if a new podcast is being added (i.e.,
-    /// the database has not given it an id yet).
-    pub fn add_or_sync_data(&mut self, pod: &PodcastNoId, pod_id: Option<i64>) -> Result<()> {
-        if let Some(id) = pod_id {
-            self.podcast.db_podcast.update_podcast(id, pod)?;
-        } else {
-            self.podcast.db_podcast.insert_podcast(pod)?;
-        }
-
-        self.podcast.podcasts = self.podcast.db_podcast.get_podcasts()?;
+    /// Replace all podcasts with the ones received from the server
+    pub fn podcast_load(&mut self, podcasts: Vec<Podcast>) {
+        self.podcast.podcasts = podcasts;
+        self.podcast.podcasts_index = self
+            .podcast
+            .podcasts_index
+            .min(self.podcast.podcasts.len().saturating_sub(1));
         self.podcast_sync_feeds_and_episodes();
-
-        Ok(())
+        self.episode_update_playlist();
     }
 
-    /// Synchronize RSS feed data for one or more podcasts.
+    /// Synchronize RSS feed data for one or more podcasts, done by the server.
     pub fn podcast_refresh_feeds(&mut self, index: Option<usize>) -> Result<()> {
-        // We pull out the data we need here first, so we can
-        // stop borrowing the podcast list as quickly as possible.
-        // Slightly less efficient (two loops instead of
-        // one), but then it won't block other tasks that
-        // need to access the list.
-
-        let mut pod_data = Vec::new();
-        match index {
+        let ids = match index {
             // just grab one podcast
             Some(i) => {
                 if self.podcast.podcasts.is_empty() {
@@ -619,45 +583,70 @@ impl Model {
                     .podcasts
                     .get(i)
                     .ok_or_else(|| anyhow!("get podcast selected failed."))?;
-                let pcf = PodcastFeed::new(
-                    Some(pod_selected.id),
-                    &pod_selected.url.clone(),
-                    Some(pod_selected.title.clone()),
-                );
-                pod_data.push(pcf);
+                vec![pod_selected.id]
             }
+            // empty means all
+            None => Vec::new(),
+        };
+        self.command(&PlayerCmd::PodcastRefresh(ids));
 
-            // get all of 'em!
-            None => {
-                pod_data = self
-                    .podcast
-                    .podcasts
-                    .iter()
-                    .map(|pod| {
-                        PodcastFeed::new(Some(pod.id), &pod.url.clone(), Some(pod.title.clone()))
-                    })
-                    .collect();
+        Ok(())
+    }
+
+    /// Handle a [`UpdateEvents::PodcastFeedChanged`](termusiclib::player::UpdateEvents::PodcastFeedChanged) from the server
+    pub fn podcast_handle_feed_event(&mut self, url: &str, state: PodcastFeedState) -> Result<()> {
+        let message = match state {
+            PodcastFeedState::SyncStarted => {
+                self.download_tracker.increase_one(url);
+                self.download_tracker.message_sync_start()
             }
-        }
-        for feed in pod_data {
-            crate::podcast::check_feed(
-                feed,
-                usize::from(
-                    self.config_server
-                        .read()
-                        .settings
-                        .podcast
-                        .max_download_retries,
-                ),
-                &self.taskpool,
-                self.tx_to_main.clone(),
-            );
-        }
-        // self.update_tracker_notif();
-        self.podcast_sync_feeds_and_episodes();
+            PodcastFeedState::Synced => {
+                self.download_tracker.decrease_one(url);
+                self.command(&PlayerCmd::PodcastReload);
+                self.download_tracker.message_sync_success()
+            }
+            PodcastFeedState::SyncFailed => {
+                self.download_tracker.decrease_one(url);
+                self.mount_error_popup(anyhow!("Error happened with feed: {url}"));
+                self.download_tracker.message_feed_sync_failed()
+            }
+            PodcastFeedState::Removed => {
+                self.command(&PlayerCmd::PodcastReload);
+                return Ok(());
+            }
+        };
+        self.show_message_timeout_label_help(message, None, None, None);
+
         Ok(())
     }
 
+    /// Handle a [`UpdateEvents::PodcastDownload`](termusiclib::player::UpdateEvents::PodcastDownload) from the server
+    pub fn podcast_handle_download_event(&mut self, info: &PodcastDownloadInfo) -> Result<()> {
+        let message = match info.state {
+            PodcastDownloadState::Started => {
+                self.download_tracker.increase_one(&info.url);
+                self.download_tracker.message_download_start(&info.title)
+            }
+            PodcastDownloadState::Progress { downloaded, total } => self
+                .download_tracker
+                .message_download_progress(&info.title, downloaded, total),
+            PodcastDownloadState::Completed => {
+                self.download_tracker.decrease_one(&info.url);
+                self.command(&PlayerCmd::PodcastReload);
+                self.download_tracker.message_download_complete()
+            }
+            PodcastDownloadState::Failed => {
+                self.download_tracker.decrease_one(&info.url);
+                self.mount_error_popup(anyhow!("download failed for episode: {}", info.title));
+                self.download_tracker.message_download_failed(&info.title)
+            }
+        };
+        self.show_message_timeout_label_help(message, None, None, None);
+
+        Ok(())
+    }
+
+    /// Download one or all episodes of the selected podcast, done by the server.
     pub fn episode_download(&mut self, index: Option<usize>) -> Result<()> {
         if self.podcast.podcasts.is_empty() {
             return Ok(());
@@ -665,121 +654,37 @@ impl Model {
         let podcast_selected = self
             .podcast
             .podcasts
-            .get_mut(self.podcast.podcasts_index)
+            .get(self.podcast.podcasts_index)
             .ok_or_else(|| anyhow!("get podcast selected failed."))?;
 
-        let pod_title;
-        let mut ep_data = Vec::new();
-        {
-            pod_title = podcast_selected.title.clone();
-
-            // if we are selecting one specific episode, just grab that
-            // one; otherwise, loop through them all
-            match index {
-                Some(idx) => {
-                    // grab just the relevant data we need
-
-                    let ep = podcast_selected
-                        .episodes
-                        .get_mut(idx)
-                        .ok_or_else(|| anyhow!("get episode selected failed"))?;
-                    let data = EpData {
-                        id: ep.id,
-                        pod_id: ep.pod_id,
-                        title: ep.title.clone(),
-                        url: ep.url.clone(),
-                        pubdate: ep.pubdate,
-                        file_path: None,
-                    };
-                    if ep.path.is_none() && !self.download_tracker.contains(&ep.url) {
-                        ep_data.push(data);
-                    }
-                }
-                None => {
-                    // grab just the relevant data we need
-                    ep_data = podcast_selected
-                        .episodes
-                        .iter()
-                        .filter_map(|ep| {
-                            if ep.path.is_none() && !self.download_tracker.contains(&ep.url) {
-                                Some(EpData {
-                                    id: ep.id,
-                                    pod_id: ep.pod_id,
-                                    title: ep.title.clone(),
-                                    url: ep.url.clone(),
-                                    pubdate: ep.pubdate,
-                                    file_path: None,
-                                })
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                }
-            }
-        }
+        // if we are selecting one specific episode, just grab that
+        // one; otherwise, loop through them all
+        let episodes = match index {
+            Some(idx) => std::slice::from_ref(
+                podcast_selected
+                    .episodes
+                    .get(idx)
+                    .ok_or_else(|| anyhow!("get episode selected failed"))?,
+            ),
+            None => podcast_selected.episodes.as_slice(),
+        };
 
         // check against episodes currently being downloaded -- so we
         // don't needlessly download them again
-        // ep_data.retain(|ep| !self.download_tracker.contains(&ep.id));
-
-        if !ep_data.is_empty() {
-            // add directory for podcast, create if it does not exist
-            let dir_name = sanitize_with_options(
-                &pod_title,
-                Options {
-                    truncate: true,
-                    windows: true, // for simplicity, we'll just use Windows-friendly paths for everyone
-                    replacement: "",
-                },
-            );
-            match crate::utils::create_podcast_dir(&self.config_server.read(), dir_name) {
-                Ok(path) => {
-                    // for ep in ep_data.iter() {
-                    //     self.download_tracker.insert(ep.id);
-                    // }
-                    download_list(
-                        ep_data,
-                        &path,
-                        usize::from(
-                            self.config_server
-                                .read()
-                                .settings
-                                .podcast
-                                .max_download_retries,
-                        ),
-                        &self.taskpool,
-                        &self.tx_to_main,
-                    );
-                }
-                Err(_) => bail!("Could not create dir: {pod_title}"),
-            }
-        }
-
-        // self.podcast_sync_feeds_and_episodes();
-        Ok(())
-    }
+        let ids: Vec<i64> = episodes
+            .iter()
+            .filter(|ep| ep.path.is_none() && !self.download_tracker.contains(&ep.url))
+            .map(|ep| ep.id)
+            .collect();
 
-    pub fn episode_download_complete(&mut self, ep_data: EpData) -> Result<()> {
-        let file_path = ep_data.file_path.unwrap();
-        let res = self.podcast.db_podcast.insert_file(ep_data.id, &file_path);
-        if res.is_err() {
-            bail!(
-                "Could not add episode file to database: {}",
-                file_path.to_string_lossy()
-            );
+        if !ids.is_empty() {
+            self.command(&PlayerCmd::PodcastDownload(ids));
         }
 
-        let podcasts = self.podcast.db_podcast.get_podcasts()?;
-        self.podcast.podcasts = podcasts;
-
-        self.podcast_sync_feeds_and_episodes();
-        self.episode_update_playlist();
         Ok(())
     }
 
-    /// Deletes a downloaded file for an episode from the user's local
-    /// system.
+    /// Delete the downloaded file of an episode, done by the server.
     pub fn episode_delete_file(&mut self, ep_index: usize) -> Result<()> {
         if self.podcast.podcasts.is_empty() {
             return Ok(());
@@ -787,36 +692,17 @@ impl Model {
         let podcast_selected = self
             .podcast
             .podcasts
-            .get_mut(self.podcast.podcasts_index)
+            .get(self.podcast.podcasts_index)
             .ok_or_else(|| anyhow!("get podcast selected failed."))?;
 
         let ep = podcast_selected
             .episodes
-            .get_mut(ep_index)
+            .get(ep_index)
             .ok_or_else(|| anyhow!("get episode selected failed"))?;
 
         if ep.path.is_some() {
-            let title = &ep.title;
-            let path = ep.path.clone().unwrap();
-            match std::fs::remove_file(path) {
-                Ok(()) => {
-                    self.podcast.db_podcast.remove_file(ep.id).map_err(|e| {
-                        anyhow!(format!("Could not remove file from db: {title} {e}"))
-                    })?;
-                    ep.path = None;
-                }
-                Err(e) => {
-                    // Repeat the same thing in case the local file is missing. Update db
-                    self.podcast.db_podcast.remove_file(ep.id).map_err(|e| {
-                        anyhow!(format!("Could not remove file from db: {title} {e}"))
-                    })?;
-                    ep.path = None;
-                    bail!(format!("Error deleting \"{title}\": {e}"));
-                }
-            }
+            self.command(&PlayerCmd::PodcastDeleteFiles(vec![ep.id]));
         }
-        self.podcast_sync_feeds_and_episodes();
-        self.episode_update_playlist();
         Ok(())
     }
 
@@ -825,74 +711,31 @@ impl Model {
         self.playlist_sync();
     }
 
-    pub fn podcast_delete_files(&mut self, pod_index: usize) -> Result<()> {
-        let mut eps_to_remove = Vec::new();
-        let mut success = true;
-        {
-            let podcast_selected = self
-                .podcast
-                .podcasts
-                .get_mut(pod_index)
-                .ok_or_else(|| anyhow!("failed to find the podcast selected for deletion."))?;
-
-            for ep in &mut podcast_selected.episodes {
-                if ep.path.is_some() {
-                    match std::fs::remove_file(ep.path.clone().unwrap()) {
-                        Ok(()) => {
-                            eps_to_remove.push(ep.id);
-                            ep.path = None;
-                        }
-                        Err(_) => success = false,
-                    }
-                }
-            }
-        }
-
-        self.podcast.db_podcast.remove_files(&eps_to_remove)?;
-        if !success {
-            bail!("Error happened when deleting a file. Please verify that it is accessible.");
-        }
-
-        Ok(())
-    }
-
+    /// Remove all podcasts and their downloaded episodes, done by the server.
     pub fn podcast_remove_all_feeds(&mut self) -> Result<()> {
-        if self.podcast.podcasts.is_empty() {
-            return Ok(());
-        }
-
-        let len = self.podcast.podcasts.len();
-
-        for index in 0..len {
-            self.podcast_delete_files(index).ok();
+        let ids: Vec<i64> = self.podcast.podcasts.iter().map(|pod| pod.id).collect();
+        if !ids.is_empty() {
+            self.command(&PlayerCmd::PodcastRemove(ids));
         }
 
-        self.podcast.db_podcast.clear_db()?;
-
-        self.podcast.podcasts = Vec::new();
-        self.podcast.podcasts_index = 0;
-
-        self.podcast_sync_feeds_and_episodes();
-        self.episode_update_playlist();
         Ok(())
     }
 
+    /// Remove the selected podcast and its downloaded episodes, done by the server.
     pub fn podcast_remove_feed(&mut self) -> Result<()> {
         if self.podcast.podcasts.is_empty() {
             return Ok(());
         }
 
         if let Ok(feed_index) = self.podcast_get_feed_index() {
-            self.podcast_delete_files(feed_index)?;
-            let podcast_selected = self.podcast.podcasts.remove(feed_index);
-            self.podcast
-                .db_podcast
-                .remove_podcast(podcast_selected.id)?;
+            let podcast_selected = self
+                .podcast
+                .podcasts
+                .get(feed_index)
+                .ok_or_else(|| anyhow!("failed to find the podcast selected for deletion."))?;
+            self.command(&PlayerCmd::PodcastRemove(vec![podcast_selected.id]));
         }
 
-        self.podcast.podcasts_index = self.podcast.podcasts_index.saturating_sub(1);
-        self.podcast_sync_feeds_and_episodes();
-        self.episode_update_playlist();
         Ok(())
     }
 
@@ -914,27 +757,26 @@ impl Model {
         if self.podcast.podcasts.is_empty() {
             return Ok(());
         }
-        if let Some(track) = self.playlist.current_track() {
-            if MediaType::Podcast == track.media_type {
-                if let Some(url) = track.file() {
-                    'outer: for pod in &mut self.podcast.podcasts {
-                        for ep in &mut pod.episodes {
-                            if ep.url == url {
-                                if !ep.played {
-                                    ep.played = true;
-                                    self.podcast
-                                        .db_podcast
-                                        .set_played_status(ep.id, ep.played)?;
-                                }
-                                break 'outer;
-                            }
-                        }
-                    }
-                }
-            }
+        let Some(track) = self.playlist.current_track() else {
+            return Ok(());
+        };
+        if MediaType::Podcast != track.media_type {
+            return Ok(());
         }
+        let Some(url) = track.file() else {
+            return Ok(());
+        };
 
-        self.podcast_sync_feeds_and_episodes();
+        let unplayed = self
+            .podcast
+            .podcasts
+            .iter()
+            .flat_map(|pod| pod.episodes.iter())
+            .find(|ep| ep.url == url && !ep.played)
+            .map(|ep| ep.id);
+        if let Some(id) = unplayed {
+            self.command(&PlayerCmd::PodcastSetPlayed(vec![id], true));
+        }
 
         Ok(())
     }
@@ -973,14 +815,13 @@ impl Model {
         let mut table: TableBuilder = TableBuilder::default();
         let mut idx = 0;
         let search = format!("*{}*", input.to_lowercase());
-        let mut db_tracks = vec![];
         // Get all episodes
-        let podcasts = self.podcast.podcasts.clone();
-        for podcast in podcasts {
-            if let Ok(episodes) = self.podcast.db_podcast.get_episodes(podcast.id, true) {
-                db_tracks.extend(episodes);
-            }
-        }
+        let db_tracks: Vec<_> = self
+            .podcast
+            .podcasts
+            .iter()
+            .flat_map(|pod| pod.episodes.iter().cloned())
+            .collect();
 
         if db_tracks.is_empty() {
             table.add_col(TextSpan::from("0"));
@@ -1118,11 +959,7 @@ fn parse_itunes_results(data: &str) -> Option<Vec<PodcastFeed>> {
         let array = value.get("results")?.as_array()?;
         for v in array {
             if let Some((title, url)) = parse_itunes_item(v) {
-                vec.push(PodcastFeed {
-                    id: None,
-                    url,
-                    title: Some(title),
-                });
+                vec.push(PodcastFeed::new(None, &url, Some(title)));
             }
         }
         return Some(vec);
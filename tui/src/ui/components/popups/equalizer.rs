@@ -0,0 +1,373 @@
+use termusiclib::config::v2::server::{EqualizerBand, EqualizerSettings};
+use termusiclib::config::SharedTuiSettings;
+use termusiclib::player::EqualizerSet;
+use termusiclib::types::{EQMsg, Id, Msg};
+use termusicplayback::PlayerCmd;
+use tui_realm_stdlib::Table;
+use tuirealm::{
+    command::{Cmd, CmdResult, Direction, Position},
+    event::{Key, KeyEvent, KeyModifiers},
+    props::{Alignment, BorderType, Borders, TableBuilder, TextSpan},
+    AttrValue, Attribute, Component, Event, MockComponent, NoUserEvent, State, StateValue,
+};
+
+use crate::ui::model::Model;
+
+/// The highest boost or cut the popup allows, in dB
+const MAX_GAIN: f32 = 12.0;
+/// The gain change of one key press, in tenths of a dB
+const GAIN_STEP: i32 = 10;
+
+#[derive(MockComponent)]
+pub struct EqualizerPopup {
+    component: Table,
+    config: SharedTuiSettings,
+}
+
+impl EqualizerPopup {
+    pub fn new(config: SharedTuiSettings) -> Self {
+        let component = {
+            let config = config.read();
+            Table::default()
+                .background(config.settings.theme.library_background())
+                .foreground(config.settings.theme.library_foreground())
+                .borders(
+                    Borders::default()
+                        .color(config.settings.theme.library_border())
+                        .modifiers(BorderType::Rounded),
+                )
+                .title(" Equalizer ", Alignment::Center)
+                .scroll(false)
+                .highlighted_color(config.settings.theme.library_highlight())
+                .highlighted_str(&config.settings.theme.style.library.highlight_symbol)
+                .rewind(false)
+                .row_height(1)
+                .headers(&[" Frequency ", " Gain ", ""])
+                .column_spacing(2)
+                .widths(&[20, 20, 60])
+                .table(
+                    TableBuilder::default()
+                        .add_col(TextSpan::from("Loading..."))
+                        .build(),
+                )
+        };
+
+        Self { component, config }
+    }
+
+    fn selected(&self) -> usize {
+        match self.state() {
+            State::One(StateValue::Usize(index)) => index,
+            _ => 0,
+        }
+    }
+}
+
+impl Component<Msg, NoUserEvent> for EqualizerPopup {
+    fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
+        let config = self.config.clone();
+        let keys = &config.read().settings.keys;
+        let _cmd_result = match ev {
+            Event::Keyboard(KeyEvent {
+                code: Key::Esc | Key::Enter,
+                ..
+            }) => return Some(Msg::Equalizer(EQMsg::PopupClose)),
+            Event::Keyboard(keyevent) if keyevent == keys.quit.get() => {
+                return Some(Msg::Equalizer(EQMsg::PopupClose))
+            }
+
+            Event::Keyboard(KeyEvent { code: Key::Up, .. }) => {
+                self.perform(Cmd::Move(Direction::Up))
+            }
+            Event::Keyboard(KeyEvent {
+                code: Key::Down, ..
+            }) => self.perform(Cmd::Move(Direction::Down)),
+            Event::Keyboard(keyevent) if keyevent == keys.navigation_keys.up.get() => {
+                self.perform(Cmd::Move(Direction::Up))
+            }
+            Event::Keyboard(keyevent) if keyevent == keys.navigation_keys.down.get() => {
+                self.perform(Cmd::Move(Direction::Down))
+            }
+            Event::Keyboard(keyevent) if keyevent == keys.navigation_keys.goto_top.get() => {
+                self.perform(Cmd::GoTo(Position::Begin))
+            }
+            Event::Keyboard(keyevent) if keyevent == keys.navigation_keys.goto_bottom.get() => {
+                self.perform(Cmd::GoTo(Position::End))
+            }
+
+            Event::Keyboard(KeyEvent {
+                code: Key::Left, ..
+            }) => {
+                return Some(Msg::Equalizer(EQMsg::AdjustGain(
+                    self.selected(),
+                    -GAIN_STEP,
+                )))
+            }
+            Event::Keyboard(KeyEvent {
+                code: Key::Right, ..
+            }) => {
+                return Some(Msg::Equalizer(EQMsg::AdjustGain(
+                    self.selected(),
+                    GAIN_STEP,
+                )))
+            }
+            Event::Keyboard(keyevent) if keyevent == keys.navigation_keys.left.get() => {
+                return Some(Msg::Equalizer(EQMsg::AdjustGain(
+                    self.selected(),
+                    -GAIN_STEP,
+                )))
+            }
+            Event::Keyboard(keyevent) if keyevent == keys.navigation_keys.right.get() => {
+                return Some(Msg::Equalizer(EQMsg::AdjustGain(
+                    self.selected(),
+                    GAIN_STEP,
+                )))
+            }
+
+            Event::Keyboard(KeyEvent {
+                code: Key::Tab,
+                modifiers: KeyModifiers::NONE,
+            }) => return Some(Msg::Equalizer(EQMsg::NextPreset)),
+            Event::Keyboard(KeyEvent {
+                code: Key::Char(' '),
+                modifiers: KeyModifiers::NONE,
+            }) => return Some(Msg::Equalizer(EQMsg::ToggleEnabled)),
+            Event::Keyboard(KeyEvent {
+                code: Key::Char('r'),
+                modifiers: KeyModifiers::NONE,
+            }) => return Some(Msg::Equalizer(EQMsg::Reset)),
+            Event::Keyboard(KeyEvent {
+                code: Key::Delete, ..
+            }) => return Some(Msg::Equalizer(EQMsg::RemovePreset)),
+            _ => CmdResult::None,
+        };
+        Some(Msg::None)
+    }
+}
+
+/// Format the frequency of a band, like `125 Hz` or `16 kHz`
+fn format_frequency(frequency: f32) -> String {
+    if frequency >= 1000.0 {
+        format!("{} kHz", frequency / 1000.0)
+    } else {
+        format!("{frequency} Hz")
+    }
+}
+
+/// Draw the gain as a bar extending left or right from the center, one cell per dB
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn gain_bar(gain: f32) -> String {
+    let width = MAX_GAIN as usize;
+    let cells = (gain.abs().round() as usize).min(width);
+
+    if gain < 0.0 {
+        format!(
+            "{}{}|{}",
+            " ".repeat(width - cells),
+            "\u{2588}".repeat(cells),
+            " ".repeat(width)
+        )
+    } else {
+        format!(
+            "{}|{}{}",
+            " ".repeat(width),
+            "\u{2588}".repeat(cells),
+            " ".repeat(width - cells)
+        )
+    }
+}
+
+/// Get the bands of the active preset, creating the preset if it does not exist
+fn active_preset_bands(equalizer: &mut EqualizerSettings) -> &mut Vec<EqualizerBand> {
+    equalizer.set(equalizer.enabled, equalizer.preset.clone(), Vec::new());
+    let preset = equalizer.preset.clone();
+
+    &mut equalizer
+        .presets
+        .iter_mut()
+        .find(|v| v.name == preset)
+        .expect("\"set\" creates the preset")
+        .bands
+}
+
+impl Model {
+    pub fn mount_equalizer_popup(&mut self) {
+        assert!(self
+            .app
+            .remount(
+                Id::EqualizerPopup,
+                Box::new(EqualizerPopup::new(self.config_tui.clone())),
+                vec![]
+            )
+            .is_ok());
+        self.equalizer_sync_popup();
+        assert!(self.app.active(&Id::EqualizerPopup).is_ok());
+    }
+
+    pub fn umount_equalizer_popup(&mut self) {
+        if self.app.mounted(&Id::EqualizerPopup) {
+            assert!(self.app.umount(&Id::EqualizerPopup).is_ok());
+        }
+    }
+
+    pub fn equalizer_toggle_enabled(&mut self) {
+        self.equalizer_change(|equalizer| equalizer.enabled = !equalizer.enabled);
+    }
+
+    pub fn equalizer_next_preset(&mut self) {
+        self.equalizer_change(|equalizer| {
+            let idx = equalizer
+                .presets
+                .iter()
+                .position(|v| v.name == equalizer.preset)
+                .map_or(0, |idx| idx + 1);
+            if let Some(preset) = equalizer.presets.get(idx % equalizer.presets.len().max(1)) {
+                equalizer.preset = preset.name.clone();
+            }
+        });
+    }
+
+    /// Adjust the gain of the band at `index` of the active preset by `tenths` of a dB
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    pub fn equalizer_adjust_gain(&mut self, index: usize, tenths: i32) {
+        self.equalizer_change(|equalizer| {
+            if let Some(band) = active_preset_bands(equalizer).get_mut(index) {
+                // calculate in tenths so that repeated steps do not accumulate float errors
+                let gain = (band.gain * 10.0).round() as i32 + tenths;
+                band.gain = (gain as f32 / 10.0).clamp(-MAX_GAIN, MAX_GAIN);
+            }
+        });
+    }
+
+    pub fn equalizer_reset(&mut self) {
+        self.equalizer_change(|equalizer| {
+            for band in active_preset_bands(equalizer) {
+                band.gain = 0.0;
+            }
+        });
+    }
+
+    /// Remove the active preset and activate the first remaining one, the last preset is kept
+    pub fn equalizer_remove_preset(&mut self) {
+        let preset = {
+            let mut config = self.config_server.write();
+            let equalizer = &mut config.settings.player.equalizer;
+            if equalizer.presets.len() <= 1 {
+                return;
+            }
+            let preset = equalizer.preset.clone();
+            equalizer.remove_preset(&preset);
+            equalizer.preset = equalizer.presets[0].name.clone();
+
+            preset
+        };
+
+        self.command(&PlayerCmd::EqualizerRemovePreset(preset));
+        // activate the new preset on the server too
+        self.equalizer_change(|_| {});
+    }
+
+    /// Apply `change` to the equalizer settings, send them to the server and update the popup
+    fn equalizer_change<F>(&mut self, change: F)
+    where
+        F: FnOnce(&mut EqualizerSettings),
+    {
+        let set = {
+            let mut config = self.config_server.write();
+            let equalizer = &mut config.settings.player.equalizer;
+            change(equalizer);
+
+            EqualizerSet {
+                enabled: equalizer.enabled,
+                preset: equalizer.preset.clone(),
+                bands: active_preset_bands(equalizer).clone(),
+            }
+        };
+
+        self.command(&PlayerCmd::EqualizerSet(set));
+        self.equalizer_sync_popup();
+    }
+
+    /// Update the bands and title of the popup from the settings
+    fn equalizer_sync_popup(&mut self) {
+        let (table, title) = {
+            let config = self.config_server.read();
+            let equalizer = &config.settings.player.equalizer;
+
+            let mut table = TableBuilder::default();
+            let bands = equalizer
+                .presets
+                .iter()
+                .find(|v| v.name == equalizer.preset)
+                .map(|v| v.bands.as_slice())
+                .unwrap_or_default();
+            for (idx, band) in bands.iter().enumerate() {
+                if idx > 0 {
+                    table.add_row();
+                }
+                table
+                    .add_col(TextSpan::new(format_frequency(band.frequency)))
+                    .add_col(TextSpan::new(format!("{:+.1} dB", band.gain)))
+                    .add_col(TextSpan::new(gain_bar(band.gain)));
+            }
+            if bands.is_empty() {
+                table.add_col(TextSpan::from("No bands"));
+            }
+
+            let title = format!(
+                " Equalizer: {} ({}) \u{2500} <SPACE> toggle, <TAB> next preset, <r> reset ",
+                equalizer.preset,
+                if equalizer.enabled { "on" } else { "off" }
+            );
+
+            (table.build(), title)
+        };
+
+        self.app
+            .attr(
+                &Id::EqualizerPopup,
+                Attribute::Content,
+                AttrValue::Table(table),
+            )
+            .ok();
+        self.app
+            .attr(
+                &Id::EqualizerPopup,
+                Attribute::Title,
+                AttrValue::Title((title, Alignment::Center)),
+            )
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{format_frequency, gain_bar};
+
+    #[test]
+    fn should_format_frequency() {
+        assert_eq!(format_frequency(62.0), "62 Hz");
+        assert_eq!(format_frequency(1000.0), "1 kHz");
+        assert_eq!(format_frequency(16000.0), "16 kHz");
+    }
+
+    #[test]
+    fn should_draw_gain_bar() {
+        assert_eq!(
+            gain_bar(2.0),
+            format!("{}|\u{2588}\u{2588}{}", " ".repeat(12), " ".repeat(10))
+        );
+        assert_eq!(
+            gain_bar(-3.4),
+            format!(
+                "{}\u{2588}\u{2588}\u{2588}|{}",
+                " ".repeat(9),
+                " ".repeat(12)
+            )
+        );
+        // limited to the width
+        assert_eq!(gain_bar(20.0).chars().count(), 25);
+    }
+}
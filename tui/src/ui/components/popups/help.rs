@@ -80,6 +80,12 @@ impl HelpPopup {
                         ]))
                         .add_col(Self::comment("Seek forward/backward 5 seconds"))
                         .add_row()
+                        .add_col(Self::key(&[
+                            &keys.player_keys.next_chapter,
+                            &keys.player_keys.previous_chapter,
+                        ]))
+                        .add_col(Self::comment("Next/previous chapter"))
+                        .add_row()
                         .add_col(Self::key(&[
                             &keys.lyric_keys.adjust_offset_forwards,
                             &keys.lyric_keys.adjust_offset_backwards,
@@ -95,6 +101,11 @@ impl HelpPopup {
                         .add_col(Self::key(&[&keys.player_keys.toggle_prefetch]))
                         .add_col(Self::comment("Toggle gapless playback"))
                         .add_row()
+                        .add_col(Self::key(&[&keys.player_keys.open_equalizer]))
+                        .add_col(Self::comment(
+                            "Open Equalizer, <SPACE> toggle, <TAB> next preset, <r> reset, <DEL> remove preset",
+                        ))
+                        .add_row()
                         .add_col(Self::key(&[
                             &keys.lyric_keys.adjust_offset_forwards,
                             &keys.lyric_keys.adjust_offset_backwards,
@@ -1,6 +1,7 @@
 #![allow(clippy::module_name_repetitions)]
 
 mod deleteconfirm;
+mod equalizer;
 mod error;
 pub mod general_search;
 mod help;
@@ -14,6 +15,8 @@ pub mod youtube_search;
 #[allow(unused_imports)]
 pub use deleteconfirm::{DeleteConfirmInputPopup, DeleteConfirmRadioPopup};
 #[allow(unused_imports)]
+pub use equalizer::EqualizerPopup;
+#[allow(unused_imports)]
 pub use error::ErrorPopup;
 #[allow(unused_imports)]
 pub use help::HelpPopup;
@@ -1,5 +1,6 @@
 use crate::ui::Model;
 use std::time::Duration;
+use termusiclib::chapters::{chapter_at, chapter_index_at};
 use termusiclib::config::TuiOverlay;
 use termusiclib::track::{MediaType, Track};
 use termusiclib::types::{Id, Msg};
@@ -64,10 +65,13 @@ impl Model {
         };
         let mut progress_title = String::new();
         if let Some(track) = &self.current_song {
+            let chapter = chapter_at(track.chapters(), self.time_pos)
+                .map(|chapter| format!("| Chapter: {:^.20} ", chapter.title))
+                .unwrap_or_default();
             match track.media_type {
                 MediaType::Music | MediaType::LiveRadio => {
                     progress_title = format!(
-                        " Status: {} | Volume: {} | Speed: {:^.1} | Gapless: {} ",
+                        " Status: {} | Volume: {} | Speed: {:^.1} | Gapless: {} {chapter}",
                         self.playlist.status(),
                         config_server.settings.player.volume,
                         config_server.settings.player.speed as f32 / 10.0,
@@ -76,7 +80,7 @@ impl Model {
                 }
                 MediaType::Podcast => {
                     progress_title = format!(
-                        " Status: {} {:^.20} | Volume: {} | Speed: {:^.1} | Gapless: {} ",
+                        " Status: {} {:^.20} | Volume: {} | Speed: {:^.1} | Gapless: {} {chapter}",
                         self.playlist.status(),
                         track.title().unwrap_or("Unknown title"),
                         config_server.settings.player.volume,
@@ -111,6 +115,12 @@ impl Model {
 
         let time_pos = time_pos.unwrap();
 
+        // the title shows the current chapter
+        let chapter_changed = self.current_song.as_ref().is_some_and(|track| {
+            chapter_index_at(track.chapters(), self.time_pos)
+                != chapter_index_at(track.chapters(), time_pos)
+        });
+
         self.time_pos = time_pos;
 
         let progress = (time_pos.as_secs() * 100)
@@ -120,6 +130,10 @@ impl Model {
         let new_prog = Self::progress_safeguard(progress);
 
         self.progress_set(new_prog, total_duration);
+
+        if chapter_changed {
+            self.progress_update_title();
+        }
     }
 
     fn progress_safeguard(progress: f64) -> f64 {
@@ -0,0 +1,491 @@
+//! Conversion of the HTML of podcast and episode descriptions into wrapped, styled lines
+//!
+//! This is not a full HTML parser, it only handles what show notes commonly use:
+//! paragraphs, line breaks, headings, lists, quotes, links and entities.
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+lazy_static! {
+    /// Regex for detecting if a description contains HTML tags at all
+    static ref RE_HTML_TAG: Regex = Regex::new(r"</?[a-zA-Z][^<>]*>").expect("Regex error");
+
+    /// Regex for finding timestamps like `1:02:03` or `02:03`, not being part of a longer number
+    static ref RE_TIMESTAMP: Regex = Regex::new(r"(?:^|[^\d:])((?:(\d{1,2}):)?(\d{1,2}):(\d{2}))(?:$|[^\d:])").expect("Regex error");
+}
+
+/// A line of the converted description, already wrapped to the requested width
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StyledLine {
+    pub text: String,
+    /// Headings
+    pub bold: bool,
+    /// Quotes
+    pub italic: bool,
+}
+
+impl StyledLine {
+    /// Get the first timestamp in this line, which can be selected to seek to it
+    pub fn timestamp(&self) -> Option<Duration> {
+        find_timestamp(&self.text)
+    }
+}
+
+/// Convert a description into lines wrapped at `width`
+///
+/// Descriptions without any HTML tags are treated as plain text, keeping their line breaks.
+pub fn html_to_lines(html: &str, width: usize) -> Vec<StyledLine> {
+    let width = width.max(10);
+    if !RE_HTML_TAG.is_match(html) {
+        return plain_to_lines(&decode_entities(html), width);
+    }
+
+    let mut converter = Converter::new(width);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        converter.text(&rest[..start]);
+        rest = &rest[start..];
+
+        // comments may contain ">", so they need to be skipped to their proper end
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        converter.tag(&rest[1..end]);
+        rest = &rest[end + 1..];
+    }
+    converter.text(rest);
+
+    converter.finish()
+}
+
+/// Find the first timestamp in `text`, like `1:02:03` or `02:03`
+pub fn find_timestamp(text: &str) -> Option<Duration> {
+    RE_TIMESTAMP.captures_iter(text).find_map(|caps| {
+        let hours: u64 = caps.get(2).map_or(Some(0), |v| v.as_str().parse().ok())?;
+        let minutes: u64 = caps.get(3)?.as_str().parse().ok()?;
+        let seconds: u64 = caps.get(4)?.as_str().parse().ok()?;
+
+        // minutes may be above 60 in the "MM:SS" form, like "75:30"
+        if seconds >= 60 || (caps.get(2).is_some() && minutes >= 60) {
+            return None;
+        }
+
+        Some(Duration::from_secs(hours * 3600 + minutes * 60 + seconds))
+    })
+}
+
+/// Decode HTML entities like `&amp;`, leaving the text as-is if it contains invalid ones
+fn decode_entities(text: &str) -> String {
+    escaper::decode_html(text).unwrap_or_else(|_| text.to_string())
+}
+
+/// Wrap plain text, collapsing multiple empty lines into one
+fn plain_to_lines(text: &str, width: usize) -> Vec<StyledLine> {
+    let mut lines: Vec<StyledLine> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            if lines.last().is_some_and(|v| !v.text.is_empty()) {
+                lines.push(StyledLine::default());
+            }
+            continue;
+        }
+
+        lines.extend(textwrap::wrap(line, width).into_iter().map(|v| StyledLine {
+            text: v.into_owned(),
+            ..StyledLine::default()
+        }));
+    }
+
+    if lines.last().is_some_and(|v| v.text.is_empty()) {
+        lines.pop();
+    }
+
+    lines
+}
+
+/// A list that is currently open
+enum List {
+    Bullet,
+    /// The number of the next item
+    Numbered(usize),
+}
+
+/// The state while converting HTML
+struct Converter {
+    width: usize,
+    lines: Vec<StyledLine>,
+    /// The text of the current block, with collapsed whitespace
+    block: String,
+    /// Whether a empty line should separate the next block from the previous one
+    blank_pending: bool,
+    /// The marker of the current list item, replaced by spaces after its first line
+    item_marker: Option<String>,
+    lists: Vec<List>,
+    /// Whether the current block is a heading
+    heading: bool,
+    quote: usize,
+    pre: usize,
+    /// Depth of `<script>` and `<style>`, whose content is not shown
+    hidden: usize,
+    /// The `href` of the open links and where their text starts in `block`
+    links: Vec<(String, usize)>,
+}
+
+impl Converter {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            lines: Vec::new(),
+            block: String::new(),
+            blank_pending: false,
+            item_marker: None,
+            lists: Vec::new(),
+            heading: false,
+            quote: 0,
+            pre: 0,
+            hidden: 0,
+            links: Vec::new(),
+        }
+    }
+
+    /// Add the text between tags
+    fn text(&mut self, raw: &str) {
+        if self.hidden > 0 || raw.is_empty() {
+            return;
+        }
+
+        let text = decode_entities(raw);
+        if self.pre > 0 {
+            for (idx, line) in text.split('\n').enumerate() {
+                if idx > 0 {
+                    self.flush();
+                }
+                self.block.push_str(line);
+            }
+            return;
+        }
+
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                if !self.block.is_empty() && !self.block.ends_with(' ') {
+                    self.block.push(' ');
+                }
+            } else {
+                self.block.push(ch);
+            }
+        }
+    }
+
+    /// Handle a tag, `tag` being everything between `<` and `>`
+    fn tag(&mut self, tag: &str) {
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(tag) => (true, tag),
+            None => (false, tag),
+        };
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match (name.as_str(), closing) {
+            ("script" | "style", false) => self.hidden += 1,
+            ("script" | "style", true) => self.hidden = self.hidden.saturating_sub(1),
+            _ if self.hidden > 0 => (),
+
+            ("br", _) => self.line_break(),
+            ("p" | "hr" | "table", _) => self.paragraph_break(),
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", _) => {
+                self.paragraph_break();
+                self.heading = !closing;
+            }
+            ("div" | "tr" | "dt" | "dd", _) => self.flush(),
+            ("blockquote", false) => {
+                self.paragraph_break();
+                self.quote += 1;
+            }
+            ("blockquote", true) => {
+                self.paragraph_break();
+                self.quote = self.quote.saturating_sub(1);
+            }
+            ("pre", false) => {
+                self.paragraph_break();
+                self.pre += 1;
+            }
+            ("pre", true) => {
+                self.paragraph_break();
+                self.pre = self.pre.saturating_sub(1);
+            }
+            ("ul" | "ol", false) => {
+                if self.lists.is_empty() {
+                    self.paragraph_break();
+                } else {
+                    self.flush();
+                }
+                self.lists.push(if name == "ol" {
+                    List::Numbered(1)
+                } else {
+                    List::Bullet
+                });
+            }
+            ("ul" | "ol", true) => {
+                self.flush();
+                self.item_marker = None;
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_pending = true;
+                }
+            }
+            ("li", false) => {
+                self.flush();
+                let marker = match self.lists.last_mut() {
+                    Some(List::Numbered(next)) => {
+                        *next += 1;
+                        format!("{}. ", *next - 1)
+                    }
+                    Some(List::Bullet) | None => "• ".to_string(),
+                };
+                self.item_marker = Some(marker);
+            }
+            ("li", true) => {
+                self.flush();
+                self.item_marker = None;
+            }
+            ("a", false) => {
+                let href = attribute(tag, "href").unwrap_or_default();
+                self.links.push((href, self.block.len()));
+            }
+            ("a", true) => {
+                if let Some((href, start)) = self.links.pop() {
+                    self.close_link(&href, start);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Show the target of a link after its text, if the text is not the url already
+    fn close_link(&mut self, href: &str, start: usize) {
+        let text = self.block.get(start..).unwrap_or_default().trim();
+        if text.is_empty()
+            || href.is_empty()
+            || href.starts_with('#')
+            || href.starts_with("javascript:")
+            || bare_url(href) == bare_url(text)
+        {
+            return;
+        }
+
+        if !self.block.ends_with(' ') {
+            self.block.push(' ');
+        }
+        self.block.push('(');
+        self.block.push_str(href);
+        self.block.push(')');
+    }
+
+    /// Start a new line, keeping the current list item and paragraph
+    fn line_break(&mut self) {
+        if self.block.trim().is_empty() {
+            // "<br><br>" is commonly used instead of paragraphs
+            if self.lines.last().is_some_and(|v| !v.text.is_empty()) {
+                self.lines.push(StyledLine::default());
+            }
+            self.block.clear();
+            return;
+        }
+        self.flush();
+    }
+
+    /// End the current block and separate the next one with a empty line
+    fn paragraph_break(&mut self) {
+        self.flush();
+        self.blank_pending = true;
+    }
+
+    /// Wrap the current block into lines
+    fn flush(&mut self) {
+        let text = if self.pre > 0 {
+            self.block.trim_end()
+        } else {
+            self.block.trim()
+        };
+        if text.is_empty() {
+            self.block.clear();
+            return;
+        }
+
+        if self.blank_pending && self.lines.last().is_some_and(|v| !v.text.is_empty()) {
+            self.lines.push(StyledLine::default());
+        }
+        self.blank_pending = false;
+
+        let indent = format!(
+            "{}{}",
+            "│ ".repeat(self.quote),
+            "  ".repeat(self.lists.len().saturating_sub(1))
+        );
+        let marker = self.item_marker.take().unwrap_or_default();
+        let initial_indent = format!("{indent}{marker}");
+        let subsequent_indent = format!("{indent}{}", " ".repeat(marker.width()));
+        // following lines of the same list item line up with the text
+        if !marker.is_empty() {
+            self.item_marker = Some(" ".repeat(marker.width()));
+        }
+
+        let options = textwrap::Options::new(self.width)
+            .initial_indent(&initial_indent)
+            .subsequent_indent(&subsequent_indent);
+        let bold = self.heading;
+        let italic = self.quote > 0;
+        self.lines.extend(
+            textwrap::wrap(text, options)
+                .into_iter()
+                .map(|v| StyledLine {
+                    text: v.into_owned(),
+                    bold,
+                    italic,
+                }),
+        );
+
+        self.block.clear();
+        // links can span multiple blocks
+        for (_, start) in &mut self.links {
+            *start = 0;
+        }
+    }
+
+    fn finish(mut self) -> Vec<StyledLine> {
+        self.flush();
+        if self.lines.last().is_some_and(|v| v.text.is_empty()) {
+            self.lines.pop();
+        }
+
+        self.lines
+    }
+}
+
+/// Get the decoded value of the attribute `name` from the content of a tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut offset = 0;
+    while let Some(found) = lower[offset..].find(name) {
+        let start = offset + found;
+        offset = start + name.len();
+
+        // only match whole attribute names, not for example "data-href"
+        let before = lower[..start].chars().next_back();
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = tag[offset..].trim_start().strip_prefix('=') else {
+            continue;
+        };
+
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split_whitespace().next().unwrap_or_default(),
+        };
+
+        return Some(decode_entities(value.trim()));
+    }
+
+    None
+}
+
+/// The url without scheme and trailing slash, to compare it with a link text
+fn bare_url(url: &str) -> &str {
+    url.trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("mailto:")
+        .trim_start_matches("www.")
+        .trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn texts(lines: &[StyledLine]) -> Vec<&str> {
+        lines.iter().map(|v| v.text.as_str()).collect()
+    }
+
+    #[test]
+    fn should_convert_paragraphs_and_lists() {
+        let html = "<h2>Show &amp; Tell</h2><p>First   paragraph<br/>second line</p>\n<ul><li>One</li><li>Two <ol><li>Nested</li></ol></li></ul><p>End</p>";
+        let lines = html_to_lines(html, 80);
+
+        assert_eq!(
+            texts(&lines),
+            [
+                "Show & Tell",
+                "",
+                "First paragraph",
+                "second line",
+                "",
+                "• One",
+                "• Two",
+                "  1. Nested",
+                "",
+                "End"
+            ]
+        );
+        assert!(lines[0].bold);
+        assert!(!lines[2].bold);
+    }
+
+    #[test]
+    fn should_wrap_list_items() {
+        let lines = html_to_lines("<ul><li>one two three four five six</li></ul>", 14);
+        assert_eq!(texts(&lines), ["• one two", "  three four", "  five six"]);
+    }
+
+    #[test]
+    fn should_show_link_targets() {
+        let html = r#"<p>See <a href="https://example.com/a?b=1&amp;c=2">the notes</a> and <a href="https://example.com/">example.com</a></p>"#;
+        let lines = html_to_lines(html, 200);
+        assert_eq!(
+            texts(&lines),
+            ["See the notes (https://example.com/a?b=1&c=2) and example.com"]
+        );
+    }
+
+    #[test]
+    fn should_skip_comments_and_scripts() {
+        let html = "<p>Visible<!-- <b>hidden</b> --></p><script>var a = 1 > 0;</script><style>p {}</style>";
+        assert_eq!(texts(&html_to_lines(html, 80)), ["Visible"]);
+    }
+
+    #[test]
+    fn should_keep_plain_text_lines() {
+        let text = "First line\nSecond &amp; line\n\n\n\nAfter a gap\n";
+        assert_eq!(
+            texts(&html_to_lines(text, 80)),
+            ["First line", "Second & line", "", "After a gap"]
+        );
+    }
+
+    #[test]
+    fn should_find_timestamps() {
+        assert_eq!(
+            find_timestamp("(01:02:03) Intro"),
+            Some(Duration::from_secs(3723))
+        );
+        assert_eq!(
+            find_timestamp("• 75:30 - Outro"),
+            Some(Duration::from_secs(4530))
+        );
+        assert_eq!(find_timestamp("at 1:2:3 or 10:99"), None);
+        assert_eq!(find_timestamp("https://example.com:8080/"), None);
+        assert_eq!(find_timestamp("no time here"), None);
+    }
+}
@@ -22,6 +22,7 @@
  * SOFTWARE.
  */
 pub mod components;
+mod html;
 pub mod model;
 mod playback;
 pub mod utils;
@@ -30,19 +31,19 @@ use anyhow::Context;
 use anyhow::Result;
 use futures::future::FutureExt;
 use model::{Model, TermusicLayout};
-use playback::Playback;
-use std::time::Duration;
+pub use playback::{Playback, PlayerClient};
+use std::time::{Duration, Instant};
 use sysinfo::System;
-use termusiclib::player::music_player_client::MusicPlayerClient;
 use termusiclib::player::PlayerProgress;
+use termusiclib::player::PlaylistTracks;
 use termusiclib::player::StreamUpdates;
 use termusiclib::player::UpdateEvents;
+use termusiclib::player::UpdatePlaylistEvents;
 pub use termusiclib::types::*;
 use termusicplayback::{PlayerCmd, Status};
 use tokio::sync::mpsc::{self, UnboundedReceiver};
 use tokio_stream::Stream;
 use tokio_stream::StreamExt;
-use tonic::transport::Channel;
 use tuirealm::application::PollStrategy;
 use tuirealm::{Application, Update};
 
@@ -50,6 +51,8 @@ use crate::CombinedSettings;
 // -- internal
 
 const FORCED_REDRAW_INTERVAL: Duration = Duration::from_millis(1000);
+/// How long to wait for more podcast changes before reloading the podcasts
+const PODCAST_RELOAD_DELAY: Duration = Duration::from_millis(500);
 
 // Let's define the messages handled by our app. NOTE: it must derive `PartialEq`
 
@@ -58,6 +61,8 @@ pub struct UI {
     model: Model,
     playback: Playback,
     cmd_rx: UnboundedReceiver<PlayerCmd>,
+    /// When to reload the podcasts, so that a batch of changes only reloads once
+    podcast_reload_at: Option<Instant>,
 }
 
 impl UI {
@@ -70,7 +75,7 @@ impl UI {
         // }
     }
     /// Instantiates a new Ui
-    pub async fn new(config: CombinedSettings, client: MusicPlayerClient<Channel>) -> Result<Self> {
+    pub async fn new(config: CombinedSettings, client: PlayerClient) -> Result<Self> {
         let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
         let mut model = Model::new(config, cmd_tx).await;
         model.init_config();
@@ -79,6 +84,7 @@ impl UI {
             model,
             playback,
             cmd_rx,
+            podcast_reload_at: None,
         })
     }
 
@@ -101,6 +107,9 @@ impl UI {
     /// This function does NOT handle initializing and finializing the terminal
     async fn run_inner(&mut self) -> Result<()> {
         let mut stream_updates = self.playback.subscribe_to_stream_updates().await?;
+        let playlist = self.playback.get_playlist().await?;
+        self.load_playlist(playlist)?;
+        self.load_podcasts().await;
 
         // Main loop
         let mut progress_interval = 0;
@@ -116,6 +125,13 @@ impl UI {
                 self.model.run();
             }
             self.run_playback().await?;
+            if self
+                .podcast_reload_at
+                .is_some_and(|at| at <= Instant::now())
+            {
+                self.podcast_reload_at = None;
+                self.load_podcasts().await;
+            }
             progress_interval += 1;
             if progress_interval >= 80 {
                 progress_interval = 0;
@@ -233,6 +249,50 @@ impl UI {
         }
     }
 
+    /// Replace the local playlist with the one received from the server and refresh the view
+    fn load_playlist(&mut self, playlist: PlaylistTracks) -> Result<()> {
+        self.model
+            .playlist
+            .load_from_grpc(playlist, &self.model.podcast.db_podcast)?;
+        self.model.playlist_sync();
+
+        Ok(())
+    }
+
+    /// Replace the podcasts with the ones from the server, errors are shown as a popup
+    async fn load_podcasts(&mut self) {
+        match self.playback.get_podcasts().await {
+            Ok(podcasts) => self.model.podcast_load(podcasts),
+            Err(err) => self.model.mount_error_popup(err.context("podcast load")),
+        }
+    }
+
+    /// Reload the podcasts soon, without delaying a already scheduled reload
+    fn schedule_podcast_reload(&mut self) {
+        self.podcast_reload_at
+            .get_or_insert_with(|| Instant::now() + PODCAST_RELOAD_DELAY);
+    }
+
+    /// Apply a playlist change the server sent to the local playlist
+    fn handle_playlist_event(&mut self, ev: UpdatePlaylistEvents) -> Result<()> {
+        match ev {
+            UpdatePlaylistEvents::AddTrack(info) => {
+                self.model
+                    .playlist
+                    .add_tracks(&info, &self.model.podcast.db_podcast)?;
+            }
+            UpdatePlaylistEvents::RemoveTrack(info) => self.model.playlist.remove_tracks(info)?,
+            UpdatePlaylistEvents::SwapTracks(info) => self.model.playlist.swap(info)?,
+            UpdatePlaylistEvents::MoveTrack(info) => self.model.playlist.move_track(info)?,
+            UpdatePlaylistEvents::Cleared => self.model.playlist.clear(),
+            UpdatePlaylistEvents::Reloaded(playlist) => return self.load_playlist(playlist),
+        }
+        self.model.playlist_sync();
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_lines)]
     async fn run_playback(&mut self) -> Result<()> {
         if let Ok(cmd) = self.cmd_rx.try_recv() {
             match cmd {
@@ -262,8 +322,8 @@ impl UI {
                 }
 
                 PlayerCmd::CycleLoop => self.playback.cycle_loop().await?,
-                PlayerCmd::PlaySelected => {
-                    self.playback.play_selected().await?;
+                PlayerCmd::PlaySelected(index) => {
+                    self.playback.play_selected(index).await?;
                     // self.model.playlist.clear_current_track();
                     // This line is required to show current playing message
                     // self.model.playlist.set_current_track_index(None);
@@ -287,6 +347,30 @@ impl UI {
                     );
                     self.model.force_redraw();
                 }
+                PlayerCmd::SeekTo(position) => {
+                    let pprogress = self.playback.seek_to(position).await?;
+                    self.model.progress_update(
+                        pprogress.position,
+                        pprogress.total_duration.unwrap_or_default(),
+                    );
+                    self.model.force_redraw();
+                }
+                PlayerCmd::ChapterNext => {
+                    let pprogress = self.playback.next_chapter().await?;
+                    self.model.progress_update(
+                        pprogress.position,
+                        pprogress.total_duration.unwrap_or_default(),
+                    );
+                    self.model.force_redraw();
+                }
+                PlayerCmd::ChapterPrevious => {
+                    let pprogress = self.playback.previous_chapter().await?;
+                    self.model.progress_update(
+                        pprogress.position,
+                        pprogress.total_duration.unwrap_or_default(),
+                    );
+                    self.model.force_redraw();
+                }
                 PlayerCmd::SpeedDown => {
                     self.model.config_server.write().settings.player.speed =
                         self.playback.speed_down().await?;
@@ -312,6 +396,78 @@ impl UI {
                     self.model.config_server.write().settings.player.volume = volume;
                     self.model.progress_update_title();
                 }
+                PlayerCmd::PlaylistAddTrack(info) => {
+                    self.playback.add_to_playlist(info).await?;
+                }
+                PlayerCmd::PlaylistRemoveTrack(info) => {
+                    self.playback.remove_from_playlist(info).await?;
+                }
+                PlayerCmd::PlaylistSwapTrack(info) => self.playback.swap_tracks(info).await?,
+                PlayerCmd::PlaylistMoveTrack(info) => self.playback.move_track(info).await?,
+                PlayerCmd::PlaylistClear => self.playback.clear_playlist().await?,
+                PlayerCmd::PlaylistShuffle => {
+                    self.playback.shuffle_playlist().await?;
+                }
+                PlayerCmd::PlaylistRemoveDeletedTracks => {
+                    self.playback.remove_deleted_tracks().await?;
+                }
+                PlayerCmd::PlaylistSetLoopMode(mode) => {
+                    self.playback.set_loop_mode(mode).await?;
+                }
+                PlayerCmd::GetPlaylist => {
+                    let playlist = self.playback.get_playlist().await?;
+                    self.load_playlist(playlist)?;
+                }
+                PlayerCmd::PodcastAdd(url) => {
+                    if let Err(err) = self.playback.add_podcast(url).await {
+                        self.model.mount_error_popup(err.context("podcast add"));
+                    }
+                }
+                PlayerCmd::PodcastRefresh(ids) => {
+                    if let Err(err) = self.playback.refresh_podcasts(ids).await {
+                        self.model
+                            .mount_error_popup(err.context("podcast refresh feeds"));
+                    }
+                }
+                PlayerCmd::PodcastDownload(ids) => {
+                    if let Err(err) = self.playback.download_episodes(ids).await {
+                        self.model
+                            .mount_error_popup(err.context("podcast episode download"));
+                    }
+                }
+                PlayerCmd::PodcastDeleteFiles(ids) => {
+                    if let Err(err) = self.playback.delete_episode_files(ids).await {
+                        self.model
+                            .mount_error_popup(err.context("podcast episode delete"));
+                    }
+                    self.schedule_podcast_reload();
+                }
+                PlayerCmd::PodcastSetPlayed(ids, played) => {
+                    if let Err(err) = self.playback.set_episodes_played(ids, played).await {
+                        self.model
+                            .mount_error_popup(err.context("podcast episode mark played"));
+                    }
+                    self.schedule_podcast_reload();
+                }
+                PlayerCmd::PodcastRemove(ids) => {
+                    // the server sends a "Removed" event, which reloads the podcasts
+                    if let Err(err) = self.playback.remove_podcasts(ids, true).await {
+                        self.model
+                            .mount_error_popup(err.context("podcast remove feed"));
+                    }
+                }
+                PlayerCmd::PodcastReload => self.schedule_podcast_reload(),
+                PlayerCmd::EqualizerSet(set) => {
+                    if let Err(err) = self.playback.set_equalizer(set).await {
+                        self.model.mount_error_popup(err.context("set equalizer"));
+                    }
+                }
+                PlayerCmd::EqualizerRemovePreset(preset) => {
+                    if let Err(err) = self.playback.remove_equalizer_preset(preset).await {
+                        self.model
+                            .mount_error_popup(err.context("remove equalizer preset"));
+                    }
+                }
                 _ => {}
             }
         }
@@ -345,6 +501,8 @@ impl UI {
                     warn!("Stream Lagged, missed events: {amount}");
                     // we know that we missed events, force to get full information from GetProgress endpoint
                     self.model.command(&PlayerCmd::GetProgress);
+                    // and also the playlist, as the changes are only send as deltas
+                    self.model.command(&PlayerCmd::GetPlaylist);
                 }
                 UpdateEvents::VolumeChanged { volume } => {
                     self.model.config_server.write().settings.player.volume = volume;
@@ -374,6 +532,33 @@ impl UI {
                         self.model.lyric_update_for_radio(title);
                     }
                 }
+                UpdateEvents::PlaylistChanged(ev) => {
+                    if let Err(err) = self.handle_playlist_event(ev) {
+                        error!("Applying playlist change failed, reloading playlist: {err:#}");
+                        self.model.command(&PlayerCmd::GetPlaylist);
+                    }
+                }
+                UpdateEvents::LoopModeChanged { mode } => {
+                    self.model.config_server.write().settings.player.loop_mode = mode;
+                    self.model.playlist.set_loop_mode(mode);
+                    self.model.playlist_update_title();
+                }
+                UpdateEvents::GaplessChanged { gapless } => {
+                    self.model.config_server.write().settings.player.gapless = gapless;
+                    self.model.progress_update_title();
+                }
+                UpdateEvents::PodcastFeedChanged { url, state } => {
+                    if let Err(err) = self.model.podcast_handle_feed_event(&url, state) {
+                        self.model
+                            .mount_error_popup(err.context("podcast feed update"));
+                    }
+                }
+                UpdateEvents::PodcastDownload(info) => {
+                    if let Err(err) = self.model.podcast_handle_download_event(&info) {
+                        self.model
+                            .mount_error_popup(err.context("podcast download update"));
+                    }
+                }
             }
         }
 
@@ -92,6 +92,33 @@ impl DownloadTracker {
         }
     }
 
+    pub fn message_download_progress(
+        &self,
+        title: &str,
+        downloaded: u64,
+        total: Option<u64>,
+    ) -> String {
+        let progress = match total {
+            Some(total) if total > 0 => format!("{}%", downloaded.saturating_mul(100) / total),
+            _ => format!("{} KiB", downloaded / 1024),
+        };
+        let len = self.items.len();
+        if len > 1 {
+            format!(" {title:^.20} downloading: {progress}, {len} items downloading... ")
+        } else {
+            format!(" {title:^.20} downloading: {progress} ")
+        }
+    }
+
+    pub fn message_download_failed(&self, title: &str) -> String {
+        let len = self.items.len();
+        if len > 0 {
+            format!(" Failed to download item: {title:^.10}! {len} downloads are still running. ")
+        } else {
+            format!(" Failed to download item: {title:^.20}. ")
+        }
+    }
+
     pub fn message_download_complete(&self) -> String {
         let len = self.items.len();
         if len > 0 {
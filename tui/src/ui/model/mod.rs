@@ -39,7 +39,7 @@ use termusiclib::track::{MediaType, Track};
 #[cfg(all(feature = "cover-ueberzug", not(target_os = "windows")))]
 use termusiclib::ueberzug::UeInstance;
 
-use anyhow::{anyhow, Result};
+use anyhow::anyhow;
 use std::path::PathBuf;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::{Duration, Instant};
@@ -47,7 +47,6 @@ use termusiclib::config::{ServerOverlay, SharedServerSettings, SharedTuiSettings
 use termusiclib::library_db::TrackDB;
 use termusiclib::podcast::{db::Database as DBPod, Podcast, PodcastFeed};
 use termusiclib::songtag::SongTag;
-use termusiclib::taskpool::TaskPool;
 use termusiclib::utils::get_app_config_path;
 use termusicplayback::{PlayerCmd, Playlist};
 use tokio::sync::mpsc::UnboundedSender;
@@ -110,7 +109,7 @@ pub struct PodcastWidgetData {
     pub podcasts: Vec<Podcast>,
     /// Selected podcast index
     pub podcasts_index: usize,
-    /// Podcast Database
+    /// Podcast Database, only used to resolve the podcast tracks of the playlist
     pub db_podcast: DBPod,
     /// Podcast search results
     pub search_results: Option<Vec<PodcastFeed>>,
@@ -172,10 +171,6 @@ pub struct Model {
     pub sender_songtag: Sender<SearchLyricState>,
     pub receiver_songtag: Receiver<SearchLyricState>,
     pub download_tracker: DownloadTracker,
-    /// Taskpool to limit number of active network requests
-    ///
-    /// Currently only used for podcast sync & download
-    pub taskpool: TaskPool,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -231,21 +226,9 @@ impl Model {
         let db_path = get_app_config_path().expect("failed to get podcast db path.");
 
         let db_podcast = DBPod::new(&db_path).expect("error connecting to podcast db.");
-
-        let podcasts = db_podcast
-            .get_podcasts()
-            .expect("failed to get podcasts from db.");
-        let taskpool = TaskPool::new(usize::from(
-            config_server
-                .read()
-                .settings
-                .podcast
-                .concurrent_downloads_max
-                .get(),
-        ));
         let (tx_to_main, rx_to_main) = mpsc::channel();
 
-        let playlist = Playlist::new(config_server.clone()).unwrap_or_default();
+        let playlist = Playlist::new(&config_server).unwrap_or_default();
         let app = Self::init_app(&tree, &config_tui);
 
         // This line is required, in order to show the playing message for the first track
@@ -291,7 +274,8 @@ impl Model {
                 search_tracks: Vec::new(),
             },
             podcast: PodcastWidgetData {
-                podcasts,
+                // loaded from the server once connected
+                podcasts: Vec::new(),
                 podcasts_index: 0,
                 db_podcast,
                 search_results: None,
@@ -303,7 +287,6 @@ impl Model {
                 layout: ConfigEditorLayout::General,
                 config_changed: false,
             },
-            taskpool,
             tx_to_main,
             rx_to_main,
             download_tracker: DownloadTracker::default(),
@@ -380,12 +363,6 @@ impl Model {
         self.lyric_update_title();
     }
 
-    pub fn player_sync_playlist(&mut self) -> Result<()> {
-        self.playlist.save()?;
-        self.command(&PlayerCmd::ReloadPlaylist);
-        Ok(())
-    }
-
     pub fn player_update_current_track_after(&mut self) {
         self.time_pos = Duration::default();
         if let Err(e) = self.update_photo() {
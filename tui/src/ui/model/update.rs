@@ -6,7 +6,7 @@ use std::time::Duration;
 use termusiclib::library_db::SearchCriteria;
 use termusiclib::track::MediaType;
 use termusiclib::types::{
-    DBMsg, DLMsg, GSMsg, Id, IdTagEditor, LIMsg, LyricMsg, Msg, PCMsg, PLMsg, XYWHMsg, YSMsg,
+    DBMsg, DLMsg, EQMsg, GSMsg, Id, IdTagEditor, LIMsg, LyricMsg, Msg, PCMsg, PLMsg, XYWHMsg, YSMsg,
 };
 use termusicplayback::PlayerCmd;
 /**
@@ -94,7 +94,9 @@ impl Update<Msg> for Model {
                 | Msg::PlayerVolumeUp
                 | Msg::PlayerVolumeDown
                 | Msg::PlayerSeekForward
-                | Msg::PlayerSeekBackward => self.update_player(&msg),
+                | Msg::PlayerSeekBackward
+                | Msg::PlayerChapterNext
+                | Msg::PlayerChapterPrevious => self.update_player(&msg),
 
                 Msg::HelpPopupShow => {
                     self.mount_help_popup();
@@ -172,6 +174,7 @@ impl Update<Msg> for Model {
                 Msg::LyricMessage(m) => self.update_lyric_textarea(m),
                 Msg::Download(m) => self.update_download_msg(&m),
                 Msg::Xywh(m) => self.update_xywh_msg(m),
+                Msg::Equalizer(m) => self.update_equalizer_msg(m),
             }
         } else {
             None
@@ -204,6 +207,19 @@ impl Model {
         None
     }
 
+    fn update_equalizer_msg(&mut self, msg: EQMsg) -> Option<Msg> {
+        match msg {
+            EQMsg::PopupShow => self.mount_equalizer_popup(),
+            EQMsg::PopupClose => self.umount_equalizer_popup(),
+            EQMsg::ToggleEnabled => self.equalizer_toggle_enabled(),
+            EQMsg::NextPreset => self.equalizer_next_preset(),
+            EQMsg::AdjustGain(index, tenths) => self.equalizer_adjust_gain(index, tenths),
+            EQMsg::Reset => self.equalizer_reset(),
+            EQMsg::RemovePreset => self.equalizer_remove_preset(),
+        }
+        None
+    }
+
     fn update_lyric_textarea(&mut self, msg: LyricMsg) -> Option<Msg> {
         match msg {
             LyricMsg::LyricTextAreaBlurUp => self.app.active(&Id::Playlist).ok(),
@@ -212,6 +228,10 @@ impl Model {
                 TermusicLayout::DataBase => self.app.active(&Id::DBListCriteria).ok(),
                 TermusicLayout::Podcast => self.app.active(&Id::Podcast).ok(),
             },
+            LyricMsg::SeekToTimestamp(position) => {
+                self.lyric_seek_to_timestamp(position);
+                None
+            }
         };
         None
     }
@@ -243,45 +263,12 @@ impl Model {
                 }
             }
             PCMsg::PodcastAddPopupCloseCancel => self.umount_podcast_add_popup(),
-            PCMsg::SyncData((id, pod)) => {
-                self.download_tracker.decrease_one(&pod.url);
-                self.show_message_timeout_label_help(
-                    self.download_tracker.message_sync_success(),
-                    None,
-                    None,
-                    None,
-                );
-                if let Err(e) = self.add_or_sync_data(pod, Some(*id)) {
-                    self.mount_error_popup(e.context("add or sync data"));
-                };
-            }
-            PCMsg::NewData(pod) => {
-                self.download_tracker.decrease_one(&pod.url);
-                self.show_message_timeout_label_help(
-                    self.download_tracker.message_feeds_added(),
-                    None,
-                    None,
-                    None,
-                );
-                if let Err(e) = self.add_or_sync_data(pod, None) {
-                    self.mount_error_popup(e.context("add or sync data"));
-                }
-            }
-            PCMsg::Error(url, feed) => {
-                self.download_tracker.decrease_one(url);
-                self.mount_error_popup(anyhow!("Error happened with feed: {:?}", feed.title));
-                self.show_message_timeout_label_help(
-                    self.download_tracker.message_feed_sync_failed(),
-                    None,
-                    None,
-                    None,
-                );
-            }
             PCMsg::PodcastSelected(index) => {
                 self.podcast.podcasts_index = *index;
                 if let Err(e) = self.podcast_sync_episodes() {
                     self.mount_error_popup(e.context("podcast sync episodes"));
                 }
+                self.lyric_update_for_podcast_details(*index);
             }
             PCMsg::DescriptionUpdate => self.lyric_update(),
             PCMsg::EpisodeAdd(index) => {
@@ -309,74 +296,23 @@ impl Model {
                     self.mount_error_popup(e.context("podcast refresh feeds all"));
                 }
             }
-            PCMsg::FetchPodcastStart(url) => {
-                self.download_tracker.increase_one(url);
-                self.show_message_timeout_label_help(
-                    self.download_tracker.message_sync_start(),
-                    None,
-                    None,
-                    None,
-                );
-            }
             PCMsg::EpisodeDownload(index) => {
                 if let Err(e) = self.episode_download(Some(*index)) {
                     self.mount_error_popup(e.context("podcast episode download"));
                 }
             }
-            PCMsg::DLStart(ep_data) => {
-                self.download_tracker.increase_one(&ep_data.url);
-                self.show_message_timeout_label_help(
-                    self.download_tracker.message_download_start(&ep_data.title),
-                    None,
-                    None,
-                    None,
-                );
-            }
-            PCMsg::DLComplete(ep_data) => {
-                if let Err(e) = self.episode_download_complete(ep_data.clone()) {
-                    self.mount_error_popup(e.context("podcast episode download complete"));
-                }
-                self.download_tracker.decrease_one(&ep_data.url);
-                self.show_message_timeout_label_help(
-                    self.download_tracker.message_download_complete(),
-                    None,
-                    None,
-                    None,
-                );
-            }
-            PCMsg::DLResponseError(ep_data) => {
-                self.download_tracker.decrease_one(&ep_data.url);
-                self.mount_error_popup(anyhow!("download failed for episode: {}", ep_data.title));
-                self.show_message_timeout_label_help(
-                    self.download_tracker
-                        .message_download_error_response(&ep_data.title),
-                    None,
-                    None,
-                    None,
-                );
-            }
-            PCMsg::DLFileCreateError(ep_data) => {
-                self.download_tracker.decrease_one(&ep_data.url);
-                self.mount_error_popup(anyhow!("download failed for episode: {}", ep_data.title));
-                self.show_message_timeout_label_help(
-                    self.download_tracker
-                        .message_download_error_file_create(&ep_data.title),
-                    None,
-                    None,
-                    None,
-                );
-            }
-            PCMsg::DLFileWriteError(ep_data) => {
-                self.download_tracker.decrease_one(&ep_data.url);
-                self.mount_error_popup(anyhow!("download failed for episode: {}", ep_data.title));
-                self.show_message_timeout_label_help(
-                    self.download_tracker
-                        .message_download_error_file_write(&ep_data.title),
-                    None,
-                    None,
-                    None,
-                );
-            }
+            // only send by the feed and download tasks, which run on the server and are reported via stream events
+            PCMsg::SyncData(_)
+            | PCMsg::SyncUnchanged(..)
+            | PCMsg::NewData(_)
+            | PCMsg::Error(..)
+            | PCMsg::FetchPodcastStart(_)
+            | PCMsg::DLStart(_)
+            | PCMsg::DLProgress(..)
+            | PCMsg::DLComplete(_)
+            | PCMsg::DLResponseError(_)
+            | PCMsg::DLFileCreateError(_)
+            | PCMsg::DLFileWriteError(_) => (),
             PCMsg::EpisodeDeleteFile(index) => {
                 if let Err(e) = self.episode_delete_file(*index) {
                     self.mount_error_popup(e.context("podcast episode delete"));
@@ -444,6 +380,12 @@ impl Model {
                 }
                 self.command(&PlayerCmd::SeekBackward);
             }
+            Msg::PlayerChapterNext => {
+                self.command(&PlayerCmd::ChapterNext);
+            }
+            Msg::PlayerChapterPrevious => {
+                self.command(&PlayerCmd::ChapterPrevious);
+            }
             Msg::PlayerSpeedUp => {
                 self.command(&PlayerCmd::SpeedUp);
             }
@@ -839,9 +781,6 @@ impl Model {
             }
             PLMsg::LoopModeCycle => {
                 self.command(&PlayerCmd::CycleLoop);
-                self.config_server.write().settings.player.loop_mode =
-                    self.playlist.cycle_loop_mode();
-                self.playlist_update_title();
             }
             PLMsg::PlaylistTableBlurDown => match self.layout {
                 TermusicLayout::TreeView => assert!(self.app.active(&Id::Library).is_ok()),
@@ -856,17 +795,13 @@ impl Model {
                 self.player_previous();
             }
             PLMsg::SwapDown(index) => {
-                self.playlist.swap_down(*index);
-                self.playlist_sync();
-                if let Err(e) = self.player_sync_playlist() {
-                    self.mount_error_popup(e.context("playlist sync playlist"));
+                if *index + 1 < self.playlist.len() {
+                    self.playlist_swap(*index, *index + 1);
                 }
             }
             PLMsg::SwapUp(index) => {
-                self.playlist.swap_up(*index);
-                self.playlist_sync();
-                if let Err(e) = self.player_sync_playlist() {
-                    self.mount_error_popup(e.context("playlist sync playlist"));
+                if *index > 0 {
+                    self.playlist_swap(*index, *index - 1);
                 }
             }
             PLMsg::AddRandomAlbum => {
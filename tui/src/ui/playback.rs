@@ -1,21 +1,38 @@
+use std::time::Duration;
+
 use anyhow::Result;
+use termusiclib::config::v2::server::LoopMode;
+use termusiclib::player::auth::ClientTokenInterceptor;
 use termusiclib::player::music_player_client::MusicPlayerClient;
 use termusiclib::player::{
-    CycleLoopRequest, EmptyReply, GetProgressRequest, GetProgressResponse, PlaySelectedRequest,
-    PlayerProgress, ReloadConfigRequest, ReloadPlaylistRequest, SeekBackwardRequest,
-    SeekForwardRequest, SkipNextRequest, SkipPreviousRequest, SpeedDownRequest, SpeedUpRequest,
+    AddPodcastRequest, ClearPlaylistRequest, CurrentTrackReply, CycleLoopRequest, EmptyReply,
+    EpisodeIds, EqualizerReply, EqualizerSet, GetCurrentTrackRequest, GetPlaylistRequest,
+    GetPodcastsRequest, GetProgressRequest, GetProgressResponse, ListOutputDevicesRequest,
+    NextChapterRequest, OutputDevicesReply, PauseRequest, PlayRequest, PlaySelectedRequest,
+    PlayerProgress, PlaylistAddTrack, PlaylistMoveTrack, PlaylistRemoveTrack, PlaylistSwapTrack,
+    PlaylistTracks, PreviousChapterRequest, RefreshPodcastsRequest, ReloadConfigRequest,
+    ReloadPlaylistRequest, RemoveDeletedTracksRequest, RemoveEqualizerPresetRequest,
+    RemovePodcastRequest, SeekBackwardRequest, SeekForwardRequest, SeekToRequest,
+    SetEpisodesPlayedRequest, SetLoopModeRequest, SetOutputDeviceRequest, SetVolumeRequest,
+    ShufflePlaylistRequest, SkipNextRequest, SkipPreviousRequest, SpeedDownRequest, SpeedUpRequest,
     ToggleGaplessRequest, TogglePauseRequest, VolumeDownRequest, VolumeUpRequest,
 };
+use termusiclib::podcast::episode::Episode;
+use termusiclib::podcast::Podcast;
 use termusicplayback::Status;
 use tokio_stream::{Stream, StreamExt as _};
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Channel;
 
+/// The gRPC client, authenticating every request with the configured token
+pub type PlayerClient = MusicPlayerClient<InterceptedService<Channel, ClientTokenInterceptor>>;
+
 pub struct Playback {
-    client: MusicPlayerClient<Channel>,
+    client: PlayerClient,
 }
 
 impl Playback {
-    pub fn new(client: MusicPlayerClient<Channel>) -> Self {
+    pub fn new(client: PlayerClient) -> Self {
         Self { client }
     }
     pub async fn toggle_pause(&mut self) -> Result<Status> {
@@ -110,6 +127,22 @@ impl Playback {
         Ok(response.into())
     }
 
+    pub async fn next_chapter(&mut self) -> Result<PlayerProgress> {
+        let request = tonic::Request::new(NextChapterRequest {});
+        let response = self.client.next_chapter(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.into())
+    }
+
+    pub async fn previous_chapter(&mut self) -> Result<PlayerProgress> {
+        let request = tonic::Request::new(PreviousChapterRequest {});
+        let response = self.client.previous_chapter(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.into())
+    }
+
     pub async fn reload_config(&mut self) -> Result<()> {
         let request = tonic::Request::new(ReloadConfigRequest {});
         let response = self.client.reload_config(request).await?;
@@ -126,8 +159,12 @@ impl Playback {
         Ok(())
     }
 
-    pub async fn play_selected(&mut self) -> Result<()> {
-        let request = tonic::Request::new(PlaySelectedRequest {});
+    pub async fn play_selected(&mut self, index: usize) -> Result<()> {
+        // protobuf does not support usize directly
+        #[allow(clippy::cast_possible_truncation)]
+        let request = tonic::Request::new(PlaySelectedRequest {
+            index: index as u32,
+        });
         let response = self.client.play_selected(request).await?;
         let response = response.into_inner();
         info!("Got response from server: {:?}", response);
@@ -142,6 +179,242 @@ impl Playback {
         Ok(())
     }
 
+    pub async fn get_playlist(&mut self) -> Result<PlaylistTracks> {
+        let request = tonic::Request::new(GetPlaylistRequest {});
+        let response = self.client.get_playlist(request).await?;
+        let response = response.into_inner();
+        info!(
+            "Got playlist from server with {} tracks",
+            response.tracks.len()
+        );
+        Ok(response)
+    }
+
+    pub async fn add_to_playlist(&mut self, info: PlaylistAddTrack) -> Result<()> {
+        let request = tonic::Request::new(info.into());
+        let response = self.client.add_to_playlist(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn remove_from_playlist(&mut self, info: PlaylistRemoveTrack) -> Result<()> {
+        let request = tonic::Request::new(info.into());
+        let response = self.client.remove_from_playlist(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn swap_tracks(&mut self, info: PlaylistSwapTrack) -> Result<()> {
+        let request = tonic::Request::new(info.into());
+        let response = self.client.swap_tracks(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn move_track(&mut self, info: PlaylistMoveTrack) -> Result<()> {
+        let request = tonic::Request::new(info.into());
+        let response = self.client.move_track(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn clear_playlist(&mut self) -> Result<()> {
+        let request = tonic::Request::new(ClearPlaylistRequest {});
+        let response = self.client.clear_playlist(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn shuffle_playlist(&mut self) -> Result<PlaylistTracks> {
+        let request = tonic::Request::new(ShufflePlaylistRequest {});
+        let response = self.client.shuffle_playlist(request).await?;
+        let response = response.into_inner();
+        info!(
+            "Got shuffled playlist from server with {} tracks",
+            response.tracks.len()
+        );
+        Ok(response)
+    }
+
+    pub async fn remove_deleted_tracks(&mut self) -> Result<()> {
+        let request = tonic::Request::new(RemoveDeletedTracksRequest {});
+        let response = self.client.remove_deleted_tracks(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn set_loop_mode(&mut self, mode: LoopMode) -> Result<LoopMode> {
+        let request = tonic::Request::new(SetLoopModeRequest {
+            mode: mode.as_u32(),
+        });
+        let response = self.client.set_loop_mode(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(LoopMode::from_u32(response.mode))
+    }
+
+    pub async fn set_equalizer(&mut self, set: EqualizerSet) -> Result<EqualizerReply> {
+        let request = tonic::Request::new(set.into());
+        let response = self.client.set_equalizer(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response)
+    }
+
+    pub async fn remove_equalizer_preset(&mut self, preset: String) -> Result<EqualizerReply> {
+        let request = tonic::Request::new(RemoveEqualizerPresetRequest { preset });
+        let response = self.client.remove_equalizer_preset(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response)
+    }
+
+    pub async fn list_output_devices(&mut self) -> Result<OutputDevicesReply> {
+        let request = tonic::Request::new(ListOutputDevicesRequest {});
+        let response = self.client.list_output_devices(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response)
+    }
+
+    pub async fn set_output_device(&mut self, device: String) -> Result<OutputDevicesReply> {
+        let request = tonic::Request::new(SetOutputDeviceRequest { device });
+        let response = self.client.set_output_device(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response)
+    }
+
+    pub async fn play(&mut self) -> Result<Status> {
+        let request = tonic::Request::new(PlayRequest {});
+        let response = self.client.play(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(Status::from_u32(response.status))
+    }
+
+    pub async fn pause(&mut self) -> Result<Status> {
+        let request = tonic::Request::new(PauseRequest {});
+        let response = self.client.pause(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(Status::from_u32(response.status))
+    }
+
+    pub async fn set_volume(&mut self, volume: u16) -> Result<u16> {
+        let request = tonic::Request::new(SetVolumeRequest {
+            volume: u32::from(volume),
+        });
+        let response = self.client.set_volume(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        // clamped to u16::MAX, also send is a u16, but protobuf does not support u16 directly
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(response.volume.min(u32::from(u16::MAX)) as u16)
+    }
+
+    pub async fn seek_to(&mut self, position: Duration) -> Result<PlayerProgress> {
+        let request = tonic::Request::new(SeekToRequest {
+            position: Some(position.into()),
+        });
+        let response = self.client.seek_to(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response.into())
+    }
+
+    pub async fn get_current_track(&mut self) -> Result<CurrentTrackReply> {
+        let request = tonic::Request::new(GetCurrentTrackRequest {});
+        let response = self.client.get_current_track(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(response)
+    }
+
+    pub async fn add_podcast(&mut self, url: String) -> Result<()> {
+        let request = tonic::Request::new(AddPodcastRequest { url });
+        let response = self.client.add_podcast(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn refresh_podcasts(&mut self, podcast_ids: Vec<i64>) -> Result<()> {
+        let request = tonic::Request::new(RefreshPodcastsRequest { podcast_ids });
+        let response = self.client.refresh_podcasts(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn download_episodes(&mut self, episode_ids: Vec<i64>) -> Result<()> {
+        let request = tonic::Request::new(EpisodeIds { episode_ids });
+        let response = self.client.download_episodes(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn delete_episode_files(&mut self, episode_ids: Vec<i64>) -> Result<()> {
+        let request = tonic::Request::new(EpisodeIds { episode_ids });
+        let response = self.client.delete_episode_files(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn set_episodes_played(&mut self, episode_ids: Vec<i64>, played: bool) -> Result<()> {
+        let request = tonic::Request::new(SetEpisodesPlayedRequest {
+            episode_ids,
+            played,
+        });
+        let response = self.client.set_episodes_played(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    pub async fn remove_podcasts(
+        &mut self,
+        podcast_ids: Vec<i64>,
+        delete_files: bool,
+    ) -> Result<()> {
+        let request = tonic::Request::new(RemovePodcastRequest {
+            podcast_ids,
+            delete_files,
+        });
+        let response = self.client.remove_podcast(request).await?;
+        let response = response.into_inner();
+        info!("Got response from server: {:?}", response);
+        Ok(())
+    }
+
+    /// Get all podcasts, including their episodes
+    pub async fn get_podcasts(&mut self) -> Result<Vec<Podcast>> {
+        let request = tonic::Request::new(GetPodcastsRequest {
+            include_episodes: true,
+        });
+        let response = self.client.get_podcasts(request).await?;
+        let response = response.into_inner();
+        info!("Got {} podcasts from server", response.podcasts.len());
+
+        let mut podcasts: Vec<Podcast> = response.podcasts.into_iter().map(Into::into).collect();
+        for episode in response.episodes {
+            let episode: Episode = episode.into();
+            if let Some(podcast) = podcasts.iter_mut().find(|pod| pod.id == episode.pod_id) {
+                podcast.episodes.push(episode);
+            }
+        }
+
+        Ok(podcasts)
+    }
+
     pub async fn subscribe_to_stream_updates(
         &mut self,
     ) -> Result<impl Stream<Item = Result<termusiclib::player::StreamUpdates>>> {